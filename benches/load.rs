@@ -0,0 +1,123 @@
+//! Reading and loading saves of different sizes.
+//!
+//! The saves are generated: rows of inverters on circuit boards, each wired
+//! to the next. To also benchmark real saves, point `BLOTTER_BENCH_CORPUS`
+//! at a directory of them.
+
+use std::io::BufReader;
+use std::path::Path;
+use std::{env, fs};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use blotter::latest::BlotterFile;
+use blotter::sandbox::component::{CircuitBoard, Delayer};
+use blotter::sandbox::{PegAddress, PegType, Sandbox};
+
+/// A save with about `components` components.
+fn synthetic(components: usize) -> Vec<u8> {
+    const ROW: usize = 100;
+    let mut sandbox = Sandbox::new();
+    let mut previous = None;
+    for row in 0..components.div_ceil(ROW + 1) {
+        let board = sandbox.add_component(
+            &CircuitBoard::new()
+                .width(ROW as u32)
+                .height(1)
+                .build()
+                .position([0, 0, row as i32 * 300]),
+        );
+        for x in 0..ROW {
+            let position = [x as i32 * 300 + 150, 0, 150];
+            let gate = sandbox.add_component(
+                &Delayer::new()
+                    .build()
+                    .parent(Some(board))
+                    .position(position),
+            );
+            if let Some(previous) = previous {
+                let output = PegAddress {
+                    component: previous,
+                    peg_type: PegType::Output,
+                    peg_index: 0,
+                };
+                let input = PegAddress {
+                    component: gate,
+                    peg_type: PegType::Input,
+                    peg_index: 0,
+                };
+                sandbox.add_wire(output, input, 0.0).unwrap();
+            }
+            previous = Some(gate);
+        }
+    }
+    let mut bytes = Vec::new();
+    BlotterFile::from(&sandbox).write(&mut bytes).unwrap();
+    bytes
+}
+
+fn saves() -> Vec<(String, Vec<u8>)> {
+    let mut saves = vec![
+        ("small".to_string(), synthetic(1_000)),
+        ("medium".to_string(), synthetic(50_000)),
+        ("large".to_string(), synthetic(1_000_000)),
+    ];
+    if let Some(dir) = env::var_os("BLOTTER_BENCH_CORPUS") {
+        let mut paths: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.is_file())
+            .collect();
+        paths.sort();
+        for path in paths {
+            let name = Path::new(path.file_name().unwrap()).display().to_string();
+            saves.push((name, fs::read(&path).unwrap()));
+        }
+    }
+    saves
+}
+
+fn load(c: &mut Criterion) {
+    let saves = saves();
+
+    let mut group = c.benchmark_group("read");
+    group.sample_size(10);
+    for (name, bytes) in &saves {
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), bytes, |b, bytes| {
+            b.iter(|| blotter::BlotterFile::read(&mut &bytes[..]).unwrap())
+        });
+    }
+    group.finish();
+
+    // How saves are read from files, through a buffered reader that isn't a
+    // plain slice.
+    let mut group = c.benchmark_group("read_maybe_compressed");
+    group.sample_size(10);
+    for (name, bytes) in &saves {
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), bytes, |b, bytes| {
+            b.iter(|| {
+                let mut reader = BufReader::new(&bytes[..]);
+                blotter::BlotterFile::read_maybe_compressed(&mut reader).unwrap()
+            })
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("sandbox");
+    group.sample_size(10);
+    for (name, bytes) in &saves {
+        let file = blotter::BlotterFile::read(&mut &bytes[..])
+            .unwrap()
+            .migrate();
+        group.throughput(Throughput::Elements(file.components.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &file, |b, file| {
+            b.iter(|| Sandbox::try_from(file).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, load);
+criterion_main!(benches);
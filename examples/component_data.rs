@@ -18,11 +18,7 @@ fn main() -> Result<(), blotter::error::Error> {
             .get(&component.type_id)
             .copied()
             .unwrap_or("(unknown)");
-        let data = component
-            .custom_data
-            .as_ref()
-            .map(Vec::as_slice)
-            .unwrap_or(&[]);
+        let data = component.custom_data.as_deref().unwrap_or(&[]);
 
         println!();
         println!("{:?}", name);
@@ -0,0 +1,7 @@
+//! Print a Markdown spec of every version of the save format, newest first.
+
+fn main() {
+    for layout in [blotter::v6::LAYOUT, blotter::v5::LAYOUT] {
+        println!("{}", layout.to_markdown());
+    }
+}
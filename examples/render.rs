@@ -0,0 +1,30 @@
+use std::path::Path;
+use std::{env::args_os, fs::File, io::BufReader, io::BufWriter, process::exit};
+
+use blotter::export::render::{write_png, write_svg, RenderOptions};
+use blotter::sandbox::Sandbox;
+use blotter::BlotterFile;
+
+fn main() -> Result<(), blotter::error::Error> {
+    let infile = args_os().nth(1).unwrap_or_else(usage);
+    let outfile = args_os().nth(2).unwrap_or_else(usage);
+    let mut reader = BufReader::new(File::open(infile)?);
+    let blotter_file = BlotterFile::read(&mut reader)?;
+    let sandbox = Sandbox::try_from(&blotter_file.migrate())?;
+
+    let options = RenderOptions::new();
+    let svg = Path::new(&outfile)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("svg"));
+    let mut writer = BufWriter::new(File::create(&outfile)?);
+    if svg {
+        write_svg(&sandbox, &options, &mut writer)
+    } else {
+        write_png(&sandbox, &options, &mut writer)
+    }
+}
+
+fn usage<T>() -> T {
+    eprintln!("usage: render <input file> <output file, .png or .svg>");
+    exit(1);
+}
@@ -0,0 +1,25 @@
+//! Collections that the sandbox is built on, for reuse by editors.
+//!
+//! Both stores keep their items in reference-counted chunks, so they are
+//! cheap to clone for undo history and snapshots. Only the methods that
+//! modify a store need `T: Clone`, to copy a chunk that is shared:
+//!
+//! ```
+//! use blotter::collections::{DenseStore, ObjectStore};
+//!
+//! #[derive(Debug)]
+//! struct Unique;
+//!
+//! struct Editor {
+//!     objects: ObjectStore<Unique>,
+//!     layers: DenseStore<Unique>,
+//! }
+//!
+//! let editor = Editor { objects: ObjectStore::new(), layers: DenseStore::default() };
+//! assert!(editor.objects.is_empty());
+//! assert_eq!(editor.layers.iter().count(), 0);
+//! assert_eq!(format!("{:?}", editor.objects), "{}");
+//! ```
+
+pub use crate::misc::dense_store::{DenseStore, Index, Rename};
+pub use crate::misc::object_store::{Address, ObjectStore};
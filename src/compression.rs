@@ -0,0 +1,157 @@
+//! Support for compressed save payloads.
+//!
+//! Each compression format is only available when its corresponding crate
+//! feature is enabled (`gzip` or `zstd`). Compressed saves can still be
+//! _detected_ without the feature, in which case reading them fails with
+//! [`Error::UnsupportedCompression`].
+
+use std::io::{Read, Write};
+
+use crate::error::Error;
+use crate::limits::ReadLimits;
+use crate::BlotterFile;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// A compression format (and level) to wrap a save in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Compression {
+    /// No compression; equivalent to [`BlotterFile::write`].
+    None,
+    /// Gzip, with a level from 0 (fastest) to 9 (smallest).
+    #[cfg(feature = "gzip")]
+    Gzip(u32),
+    /// Zstandard, with a level from 1 (fastest) to 22 (smallest). 0 selects
+    /// the library default.
+    #[cfg(feature = "zstd")]
+    Zstd(i32),
+}
+
+impl BlotterFile {
+    /// Read a save that may be wrapped in a gzip or zstd stream.
+    ///
+    /// The format is detected from the first few bytes of the stream.
+    /// Uncompressed saves are read as-is.
+    ///
+    /// There is no limit on how large a compressed save can get once
+    /// decompressed; use
+    /// [`read_maybe_compressed_with_limits`](Self::read_maybe_compressed_with_limits)
+    /// for files from untrusted sources.
+    pub fn read_maybe_compressed<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        Self::read_maybe_compressed_with_limits(reader, &ReadLimits::unlimited())
+    }
+
+    /// Read a save that may be wrapped in a gzip or zstd stream, failing if
+    /// it exceeds any of the given limits.
+    ///
+    /// A compressed save that is larger than
+    /// [`ReadLimits::max_decompressed_len`] once decompressed fails with
+    /// [`Error::LimitExceeded`], without decompressing the rest of it.
+    ///
+    /// ```
+    /// # #[cfg(feature = "gzip")]
+    /// # {
+    /// use blotter::compression::Compression;
+    /// use blotter::error::Error;
+    /// use blotter::latest;
+    /// use blotter::limits::{Limit, ReadLimits};
+    /// use blotter::BlotterFile;
+    ///
+    /// let mut file = latest::BlotterFile::new([0, 91, 0, 510]);
+    /// file.circuit_states = latest::CircuitStates::WorldFormat {
+    ///     circuit_states: vec![0; 1 << 20],
+    /// };
+    /// let mut bytes = Vec::new();
+    /// BlotterFile::V6(file)
+    ///     .write_compressed(&mut bytes, Compression::Gzip(6))
+    ///     .unwrap();
+    /// assert!(bytes.len() < 1 << 16);
+    ///
+    /// let limits = ReadLimits::unlimited().max_decompressed_len(1 << 16);
+    /// assert!(matches!(
+    ///     BlotterFile::read_maybe_compressed_with_limits(&mut &bytes[..], &limits),
+    ///     Err(Error::LimitExceeded(Limit::DecompressedLength)),
+    /// ));
+    /// let limits = ReadLimits::unlimited().max_decompressed_len(2 << 20);
+    /// assert!(BlotterFile::read_maybe_compressed_with_limits(&mut &bytes[..], &limits).is_ok());
+    /// # }
+    /// ```
+    pub fn read_maybe_compressed_with_limits<R: Read>(
+        reader: &mut R,
+        limits: &ReadLimits,
+    ) -> Result<Self, Error> {
+        let mut prefix = [0u8; 4];
+        reader.read_exact(&mut prefix)?;
+        let mut reader = prefix.as_slice().chain(reader);
+
+        if prefix[..2] == GZIP_MAGIC {
+            #[cfg(feature = "gzip")]
+            {
+                return Self::read_decompressed(flate2::read::GzDecoder::new(reader), limits);
+            }
+            #[cfg(not(feature = "gzip"))]
+            {
+                return Err(Error::UnsupportedCompression);
+            }
+        }
+
+        if prefix == ZSTD_MAGIC {
+            #[cfg(feature = "zstd")]
+            {
+                return Self::read_decompressed(zstd::stream::read::Decoder::new(reader)?, limits);
+            }
+            #[cfg(not(feature = "zstd"))]
+            {
+                return Err(Error::UnsupportedCompression);
+            }
+        }
+
+        Self::read_with_limits(&mut reader, limits)
+    }
+
+    /// Read a save from a decompressing reader, stopping once it has
+    /// produced more than the decompressed length limit.
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    fn read_decompressed<R: Read>(decoder: R, limits: &ReadLimits) -> Result<Self, Error> {
+        use crate::limits::Limit;
+
+        let max_len = limits.get(Limit::DecompressedLength);
+        let mut reader =
+            decoder.take(u64::try_from(max_len).map_or(u64::MAX, |len| len.saturating_add(1)));
+        let result = Self::read_with_limits(&mut reader, limits);
+        // Reading past the limit either fails with an unexpected end of
+        // file, or succeeds with a save that is too large.
+        if reader.limit() == 0 {
+            return Err(Error::LimitExceeded(Limit::DecompressedLength));
+        }
+        result
+    }
+
+    /// Write the save, wrapped in the given compression format.
+    pub fn write_compressed<W: Write>(
+        &self,
+        writer: &mut W,
+        compression: Compression,
+    ) -> Result<(), Error> {
+        match compression {
+            Compression::None => self.write(writer),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip(level) => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(writer, flate2::Compression::new(level));
+                self.write(&mut encoder)?;
+                encoder.finish()?;
+                Ok(())
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd(level) => {
+                let mut encoder = zstd::stream::write::Encoder::new(writer, level)?;
+                self.write(&mut encoder)?;
+                encoder.finish()?;
+                Ok(())
+            }
+        }
+    }
+}
@@ -1,4 +1,6 @@
+use crate::v6::UNITS_PER_METER;
 use crate::{v5, v6};
+use alloc::vec::Vec;
 
 impl From<v5::BlotterFile> for v6::BlotterFile {
     fn from(file: v5::BlotterFile) -> Self {
@@ -10,6 +12,8 @@ impl From<v5::BlotterFile> for v6::BlotterFile {
             components: file.components.into_iter().map(Into::into).collect(),
             wires: file.wires,
             circuit_states: file.circuit_states,
+            extra: Vec::new(),
+            trailing: file.trailing,
         }
     }
 }
@@ -30,10 +34,45 @@ impl From<v5::Component> for v6::Component {
 }
 
 fn floating_to_fixed_position([x, y, z]: [f32; 3]) -> [i32; 3] {
-    const CONVERSION_FACTOR: f32 = 1000.0;
     [
-        (x * CONVERSION_FACTOR) as i32,
-        (y * CONVERSION_FACTOR) as i32,
-        (z * CONVERSION_FACTOR) as i32,
+        (x * UNITS_PER_METER) as i32,
+        (y * UNITS_PER_METER) as i32,
+        (z * UNITS_PER_METER) as i32,
     ]
 }
+
+/// Going back to v5 only converts positions; like the upgrade, custom data is
+/// kept as it is. Extra data is dropped, since v5 saves have no room for it.
+impl From<v6::BlotterFile> for v5::BlotterFile {
+    fn from(file: v6::BlotterFile) -> Self {
+        Self {
+            game_version: file.game_version,
+            save_type: file.save_type,
+            mods: file.mods,
+            component_types: file.component_types,
+            components: file.components.into_iter().map(Into::into).collect(),
+            wires: file.wires,
+            circuit_states: file.circuit_states,
+            trailing: file.trailing,
+        }
+    }
+}
+
+impl From<v6::Component> for v5::Component {
+    fn from(component: v6::Component) -> Self {
+        Self {
+            address: component.address,
+            parent: component.parent,
+            type_id: component.type_id,
+            position: fixed_to_floating_position(component.position),
+            rotation: component.rotation,
+            inputs: component.inputs,
+            outputs: component.outputs,
+            custom_data: component.custom_data,
+        }
+    }
+}
+
+fn fixed_to_floating_position(position: [i32; 3]) -> [f32; 3] {
+    position.map(|x| x as f32 / UNITS_PER_METER)
+}
@@ -0,0 +1,133 @@
+//! Reading custom data one field at a time.
+
+use alloc::string::String;
+
+use crate::error::Error;
+use crate::io::{unexpected_eof, IoError, Read, ReadFrom, VarInt, VarString};
+
+/// A position in a custom data blob, for reading it field by field.
+///
+/// A read that fails, because the data ends too soon or is invalid, leaves
+/// the cursor where it was.
+///
+/// ```
+/// use blotter::custom_data::Cursor;
+///
+/// let data = [7, 0, 0, 0, 1, 0xaa, 0xbb];
+/// let mut cursor = Cursor::new(&data);
+/// assert_eq!(cursor.read_u32().unwrap(), 7);
+/// assert!(cursor.read_bool().unwrap());
+/// assert!(cursor.read_u32().is_err());
+/// assert_eq!(cursor.remaining(), 2);
+/// assert_eq!(cursor.read_rest(), [0xaa, 0xbb]);
+/// assert!(cursor.is_at_end());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Cursor<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+macro_rules! typed_reads {
+    ($($name:ident: $t:ty,)*) => {$(
+        #[doc = concat!("Read a little-endian `", stringify!($t), "`.")]
+        pub fn $name(&mut self) -> Result<$t, Error> {
+            self.read()
+        }
+    )*};
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    /// The number of bytes that have been read.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// The number of bytes left to read.
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+    pub fn is_at_end(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// Read any type that can be read from custom data.
+    pub fn read<T: ReadFrom>(&mut self) -> Result<T, Error> {
+        let start = self.position;
+        T::read_from(self).inspect_err(|_| self.position = start)
+    }
+
+    typed_reads! {
+        read_u8: u8,
+        read_i8: i8,
+        read_u16: u16,
+        read_i16: i16,
+        read_u32: u32,
+        read_i32: i32,
+        read_u64: u64,
+        read_i64: i64,
+        read_f32: f32,
+        read_f64: f64,
+    }
+
+    /// Read a byte, which is `true` unless it is 0.
+    pub fn read_bool(&mut self) -> Result<bool, Error> {
+        self.read()
+    }
+
+    /// Read a string with a four-byte length prefix, like those in save
+    /// files.
+    pub fn read_string(&mut self) -> Result<String, Error> {
+        self.read()
+    }
+
+    /// Read an integer in the variable-length encoding of [`VarInt`].
+    pub fn read_var_int(&mut self) -> Result<u32, Error> {
+        self.read().map(|VarInt(value)| value)
+    }
+
+    /// Read a string with a [`VarInt`] length prefix.
+    pub fn read_var_string(&mut self) -> Result<String, Error> {
+        self.read().map(|VarString(value)| value)
+    }
+
+    /// Read the next `len` bytes, without copying them.
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(unexpected_eof)?;
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    /// Skip the next `len` bytes.
+    pub fn skip(&mut self, len: usize) -> Result<(), Error> {
+        self.read_bytes(len).map(drop)
+    }
+
+    /// Read all of the remaining bytes.
+    pub fn read_rest(&mut self) -> &'a [u8] {
+        let rest = &self.data[self.position..];
+        self.position = self.data.len();
+        rest
+    }
+}
+
+/// A cursor can be passed to [`ComponentData::read`](super::ComponentData::read)
+/// to read a nested structure.
+impl Read for Cursor<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+        let len = buf.len().min(self.remaining());
+        buf[..len].copy_from_slice(&self.data[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
+    }
+}
@@ -0,0 +1,197 @@
+//! Parsers and serializers for vanilla component data.
+//!
+//! These are designed for Logic World 0.91.0 Preview 510 and may not work for
+//! other game versions.
+//!
+//! Parsers for other component types can use [`ReadFrom`] and [`WriteTo`],
+//! which are implemented for the primitive types that custom data is made of,
+//! all little-endian; see [`wire_format`](crate::wire_format). Strings and arrays are the same as in save files: a
+//! four-byte length prefix for strings, and no prefix for arrays.
+//! [`VarInt`] and [`VarString`] are the variable-length encodings that some
+//! mods use. A [`Cursor`] reads these types from a blob one at a time.
+
+mod cursor;
+#[cfg(feature = "std")]
+mod registry;
+
+pub use self::cursor::Cursor;
+#[cfg(feature = "std")]
+pub use self::registry::{CustomDataRegistry, Decoded, DynComponentData};
+pub use crate::io::{ReadFrom, VarInt, VarString, WriteTo};
+
+use alloc::string::String;
+
+use crate::io::{Read, Write};
+
+use crate::error::Error;
+
+pub trait ComponentData: Sized {
+    const TYPE_STRING: &'static str;
+
+    fn read<R: Read>(reader: &mut R) -> Result<Self, Error>;
+
+    fn write<W: Write>(&self, writer: &mut W) -> Result<(), Error>;
+}
+
+#[derive(Debug)]
+pub struct CircuitBoard {
+    pub color: [u8; 3],
+    pub size_x: u32,
+    pub size_z: u32,
+}
+
+impl ComponentData for CircuitBoard {
+    const TYPE_STRING: &'static str = "MHG.CircuitBoard";
+
+    fn read<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        let color = ReadFrom::read_from(reader)?;
+        let size_x = ReadFrom::read_from(reader)?;
+        let size_z = ReadFrom::read_from(reader)?;
+        Ok(Self {
+            color,
+            size_x,
+            size_z,
+        })
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        self.color.write_to(writer)?;
+        self.size_x.write_to(writer)?;
+        self.size_z.write_to(writer)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Delayer {
+    pub timer: u32,
+    pub delay: u32,
+}
+
+impl ComponentData for Delayer {
+    const TYPE_STRING: &'static str = "MHG.Delayer";
+
+    fn read<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        let timer = ReadFrom::read_from(reader)?;
+        let delay = ReadFrom::read_from(reader)?;
+        Ok(Self { timer, delay })
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        self.timer.write_to(writer)?;
+        self.delay.write_to(writer)?;
+        Ok(())
+    }
+}
+
+/// A key on a keyboard, which outputs while its bound input is held.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Key {
+    pub key_down: bool,
+    /// The game's numeric ID of the keyboard or controller input that
+    /// activates the key.
+    pub bound_input: i32,
+    pub key_color: [u8; 3],
+    pub key_label_color: [u8; 3],
+    pub size_x: i32,
+}
+
+impl ComponentData for Key {
+    const TYPE_STRING: &'static str = "MHG.Key";
+
+    fn read<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        let key_down = ReadFrom::read_from(reader)?;
+        let bound_input = ReadFrom::read_from(reader)?;
+        let key_color = ReadFrom::read_from(reader)?;
+        let key_label_color = ReadFrom::read_from(reader)?;
+        let size_x = ReadFrom::read_from(reader)?;
+        Ok(Self {
+            key_down,
+            bound_input,
+            key_color,
+            key_label_color,
+            size_x,
+        })
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        self.key_down.write_to(writer)?;
+        self.bound_input.write_to(writer)?;
+        self.key_color.write_to(writer)?;
+        self.key_label_color.write_to(writer)?;
+        self.size_x.write_to(writer)?;
+        Ok(())
+    }
+}
+
+/// A key mounted flush in a panel. The data is the same as [`Key`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PanelKey(pub Key);
+
+impl ComponentData for PanelKey {
+    const TYPE_STRING: &'static str = "MHG.PanelKey";
+
+    fn read<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        Key::read(reader).map(Self)
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        self.0.write(writer)
+    }
+}
+
+/// A text label.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub text: String,
+    pub color: [u8; 3],
+    pub monospace: bool,
+    pub font_size_max: f32,
+    pub horizontal_alignment: u8,
+    pub vertical_alignment: u8,
+}
+
+impl Label {
+    /// A label with the game's default appearance.
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            color: [0, 0, 0],
+            monospace: false,
+            font_size_max: 0.8,
+            horizontal_alignment: 1,
+            vertical_alignment: 1,
+        }
+    }
+}
+
+impl ComponentData for Label {
+    const TYPE_STRING: &'static str = "MHG.Label";
+
+    fn read<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        let text = ReadFrom::read_from(reader)?;
+        let color = ReadFrom::read_from(reader)?;
+        let monospace = ReadFrom::read_from(reader)?;
+        let font_size_max = ReadFrom::read_from(reader)?;
+        let horizontal_alignment = ReadFrom::read_from(reader)?;
+        let vertical_alignment = ReadFrom::read_from(reader)?;
+        Ok(Self {
+            text,
+            color,
+            monospace,
+            font_size_max,
+            horizontal_alignment,
+            vertical_alignment,
+        })
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        self.text.write_to(writer)?;
+        self.color.write_to(writer)?;
+        self.monospace.write_to(writer)?;
+        self.font_size_max.write_to(writer)?;
+        self.horizontal_alignment.write_to(writer)?;
+        self.vertical_alignment.write_to(writer)?;
+        Ok(())
+    }
+}
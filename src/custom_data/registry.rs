@@ -0,0 +1,208 @@
+//! Decoding custom data for arbitrary component types, including modded ones.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+use crate::error::Error;
+use crate::latest;
+use crate::sandbox::{ComponentId, ComponentRef, Sandbox};
+
+use super::{CircuitBoard, ComponentData, Delayer, Key, Label, PanelKey};
+
+/// Decoded custom data of some component type, with the type erased.
+///
+/// This is implemented for every [`ComponentData`] type that is also `Debug`.
+pub trait DynComponentData: Debug + Any {
+    /// The type string of the component that this data belongs to.
+    fn type_string(&self) -> &'static str;
+
+    fn as_any(&self) -> &dyn Any;
+
+    /// Serialize the data back into custom data bytes.
+    fn to_bytes(&self) -> Result<Vec<u8>, Error>;
+}
+
+impl<T: ComponentData + Debug + 'static> DynComponentData for T {
+    fn type_string(&self) -> &'static str {
+        T::TYPE_STRING
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let mut bytes = Vec::new();
+        self.write(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+/// The result of decoding a component's custom data through a
+/// [`CustomDataRegistry`].
+#[derive(Debug)]
+pub enum Decoded<'a> {
+    /// The component has no custom data.
+    None,
+    /// The component type has no registered decoder.
+    Raw(&'a [u8]),
+    Data(Box<dyn DynComponentData>),
+}
+
+impl Decoded<'_> {
+    /// Get the decoded data as a concrete type, if it is one.
+    pub fn downcast_ref<T: ComponentData + 'static>(&self) -> Option<&T> {
+        match self {
+            Self::Data(data) => data.as_any().downcast_ref(),
+            _ => None,
+        }
+    }
+}
+
+type Decoder = fn(&[u8]) -> Result<Box<dyn DynComponentData>, Error>;
+
+/// A set of custom data decoders, keyed by component type string.
+///
+/// The default registry knows about the vanilla component types supported by
+/// this crate. Mods can define their own data layouts by implementing
+/// [`ComponentData`] and registering the type:
+///
+/// ```
+/// use std::io::{Read, Write};
+///
+/// use blotter::custom_data::{ComponentData, CustomDataRegistry, ReadFrom, WriteTo};
+/// use blotter::error::Error;
+///
+/// #[derive(Debug)]
+/// struct Counter {
+///     count: u8,
+///     enabled: bool,
+/// }
+///
+/// impl ComponentData for Counter {
+///     const TYPE_STRING: &'static str = "Example.Counter";
+///
+///     fn read<R: Read>(reader: &mut R) -> Result<Self, Error> {
+///         Ok(Self {
+///             count: ReadFrom::read_from(reader)?,
+///             enabled: ReadFrom::read_from(reader)?,
+///         })
+///     }
+///
+///     fn write<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+///         self.count.write_to(writer)?;
+///         self.enabled.write_to(writer)
+///     }
+/// }
+///
+/// let registry = CustomDataRegistry::default().with::<Counter>();
+/// let decoded = registry.decode("Example.Counter", Some(&[7, 1])).unwrap();
+/// assert_eq!(decoded.downcast_ref::<Counter>().unwrap().count, 7);
+/// ```
+#[derive(Clone)]
+pub struct CustomDataRegistry {
+    decoders: HashMap<String, Decoder>,
+}
+
+impl CustomDataRegistry {
+    /// A registry with no decoders.
+    pub fn empty() -> Self {
+        Self {
+            decoders: HashMap::new(),
+        }
+    }
+
+    /// Register a decoder for `T`, replacing any existing decoder for the
+    /// same type string.
+    pub fn register<T: ComponentData + Debug + 'static>(&mut self) {
+        self.decoders
+            .insert(T::TYPE_STRING.to_owned(), |mut bytes| {
+                Ok(Box::new(T::read(&mut bytes)?))
+            });
+    }
+
+    /// Like [`register`](Self::register), in builder style.
+    pub fn with<T: ComponentData + Debug + 'static>(mut self) -> Self {
+        self.register::<T>();
+        self
+    }
+
+    pub fn contains(&self, type_string: &str) -> bool {
+        self.decoders.contains_key(type_string)
+    }
+
+    /// Decode custom data belonging to a component of the given type.
+    ///
+    /// If there is no decoder registered for the type, the raw bytes are
+    /// returned as-is.
+    pub fn decode<'a>(
+        &self,
+        type_string: &str,
+        custom_data: Option<&'a [u8]>,
+    ) -> Result<Decoded<'a>, Error> {
+        let bytes = match custom_data {
+            None => return Ok(Decoded::None),
+            Some(bytes) => bytes,
+        };
+        match self.decoders.get(type_string) {
+            Some(decoder) => decoder(bytes).map(Decoded::Data),
+            None => Ok(Decoded::Raw(bytes)),
+        }
+    }
+}
+
+impl Default for CustomDataRegistry {
+    fn default() -> Self {
+        Self::empty()
+            .with::<CircuitBoard>()
+            .with::<Delayer>()
+            .with::<Key>()
+            .with::<Label>()
+            .with::<PanelKey>()
+    }
+}
+
+impl Debug for CustomDataRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.decoders.keys()).finish()
+    }
+}
+
+impl<'a> ComponentRef<'a> {
+    /// Decode the component's custom data using the given registry.
+    pub fn decode_custom_data(&self, registry: &CustomDataRegistry) -> Result<Decoded<'a>, Error> {
+        registry.decode(self.type_name(), self.custom_data())
+    }
+}
+
+impl Sandbox {
+    /// Decode the custom data of a component using the given registry.
+    ///
+    /// Returns `None` if the component doesn't exist.
+    pub fn decode_custom_data(
+        &self,
+        id: ComponentId,
+        registry: &CustomDataRegistry,
+    ) -> Option<Result<Decoded<'_>, Error>> {
+        self.component(id)
+            .map(|component| component.decode_custom_data(registry))
+    }
+}
+
+impl latest::BlotterFile {
+    /// Decode the custom data of one of this file's components using the
+    /// given registry.
+    pub fn decode_custom_data<'a>(
+        &self,
+        component: &'a latest::Component,
+        registry: &CustomDataRegistry,
+    ) -> Result<Decoded<'a>, Error> {
+        let type_string = self
+            .component_types
+            .iter()
+            .find(|ctype| ctype.numeric_id == component.type_id)
+            .map_or("", |ctype| ctype.text_id.as_str());
+        registry.decode(type_string, component.custom_data.as_deref())
+    }
+}
@@ -0,0 +1,230 @@
+//! Tools for inspecting the raw structure of save files.
+
+use std::{
+    fmt::Debug,
+    io::{sink, ErrorKind, Read, Write},
+};
+
+use crate::error::Error;
+use crate::io::{skip_until_magic, ReadFrom};
+use crate::{v5, v6};
+
+/// The number of raw bytes shown per field before the dump is truncated.
+const MAX_RAW_BYTES: usize = 16;
+
+/// Walk a save and write an annotated dump of its structure.
+///
+/// Every field is written on its own line, showing its byte offset, its raw
+/// bytes, its name and its decoded value:
+///
+/// ```text
+/// 00000022  03 00 00 00                                          num_components = 3
+/// ```
+///
+/// The dump is written as the save is parsed, so if the save is corrupt, the
+/// output shows everything up to the point of failure before the error is
+/// returned.
+pub fn annotate<R: Read, W: Write>(reader: &mut R, writer: &mut W) -> Result<(), Error> {
+    let mut annotator = Annotator {
+        reader: Recorder {
+            inner: reader,
+            offset: 0,
+            recorded: Vec::new(),
+        },
+        writer,
+    };
+    annotator.save()
+}
+
+/// A reader that keeps track of its position and the bytes read for the
+/// current field.
+struct Recorder<R> {
+    inner: R,
+    offset: usize,
+    recorded: Vec<u8>,
+}
+
+impl<R: Read> Read for Recorder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.offset += len;
+        // Only the first few bytes of each field are shown, so there is no
+        // need to keep the rest of large blobs around.
+        let keep = (MAX_RAW_BYTES + 1)
+            .saturating_sub(self.recorded.len())
+            .min(len);
+        self.recorded.extend_from_slice(&buf[..keep]);
+        Ok(len)
+    }
+}
+
+struct Annotator<'w, R, W> {
+    reader: Recorder<R>,
+    writer: &'w mut W,
+}
+
+impl<R: Read, W: Write> Annotator<'_, R, W> {
+    /// Read a single field, and write its annotation.
+    fn field<T: ReadFrom + Debug>(&mut self, name: &str) -> Result<T, Error> {
+        let start = self.begin();
+        let value = T::read_from(&mut self.reader)?;
+        self.end(start, name, &format!("{:?}", value))?;
+        Ok(value)
+    }
+
+    /// Read a length-prefixed blob of bytes, and write its annotation.
+    fn blob(&mut self, name: &str, len: usize) -> Result<(), Error> {
+        let start = self.begin();
+        // Stream the blob instead of allocating it; the length may be garbage
+        // in a corrupt file.
+        let copied = std::io::copy(&mut (&mut self.reader).take(len as u64), &mut sink())?;
+        if copied != len as u64 {
+            return Err(std::io::Error::from(ErrorKind::UnexpectedEof).into());
+        }
+        self.end(start, name, &format!("<{} bytes>", len))
+    }
+
+    fn magic(&mut self, name: &str) -> Result<(), Error> {
+        let start = self.begin();
+        let value = <[u8; 16]>::read_from(&mut self.reader)?;
+        self.end(
+            start,
+            name,
+            &format!("{:?}", String::from_utf8_lossy(&value)),
+        )
+    }
+
+    /// Read the footer of a v6 save, and any extra data before it.
+    fn extra_and_footer(&mut self) -> Result<(), Error> {
+        let start = self.begin();
+        let len = skip_until_magic(&mut self.reader, v6::SAVE_FOOTER)? as usize;
+        if len > 0 {
+            self.reader.recorded.truncate(len.min(MAX_RAW_BYTES + 1));
+            self.end(start, "extra", &format!("<{} bytes>", len))?;
+        }
+        self.reader.recorded = v6::SAVE_FOOTER.to_vec();
+        self.end(
+            start + len,
+            "footer",
+            &format!("{:?}", String::from_utf8_lossy(v6::SAVE_FOOTER)),
+        )
+    }
+
+    fn begin(&mut self) -> usize {
+        self.reader.recorded.clear();
+        self.reader.offset
+    }
+
+    fn end(&mut self, start: usize, name: &str, value: &str) -> Result<(), Error> {
+        let recorded = &self.reader.recorded;
+        let mut raw: Vec<String> = recorded
+            .iter()
+            .take(MAX_RAW_BYTES)
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        if recorded.len() > MAX_RAW_BYTES {
+            raw.push("..".into());
+        }
+        writeln!(
+            self.writer,
+            "{:08x}  {:<50}  {} = {}",
+            start,
+            raw.join(" "),
+            name,
+            value
+        )?;
+        Ok(())
+    }
+
+    fn save(&mut self) -> Result<(), Error> {
+        self.magic("header")?;
+        let save_version: u8 = self.field("save_version")?;
+        if save_version != v5::SAVE_VERSION && save_version != v6::SAVE_VERSION {
+            return Err(Error::IncompatibleVersion(save_version));
+        }
+
+        self.field::<[i32; 4]>("game_version")?;
+        let save_type: v5::SaveType = self.field("save_type")?;
+        let num_components: usize = self.field("num_components")?;
+        let num_wires: usize = self.field("num_wires")?;
+
+        let num_mods: usize = self.field("num_mods")?;
+        for i in 0..num_mods {
+            self.field::<String>(&format!("mods[{}].mod_id", i))?;
+            self.field::<[i32; 4]>(&format!("mods[{}].mod_version", i))?;
+        }
+
+        let num_component_types: usize = self.field("num_component_types")?;
+        for i in 0..num_component_types {
+            self.field::<u16>(&format!("component_types[{}].numeric_id", i))?;
+            self.field::<String>(&format!("component_types[{}].text_id", i))?;
+        }
+
+        for i in 0..num_components {
+            self.component(i, save_version)?;
+        }
+        for i in 0..num_wires {
+            self.wire(i)?;
+        }
+
+        match save_type {
+            v5::SaveType::World => {
+                let num_bytes: usize = self.field("circuit_states.len")?;
+                self.blob("circuit_states", num_bytes)?;
+            }
+            v5::SaveType::Subassembly => {
+                let num_on_states: usize = self.field("on_states.len")?;
+                for i in 0..num_on_states {
+                    self.field::<i32>(&format!("on_states[{}]", i))?;
+                }
+            }
+        }
+
+        if save_version == v5::SAVE_VERSION {
+            self.magic("footer")?;
+        } else {
+            self.extra_and_footer()?;
+        }
+        Ok(())
+    }
+
+    fn component(&mut self, i: usize, save_version: u8) -> Result<(), Error> {
+        let prefix = format!("components[{}]", i);
+        self.field::<u32>(&format!("{}.address", prefix))?;
+        self.field::<u32>(&format!("{}.parent", prefix))?;
+        self.field::<u16>(&format!("{}.type_id", prefix))?;
+        if save_version == v5::SAVE_VERSION {
+            self.field::<[f32; 3]>(&format!("{}.position", prefix))?;
+        } else {
+            self.field::<[i32; 3]>(&format!("{}.position", prefix))?;
+        }
+        self.field::<[f32; 4]>(&format!("{}.rotation", prefix))?;
+
+        let num_inputs: usize = self.field(&format!("{}.inputs.len", prefix))?;
+        for j in 0..num_inputs {
+            self.field::<i32>(&format!("{}.inputs[{}].circuit_state_id", prefix, j))?;
+        }
+        let num_outputs: usize = self.field(&format!("{}.outputs.len", prefix))?;
+        for j in 0..num_outputs {
+            self.field::<i32>(&format!("{}.outputs[{}].circuit_state_id", prefix, j))?;
+        }
+
+        let custom_data_len: i32 = self.field(&format!("{}.custom_data.len", prefix))?;
+        if custom_data_len >= 0 {
+            self.blob(&format!("{}.custom_data", prefix), custom_data_len as usize)?;
+        }
+        Ok(())
+    }
+
+    fn wire(&mut self, i: usize) -> Result<(), Error> {
+        let prefix = format!("wires[{}]", i);
+        for peg in ["start_peg", "end_peg"] {
+            self.field::<v5::PegType>(&format!("{}.{}.peg_type", prefix, peg))?;
+            self.field::<u32>(&format!("{}.{}.component_address", prefix, peg))?;
+            self.field::<i32>(&format!("{}.{}.peg_index", prefix, peg))?;
+        }
+        self.field::<i32>(&format!("{}.circuit_state_id", prefix))?;
+        self.field::<f32>(&format!("{}.rotation", prefix))?;
+        Ok(())
+    }
+}
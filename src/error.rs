@@ -1,13 +1,87 @@
+#[cfg(feature = "std")]
+use crate::import::blif::BlifError;
+use crate::limits::Limit;
+#[cfg(feature = "std")]
+use crate::sandbox::{SandboxLoadError, SandboxSaveError};
+#[cfg(feature = "std")]
+use crate::validate::Report;
+
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum Error {
+    #[cfg(feature = "std")]
     IoError(std::io::Error),
+    /// The save ended in the middle of an item. Only readers without the
+    /// `std` feature fail with this; `std::io` readers fail with an
+    /// `IoError` of kind `UnexpectedEof` instead.
+    UnexpectedEof,
     InvalidSave,
+    /// The save ended before its footer, or has something else where the
+    /// footer should be.
+    MissingFooter,
+    /// There is data after the footer, which
+    /// [`TrailingData::Reject`](crate::TrailingData::Reject) doesn't allow.
+    TrailingData,
     IncompatibleVersion(u8),
+    /// The save is compressed with a format whose crate feature is not
+    /// enabled.
+    UnsupportedCompression,
+    /// Validation found issues that are fatal under the requested policy.
+    #[cfg(feature = "std")]
+    Validation(Report),
+    /// The save exceeds one of the configured read limits.
+    LimitExceeded(Limit),
+    /// The save is well-formed, but can't be loaded into a sandbox.
+    #[cfg(feature = "std")]
+    SandboxLoad(SandboxLoadError),
+    /// A sandbox has wires that can't be saved.
+    #[cfg(feature = "std")]
+    SandboxSave(SandboxSaveError),
+    /// A BLIF netlist is malformed or uses unsupported features.
+    #[cfg(feature = "std")]
+    Blif(BlifError),
+    /// A save read with [`read_preserving`](crate::BlotterFile::read_preserving)
+    /// wouldn't be written back as it was. Holds the offset of the first
+    /// byte that would differ.
+    NotPreserved(usize),
+    #[cfg(feature = "json")]
+    Json(serde_json::Error),
+    /// An image couldn't be encoded.
+    #[cfg(feature = "render")]
+    Image(image::ImageError),
 }
 
+#[cfg(feature = "std")]
 impl From<std::io::Error> for Error {
     fn from(error: std::io::Error) -> Self {
         Self::IoError(error)
     }
 }
+
+#[cfg(feature = "std")]
+impl From<SandboxLoadError> for Error {
+    fn from(error: SandboxLoadError) -> Self {
+        Self::SandboxLoad(error)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<SandboxSaveError> for Error {
+    fn from(error: SandboxSaveError) -> Self {
+        Self::SandboxSave(error)
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Json(error)
+    }
+}
+
+#[cfg(feature = "render")]
+impl From<image::ImageError> for Error {
+    fn from(error: image::ImageError) -> Self {
+        Self::Image(error)
+    }
+}
@@ -0,0 +1,48 @@
+//! Export of board outlines and component footprints to DXF.
+//!
+//! The output is a minimal ASCII DXF drawing containing only an `ENTITIES`
+//! section, which is accepted by most CAD programs. The drawing is a top-down
+//! view: DXF X and Y correspond to world X and Z, in meters. Boards are drawn
+//! on the `BOARDS` layer and other components on the `COMPONENTS` layer.
+
+use std::io::Write;
+
+use crate::error::Error;
+use crate::sandbox::{Footprint, FootprintKind, Sandbox};
+
+/// Write the footprints of every component in the sandbox as a DXF drawing.
+pub fn write_dxf<W: Write>(sandbox: &Sandbox, writer: &mut W) -> Result<(), Error> {
+    write_footprints(&sandbox.footprints(), writer)
+}
+
+/// Write the given footprints as a DXF drawing.
+pub fn write_footprints<W: Write>(footprints: &[Footprint], writer: &mut W) -> Result<(), Error> {
+    writeln!(writer, "0\nSECTION\n2\nENTITIES")?;
+    for footprint in footprints {
+        let layer = match footprint.kind {
+            FootprintKind::Board => "BOARDS",
+            FootprintKind::Component => "COMPONENTS",
+        };
+        let outline = &footprint.outline;
+        for (i, start) in outline.iter().enumerate() {
+            let end = outline[(i + 1) % outline.len()];
+            write_line(writer, layer, *start, end)?;
+        }
+    }
+    writeln!(writer, "0\nENDSEC\n0\nEOF")?;
+    Ok(())
+}
+
+fn write_line<W: Write>(
+    writer: &mut W,
+    layer: &str,
+    [x1, y1]: [f32; 2],
+    [x2, y2]: [f32; 2],
+) -> Result<(), Error> {
+    writeln!(
+        writer,
+        "0\nLINE\n8\n{}\n10\n{}\n20\n{}\n30\n0.0\n11\n{}\n21\n{}\n31\n0.0",
+        layer, x1, y1, x2, y2
+    )?;
+    Ok(())
+}
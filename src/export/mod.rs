@@ -0,0 +1,6 @@
+//! Exporters to formats used by other tools.
+
+pub mod dxf;
+#[cfg(feature = "render")]
+pub mod render;
+pub mod verilog;
@@ -0,0 +1,374 @@
+//! Top-down occupancy maps of saves, as PNG images or SVG drawings.
+//!
+//! Requires the `render` feature. Circuit boards are drawn as rectangles in
+//! their own color, other components as dots, and wires as lines between the
+//! components at their ends. Higher boards are drawn over lower ones.
+//!
+//! The image's X axis runs along the world X axis and its Y axis along the
+//! world Z axis, like in [`PixelArt`](crate::generate::pixel_art::PixelArt),
+//! so rendering generated pixel art gives back the original picture.
+//!
+//! ```
+//! use blotter::export::render::{render_image, RenderOptions};
+//! use blotter::sandbox::component::CircuitBoard;
+//! use blotter::sandbox::Sandbox;
+//!
+//! let mut sandbox = Sandbox::new();
+//! sandbox.add_component(&CircuitBoard::new().width(4).height(2).color([255, 0, 0]).build());
+//!
+//! let options = RenderOptions::new().pixels_per_meter(10.0).margin(0);
+//! let image = render_image(&sandbox, &options);
+//! assert_eq!(image.dimensions(), (12, 6));
+//! assert_eq!(image.get_pixel(6, 3).0, [255, 0, 0, 255]);
+//! ```
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use image::codecs::png::PngEncoder;
+use image::{ImageEncoder, Rgba, RgbaImage};
+
+use crate::error::Error;
+use crate::geometry::SQUARE_SIZE;
+use crate::sandbox::{FootprintKind, Sandbox};
+
+const COMPONENT_COLOR: [u8; 3] = [32, 32, 32];
+const WIRE_COLOR: [u8; 3] = [200, 40, 40];
+
+/// Options for rendering a sandbox.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderOptions {
+    pixels_per_meter: f32,
+    max_size: u32,
+    margin: u32,
+    boards: bool,
+    components: bool,
+    wires: bool,
+}
+
+impl RenderOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The scale of the picture. Defaults to 20, so that a grid square is six
+    /// pixels wide.
+    pub fn pixels_per_meter(self, pixels_per_meter: f32) -> Self {
+        Self {
+            pixels_per_meter,
+            ..self
+        }
+    }
+
+    /// The largest width or height of the picture, in pixels, including the
+    /// margin. Large worlds are scaled down to fit. Defaults to 4096.
+    pub fn max_size(self, max_size: u32) -> Self {
+        Self { max_size, ..self }
+    }
+
+    /// Empty space around the contents, in pixels. Defaults to 8.
+    pub fn margin(self, margin: u32) -> Self {
+        Self { margin, ..self }
+    }
+
+    /// Whether to draw circuit boards. Defaults to true.
+    pub fn boards(self, boards: bool) -> Self {
+        Self { boards, ..self }
+    }
+
+    /// Whether to draw components other than circuit boards. Defaults to
+    /// true.
+    pub fn components(self, components: bool) -> Self {
+        Self { components, ..self }
+    }
+
+    /// Whether to draw wires. Defaults to true.
+    pub fn wires(self, wires: bool) -> Self {
+        Self { wires, ..self }
+    }
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            pixels_per_meter: 20.0,
+            max_size: 4096,
+            margin: 8,
+            boards: true,
+            components: true,
+            wires: true,
+        }
+    }
+}
+
+/// Something to draw, in pixel coordinates.
+#[derive(Debug, Clone, Copy)]
+enum Shape {
+    Board {
+        corners: [[f32; 2]; 4],
+        color: [u8; 3],
+    },
+    Line {
+        from: [f32; 2],
+        to: [f32; 2],
+    },
+    Dot {
+        center: [f32; 2],
+        radius: f32,
+    },
+}
+
+/// Everything to draw, in drawing order.
+#[derive(Debug, Clone)]
+struct Scene {
+    width: u32,
+    height: u32,
+    shapes: Vec<Shape>,
+}
+
+impl Scene {
+    fn new(sandbox: &Sandbox, options: &RenderOptions) -> Self {
+        let footprints = sandbox.footprints();
+        let mut min = [f32::INFINITY; 2];
+        let mut max = [f32::NEG_INFINITY; 2];
+        for corner in footprints.iter().flat_map(|footprint| footprint.outline) {
+            for axis in 0..2 {
+                min[axis] = min[axis].min(corner[axis]);
+                max[axis] = max[axis].max(corner[axis]);
+            }
+        }
+        if footprints.is_empty() {
+            (min, max) = ([0.0; 2], [0.0; 2]);
+        }
+
+        let margin = options.margin as f32;
+        let extent = (max[0] - min[0]).max(max[1] - min[1]);
+        let available = (options.max_size as f32 - 2.0 * margin).max(1.0);
+        let scale = options
+            .pixels_per_meter
+            .min(available / extent.max(f32::EPSILON));
+        let size = |axis: usize| ((max[axis] - min[axis]) * scale + 2.0 * margin).round() as u32;
+        let point =
+            |[x, z]: [f32; 2]| [(x - min[0]) * scale + margin, (z - min[1]) * scale + margin];
+
+        let mut shapes = Vec::new();
+        if options.boards {
+            let boards: HashMap<_, _> = sandbox
+                .board_map()
+                .boards
+                .into_iter()
+                .map(|board| (board.component, board))
+                .collect();
+            let mut outlines: Vec<_> = footprints
+                .iter()
+                .filter_map(|footprint| Some((boards.get(&footprint.component)?, footprint)))
+                .collect();
+            outlines.sort_by(|(a, _), (b, _)| {
+                a.centroid[1]
+                    .total_cmp(&b.centroid[1])
+                    .then(a.component.cmp(&b.component))
+            });
+            shapes.extend(outlines.into_iter().map(|(board, footprint)| Shape::Board {
+                corners: footprint.outline.map(point),
+                color: board.color,
+            }));
+        }
+        if options.wires {
+            let mut wires: Vec<_> = sandbox.wire_ids().collect();
+            wires.sort_unstable();
+            shapes.extend(wires.into_iter().map(|id| {
+                let (a, b) = sandbox.wire_pegs(id).unwrap();
+                let end = |component| {
+                    let [x, _, z] = sandbox.world_position(component);
+                    point([x, z])
+                };
+                Shape::Line {
+                    from: end(a.component),
+                    to: end(b.component),
+                }
+            }));
+        }
+        if options.components {
+            let radius = (SQUARE_SIZE * scale / 4.0).max(0.5);
+            let mut dots: Vec<_> = footprints
+                .iter()
+                .filter(|footprint| footprint.kind == FootprintKind::Component)
+                .collect();
+            dots.sort_by_key(|footprint| footprint.component);
+            shapes.extend(dots.into_iter().map(|footprint| {
+                let [x, z] = footprint.outline.iter().fold([0.0; 2], |sum, corner| {
+                    [sum[0] + corner[0] / 4.0, sum[1] + corner[1] / 4.0]
+                });
+                Shape::Dot {
+                    center: point([x, z]),
+                    radius,
+                }
+            }));
+        }
+
+        Self {
+            width: size(0).max(1),
+            height: size(1).max(1),
+            shapes,
+        }
+    }
+}
+
+/// Rasterize a sandbox. Pixels with nothing on them are transparent.
+pub fn render_image(sandbox: &Sandbox, options: &RenderOptions) -> RgbaImage {
+    let scene = Scene::new(sandbox, options);
+    let mut image = RgbaImage::new(scene.width, scene.height);
+    for shape in &scene.shapes {
+        match *shape {
+            Shape::Board { corners, color } => {
+                fill(&mut image, color, bounds(&corners), |p| {
+                    in_quad(&corners, p)
+                });
+            }
+            Shape::Line { from, to } => {
+                let (min, max) = bounds(&[from, to]);
+                let area = ([min[0] - 1.0, min[1] - 1.0], [max[0] + 1.0, max[1] + 1.0]);
+                fill(&mut image, WIRE_COLOR, area, |p| {
+                    segment_distance(from, to, p) <= 0.5
+                });
+            }
+            Shape::Dot { center, radius } => {
+                let area = (
+                    [center[0] - radius, center[1] - radius],
+                    [center[0] + radius, center[1] + radius],
+                );
+                fill(&mut image, COMPONENT_COLOR, area, |[x, y]| {
+                    (x - center[0]).hypot(y - center[1]) <= radius
+                });
+            }
+        }
+    }
+    image
+}
+
+/// Color the pixels within `area` whose centers are `inside` a shape.
+fn fill(
+    image: &mut RgbaImage,
+    [r, g, b]: [u8; 3],
+    (min, max): ([f32; 2], [f32; 2]),
+    inside: impl Fn([f32; 2]) -> bool,
+) {
+    let range = |axis: usize, size: u32| {
+        min[axis].floor().max(0.0) as u32..(max[axis].ceil().max(0.0) as u32).min(size)
+    };
+    for y in range(1, image.height()) {
+        for x in range(0, image.width()) {
+            if inside([x as f32 + 0.5, y as f32 + 0.5]) {
+                image.put_pixel(x, y, Rgba([r, g, b, 255]));
+            }
+        }
+    }
+}
+
+/// Render a sandbox as a PNG image.
+pub fn write_png<W: Write>(
+    sandbox: &Sandbox,
+    options: &RenderOptions,
+    writer: &mut W,
+) -> Result<(), Error> {
+    let image = render_image(sandbox, options);
+    PngEncoder::new(writer).write_image(
+        &image,
+        image.width(),
+        image.height(),
+        image::ExtendedColorType::Rgba8,
+    )?;
+    Ok(())
+}
+
+/// Render a sandbox as an SVG drawing.
+pub fn write_svg<W: Write>(
+    sandbox: &Sandbox,
+    options: &RenderOptions,
+    writer: &mut W,
+) -> Result<(), Error> {
+    let scene = Scene::new(sandbox, options);
+    writeln!(
+        writer,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+        scene.width, scene.height
+    )?;
+    for shape in &scene.shapes {
+        match *shape {
+            Shape::Board { corners, color } => {
+                let points: Vec<String> = corners
+                    .iter()
+                    .map(|[x, y]| format!("{},{}", x, y))
+                    .collect();
+                writeln!(
+                    writer,
+                    r#"<polygon points="{}" fill="{}"/>"#,
+                    points.join(" "),
+                    hex(color)
+                )?;
+            }
+            Shape::Line { from, to } => writeln!(
+                writer,
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}"/>"#,
+                from[0],
+                from[1],
+                to[0],
+                to[1],
+                hex(WIRE_COLOR)
+            )?,
+            Shape::Dot { center, radius } => writeln!(
+                writer,
+                r#"<circle cx="{}" cy="{}" r="{}" fill="{}"/>"#,
+                center[0],
+                center[1],
+                radius,
+                hex(COMPONENT_COLOR)
+            )?,
+        }
+    }
+    writeln!(writer, "</svg>")?;
+    Ok(())
+}
+
+fn hex([r, g, b]: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+fn bounds(points: &[[f32; 2]]) -> ([f32; 2], [f32; 2]) {
+    points.iter().fold(
+        ([f32::INFINITY; 2], [f32::NEG_INFINITY; 2]),
+        |(min, max), &[x, y]| {
+            (
+                [min[0].min(x), min[1].min(y)],
+                [max[0].max(x), max[1].max(y)],
+            )
+        },
+    )
+}
+
+/// Whether a point is inside a convex quadrilateral, in either winding order.
+fn in_quad(corners: &[[f32; 2]; 4], [x, y]: [f32; 2]) -> bool {
+    let sides = (0..4).map(|i| {
+        let [ax, ay] = corners[i];
+        let [bx, by] = corners[(i + 1) % 4];
+        (bx - ax) * (y - ay) - (by - ay) * (x - ax)
+    });
+    let (mut positive, mut negative) = (false, false);
+    for side in sides {
+        positive |= side > 0.0;
+        negative |= side < 0.0;
+    }
+    !(positive && negative)
+}
+
+/// The distance from a point to a line segment.
+fn segment_distance(a: [f32; 2], b: [f32; 2], p: [f32; 2]) -> f32 {
+    let d = [b[0] - a[0], b[1] - a[1]];
+    let length_squared = d[0] * d[0] + d[1] * d[1];
+    let t = if length_squared > 0.0 {
+        (((p[0] - a[0]) * d[0] + (p[1] - a[1]) * d[1]) / length_squared).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (p[0] - a[0] - t * d[0]).hypot(p[1] - a[1] - t * d[1])
+}
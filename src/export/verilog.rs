@@ -0,0 +1,216 @@
+//! Export of a circuit as a structural Verilog module.
+//!
+//! Each net becomes a Verilog net named `n` followed by its number in the
+//! sandbox's [`Netlist`]. Nets of input pegs are declared `wor`, since the
+//! game ORs together every output wired into them. Gates become Verilog gate
+//! primitives, with delays in game ticks: one for every gate, and the
+//! configured delay for delayers. D latches, which have no primitive, are
+//! written as a small `always` block, with the data on their first input
+//! and the enable on their second.
+//!
+//! The outputs of switches, buttons and keys are driven by players, so they
+//! become module inputs, named after their component type and number.
+//! Other components with outputs that can't be expressed, like relays and
+//! randomizers, are left out with a comment. Components without outputs,
+//! like pegs, labels and chairs, are left out silently.
+//!
+//! ```
+//! use blotter::export::verilog::write_verilog;
+//! use blotter::import::blif::read_blif;
+//! use blotter::sandbox::Sandbox;
+//!
+//! let source = ".model nand2\n.inputs a b\n.outputs y\n.names a b y\n11 0\n.end\n";
+//! let mut sandbox = Sandbox::new();
+//! let ports = read_blif(&mut sandbox, &mut source.as_bytes()).unwrap();
+//!
+//! let mut verilog = Vec::new();
+//! write_verilog(&sandbox, &ports, "nand2", &mut verilog).unwrap();
+//! let verilog = String::from_utf8(verilog).unwrap();
+//! assert!(verilog.starts_with("module nand2 (a, b, y);"));
+//! assert!(verilog.contains("and #1"));
+//! ```
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use crate::custom_data::{ComponentData, Delayer};
+use crate::error::Error;
+use crate::sandbox::{Netlist, PegAddress, PegType, Ports, Sandbox};
+
+/// Component types whose outputs are controlled by players.
+const PLAYER_INPUTS: &[&str] = &[
+    "MHG.Switch",
+    "MHG.PanelSwitch",
+    "MHG.Button",
+    "MHG.PanelButton",
+    "MHG.Key",
+    "MHG.PanelKey",
+];
+
+/// Verilog keywords that can't be used as plain identifiers. Only the ones
+/// that could plausibly be port names are listed; others are escaped anyway
+/// if they contain anything but letters, digits and underscores.
+const KEYWORDS: &[&str] = &[
+    "always", "and", "assign", "begin", "buf", "case", "default", "else", "end", "for", "if",
+    "initial", "inout", "input", "module", "nand", "nor", "not", "or", "output", "reg", "wire",
+    "wor", "xnor", "xor",
+];
+
+/// Write the logic of the sandbox as a Verilog module.
+///
+/// `ports` become the ports of the module. A port on an input peg is a
+/// module input if nothing in the sandbox drives its net, and a module output
+/// otherwise; a port on an output peg is always a module output.
+pub fn write_verilog<W: Write>(
+    sandbox: &Sandbox,
+    ports: &Ports,
+    module_name: &str,
+    writer: &mut W,
+) -> Result<(), Error> {
+    let netlist = sandbox.netlist();
+    let numbers: HashMap<_, _> = netlist
+        .components
+        .iter()
+        .enumerate()
+        .map(|(index, component)| (component.id, index))
+        .collect();
+    let net_of = |peg: PegAddress| {
+        let component = &netlist.components[numbers[&peg.component]];
+        match peg.peg_type {
+            PegType::Input => component.inputs[peg.peg_index],
+            PegType::Output => component.outputs[peg.peg_index],
+        }
+    };
+
+    // Module ports, and the assignments that connect them to nets.
+    let mut inputs = Vec::new();
+    let mut outputs = Vec::new();
+    let mut connections = Vec::new();
+    for (name, peg) in ports.iter() {
+        let name = identifier(name);
+        let net = net_of(peg);
+        if peg.peg_type == PegType::Input && netlist.nets[net].drivers.is_empty() {
+            connections.push(format!("assign n{} = {};", net, name));
+            inputs.push(name);
+        } else {
+            connections.push(format!("assign {} = n{};", name, net));
+            outputs.push(name);
+        }
+    }
+    for (index, component) in netlist.components.iter().enumerate() {
+        if !PLAYER_INPUTS.contains(&component.type_name.as_str()) {
+            continue;
+        }
+        for (peg_index, &net) in component.outputs.iter().enumerate() {
+            let kind = component
+                .type_name
+                .trim_start_matches("MHG.")
+                .to_lowercase();
+            let name = match component.outputs.len() {
+                1 => format!("{}_{}", kind, index),
+                _ => format!("{}_{}_{}", kind, index, peg_index),
+            };
+            connections.push(format!("assign n{} = {};", net, name));
+            inputs.push(name);
+        }
+    }
+
+    let all_ports: Vec<&str> = inputs.iter().chain(&outputs).map(String::as_str).collect();
+    writeln!(
+        writer,
+        "module {} ({});",
+        identifier(module_name),
+        all_ports.join(", ")
+    )?;
+    for name in &inputs {
+        writeln!(writer, "  input {};", name)?;
+    }
+    for name in &outputs {
+        writeln!(writer, "  output {};", name)?;
+    }
+    writeln!(writer)?;
+    write_nets(writer, &netlist)?;
+    writeln!(writer)?;
+    for connection in &connections {
+        writeln!(writer, "  {}", connection)?;
+    }
+    for (number, net) in netlist.nets.iter().enumerate() {
+        for driver in &net.drivers {
+            writeln!(writer, "  assign n{} = n{};", number, driver)?;
+        }
+    }
+    writeln!(writer)?;
+
+    for (index, component) in netlist.components.iter().enumerate() {
+        let Some(&output) = component.outputs.first() else {
+            continue;
+        };
+        let type_name = component.type_name.as_str();
+        let pins = |inputs: &[usize]| {
+            std::iter::once(output)
+                .chain(inputs.iter().copied())
+                .map(|net| format!("n{}", net))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match (type_name, &component.inputs[..]) {
+            ("MHG.Inverter", inputs @ [_]) => {
+                writeln!(writer, "  not #1 g{} ({});", index, pins(inputs))?
+            }
+            ("MHG.AndGate", inputs @ [_, _, ..]) => {
+                writeln!(writer, "  and #1 g{} ({});", index, pins(inputs))?
+            }
+            ("MHG.XorGate", inputs @ [_, _, ..]) => {
+                writeln!(writer, "  xor #1 g{} ({});", index, pins(inputs))?
+            }
+            ("MHG.Buffer" | "MHG.Buffer_WithOutput", inputs @ [_]) => {
+                writeln!(writer, "  buf #1 g{} ({});", index, pins(inputs))?
+            }
+            ("MHG.Delayer", inputs @ [_]) => {
+                let delay = sandbox
+                    .component(component.id)
+                    .and_then(|component| component.custom_data())
+                    .and_then(|mut data| Delayer::read(&mut data).ok())
+                    .map_or(1, |delayer| delayer.delay);
+                writeln!(writer, "  buf #{} g{} ({});", delay, index, pins(inputs))?
+            }
+            ("MHG.DLatch", [data, enable]) => {
+                writeln!(writer, "  reg q{};", index)?;
+                writeln!(
+                    writer,
+                    "  always @* if (n{}) q{} <= #1 n{};",
+                    enable, index, data
+                )?;
+                writeln!(writer, "  assign n{} = q{};", output, index)?;
+            }
+            _ if PLAYER_INPUTS.contains(&type_name) => {}
+            _ => writeln!(writer, "  // {} g{}: not exported", type_name, index)?,
+        }
+    }
+    writeln!(writer, "endmodule")?;
+    Ok(())
+}
+
+fn write_nets<W: Write>(writer: &mut W, netlist: &Netlist) -> Result<(), Error> {
+    for (number, net) in netlist.nets.iter().enumerate() {
+        let has_inputs = net.pegs.iter().any(|peg| peg.peg_type == PegType::Input);
+        let kind = if has_inputs { "wor" } else { "wire" };
+        writeln!(writer, "  {} n{};", kind, number)?;
+    }
+    Ok(())
+}
+
+/// A port name as a Verilog identifier, escaping it if it isn't a plain one.
+fn identifier(name: &str) -> String {
+    let plain = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !KEYWORDS.contains(&name);
+    if plain {
+        name.to_owned()
+    } else {
+        format!("\\{} ", name)
+    }
+}
@@ -0,0 +1,168 @@
+//! Composable procedural generation.
+//!
+//! A [`WorldGenerator`] fills a region of a [`Sandbox`] with components.
+//! Generators can be combined with the provided adaptors to lay out copies of
+//! a design without each generator having to handle placement itself:
+//!
+//! ```
+//! use blotter::generate::WorldGenerator;
+//! use blotter::geometry::Aabb;
+//! use blotter::sandbox::{component::Peg, Sandbox};
+//!
+//! let peg = |sandbox: &mut Sandbox, region: Aabb| {
+//!     let position = blotter::generate::region_origin(&region);
+//!     sandbox.add_component(&Peg::new().build().position(position));
+//! };
+//!
+//! let mut sandbox = Sandbox::new();
+//! peg.grid_of(4, 4).generate(
+//!     &mut sandbox,
+//!     Aabb::new([0.0, 0.0, 0.0], [1.2, 0.3, 1.2]),
+//! );
+//! ```
+
+#[cfg(feature = "image")]
+pub mod pixel_art;
+
+use crate::geometry::{meters_to_position, Aabb};
+use crate::sandbox::Sandbox;
+
+/// Something that can add content to a region of a sandbox.
+pub trait WorldGenerator {
+    /// Add content to `sandbox`, placed within `region`.
+    fn generate(&self, sandbox: &mut Sandbox, region: Aabb);
+
+    /// Divide the region into a grid of `count_x` by `count_z` equal cells on
+    /// the horizontal plane, and run this generator in each cell.
+    fn grid_of(self, count_x: u32, count_z: u32) -> GridOf<Self>
+    where
+        Self: Sized,
+    {
+        GridOf {
+            inner: self,
+            count_x,
+            count_z,
+        }
+    }
+
+    /// Run this generator `count` times, moving the region by `step` meters
+    /// each time.
+    fn repeat(self, count: u32, step: [f32; 3]) -> Repeat<Self>
+    where
+        Self: Sized,
+    {
+        Repeat {
+            inner: self,
+            count,
+            step,
+        }
+    }
+
+    /// Run this generator in a region moved by `offset` meters.
+    fn offset(self, offset: [f32; 3]) -> Offset<Self>
+    where
+        Self: Sized,
+    {
+        Offset {
+            inner: self,
+            offset,
+        }
+    }
+
+    /// Run this generator, then `next`, in the same region.
+    fn and<G: WorldGenerator>(self, next: G) -> And<Self, G>
+    where
+        Self: Sized,
+    {
+        And {
+            first: self,
+            second: next,
+        }
+    }
+}
+
+impl<F> WorldGenerator for F
+where
+    F: Fn(&mut Sandbox, Aabb),
+{
+    fn generate(&self, sandbox: &mut Sandbox, region: Aabb) {
+        self(sandbox, region)
+    }
+}
+
+/// The fixed-point position of the minimum corner of a region, for placing
+/// root components.
+pub fn region_origin(region: &Aabb) -> [i32; 3] {
+    meters_to_position(region.min)
+}
+
+/// Generator adaptor returned by [`WorldGenerator::grid_of`].
+#[derive(Debug, Clone)]
+pub struct GridOf<G> {
+    inner: G,
+    count_x: u32,
+    count_z: u32,
+}
+
+impl<G: WorldGenerator> WorldGenerator for GridOf<G> {
+    fn generate(&self, sandbox: &mut Sandbox, region: Aabb) {
+        let [size_x, _, size_z] = region.size();
+        let cell_x = size_x / self.count_x as f32;
+        let cell_z = size_z / self.count_z as f32;
+        for i in 0..self.count_x {
+            for j in 0..self.count_z {
+                let min = [
+                    region.min[0] + i as f32 * cell_x,
+                    region.min[1],
+                    region.min[2] + j as f32 * cell_z,
+                ];
+                let max = [min[0] + cell_x, region.max[1], min[2] + cell_z];
+                self.inner.generate(sandbox, Aabb::new(min, max));
+            }
+        }
+    }
+}
+
+/// Generator adaptor returned by [`WorldGenerator::repeat`].
+#[derive(Debug, Clone)]
+pub struct Repeat<G> {
+    inner: G,
+    count: u32,
+    step: [f32; 3],
+}
+
+impl<G: WorldGenerator> WorldGenerator for Repeat<G> {
+    fn generate(&self, sandbox: &mut Sandbox, region: Aabb) {
+        for i in 0..self.count {
+            let offset = self.step.map(|x| x * i as f32);
+            self.inner.generate(sandbox, region.translate(offset));
+        }
+    }
+}
+
+/// Generator adaptor returned by [`WorldGenerator::offset`].
+#[derive(Debug, Clone)]
+pub struct Offset<G> {
+    inner: G,
+    offset: [f32; 3],
+}
+
+impl<G: WorldGenerator> WorldGenerator for Offset<G> {
+    fn generate(&self, sandbox: &mut Sandbox, region: Aabb) {
+        self.inner.generate(sandbox, region.translate(self.offset));
+    }
+}
+
+/// Generator adaptor returned by [`WorldGenerator::and`].
+#[derive(Debug, Clone)]
+pub struct And<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A: WorldGenerator, B: WorldGenerator> WorldGenerator for And<A, B> {
+    fn generate(&self, sandbox: &mut Sandbox, region: Aabb) {
+        self.first.generate(sandbox, region);
+        self.second.generate(sandbox, region);
+    }
+}
@@ -0,0 +1,141 @@
+//! Pixel art made of colored circuit boards.
+//!
+//! Requires the `image` feature. No image formats are enabled through it;
+//! decode the image with the `image` crate and its format features, then
+//! pass the pixels to [`PixelArt`].
+
+use image::RgbaImage;
+
+use crate::generate::WorldGenerator;
+use crate::geometry::{meters_to_position, Aabb, SQUARE_SIZE};
+use crate::sandbox::component::CircuitBoard;
+use crate::sandbox::Sandbox;
+
+/// Lays an image out as circuit boards on the horizontal plane, one square
+/// board per pixel, colored to match.
+///
+/// The image's X axis runs along the world X axis and its Y axis along the
+/// world Z axis, starting at the region's minimum corner. The region's size
+/// is ignored. Pixels that are mostly transparent are left out, and runs of
+/// same-colored pixels in a row share a single board.
+///
+/// ```
+/// use blotter::generate::pixel_art::PixelArt;
+/// use blotter::generate::WorldGenerator;
+/// use blotter::geometry::Aabb;
+/// use blotter::sandbox::Sandbox;
+/// use image::{Rgba, RgbaImage};
+///
+/// let image = RgbaImage::from_fn(4, 4, |x, _| {
+///     if x < 2 {
+///         Rgba([255, 0, 0, 255])
+///     } else {
+///         Rgba([0, 0, 250, 255])
+///     }
+/// });
+///
+/// let mut sandbox = Sandbox::new();
+/// PixelArt::new(image)
+///     .scale(2)
+///     .levels(2)
+///     .generate(&mut sandbox, Aabb::new([0.0; 3], [0.0; 3]));
+/// assert_eq!(sandbox.components_of_type("MHG.CircuitBoard").count(), 8);
+/// ```
+#[derive(Debug, Clone)]
+pub struct PixelArt {
+    image: RgbaImage,
+    scale: u32,
+    levels: Option<u8>,
+    palette: Option<Vec<[u8; 3]>>,
+}
+
+impl PixelArt {
+    pub fn new(image: RgbaImage) -> Self {
+        Self {
+            image,
+            scale: 1,
+            levels: None,
+            palette: None,
+        }
+    }
+
+    /// The width of each pixel, in grid squares.
+    pub fn scale(self, scale: u32) -> Self {
+        assert!(scale > 0);
+        Self { scale, ..self }
+    }
+
+    /// Round each color channel to one of `levels` evenly spaced values,
+    /// which gives longer runs of the same color, and so fewer boards.
+    pub fn levels(self, levels: u8) -> Self {
+        assert!(levels >= 2);
+        Self {
+            levels: Some(levels),
+            ..self
+        }
+    }
+
+    /// Replace each color with the nearest color of the palette. This
+    /// applies after [`levels`](Self::levels).
+    pub fn palette(self, palette: Vec<[u8; 3]>) -> Self {
+        assert!(!palette.is_empty());
+        Self {
+            palette: Some(palette),
+            ..self
+        }
+    }
+
+    /// The color of a board for a pixel, or `None` if it is transparent.
+    fn color(&self, x: u32, y: u32) -> Option<[u8; 3]> {
+        let [r, g, b, a] = self.image.get_pixel(x, y).0;
+        if a < 128 {
+            return None;
+        }
+        let mut color = [r, g, b];
+        if let Some(levels) = self.levels {
+            let step = 255.0 / f32::from(levels - 1);
+            color = color.map(|c| ((f32::from(c) / step).round() * step).round() as u8);
+        }
+        if let Some(palette) = &self.palette {
+            let distance = |other: &[u8; 3]| -> i32 {
+                (0..3)
+                    .map(|i| (i32::from(color[i]) - i32::from(other[i])).pow(2))
+                    .sum()
+            };
+            color = *palette.iter().min_by_key(|other| distance(other)).unwrap();
+        }
+        Some(color)
+    }
+}
+
+impl WorldGenerator for PixelArt {
+    fn generate(&self, sandbox: &mut Sandbox, region: Aabb) {
+        let pixel_size = self.scale as f32 * SQUARE_SIZE;
+        for y in 0..self.image.height() {
+            let mut x = 0;
+            while x < self.image.width() {
+                let Some(color) = self.color(x, y) else {
+                    x += 1;
+                    continue;
+                };
+                let start = x;
+                while x < self.image.width() && self.color(x, y) == Some(color) {
+                    x += 1;
+                }
+                let position = meters_to_position([
+                    region.min[0] + start as f32 * pixel_size,
+                    region.min[1],
+                    region.min[2] + y as f32 * pixel_size,
+                ]);
+                sandbox.add_component(
+                    &CircuitBoard::new()
+                        .width((x - start) * self.scale)
+                        .height(self.scale)
+                        .color(color)
+                        .build()
+                        .position(position),
+                );
+            }
+        }
+    }
+}
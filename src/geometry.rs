@@ -0,0 +1,400 @@
+//! Spatial math for component positions and rotations.
+//!
+//! Positions in v6 saves are fixed-point integers relative to the parent
+//! component; rotations are `[x, y, z, w]` quaternions. World-space values in
+//! this module are floating-point meters. [`Position`] and [`Rotation`] wrap
+//! the saved values with conversions to and from those.
+
+pub use crate::v6::UNITS_PER_METER;
+
+/// The width of one grid square on a circuit board, in meters.
+pub const SQUARE_SIZE: f32 = 0.3;
+
+/// The approximate thickness of a circuit board, in meters.
+pub(crate) const BOARD_THICKNESS: f32 = 0.075;
+
+/// Convert a world-space point in meters to a fixed-point position.
+pub(crate) fn meters_to_position(point: [f32; 3]) -> [i32; 3] {
+    point.map(|x| (x * UNITS_PER_METER).round() as i32)
+}
+
+/// Fixed-point position units in one grid square.
+pub const UNITS_PER_SQUARE: i32 = 300;
+
+/// A component position: fixed-point units relative to the parent component,
+/// as stored in v6 saves.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Position(pub [i32; 3]);
+
+impl Position {
+    pub const ORIGIN: Self = Self([0; 3]);
+
+    /// The position closest to a point in meters.
+    pub fn from_meters(point: [f32; 3]) -> Self {
+        Self(meters_to_position(point))
+    }
+
+    pub fn to_meters(self) -> [f32; 3] {
+        self.0.map(|x| x as f32 / UNITS_PER_METER)
+    }
+
+    /// The center of grid square `[x, z]` of a board, at the board's
+    /// surface. Squares are counted from the board's origin corner.
+    pub fn square(x: i32, z: i32) -> Self {
+        let center = |square: i32| square * UNITS_PER_SQUARE + UNITS_PER_SQUARE / 2;
+        Self([center(x), 0, center(z)])
+    }
+
+    /// The grid square that contains this position, along the X and Z axes.
+    pub fn grid_square(self) -> [i32; 2] {
+        let [x, _, z] = self.0;
+        [
+            x.div_euclid(UNITS_PER_SQUARE),
+            z.div_euclid(UNITS_PER_SQUARE),
+        ]
+    }
+
+    /// Move to the center of the grid square that contains this position,
+    /// keeping the height.
+    pub fn snap_to_grid(self) -> Self {
+        let [x, z] = self.grid_square();
+        let [cx, _, cz] = Self::square(x, z).0;
+        Self([cx, self.0[1], cz])
+    }
+
+    /// Move by a whole number of grid squares along the X and Z axes.
+    pub fn step(self, x: i32, z: i32) -> Self {
+        let [px, py, pz] = self.0;
+        Self([px + x * UNITS_PER_SQUARE, py, pz + z * UNITS_PER_SQUARE])
+    }
+
+    /// Move by an offset in fixed-point units.
+    pub fn offset(self, offset: [i32; 3]) -> Self {
+        Self([0, 1, 2].map(|axis| self.0[axis] + offset[axis]))
+    }
+}
+
+impl From<[i32; 3]> for Position {
+    fn from(position: [i32; 3]) -> Self {
+        Self(position)
+    }
+}
+
+impl From<Position> for [i32; 3] {
+    fn from(position: Position) -> Self {
+        position.0
+    }
+}
+
+/// A component rotation: an `[x, y, z, w]` quaternion, as stored in saves.
+///
+/// Angles are in radians, and axes follow the game's coordinate system: Y is
+/// up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rotation(pub [f32; 4]);
+
+impl Rotation {
+    pub const IDENTITY: Self = Self([0.0, 0.0, 0.0, 1.0]);
+
+    /// A rotation by `angle` around `axis`. The axis doesn't need to be
+    /// normalized, but must not be zero.
+    pub fn from_axis_angle(axis: [f32; 3], angle: f32) -> Self {
+        let length = dot(axis, axis).sqrt();
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        let [x, y, z] = axis.map(|a| a / length * sin);
+        Self([x, y, z, cos])
+    }
+
+    /// A rotation from Euler angles around the X, Y and Z axes.
+    ///
+    /// Like Unity's `Quaternion.Euler`, which the game uses, the rotations
+    /// are applied around Z first, then X, then Y.
+    pub fn from_euler(angles: [f32; 3]) -> Self {
+        let [x, y, z] = angles;
+        Self::about_y(y)
+            .then(Self::from_axis_angle([1.0, 0.0, 0.0], x))
+            .then(Self::from_axis_angle([0.0, 0.0, 1.0], z))
+    }
+
+    /// A rotation around the vertical axis, the usual way to turn a
+    /// component on a board.
+    pub fn about_y(angle: f32) -> Self {
+        Self::from_axis_angle([0.0, 1.0, 0.0], angle)
+    }
+
+    /// This rotation followed by `local`, which is relative to it.
+    pub fn then(self, local: Self) -> Self {
+        Self(quat_mul(self.0, local.0))
+    }
+
+    /// Rotate a vector.
+    pub fn apply(self, vector: [f32; 3]) -> [f32; 3] {
+        rotate(self.0, vector)
+    }
+
+    /// Whether two rotations turn things the same way, to within `epsilon`
+    /// per component.
+    ///
+    /// Unlike `==`, this considers a quaternion and its negation equal, since
+    /// they describe the same rotation.
+    pub fn approx_eq(self, other: Self, epsilon: f32) -> bool {
+        let close = |sign: f32| (0..4).all(|i| (self.0[i] - sign * other.0[i]).abs() <= epsilon);
+        close(1.0) || close(-1.0)
+    }
+
+    /// Scale to a unit quaternion, correcting for accumulated rounding.
+    pub fn normalize(self) -> Self {
+        let length = self.0.iter().map(|c| c * c).sum::<f32>().sqrt();
+        Self(self.0.map(|c| c / length))
+    }
+}
+
+impl Default for Rotation {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl From<[f32; 4]> for Rotation {
+    fn from(rotation: [f32; 4]) -> Self {
+        Self(rotation)
+    }
+}
+
+impl From<Rotation> for [f32; 4] {
+    fn from(rotation: Rotation) -> Self {
+        rotation.0
+    }
+}
+
+/// An axis-aligned bounding box in world-space meters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+impl Aabb {
+    pub fn new(min: [f32; 3], max: [f32; 3]) -> Self {
+        Self { min, max }
+    }
+
+    /// The extent of the box along each axis.
+    pub fn size(&self) -> [f32; 3] {
+        [0, 1, 2].map(|axis| self.max[axis] - self.min[axis])
+    }
+
+    pub fn center(&self) -> [f32; 3] {
+        [0, 1, 2].map(|axis| (self.min[axis] + self.max[axis]) / 2.0)
+    }
+
+    /// The same box, moved by `offset`.
+    pub fn translate(&self, offset: [f32; 3]) -> Self {
+        Self {
+            min: [0, 1, 2].map(|axis| self.min[axis] + offset[axis]),
+            max: [0, 1, 2].map(|axis| self.max[axis] + offset[axis]),
+        }
+    }
+
+    pub fn contains(&self, point: [f32; 3]) -> bool {
+        (0..3).all(|axis| self.min[axis] <= point[axis] && point[axis] <= self.max[axis])
+    }
+
+    pub fn intersects(&self, other: &Self) -> bool {
+        (0..3).all(|axis| self.min[axis] <= other.max[axis] && other.min[axis] <= self.max[axis])
+    }
+}
+
+/// An oriented bounding box in world-space meters.
+///
+/// Unlike [`Aabb`], this follows the rotation of the component it bounds, so
+/// rotated boards are bounded tightly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Obb {
+    pub center: [f32; 3],
+    /// The box's local X, Y and Z axes, as unit vectors.
+    pub axes: [[f32; 3]; 3],
+    /// Half the size of the box along each of its axes.
+    pub half_extents: [f32; 3],
+}
+
+impl Obb {
+    /// The box spanning `min..max` in the local space of `transform`.
+    pub fn from_local(transform: &Transform, min: [f32; 3], max: [f32; 3]) -> Self {
+        let local_center = [0, 1, 2].map(|axis| (min[axis] + max[axis]) / 2.0);
+        Self {
+            center: transform.apply(local_center),
+            axes: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]
+                .map(|axis| rotate(transform.rotation, axis)),
+            half_extents: [0, 1, 2].map(|axis| (max[axis] - min[axis]).abs() / 2.0),
+        }
+    }
+
+    /// The eight corners of the box.
+    pub fn corners(&self) -> [[f32; 3]; 8] {
+        let mut corners = [[0.0; 3]; 8];
+        for (i, corner) in corners.iter_mut().enumerate() {
+            let signs = [0, 1, 2].map(|bit| if i & (1 << bit) == 0 { -1.0 } else { 1.0 });
+            *corner = [0, 1, 2].map(|dim| {
+                self.center[dim]
+                    + (0..3)
+                        .map(|axis| signs[axis] * self.half_extents[axis] * self.axes[axis][dim])
+                        .sum::<f32>()
+            });
+        }
+        corners
+    }
+
+    /// The smallest axis-aligned box containing this one.
+    pub fn aabb(&self) -> Aabb {
+        let extent = [0, 1, 2].map(|dim| {
+            (0..3)
+                .map(|axis| self.half_extents[axis] * self.axes[axis][dim].abs())
+                .sum::<f32>()
+        });
+        Aabb {
+            min: [0, 1, 2].map(|dim| self.center[dim] - extent[dim]),
+            max: [0, 1, 2].map(|dim| self.center[dim] + extent[dim]),
+        }
+    }
+
+    /// Whether the two boxes overlap by more than `tolerance` meters.
+    ///
+    /// Boxes that merely touch, like a board placed on top of another, are
+    /// not considered to overlap as long as `tolerance` is positive.
+    pub fn intersects(&self, other: &Self, tolerance: f32) -> bool {
+        let offset = sub(other.center, self.center);
+        let mut axes = Vec::with_capacity(15);
+        axes.extend(self.axes);
+        axes.extend(other.axes);
+        for a in self.axes {
+            for b in other.axes {
+                let axis = cross(a, b);
+                // Parallel edges don't produce a separating axis.
+                if dot(axis, axis) > 1e-6 {
+                    axes.push(axis);
+                }
+            }
+        }
+        axes.into_iter().all(|axis| {
+            let length = dot(axis, axis).sqrt();
+            let radius = |obb: &Self| {
+                (0..3)
+                    .map(|i| obb.half_extents[i] * dot(obb.axes[i], axis).abs())
+                    .sum::<f32>()
+            };
+            let distance = dot(offset, axis).abs();
+            (radius(self) + radius(other) - distance) / length > tolerance
+        })
+    }
+}
+
+/// A rigid transform: a rotation followed by a translation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    /// Translation in meters.
+    pub translation: [f32; 3],
+    /// Rotation quaternion, `[x, y, z, w]`.
+    pub rotation: [f32; 4],
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        translation: [0.0; 3],
+        rotation: [0.0, 0.0, 0.0, 1.0],
+    };
+
+    /// The transform described by a component's saved position and rotation,
+    /// relative to its parent.
+    pub fn from_local(position: [i32; 3], rotation: [f32; 4]) -> Self {
+        Self {
+            translation: position.map(|x| x as f32 / UNITS_PER_METER),
+            rotation,
+        }
+    }
+
+    /// Compose with a transform that is relative to this one, e.g. a child
+    /// component relative to its parent.
+    pub fn then(&self, local: &Self) -> Self {
+        Self {
+            translation: self.apply(local.translation),
+            rotation: quat_mul(self.rotation, local.rotation),
+        }
+    }
+
+    /// Transform a point into the outer coordinate space.
+    pub fn apply(&self, point: [f32; 3]) -> [f32; 3] {
+        let [x, y, z] = rotate(self.rotation, point);
+        let [tx, ty, tz] = self.translation;
+        [x + tx, y + ty, z + tz]
+    }
+
+    /// The transform that undoes this one. The rotation must be a unit
+    /// quaternion, as saved rotations are.
+    pub fn inverse(&self) -> Self {
+        let [x, y, z, w] = self.rotation;
+        let rotation = [-x, -y, -z, w];
+        Self {
+            translation: rotate(rotation, self.translation).map(|t| -t),
+            rotation,
+        }
+    }
+
+    /// Transform a point from the outer coordinate space into this one; the
+    /// opposite of [`apply`](Self::apply).
+    pub fn unapply(&self, point: [f32; 3]) -> [f32; 3] {
+        self.inverse().apply(point)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// Hamilton product of two `[x, y, z, w]` quaternions.
+pub(crate) fn quat_mul(a: [f32; 4], b: [f32; 4]) -> [f32; 4] {
+    let [ax, ay, az, aw] = a;
+    let [bx, by, bz, bw] = b;
+    [
+        aw * bx + ax * bw + ay * bz - az * by,
+        aw * by - ax * bz + ay * bw + az * bx,
+        aw * bz + ax * by - ay * bx + az * bw,
+        aw * bw - ax * bx - ay * by - az * bz,
+    ]
+}
+
+/// Rotate a vector by an `[x, y, z, w]` quaternion.
+pub(crate) fn rotate(q: [f32; 4], v: [f32; 3]) -> [f32; 3] {
+    let [qx, qy, qz, qw] = q;
+    let [vx, vy, vz] = v;
+    // t = 2 * cross(q.xyz, v)
+    let tx = 2.0 * (qy * vz - qz * vy);
+    let ty = 2.0 * (qz * vx - qx * vz);
+    let tz = 2.0 * (qx * vy - qy * vx);
+    // v + w * t + cross(q.xyz, t)
+    [
+        vx + qw * tx + (qy * tz - qz * ty),
+        vy + qw * ty + (qz * tx - qx * tz),
+        vz + qw * tz + (qx * ty - qy * tx),
+    ]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
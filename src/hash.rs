@@ -0,0 +1,215 @@
+//! Hashing the logical content of a save.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::latest::{BlotterFile, CircuitStates, PegType};
+use crate::sandbox::Sandbox;
+
+/// A 64-bit FNV-1a hasher. Unlike the standard library's hashers, its output
+/// is fixed, so hashes can be stored and compared between runs.
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+        self
+    }
+
+    fn u64(&mut self, value: u64) -> &mut Self {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    /// Variable-length data is prefixed with its length, so that adjacent
+    /// fields can't run into each other.
+    fn str(&mut self, s: &[u8]) -> &mut Self {
+        self.u64(s.len() as u64).bytes(s)
+    }
+
+    fn f32(&mut self, value: f32) -> &mut Self {
+        // Treat negative zero the same as zero.
+        let value = if value == 0.0 { 0.0 } else { value };
+        self.bytes(&value.to_bits().to_le_bytes())
+    }
+
+    /// Hash a multiset of hashes, regardless of their order.
+    fn unordered(&mut self, mut hashes: Vec<u64>) -> &mut Self {
+        hashes.sort_unstable();
+        self.u64(hashes.len() as u64);
+        for hash in hashes {
+            self.u64(hash);
+        }
+        self
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl BlotterFile {
+    /// A hash of what the save contains, that doesn't depend on how it
+    /// happens to be stored.
+    ///
+    /// The order of components, wires, mods and component types doesn't
+    /// matter, and neither do component addresses, numeric type IDs or
+    /// circuit state IDs; only which pegs are connected and which are on.
+    /// The game version is left out, so re-saving a world in a newer version
+    /// of the game keeps its hash.
+    ///
+    /// The hash is stable between runs and versions of this crate, unless
+    /// noted in the changelog.
+    pub fn content_hash(&self) -> u64 {
+        let type_names: HashMap<u16, &str> = self
+            .component_types
+            .iter()
+            .map(|ctype| (ctype.numeric_id, ctype.text_id.as_str()))
+            .collect();
+        let on_states: HashSet<i32> = match &self.circuit_states {
+            CircuitStates::WorldFormat { .. } => HashSet::new(),
+            CircuitStates::SubassemblyFormat { on_states } => on_states.iter().copied().collect(),
+        };
+        let is_on = |id: i32| match &self.circuit_states {
+            CircuitStates::WorldFormat { circuit_states } => usize::try_from(id)
+                .ok()
+                .and_then(|id| {
+                    circuit_states
+                        .get(id / 8)
+                        .map(|byte| byte & (1 << (id % 8)))
+                })
+                .is_some_and(|bit| bit != 0),
+            CircuitStates::SubassemblyFormat { .. } => on_states.contains(&id),
+        };
+
+        // Each component is identified by its own content and its parent's
+        // identity, so that it doesn't depend on its address.
+        let by_address: HashMap<u32, usize> = self
+            .components
+            .iter()
+            .enumerate()
+            .map(|(index, component)| (component.address, index))
+            .collect();
+        let mut identities: Vec<Option<u64>> = vec![None; self.components.len()];
+        for start in 0..self.components.len() {
+            // Walk up to the first ancestor that has an identity already.
+            let mut chain = Vec::new();
+            let mut current = Some(start);
+            while let Some(index) = current {
+                if identities[index].is_some() || chain.len() > self.components.len() {
+                    break;
+                }
+                chain.push(index);
+                let parent = self.components[index].parent;
+                current = (parent != 0)
+                    .then(|| by_address.get(&parent).copied())
+                    .flatten();
+            }
+            let mut parent_identity = current.and_then(|index| identities[index]).unwrap_or(0);
+            for &index in chain.iter().rev() {
+                let component = &self.components[index];
+                let mut hasher = Fnv::new();
+                hasher.u64(parent_identity);
+                match type_names.get(&component.type_id) {
+                    Some(name) => hasher.str(name.as_bytes()),
+                    None => hasher.str(b"").u64(component.type_id.into()),
+                };
+                for x in component.position {
+                    hasher.bytes(&x.to_le_bytes());
+                }
+                for x in component.rotation {
+                    hasher.f32(x);
+                }
+                hasher.u64(component.inputs.len() as u64);
+                for peg in &component.inputs {
+                    hasher.bytes(&[is_on(peg.circuit_state_id) as u8]);
+                }
+                hasher.u64(component.outputs.len() as u64);
+                for peg in &component.outputs {
+                    hasher.bytes(&[is_on(peg.circuit_state_id) as u8]);
+                }
+                match &component.custom_data {
+                    Some(data) => hasher.bytes(&[1]).str(data),
+                    None => hasher.bytes(&[0]),
+                };
+                parent_identity = hasher.finish();
+                identities[index] = Some(parent_identity);
+            }
+        }
+
+        let peg_key = |addr: &crate::latest::PegAddress| {
+            let identity = by_address
+                .get(&addr.component_address)
+                .and_then(|&index| identities[index])
+                .unwrap_or(0);
+            Fnv::new()
+                .u64(identity)
+                .bytes(&[(addr.peg_type == PegType::Output) as u8])
+                .bytes(&addr.peg_index.to_le_bytes())
+                .finish()
+        };
+        let wires = self
+            .wires
+            .iter()
+            .map(|wire| {
+                let a = peg_key(&wire.start_peg);
+                let b = peg_key(&wire.end_peg);
+                Fnv::new()
+                    .u64(a.min(b))
+                    .u64(a.max(b))
+                    .f32(wire.rotation)
+                    .bytes(&[is_on(wire.circuit_state_id) as u8])
+                    .finish()
+            })
+            .collect();
+
+        let mods = self
+            .mods
+            .iter()
+            .map(|mod_info| {
+                let mut hasher = Fnv::new();
+                hasher.str(mod_info.mod_id.as_bytes());
+                for x in mod_info.mod_version {
+                    hasher.bytes(&x.to_le_bytes());
+                }
+                hasher.finish()
+            })
+            .collect();
+
+        Fnv::new()
+            .bytes(&[self.save_type as u8])
+            .unordered(mods)
+            .unordered(identities.into_iter().flatten().collect())
+            .unordered(wires)
+            .finish()
+    }
+}
+
+impl Sandbox {
+    /// A hash of the sandbox's content; the same as the
+    /// [`content_hash`](BlotterFile::content_hash) of the file it would be
+    /// saved as.
+    ///
+    /// ```
+    /// use blotter::sandbox::{ComponentBuilder, Sandbox};
+    ///
+    /// let mut a = Sandbox::new();
+    /// let mut b = Sandbox::new();
+    /// let inverter = a.add_component(&ComponentBuilder::new("MHG.Inverter"));
+    /// a.add_component(&ComponentBuilder::new("MHG.Peg"));
+    /// b.add_component(&ComponentBuilder::new("MHG.Peg"));
+    /// b.add_component(&ComponentBuilder::new("MHG.Inverter"));
+    /// assert_eq!(a.content_hash(), b.content_hash());
+    ///
+    /// a.remove_component(inverter);
+    /// assert_ne!(a.content_hash(), b.content_hash());
+    /// ```
+    pub fn content_hash(&self) -> u64 {
+        BlotterFile::from(self).content_hash()
+    }
+}
@@ -0,0 +1,693 @@
+//! Import of gate-level netlists in the Berkeley Logic Interchange Format.
+//!
+//! BLIF is written by most open-source synthesis tools, like Yosys and ABC.
+//! The supported subset is `.model`, `.inputs`, `.outputs`, `.clock`,
+//! `.names`, `.latch`, `.subckt` and `.end`; technology-mapped `.gate` cells
+//! and don't-care networks are not.
+//!
+//! Each `.names` cover is built as a sum of products: inverters for
+//! complemented literals, chains of AND gates for cubes, and outputs wired
+//! together for the OR of the cubes. Every net is a peg, with its driver
+//! wired into it and the pegs that read it wired out of it. Edge-triggered
+//! latches become a pair of D latches; level-sensitive ones a single D latch,
+//! with the data on its first input and the enable on its second. Initial
+//! latch values are ignored, and every circuit state starts off.
+//!
+//! Every instance of a model gets a circuit board of its own, holding its
+//! gates in a grid. The boards are placed in a row along the X axis, starting
+//! at the world origin.
+//!
+//! ```
+//! use blotter::import::blif::read_blif;
+//! use blotter::sandbox::{PegType, Sandbox};
+//!
+//! let source = "
+//! .model majority
+//! .inputs a b c
+//! .outputs y
+//! .names a b c y
+//! 11- 1
+//! 1-1 1
+//! -11 1
+//! .end
+//! ";
+//! let mut sandbox = Sandbox::new();
+//! let ports = read_blif(&mut sandbox, &mut source.as_bytes()).unwrap();
+//! assert_eq!(ports.len(), 4);
+//! assert_eq!(ports.get("y").unwrap().peg_type, PegType::Input);
+//! assert_eq!(sandbox.components_of_type("MHG.AndGate").count(), 3);
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+
+use crate::custom_data::{self, ComponentData};
+use crate::error::Error;
+use crate::geometry::{Position, UNITS_PER_SQUARE};
+use crate::sandbox::component::{CircuitBoard, Peg};
+use crate::sandbox::{ComponentBuilder, ComponentId, PegAddress, PegType, Ports, Sandbox};
+
+/// An error in a BLIF netlist, with the line it was found on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlifError {
+    /// The line number, counting from 1.
+    pub line: usize,
+    pub kind: BlifErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BlifErrorKind {
+    /// The line doesn't have the form its directive requires.
+    Syntax,
+    /// The directive isn't in the supported subset of BLIF.
+    UnknownDirective(String),
+    /// A `.subckt` names a model that isn't defined.
+    UnknownModel(String),
+    /// A `.subckt` connects a signal that isn't an input or output of the
+    /// model.
+    UnknownPort { model: String, port: String },
+    /// A net is driven by more than one cell, or is also a model input.
+    MultipleDrivers(String),
+    /// A latch is clocked by the global clock, or asynchronously, which has
+    /// no equivalent in the game.
+    UnsupportedLatch,
+    /// A model contains an instance of itself.
+    RecursiveModel(String),
+}
+
+impl From<BlifError> for Error {
+    fn from(error: BlifError) -> Self {
+        Self::Blif(error)
+    }
+}
+
+/// Read a BLIF netlist and build it in the sandbox.
+///
+/// The first model in the file is the top-level one. Returns the peg of each
+/// of its inputs and outputs, by signal name.
+///
+/// The whole netlist is checked before anything is built, so if an error is
+/// returned, the sandbox is unchanged.
+pub fn read_blif<R: Read>(sandbox: &mut Sandbox, reader: &mut R) -> Result<Ports, Error> {
+    let mut source = String::new();
+    reader.read_to_string(&mut source)?;
+    let models = parse(&source)?;
+    let Some(top) = models.first() else {
+        return Ok(Ports::new());
+    };
+    let by_name: HashMap<&str, &Model> = models
+        .iter()
+        .map(|model| (model.name.as_str(), model))
+        .collect();
+    for model in &models {
+        check(model, &by_name)?;
+    }
+    check_recursion(top, &by_name, &mut Vec::new())?;
+
+    let mut builder = Builder {
+        sandbox,
+        models: &by_name,
+        boards: Vec::new(),
+        wires: Vec::new(),
+    };
+    let nets = builder.instantiate(top, HashMap::new());
+
+    let mut x = 0;
+    for (board, width) in builder.boards {
+        builder
+            .sandbox
+            .component_mut(board)
+            .unwrap()
+            .set_position([x * UNITS_PER_SQUARE, 0, 0]);
+        x += width as i32 + 1;
+    }
+    builder
+        .sandbox
+        .add_wires(builder.wires)
+        .expect("imported wires should connect existing pegs");
+
+    Ok(top
+        .inputs
+        .iter()
+        .chain(&top.outputs)
+        .map(|name| (name.clone(), nets[name]))
+        .collect())
+}
+
+struct Model {
+    name: String,
+    line: usize,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    cells: Vec<Cell>,
+}
+
+struct Cell {
+    line: usize,
+    kind: CellKind,
+}
+
+enum CellKind {
+    /// A single-output cover: each row is a cube over the inputs, with
+    /// `None` for don't-care, and `value` is the output of every row.
+    Names {
+        inputs: Vec<String>,
+        output: String,
+        rows: Vec<Vec<Option<bool>>>,
+        value: bool,
+    },
+    Latch {
+        input: String,
+        output: String,
+        control: LatchControl,
+        clock: String,
+    },
+    Subckt {
+        model: String,
+        connections: Vec<(String, String)>,
+    },
+}
+
+#[derive(Clone, Copy)]
+enum LatchControl {
+    RisingEdge,
+    FallingEdge,
+    ActiveHigh,
+    ActiveLow,
+}
+
+fn parse(source: &str) -> Result<Vec<Model>, BlifError> {
+    let mut models: Vec<Model> = Vec::new();
+    let mut in_model = false;
+    let mut in_cover = false;
+
+    let mut lines = source.lines().enumerate();
+    while let Some((index, line)) = lines.next() {
+        let line_number = index + 1;
+        let error = |kind| BlifError {
+            line: line_number,
+            kind,
+        };
+
+        // Strip comments and join continued lines.
+        let mut text = String::new();
+        let mut current = line;
+        loop {
+            let current_text = current.split('#').next().unwrap();
+            match current_text.trim_end().strip_suffix('\\') {
+                Some(continued) => {
+                    text.push_str(continued);
+                    text.push(' ');
+                    match lines.next() {
+                        Some((_, next)) => current = next,
+                        None => break,
+                    }
+                }
+                None => {
+                    text.push_str(current_text);
+                    break;
+                }
+            }
+        }
+        let tokens: Vec<&str> = text.split_whitespace().collect();
+        let Some((&directive, args)) = tokens.split_first() else {
+            continue;
+        };
+
+        if !directive.starts_with('.') {
+            // A row of the cover of the last `.names`.
+            let cell = models
+                .last_mut()
+                .filter(|_| in_cover)
+                .and_then(|model| model.cells.last_mut());
+            let Some(Cell {
+                kind:
+                    CellKind::Names {
+                        inputs,
+                        rows,
+                        value,
+                        ..
+                    },
+                ..
+            }) = cell
+            else {
+                return Err(error(BlifErrorKind::Syntax));
+            };
+            let (cube, output) = match tokens[..] {
+                [output] if inputs.is_empty() => ("", output),
+                [cube, output] => (cube, output),
+                _ => return Err(error(BlifErrorKind::Syntax)),
+            };
+            let output = match output {
+                "0" => false,
+                "1" => true,
+                _ => return Err(error(BlifErrorKind::Syntax)),
+            };
+            if !rows.is_empty() && output != *value {
+                return Err(error(BlifErrorKind::Syntax));
+            }
+            *value = output;
+            let cube: Option<Vec<Option<bool>>> = cube
+                .chars()
+                .map(|c| match c {
+                    '0' => Some(Some(false)),
+                    '1' => Some(Some(true)),
+                    '-' => Some(None),
+                    _ => None,
+                })
+                .collect();
+            match cube {
+                Some(cube) if cube.len() == inputs.len() => rows.push(cube),
+                _ => return Err(error(BlifErrorKind::Syntax)),
+            }
+            continue;
+        }
+        in_cover = false;
+
+        if directive == ".model" {
+            let [name] = args else {
+                return Err(error(BlifErrorKind::Syntax));
+            };
+            models.push(Model {
+                name: name.to_string(),
+                line: line_number,
+                inputs: Vec::new(),
+                outputs: Vec::new(),
+                cells: Vec::new(),
+            });
+            in_model = true;
+            continue;
+        }
+        let model = match models.last_mut() {
+            Some(model) if in_model => model,
+            _ => return Err(error(BlifErrorKind::Syntax)),
+        };
+        let strings = |args: &[&str]| args.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let kind = match directive {
+            ".inputs" | ".clock" => {
+                model.inputs.extend(strings(args));
+                continue;
+            }
+            ".outputs" => {
+                model.outputs.extend(strings(args));
+                continue;
+            }
+            ".end" => {
+                in_model = false;
+                continue;
+            }
+            ".names" => {
+                let Some((output, inputs)) = args.split_last() else {
+                    return Err(error(BlifErrorKind::Syntax));
+                };
+                in_cover = true;
+                CellKind::Names {
+                    inputs: strings(inputs),
+                    output: output.to_string(),
+                    rows: Vec::new(),
+                    value: true,
+                }
+            }
+            ".latch" => {
+                let (input, output, control, clock) = match *args {
+                    [input, output, control, clock] | [input, output, control, clock, _] => {
+                        (input, output, control, clock)
+                    }
+                    [_, _] | [_, _, _] => return Err(error(BlifErrorKind::UnsupportedLatch)),
+                    _ => return Err(error(BlifErrorKind::Syntax)),
+                };
+                let control = match control {
+                    "re" => LatchControl::RisingEdge,
+                    "fe" => LatchControl::FallingEdge,
+                    "ah" => LatchControl::ActiveHigh,
+                    "al" => LatchControl::ActiveLow,
+                    "as" => return Err(error(BlifErrorKind::UnsupportedLatch)),
+                    _ => return Err(error(BlifErrorKind::Syntax)),
+                };
+                if clock == "NIL" {
+                    return Err(error(BlifErrorKind::UnsupportedLatch));
+                }
+                CellKind::Latch {
+                    input: input.to_string(),
+                    output: output.to_string(),
+                    control,
+                    clock: clock.to_string(),
+                }
+            }
+            ".subckt" => {
+                let Some((name, connections)) = args.split_first() else {
+                    return Err(error(BlifErrorKind::Syntax));
+                };
+                let connections = connections
+                    .iter()
+                    .map(|connection| {
+                        connection
+                            .split_once('=')
+                            .map(|(formal, actual)| (formal.to_string(), actual.to_string()))
+                    })
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or(error(BlifErrorKind::Syntax))?;
+                CellKind::Subckt {
+                    model: name.to_string(),
+                    connections,
+                }
+            }
+            _ => {
+                return Err(error(BlifErrorKind::UnknownDirective(
+                    directive.to_string(),
+                )))
+            }
+        };
+        model.cells.push(Cell {
+            line: line_number,
+            kind,
+        });
+    }
+    Ok(models)
+}
+
+/// Check that a model's subcircuits exist and that every net has at most one
+/// driver.
+fn check(model: &Model, models: &HashMap<&str, &Model>) -> Result<(), BlifError> {
+    let mut driven = HashSet::new();
+    let mut drive = |net: &str, line: usize| {
+        if driven.insert(net.to_owned()) {
+            Ok(())
+        } else {
+            Err(BlifError {
+                line,
+                kind: BlifErrorKind::MultipleDrivers(net.to_owned()),
+            })
+        }
+    };
+    for input in &model.inputs {
+        drive(input, model.line)?;
+    }
+    for cell in &model.cells {
+        match &cell.kind {
+            CellKind::Names { output, .. } | CellKind::Latch { output, .. } => {
+                drive(output, cell.line)?
+            }
+            CellKind::Subckt {
+                model: name,
+                connections,
+            } => {
+                let child = models.get(name.as_str()).ok_or(BlifError {
+                    line: cell.line,
+                    kind: BlifErrorKind::UnknownModel(name.clone()),
+                })?;
+                for (formal, actual) in connections {
+                    if child.outputs.contains(formal) {
+                        drive(actual, cell.line)?;
+                    } else if !child.inputs.contains(formal) {
+                        return Err(BlifError {
+                            line: cell.line,
+                            kind: BlifErrorKind::UnknownPort {
+                                model: name.clone(),
+                                port: formal.clone(),
+                            },
+                        });
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_recursion<'a>(
+    model: &'a Model,
+    models: &HashMap<&str, &'a Model>,
+    stack: &mut Vec<&'a str>,
+) -> Result<(), BlifError> {
+    stack.push(&model.name);
+    for cell in &model.cells {
+        if let CellKind::Subckt { model: name, .. } = &cell.kind {
+            if stack.contains(&name.as_str()) {
+                return Err(BlifError {
+                    line: cell.line,
+                    kind: BlifErrorKind::RecursiveModel(name.clone()),
+                });
+            }
+            check_recursion(models[name.as_str()], models, stack)?;
+        }
+    }
+    stack.pop();
+    Ok(())
+}
+
+struct Builder<'a, 'm> {
+    sandbox: &'a mut Sandbox,
+    models: &'a HashMap<&'m str, &'m Model>,
+    /// Every instance board, with its width in squares, in creation order.
+    boards: Vec<(ComponentId, u32)>,
+    wires: Vec<(PegAddress, PegAddress, f32)>,
+}
+
+/// The components of one instance of a model, while it is being built.
+struct Instance {
+    board: ComponentId,
+    components: Vec<ComponentId>,
+    nets: HashMap<String, PegAddress>,
+    inverted: HashMap<PegAddress, PegAddress>,
+    one: Option<PegAddress>,
+}
+
+impl Builder<'_, '_> {
+    /// Build an instance of a model, with some of its nets bound to pegs of
+    /// the parent instance, and return the pegs of all of its nets.
+    fn instantiate(
+        &mut self,
+        model: &Model,
+        bindings: HashMap<String, PegAddress>,
+    ) -> HashMap<String, PegAddress> {
+        let board = self.sandbox.add_component(&CircuitBoard::new().build());
+        let mut instance = Instance {
+            board,
+            components: Vec::new(),
+            nets: bindings,
+            inverted: HashMap::new(),
+            one: None,
+        };
+        let board_index = self.boards.len();
+        self.boards.push((board, 1));
+
+        for name in model.inputs.iter().chain(&model.outputs) {
+            self.net(&mut instance, name);
+        }
+        for cell in &model.cells {
+            match &cell.kind {
+                CellKind::Names {
+                    inputs,
+                    output,
+                    rows,
+                    value,
+                } => {
+                    let output = self.net(&mut instance, output);
+                    let inputs: Vec<PegAddress> = inputs
+                        .iter()
+                        .map(|name| self.net(&mut instance, name))
+                        .collect();
+                    self.cover(&mut instance, &inputs, rows, *value, output);
+                }
+                CellKind::Latch {
+                    input,
+                    output,
+                    control,
+                    clock,
+                } => {
+                    let input = self.net(&mut instance, input);
+                    let output = self.net(&mut instance, output);
+                    let clock = self.net(&mut instance, clock);
+                    let q = match control {
+                        LatchControl::ActiveHigh => self.latch(&mut instance, input, clock),
+                        LatchControl::ActiveLow => {
+                            let enable = self.invert(&mut instance, clock);
+                            self.latch(&mut instance, input, enable)
+                        }
+                        LatchControl::RisingEdge | LatchControl::FallingEdge => {
+                            let inverted = self.invert(&mut instance, clock);
+                            let (master, slave) = match control {
+                                LatchControl::RisingEdge => (inverted, clock),
+                                _ => (clock, inverted),
+                            };
+                            let master = self.latch(&mut instance, input, master);
+                            self.latch(&mut instance, master, slave)
+                        }
+                    };
+                    self.wires.push((q, output, 0.0));
+                }
+                CellKind::Subckt {
+                    model: name,
+                    connections,
+                } => {
+                    let bindings = connections
+                        .iter()
+                        .map(|(formal, actual)| (formal.clone(), self.net(&mut instance, actual)))
+                        .collect();
+                    let child = self.models[name.as_str()];
+                    self.instantiate(child, bindings);
+                }
+            }
+        }
+
+        // Lay the components out in a square grid, and fit the board to it.
+        let count = instance.components.len().max(1) as u32;
+        let width = (count as f32).sqrt().ceil() as u32;
+        let height = count.div_ceil(width);
+        for (index, &id) in instance.components.iter().enumerate() {
+            let index = index as i32;
+            self.sandbox
+                .component_mut(id)
+                .unwrap()
+                .set_position(Position::square(index % width as i32, index / width as i32));
+        }
+        let mut data = Vec::new();
+        custom_data::CircuitBoard {
+            color: [0x78, 0x78, 0x78],
+            size_x: width,
+            size_z: height,
+        }
+        .write(&mut data)
+        .unwrap();
+        self.sandbox
+            .component_mut(board)
+            .unwrap()
+            .set_custom_data(Some(data));
+        self.boards[board_index].1 = width;
+
+        instance.nets
+    }
+
+    /// Add a component to the instance's board.
+    fn add(&mut self, instance: &mut Instance, builder: ComponentBuilder) -> ComponentId {
+        let id = self
+            .sandbox
+            .add_component(&builder.parent(Some(instance.board)));
+        instance.components.push(id);
+        id
+    }
+
+    /// Add a gate, wire its inputs, and return its output.
+    fn gate(
+        &mut self,
+        instance: &mut Instance,
+        type_name: &str,
+        inputs: &[PegAddress],
+    ) -> PegAddress {
+        let id = self.add(
+            instance,
+            ComponentBuilder::new(type_name)
+                .num_inputs(inputs.len() as u32)
+                .num_outputs(1),
+        );
+        for (peg_index, &input) in inputs.iter().enumerate() {
+            self.wires
+                .push((input, peg(id, PegType::Input, peg_index), 0.0));
+        }
+        peg(id, PegType::Output, 0)
+    }
+
+    /// The peg of a net, adding it if it doesn't exist yet.
+    fn net(&mut self, instance: &mut Instance, name: &str) -> PegAddress {
+        if let Some(&peg) = instance.nets.get(name) {
+            return peg;
+        }
+        let id = self.add(instance, Peg::new().build());
+        let peg = peg(id, PegType::Input, 0);
+        instance.nets.insert(name.to_owned(), peg);
+        peg
+    }
+
+    fn invert(&mut self, instance: &mut Instance, signal: PegAddress) -> PegAddress {
+        if let Some(&inverted) = instance.inverted.get(&signal) {
+            return inverted;
+        }
+        let inverted = self.gate(instance, "MHG.Inverter", &[signal]);
+        instance.inverted.insert(signal, inverted);
+        inverted
+    }
+
+    /// An output that is always on: an inverter with nothing on its input.
+    fn one(&mut self, instance: &mut Instance) -> PegAddress {
+        if let Some(one) = instance.one {
+            return one;
+        }
+        let one = self.gate(instance, "MHG.Inverter", &[]);
+        instance.one = Some(one);
+        one
+    }
+
+    fn latch(
+        &mut self,
+        instance: &mut Instance,
+        data: PegAddress,
+        enable: PegAddress,
+    ) -> PegAddress {
+        self.gate(instance, "MHG.DLatch", &[data, enable])
+    }
+
+    /// Build a sum of products, and wire it to drive `output`.
+    fn cover(
+        &mut self,
+        instance: &mut Instance,
+        inputs: &[PegAddress],
+        rows: &[Vec<Option<bool>>],
+        value: bool,
+        output: PegAddress,
+    ) {
+        if rows.is_empty() {
+            // The constant 0, which an undriven net already is.
+            return;
+        }
+        let mut cubes = Vec::new();
+        for row in rows {
+            let mut literals = Vec::new();
+            for (&input, &literal) in inputs.iter().zip(row) {
+                match literal {
+                    Some(true) => literals.push(input),
+                    Some(false) => literals.push(self.invert(instance, input)),
+                    None => {}
+                }
+            }
+            let cube = match literals.split_first() {
+                None => self.one(instance),
+                Some((&first, rest)) => rest.iter().fold(first, |acc, &literal| {
+                    self.gate(instance, "MHG.AndGate", &[acc, literal])
+                }),
+            };
+            cubes.push(cube);
+        }
+
+        let mut result = if let [cube] = cubes[..] {
+            cube
+        } else {
+            // Outputs wired to the same input are ORed together. Cubes that
+            // are a bare input need a buffer, or wiring them together would
+            // join their nets instead.
+            let or = self.add(instance, Peg::new().build());
+            let or = peg(or, PegType::Input, 0);
+            for cube in cubes {
+                let cube = match cube.peg_type {
+                    PegType::Output => cube,
+                    PegType::Input => self.gate(instance, "MHG.Buffer", &[cube]),
+                };
+                self.wires.push((cube, or, 0.0));
+            }
+            or
+        };
+        if !value {
+            result = self.invert(instance, result);
+        }
+        self.wires.push((result, output, 0.0));
+    }
+}
+
+fn peg(component: ComponentId, peg_type: PegType, peg_index: usize) -> PegAddress {
+    PegAddress {
+        component,
+        peg_type,
+        peg_index,
+    }
+}
@@ -0,0 +1,3 @@
+//! Importers from formats used by other tools.
+
+pub mod blif;
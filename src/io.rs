@@ -1,24 +1,139 @@
 use crate::error::Error;
-use std::{
-    io::{Read, Write},
-    iter::repeat_with,
-};
+use crate::limits::{Limit, ReadLimits};
+use alloc::{string::String, vec, vec::Vec};
+use core::mem::size_of;
 
+#[cfg(feature = "std")]
+pub use std::io::{Read, Write};
+
+/// What a [`Read`] or [`Write`] implementation fails with.
+#[cfg(feature = "std")]
+pub type IoError = std::io::Error;
+
+#[cfg(not(feature = "std"))]
+pub type IoError = Error;
+
+/// A source of bytes, like `std::io::Read`, for builds without `std`.
+#[cfg(not(feature = "std"))]
+pub trait Read {
+    /// Read some bytes into `buf`, returning how many were read. Returns 0
+    /// only at the end of the data, or if `buf` is empty.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error>;
+
+    /// Fill `buf`, failing with [`Error::UnexpectedEof`] if the data ends
+    /// first.
+    fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<(), Error> {
+        while !buf.is_empty() {
+            match self.read(buf)? {
+                0 => return Err(Error::UnexpectedEof),
+                len => buf = &mut buf[len..],
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<R: Read + ?Sized> Read for &mut R {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        (**self).read(buf)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        (**self).read_exact(buf)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Read for &[u8] {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let len = buf.len().min(self.len());
+        let (head, tail) = self.split_at(len);
+        buf[..len].copy_from_slice(head);
+        *self = tail;
+        Ok(len)
+    }
+}
+
+/// A sink for bytes, like `std::io::Write`, for builds without `std`.
+#[cfg(not(feature = "std"))]
+pub trait Write {
+    /// Write all of `buf`.
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), Error>;
+}
+
+#[cfg(not(feature = "std"))]
+impl<W: Write + ?Sized> Write for &mut W {
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), Error> {
+        (**self).write_all(buf)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Write for Vec<u8> {
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), Error> {
+        self.extend_from_slice(buf);
+        Ok(())
+    }
+}
+
+/// The error for data that ends in the middle of an item.
+#[cfg(feature = "std")]
+pub(crate) fn unexpected_eof() -> IoError {
+    std::io::Error::from(std::io::ErrorKind::UnexpectedEof)
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn unexpected_eof() -> IoError {
+    Error::UnexpectedEof
+}
+
+/// Whether `error` is a read that was interrupted and can be retried.
+#[cfg(feature = "std")]
+fn is_interrupted(error: &IoError) -> bool {
+    error.kind() == std::io::ErrorKind::Interrupted
+}
+
+#[cfg(not(feature = "std"))]
+fn is_interrupted(_error: &IoError) -> bool {
+    false
+}
+
+/// Whether `error` is from data that ended in the middle of an item.
+#[cfg(feature = "std")]
+fn is_eof(error: &IoError) -> bool {
+    error.kind() == std::io::ErrorKind::UnexpectedEof
+}
+
+#[cfg(not(feature = "std"))]
+fn is_eof(error: &IoError) -> bool {
+    matches!(error, Error::UnexpectedEof)
+}
+
+/// A type that can be read from a save on its own.
 pub trait ReadFrom: Sized {
     fn read_from<R: Read>(reader: &mut R) -> Result<Self, Error>;
 }
 
+/// A type that needs more information to be read, like the length of a list
+/// that is stored elsewhere.
 pub trait ReadFromSeed<Seed>: Sized {
     fn read_from_seed<R: Read>(reader: &mut R, seed: Seed) -> Result<Self, Error>;
 }
 
 impl<T: ReadFrom> ReadFromSeed<()> for T {
-    fn read_from_seed<R: Read>(reader: &mut R, seed: ()) -> Result<Self, Error> {
-        let _ = seed;
+    fn read_from_seed<R: Read>(reader: &mut R, _seed: ()) -> Result<Self, Error> {
         Self::read_from(reader)
     }
 }
 
+/// A type that always takes the same number of bytes in a save, so that
+/// lists of it can be read in chunks instead of one field at a time.
+pub trait FixedSize: ReadFrom {
+    const SIZE: usize;
+}
+
+/// A type that can be written to a save.
 pub trait WriteTo {
     fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), Error>;
 }
@@ -39,15 +154,107 @@ macro_rules! primitive_io {
                 Ok(())
             }
         }
+
+        impl FixedSize for $t {
+            const SIZE: usize = $len;
+        }
     )*};
 }
 
 primitive_io! {
     u8: 1,
+    i8: 1,
     u16: 2,
+    i16: 2,
     i32: 4,
     u32: 4,
+    u64: 8,
+    i64: 8,
     f32: 4,
+    f64: 8,
+}
+
+/// A single byte; anything other than 0 reads as `true`.
+impl ReadFrom for bool {
+    fn read_from<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        u8::read_from(reader).map(|x| x != 0)
+    }
+}
+
+impl WriteTo for bool {
+    fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        u8::from(*self).write_to(writer)
+    }
+}
+
+/// An integer in the variable-length encoding of .NET's `BinaryWriter`: seven
+/// bits per byte, least significant first, with the high bit set on every
+/// byte but the last.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct VarInt(pub u32);
+
+impl ReadFrom for VarInt {
+    fn read_from<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        let mut value = 0u32;
+        for shift in (0..35).step_by(7) {
+            let byte = u8::read_from(reader)?;
+            // The fifth byte only has room for the top four bits.
+            if shift == 28 && byte > 0x0f {
+                return Err(Error::InvalidSave);
+            }
+            value |= u32::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(Self(value));
+            }
+        }
+        unreachable!("the fifth byte has no continuation bit")
+    }
+}
+
+impl WriteTo for VarInt {
+    fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        let mut value = self.0;
+        while value >= 0x80 {
+            (value as u8 | 0x80).write_to(writer)?;
+            value >>= 7;
+        }
+        (value as u8).write_to(writer)
+    }
+}
+
+/// A UTF-8 string with a [`VarInt`] length prefix, as written by .NET's
+/// `BinaryWriter`. Save files themselves use `String`, which has a fixed
+/// four-byte prefix.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct VarString(pub String);
+
+impl ReadFrom for VarString {
+    fn read_from<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        let VarInt(len) = VarInt::read_from(reader)?;
+        // Read in chunks rather than allocating up front, since custom data
+        // isn't checked against any limits.
+        let mut bytes = Vec::new();
+        let mut remaining = len as usize;
+        while remaining > 0 {
+            let start = bytes.len();
+            let count = remaining.min(CHUNK_SIZE);
+            bytes.resize(start + count, 0);
+            reader.read_exact(&mut bytes[start..])?;
+            remaining -= count;
+        }
+        String::from_utf8(bytes)
+            .map(Self)
+            .map_err(|_| Error::InvalidSave)
+    }
+}
+
+impl WriteTo for VarString {
+    fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        let len = u32::try_from(self.0.len()).map_err(|_| Error::InvalidSave)?;
+        VarInt(len).write_to(writer)?;
+        writer.write_all(self.0.as_bytes())?;
+        Ok(())
+    }
 }
 
 impl ReadFrom for usize {
@@ -64,11 +271,16 @@ impl WriteTo for usize {
     }
 }
 
+/// The number of items to read into a `Vec`.
 pub struct Length(pub usize);
 
 impl<T: ReadFrom> ReadFromSeed<Length> for Vec<T> {
     fn read_from_seed<R: Read>(reader: &mut R, seed: Length) -> Result<Self, Error> {
-        repeat_with(|| T::read_from(reader)).take(seed.0).collect()
+        let mut items = with_capacity_for(seed.0);
+        for _ in 0..seed.0 {
+            items.push(T::read_from(reader)?);
+        }
+        Ok(items)
     }
 }
 
@@ -93,9 +305,15 @@ impl<T: WriteTo> WriteTo for [T] {
 
 impl ReadFrom for String {
     fn read_from<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        Self::read_from_seed(reader, &mut Budget::unlimited())
+    }
+}
+
+impl ReadFromSeed<&mut Budget> for String {
+    fn read_from_seed<R: Read>(reader: &mut R, budget: &mut Budget) -> Result<Self, Error> {
         let string_len = usize::read_from(reader)?;
-        let mut bytes = vec![0u8; string_len];
-        reader.read_exact(&mut bytes)?;
+        budget.check(Limit::StringLength, string_len)?;
+        let bytes = read_bytes(reader, string_len, budget)?;
         String::from_utf8(bytes).map_err(|_| Error::InvalidSave)
     }
 }
@@ -108,6 +326,150 @@ impl WriteTo for str {
     }
 }
 
+/// Tracks resource usage against [`ReadLimits`] while parsing.
+pub struct Budget {
+    limits: ReadLimits,
+    allocated: usize,
+}
+
+impl Budget {
+    pub fn new(limits: ReadLimits) -> Self {
+        Self {
+            limits,
+            allocated: 0,
+        }
+    }
+
+    pub fn unlimited() -> Self {
+        Self::new(ReadLimits::unlimited())
+    }
+
+    /// Check a count or length read from the save against a limit.
+    pub fn check(&self, limit: Limit, value: usize) -> Result<(), Error> {
+        if value > self.limits.get(limit) {
+            return Err(Error::LimitExceeded(limit));
+        }
+        Ok(())
+    }
+
+    /// Account for an allocation of `bytes` bytes.
+    pub fn alloc(&mut self, bytes: usize) -> Result<(), Error> {
+        self.allocated = self.allocated.saturating_add(bytes);
+        self.check(Limit::TotalAllocation, self.allocated)
+    }
+}
+
+/// Read `len` raw bytes, after checking that they fit in the budget.
+pub fn read_bytes<R: Read>(
+    reader: &mut R,
+    len: usize,
+    budget: &mut Budget,
+) -> Result<Vec<u8>, Error> {
+    budget.alloc(len)?;
+    let mut bytes = vec![0u8; len];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// The most memory to reserve for a list up front, in bytes. Longer lists
+/// grow as they are read, so a corrupt count can't reserve more memory than
+/// the save could fill.
+const MAX_PREALLOCATED: usize = 16 << 20;
+
+/// The most bytes of fixed-size items to read at once.
+const CHUNK_SIZE: usize = 64 << 10;
+
+/// The most bytes of fixed-size items to read into a buffer on the stack.
+const SMALL_CHUNK_SIZE: usize = 64;
+
+/// An empty `Vec` with room for `len` items, up to [`MAX_PREALLOCATED`].
+pub fn with_capacity_for<T>(len: usize) -> Vec<T> {
+    Vec::with_capacity(len.min(MAX_PREALLOCATED / size_of::<T>().max(1)))
+}
+
+/// Read `len` fixed-size items, after checking `len` against `limit`.
+///
+/// The items are read in chunks, with one call to the reader for each
+/// chunk, which matters for readers that are slow to call, like
+/// decompressors.
+pub fn read_counted<R: Read, T: FixedSize>(
+    reader: &mut R,
+    len: usize,
+    limit: Limit,
+    budget: &mut Budget,
+) -> Result<Vec<T>, Error> {
+    budget.check(limit, len)?;
+    let mut items = with_capacity_for(len);
+    let per_chunk = (CHUNK_SIZE / T::SIZE).max(1);
+    // Short lists, like the pegs of a component, fit on the stack.
+    let mut small = [0u8; SMALL_CHUNK_SIZE];
+    let mut large = Vec::new();
+    let mut remaining = len;
+    while remaining > 0 {
+        let count = remaining.min(per_chunk);
+        budget.alloc(count * size_of::<T>())?;
+        let bytes = count * T::SIZE;
+        let buffer = if bytes <= small.len() {
+            &mut small[..bytes]
+        } else {
+            large.resize(bytes, 0);
+            &mut large[..bytes]
+        };
+        reader.read_exact(buffer)?;
+        let mut chunk = &buffer[..];
+        for _ in 0..count {
+            items.push(T::read_from(&mut chunk)?);
+        }
+        remaining -= count;
+    }
+    Ok(items)
+}
+
+/// Like [`read_counted`], and also read the `i32` after the items, like the
+/// length of the next list, in the same call to the reader if they are few.
+pub fn read_counted_then_i32<R: Read, T: FixedSize>(
+    reader: &mut R,
+    len: usize,
+    limit: Limit,
+    budget: &mut Budget,
+) -> Result<(Vec<T>, i32), Error> {
+    let bytes = len.saturating_mul(T::SIZE).saturating_add(size_of::<i32>());
+    if bytes > SMALL_CHUNK_SIZE {
+        let items = read_counted(reader, len, limit, budget)?;
+        return Ok((items, i32::read_from(reader)?));
+    }
+    budget.check(limit, len)?;
+    budget.alloc(len * size_of::<T>())?;
+    let mut buffer = [0u8; SMALL_CHUNK_SIZE];
+    reader.read_exact(&mut buffer[..bytes])?;
+    let mut chunk = &buffer[..bytes];
+    let mut items = Vec::with_capacity(len);
+    for _ in 0..len {
+        items.push(T::read_from(&mut chunk)?);
+    }
+    Ok((items, i32::read_from(&mut chunk)?))
+}
+
+/// Like [`read_counted`], for items that allocate and so need the budget
+/// themselves.
+pub fn read_counted_with<R: Read, T>(
+    reader: &mut R,
+    len: usize,
+    limit: Limit,
+    budget: &mut Budget,
+) -> Result<Vec<T>, Error>
+where
+    T: for<'a> ReadFromSeed<&'a mut Budget>,
+{
+    budget.check(limit, len)?;
+    let mut items = with_capacity_for(len);
+    for _ in 0..len {
+        budget.alloc(size_of::<T>())?;
+        items.push(T::read_from_seed(reader, budget)?);
+    }
+    Ok(items)
+}
+
 pub fn read_magic<R: Read>(reader: &mut R, magic_bytes: &[u8]) -> Result<(), Error> {
     let mut header = [0u8; 16];
     reader.read_exact(&mut header)?;
@@ -116,3 +478,117 @@ pub fn read_magic<R: Read>(reader: &mut R, magic_bytes: &[u8]) -> Result<(), Err
     }
     Ok(())
 }
+
+/// Read the footer of a save, failing with [`Error::MissingFooter`] if the
+/// save ends or has something else where the footer should be.
+pub fn read_footer<R: Read>(reader: &mut R, footer: &[u8]) -> Result<(), Error> {
+    let mut bytes = [0u8; 16];
+    read_before_footer(reader, &mut bytes)?;
+    if bytes != *footer {
+        return Err(Error::MissingFooter);
+    }
+    Ok(())
+}
+
+/// Whether there is nothing left to read.
+pub fn at_end<R: Read>(reader: &mut R) -> Result<bool, Error> {
+    let mut byte = [0u8; 1];
+    match reader.read_exact(&mut byte) {
+        Err(err) if is_eof(&err) => Ok(true),
+        result => {
+            result?;
+            Ok(false)
+        }
+    }
+}
+
+/// Read everything up to the end of the data, in chunks, accounting for
+/// each chunk in the budget.
+pub fn read_to_end<R: Read>(reader: &mut R, budget: &mut Budget) -> Result<Vec<u8>, Error> {
+    let mut bytes = Vec::new();
+    let mut chunk = vec![0u8; SMALL_CHUNK_SIZE];
+    loop {
+        let len = match reader.read(&mut chunk) {
+            Err(err) if is_interrupted(&err) => continue,
+            result => result?,
+        };
+        if len == 0 {
+            return Ok(bytes);
+        }
+        budget.alloc(len)?;
+        bytes.extend_from_slice(&chunk[..len]);
+        if chunk.len() < CHUNK_SIZE {
+            chunk.resize(chunk.len() * 2, 0);
+        }
+    }
+}
+
+/// Read up to and including `magic_bytes`, returning the bytes before them.
+///
+/// This lets newer saves add data before the footer. If the end of the save
+/// is reached without finding the magic bytes, the footer is missing.
+pub fn read_until_magic<R: Read>(
+    reader: &mut R,
+    magic_bytes: &[u8],
+    budget: &mut Budget,
+) -> Result<Vec<u8>, Error> {
+    let mut bytes = Vec::new();
+    scan_until_magic(reader, magic_bytes, |byte| {
+        budget.alloc(1)?;
+        bytes.push(byte);
+        Ok(())
+    })?;
+    Ok(bytes)
+}
+
+/// Like [`read_until_magic`], but only count the bytes before the magic
+/// bytes.
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+pub fn skip_until_magic<R: Read>(reader: &mut R, magic_bytes: &[u8]) -> Result<u64, Error> {
+    let mut skipped = 0;
+    scan_until_magic(reader, magic_bytes, |_| {
+        skipped += 1;
+        Ok(())
+    })?;
+    Ok(skipped)
+}
+
+fn scan_until_magic<R: Read, F>(reader: &mut R, magic_bytes: &[u8], mut f: F) -> Result<(), Error>
+where
+    F: FnMut(u8) -> Result<(), Error>,
+{
+    let mut window = [0u8; 16];
+    read_before_footer(reader, &mut window)?;
+    while window != *magic_bytes {
+        f(window[0])?;
+        window.rotate_left(1);
+        read_before_footer(reader, &mut window[15..])?;
+    }
+    Ok(())
+}
+
+/// Like `read_exact`, where the data ending first means that the footer is
+/// missing.
+fn read_before_footer<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<(), Error> {
+    match reader.read_exact(buf) {
+        Err(err) if is_eof(&err) => Err(Error::MissingFooter),
+        result => {
+            result?;
+            Ok(())
+        }
+    }
+}
+
+/// A reader that keeps a copy of everything read through it.
+pub(crate) struct Recording<R> {
+    pub(crate) inner: R,
+    pub(crate) bytes: Vec<u8>,
+}
+
+impl<R: Read> Read for Recording<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+        let len = self.inner.read(buf)?;
+        self.bytes.extend_from_slice(&buf[..len]);
+        Ok(len)
+    }
+}
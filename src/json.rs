@@ -0,0 +1,91 @@
+//! A JSON text representation of saves, for inspection and hand-editing.
+//!
+//! Requires the `json` crate feature.
+//!
+//! # Schema
+//!
+//! The document is a single object with the following fields. Field order is
+//! not significant when reading.
+//!
+//! - `format`: always `"blotter-json"`.
+//! - `format_version`: the version of this JSON schema; currently `1`.
+//! - `save_version`: the binary save version the document corresponds to;
+//!   currently `6`.
+//! - `game_version`: array of four integers.
+//! - `save_type`: `"World"` or `"Subassembly"`.
+//! - `mods`: array of `{ "mod_id": string, "mod_version": [int; 4] }`.
+//! - `component_types`: array of `{ "numeric_id": int, "text_id": string }`.
+//! - `components`: array of objects with fields `address`, `parent` (0 for
+//!   none), `type_id`, `position` (`[int; 3]`, fixed-point), `rotation`
+//!   (`[float; 4]` quaternion, `x y z w`), `inputs` and `outputs` (arrays of
+//!   `{ "circuit_state_id": int }`), and `custom_data` (hex string, or `null`
+//!   for none).
+//! - `wires`: array of objects with fields `start_peg` and `end_peg` (each
+//!   `{ "peg_type": "Input" | "Output", "component_address": int,
+//!   "peg_index": int }`), `circuit_state_id`, and `rotation` (float).
+//! - `circuit_states`: either `{ "format": "world_format", "circuit_states":
+//!   hex string }` (one bit per state, least significant bit first), or
+//!   `{ "format": "subassembly_format", "on_states": [int] }`.
+//! - `extra`: hex string of data before the footer that only newer games
+//!   write. Optional; left out when there is none.
+//! - `trailing`: hex string of data after the footer, if it was captured.
+//!   Optional; left out when there is none.
+//!
+//! Custom data, world circuit states, extra and trailing data are encoded as
+//! lowercase hex strings.
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::latest::{BlotterFile, SAVE_VERSION};
+
+const FORMAT: &str = "blotter-json";
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    format: &'a str,
+    format_version: u32,
+    save_version: u8,
+    #[serde(flatten)]
+    file: &'a BlotterFile,
+}
+
+#[derive(Deserialize)]
+struct OwnedEnvelope {
+    format: String,
+    format_version: u32,
+    save_version: u8,
+    #[serde(flatten)]
+    file: BlotterFile,
+}
+
+impl BlotterFile {
+    /// Serialize the save as a JSON document.
+    pub fn to_json(&self, pretty: bool) -> Result<String, Error> {
+        let envelope = Envelope {
+            format: FORMAT,
+            format_version: FORMAT_VERSION,
+            save_version: SAVE_VERSION,
+            file: self,
+        };
+        let json = if pretty {
+            serde_json::to_string_pretty(&envelope)?
+        } else {
+            serde_json::to_string(&envelope)?
+        };
+        Ok(json)
+    }
+
+    /// Parse a save from a JSON document produced by [`Self::to_json`].
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let envelope: OwnedEnvelope = serde_json::from_str(json)?;
+        if envelope.format != FORMAT || envelope.format_version != FORMAT_VERSION {
+            return Err(Error::InvalidSave);
+        }
+        if envelope.save_version != SAVE_VERSION {
+            return Err(Error::IncompatibleVersion(envelope.save_version));
+        }
+        Ok(envelope.file)
+    }
+}
@@ -0,0 +1,231 @@
+//! Descriptions of the records that make up each version of the save format,
+//! as data, and a generator of format specs from them.
+//!
+//! The layouts are kept next to the parsers, in [`v5::LAYOUT`](crate::v5::LAYOUT)
+//! and [`v6::LAYOUT`](crate::v6::LAYOUT), and fixed-size records are checked
+//! against the parser at compile time.
+//!
+//! ```
+//! use blotter::v6;
+//!
+//! let wire = v6::LAYOUT.record("Wire").unwrap();
+//! assert_eq!(wire.size(), Some(26));
+//!
+//! let spec = v6::LAYOUT.to_markdown();
+//! assert!(spec.starts_with("# Save format version 6"));
+//! ```
+
+use alloc::string::{String, ToString};
+use core::fmt::{self, Display, Formatter, Write};
+
+/// The layout of one version of the save format.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layout {
+    /// The save version that these records are for.
+    pub version: u8,
+    /// Every record, starting with the save as a whole.
+    pub records: &'static [&'static Record],
+}
+
+impl Layout {
+    /// The record with the given name.
+    pub fn record(&self, name: &str) -> Option<&'static Record> {
+        self.records
+            .iter()
+            .copied()
+            .find(|record| record.name == name)
+    }
+
+    /// A Markdown spec of the format, with a table of the fields of each
+    /// record.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        self.write_markdown(&mut out).unwrap();
+        out
+    }
+
+    fn write_markdown(&self, out: &mut String) -> fmt::Result {
+        writeln!(out, "# Save format version {}", self.version)?;
+        writeln!(out)?;
+        writeln!(
+            out,
+            "All numbers are little-endian. Strings are UTF-8, with a four-byte \
+             signed length prefix. Arrays of fixed length have no prefix, and \
+             other lists are counted by an earlier field."
+        )?;
+        for record in self.records {
+            writeln!(out)?;
+            writeln!(out, "## {}", record.name)?;
+            writeln!(out)?;
+            write_doc(out, record.doc)?;
+            writeln!(out, "| Field | Type | Size | Description |")?;
+            writeln!(out, "| --- | --- | --- | --- |")?;
+            for field in record.fields {
+                let size = match field.ty.size() {
+                    Some(size) => size.to_string(),
+                    None => "varies".to_string(),
+                };
+                write!(out, "| `{}` | {} | {} | ", field.name, field.ty, size)?;
+                for (i, line) in field.doc.lines().map(str::trim).enumerate() {
+                    if i > 0 {
+                        write!(out, " ")?;
+                    }
+                    write!(out, "{}", line)?;
+                }
+                writeln!(out, " |")?;
+            }
+            if let Some(size) = record.size() {
+                writeln!(out)?;
+                writeln!(out, "Always {} bytes.", size)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_doc(out: &mut String, doc: &str) -> fmt::Result {
+    if doc.is_empty() {
+        return Ok(());
+    }
+    for line in doc.lines() {
+        writeln!(out, "{}", line.strip_prefix(' ').unwrap_or(line))?;
+    }
+    writeln!(out)
+}
+
+/// A structure in a save, like a component or a wire.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Record {
+    pub name: &'static str,
+    pub doc: &'static str,
+    /// The fields of the record, in the order they are stored.
+    pub fields: &'static [Field],
+}
+
+impl Record {
+    /// The number of bytes the record takes, if it is always the same.
+    pub const fn size(&self) -> Option<usize> {
+        let mut total = 0;
+        let mut i = 0;
+        while i < self.fields.len() {
+            match self.fields[i].ty.size() {
+                Some(size) => total += size,
+                None => return None,
+            }
+            i += 1;
+        }
+        Some(total)
+    }
+}
+
+/// One field of a [`Record`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Field {
+    pub name: &'static str,
+    pub ty: FieldType,
+    pub doc: &'static str,
+}
+
+/// How a [`Field`] is encoded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum FieldType {
+    U8,
+    U16,
+    I32,
+    U32,
+    F32,
+    /// A fixed number of items, with no length prefix.
+    Array(&'static FieldType, usize),
+    /// A UTF-8 string with a four-byte length prefix.
+    String,
+    /// These exact bytes, which mark the start or end of a save.
+    Magic(&'static [u8; 16]),
+    /// As many items as the named field says, like a list of records.
+    Counted(&'static FieldType, &'static str),
+    /// A record, inline.
+    Record(&'static Record),
+    /// One of several records, depending on an earlier field.
+    OneOf(&'static [&'static Record]),
+    /// Any number of bytes, up to but not including these magic bytes.
+    BytesUntil(&'static [u8; 16]),
+}
+
+impl FieldType {
+    /// The number of bytes the field takes, if it is always the same.
+    pub const fn size(&self) -> Option<usize> {
+        match *self {
+            Self::U8 => Some(1),
+            Self::U16 => Some(2),
+            Self::I32 | Self::U32 | Self::F32 => Some(4),
+            Self::Array(ty, len) => match ty.size() {
+                Some(size) => Some(size * len),
+                None => None,
+            },
+            Self::Magic(bytes) => Some(bytes.len()),
+            Self::Record(record) => record.size(),
+            Self::String | Self::Counted(..) | Self::OneOf(_) | Self::BytesUntil(_) => None,
+        }
+    }
+}
+
+/// The type as it appears in a spec, with records linked to their sections.
+impl Display for FieldType {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let link = |f: &mut Formatter, record: &Record| {
+            write!(f, "[{}](#{})", record.name, record.name.to_lowercase())
+        };
+        match *self {
+            Self::U8 => write!(f, "`u8`"),
+            Self::U16 => write!(f, "`u16`"),
+            Self::I32 => write!(f, "`i32`"),
+            Self::U32 => write!(f, "`u32`"),
+            Self::F32 => write!(f, "`f32`"),
+            Self::Array(ty, len) => write!(f, "{} × {}", ty, len),
+            Self::String => write!(f, "string"),
+            Self::Magic(bytes) => write!(f, "`{:?}`", String::from_utf8_lossy(bytes)),
+            Self::Counted(ty, len) => write!(f, "{} × `{}`", ty, len),
+            Self::Record(record) => link(f, record),
+            Self::OneOf(records) => {
+                for (i, record) in records.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " or ")?;
+                    }
+                    link(f, record)?;
+                }
+                Ok(())
+            }
+            Self::BytesUntil(_) => write!(f, "`u8` × any"),
+        }
+    }
+}
+
+/// A [`Record`] from a struct-like description, with doc comments on the
+/// record and its fields, and a [`FieldType`] for each field.
+macro_rules! record {
+    (
+        $(#[doc = $doc:literal])*
+        $name:ident {
+            $(
+                $(#[doc = $field_doc:literal])*
+                $field:ident: $ty:expr,
+            )*
+        }
+    ) => {{
+        #[allow(unused_imports)]
+        use $crate::layout::FieldType::*;
+        $crate::layout::Record {
+            name: stringify!($name),
+            doc: concat!($($doc, "\n",)*),
+            fields: &[$(
+                $crate::layout::Field {
+                    name: stringify!($field),
+                    ty: $ty,
+                    doc: concat!($($field_doc, "\n",)*),
+                },
+            )*],
+        }
+    }};
+}
+
+pub(crate) use record;
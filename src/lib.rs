@@ -1,20 +1,70 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod collections;
+#[cfg(feature = "std")]
+pub mod compression;
 mod convert;
 pub mod custom_data;
+#[cfg(feature = "std")]
+pub mod debug;
 pub mod error;
+#[cfg(feature = "std")]
+pub mod export;
+#[cfg(feature = "std")]
+pub mod generate;
+#[cfg(feature = "std")]
+pub mod geometry;
+#[cfg(feature = "std")]
+mod hash;
+#[cfg(feature = "std")]
+pub mod import;
 pub(crate) mod io;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod layout;
+pub mod limits;
+#[cfg(feature = "std")]
+pub mod metadata;
+#[cfg(feature = "std")]
 pub(crate) mod misc;
+#[cfg(feature = "std")]
+pub mod mods;
+#[cfg(feature = "std")]
+pub mod query;
+pub mod recover;
+#[cfg(feature = "std")]
 pub mod sandbox;
+#[cfg(feature = "std")]
+pub mod sanitize;
+#[cfg(feature = "std")]
+pub mod states;
+#[cfg(feature = "std")]
+pub mod testing;
 pub mod v5;
 pub mod v6;
+#[cfg(feature = "std")]
+pub mod validate;
+pub mod version;
+pub mod wire_format;
+#[cfg(feature = "std")]
+pub mod world;
 
-use std::io::{Read, Write};
+use alloc::vec::Vec;
 
 use crate::error::Error;
-use crate::io::{read_magic, ReadFrom};
+use crate::io::{at_end, read_magic, read_to_end, Budget, ReadFrom, Recording};
+use crate::limits::ReadLimits;
 
+/// The traits that saves are read from and written to: those of `std::io`,
+/// or without the `std` feature, minimal versions of them that are
+/// implemented for byte slices and `Vec<u8>`.
+pub use crate::io::{Read, Write};
 pub use v6 as latest;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum BlotterFile {
     V5(v5::BlotterFile),
     V6(v6::BlotterFile),
@@ -22,13 +72,95 @@ pub enum BlotterFile {
 
 impl BlotterFile {
     pub fn read<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        Self::read_with_limits(reader, &ReadLimits::unlimited())
+    }
+
+    /// Read a save, failing if it exceeds any of the given limits.
+    ///
+    /// Use this with [`ReadLimits::untrusted`] when parsing files from
+    /// untrusted sources.
+    pub fn read_with_limits<R: Read>(reader: &mut R, limits: &ReadLimits) -> Result<Self, Error> {
+        Self::read_with(reader, &ReadOptions::new().limits(*limits))
+    }
+
+    /// Read a save, applying the given options.
+    ///
+    /// ```
+    /// use blotter::{BlotterFile, ReadOptions, TrailingData};
+    /// use blotter::error::Error;
+    /// use blotter::latest;
+    ///
+    /// let mut bytes = Vec::new();
+    /// latest::BlotterFile::new([0, 91, 0, 510]).write(&mut bytes).unwrap();
+    /// bytes.extend_from_slice(b"{\"tool\": \"example\"}");
+    ///
+    /// let options = ReadOptions::new().trailing(TrailingData::Capture);
+    /// let file = BlotterFile::read_with(&mut &bytes[..], &options).unwrap().migrate();
+    /// assert_eq!(file.trailing, b"{\"tool\": \"example\"}");
+    ///
+    /// let options = ReadOptions::new().trailing(TrailingData::Reject);
+    /// let error = BlotterFile::read_with(&mut &bytes[..], &options).unwrap_err();
+    /// assert!(matches!(error, Error::TrailingData));
+    ///
+    /// let error = BlotterFile::read(&mut &bytes[..bytes.len() - 30]).unwrap_err();
+    /// assert!(matches!(error, Error::MissingFooter));
+    /// ```
+    pub fn read_with<R: Read>(reader: &mut R, options: &ReadOptions) -> Result<Self, Error> {
         read_magic(reader, latest::SAVE_HEADER)?;
         let save_version = u8::read_from(reader)?;
-        match save_version {
-            v5::SAVE_VERSION => v5::BlotterFile::read_after_save_version(reader).map(Self::V5),
-            v6::SAVE_VERSION => v6::BlotterFile::read_after_save_version(reader).map(Self::V6),
-            _ => Err(Error::IncompatibleVersion(save_version)),
+        let budget = &mut Budget::new(options.limits);
+        let mut file = match save_version {
+            v5::SAVE_VERSION => {
+                v5::BlotterFile::read_after_save_version(reader, budget).map(Self::V5)?
+            }
+            v6::SAVE_VERSION => {
+                v6::BlotterFile::read_after_save_version(reader, budget).map(Self::V6)?
+            }
+            _ => return Err(Error::IncompatibleVersion(save_version)),
+        };
+        match options.trailing {
+            TrailingData::Ignore => {}
+            TrailingData::Capture => {
+                let trailing = read_to_end(reader, budget)?;
+                match &mut file {
+                    Self::V5(file) => file.trailing = trailing,
+                    Self::V6(file) => file.trailing = trailing,
+                }
+            }
+            TrailingData::Reject => {
+                if !at_end(reader)? {
+                    return Err(Error::TrailingData);
+                }
+            }
         }
+        Ok(file)
+    }
+
+    /// Read a save, checking that writing it again gives back exactly the
+    /// bytes that were read.
+    ///
+    /// Everything in a save is kept when it is read, except for the length
+    /// of missing custom data, which is always written as -1, like the game
+    /// does. Saves that would change anyway fail with
+    /// [`Error::NotPreserved`], so that tools that promise not to change
+    /// anything but their edits can refuse them instead.
+    pub fn read_preserving<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        let mut recording = Recording {
+            inner: reader,
+            bytes: Vec::new(),
+        };
+        let file = Self::read(&mut recording)?;
+        let mut written = Vec::with_capacity(recording.bytes.len());
+        file.write(&mut written)?;
+        if written != recording.bytes {
+            let offset = written
+                .iter()
+                .zip(&recording.bytes)
+                .position(|(a, b)| a != b)
+                .unwrap_or(written.len().min(recording.bytes.len()));
+            return Err(Error::NotPreserved(offset));
+        }
+        Ok(file)
     }
 
     pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
@@ -45,4 +177,56 @@ impl BlotterFile {
             Self::V6(file) => file,
         }
     }
+
+    /// Convert the file into the latest version, along with where each of
+    /// its circuit states ended up.
+    ///
+    /// The migrations so far keep every circuit state ID as it was, but
+    /// tools that follow signals through upgrades should go through the map
+    /// rather than rely on that.
+    #[cfg(feature = "std")]
+    pub fn migrate_tracked(self) -> (latest::BlotterFile, states::StateMap) {
+        let file = self.migrate();
+        let map = states::StateMap::between(&file, &file);
+        (file, map)
+    }
+}
+
+/// What to do with data after the footer of a save.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrailingData {
+    /// Stop reading at the footer, leaving anything after it unread.
+    #[default]
+    Ignore,
+    /// Read everything after the footer into the file's `trailing` field,
+    /// to be written back after the footer.
+    Capture,
+    /// Fail with [`Error::TrailingData`] if there is anything after the
+    /// footer.
+    Reject,
+}
+
+/// Options for [`BlotterFile::read_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ReadOptions {
+    limits: ReadLimits,
+    trailing: TrailingData,
+}
+
+impl ReadOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail if the save exceeds any of these limits. Defaults to
+    /// [`ReadLimits::unlimited`].
+    pub fn limits(self, limits: ReadLimits) -> Self {
+        Self { limits, ..self }
+    }
+
+    /// What to do with data after the footer. Defaults to
+    /// [`TrailingData::Ignore`].
+    pub fn trailing(self, trailing: TrailingData) -> Self {
+        Self { trailing, ..self }
+    }
 }
@@ -0,0 +1,174 @@
+//! Resource limits for parsing untrusted saves.
+//!
+//! By default, the parser trusts the lengths and counts stored in a save, so a
+//! malicious file can make it allocate huge buffers or loop for a very long
+//! time. [`ReadLimits`] caps those values; reading a save that exceeds any of
+//! them fails with [`Error::LimitExceeded`](crate::error::Error::LimitExceeded)
+//! before the corresponding allocation is made.
+
+/// Identifies which limit in a [`ReadLimits`] was exceeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Limit {
+    Components,
+    Wires,
+    Mods,
+    ComponentTypes,
+    PegsPerComponent,
+    StringLength,
+    CustomDataLength,
+    CircuitStates,
+    TotalAllocation,
+    DecompressedLength,
+}
+
+/// Resource limits for parsing a save.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadLimits {
+    max_components: usize,
+    max_wires: usize,
+    max_mods: usize,
+    max_component_types: usize,
+    max_pegs_per_component: usize,
+    max_string_len: usize,
+    max_custom_data_len: usize,
+    max_circuit_states: usize,
+    max_total_alloc: usize,
+    max_decompressed_len: usize,
+}
+
+impl ReadLimits {
+    /// No limits at all; the behavior of the plain `read` functions.
+    pub fn unlimited() -> Self {
+        Self {
+            max_components: usize::MAX,
+            max_wires: usize::MAX,
+            max_mods: usize::MAX,
+            max_component_types: usize::MAX,
+            max_pegs_per_component: usize::MAX,
+            max_string_len: usize::MAX,
+            max_custom_data_len: usize::MAX,
+            max_circuit_states: usize::MAX,
+            max_total_alloc: usize::MAX,
+            max_decompressed_len: usize::MAX,
+        }
+    }
+
+    /// Conservative limits suitable for files uploaded by strangers.
+    ///
+    /// These comfortably fit large hand-built worlds, but cap total memory
+    /// use at around 256 MiB.
+    pub fn untrusted() -> Self {
+        Self {
+            max_components: 1 << 20,
+            max_wires: 1 << 21,
+            max_mods: 256,
+            max_component_types: 4096,
+            max_pegs_per_component: 1024,
+            max_string_len: 1024,
+            max_custom_data_len: 1 << 16,
+            max_circuit_states: 1 << 24,
+            max_total_alloc: 256 << 20,
+            max_decompressed_len: 256 << 20,
+        }
+    }
+
+    pub fn max_components(self, max_components: usize) -> Self {
+        Self {
+            max_components,
+            ..self
+        }
+    }
+
+    pub fn max_wires(self, max_wires: usize) -> Self {
+        Self { max_wires, ..self }
+    }
+
+    pub fn max_mods(self, max_mods: usize) -> Self {
+        Self { max_mods, ..self }
+    }
+
+    pub fn max_component_types(self, max_component_types: usize) -> Self {
+        Self {
+            max_component_types,
+            ..self
+        }
+    }
+
+    /// The maximum number of input or output pegs on a single component.
+    pub fn max_pegs_per_component(self, max_pegs_per_component: usize) -> Self {
+        Self {
+            max_pegs_per_component,
+            ..self
+        }
+    }
+
+    /// The maximum length of a string, in bytes.
+    pub fn max_string_len(self, max_string_len: usize) -> Self {
+        Self {
+            max_string_len,
+            ..self
+        }
+    }
+
+    /// The maximum length of a single component's custom data, in bytes.
+    pub fn max_custom_data_len(self, max_custom_data_len: usize) -> Self {
+        Self {
+            max_custom_data_len,
+            ..self
+        }
+    }
+
+    /// The maximum number of circuit state bytes (world saves) or on-states
+    /// (subassembly saves).
+    pub fn max_circuit_states(self, max_circuit_states: usize) -> Self {
+        Self {
+            max_circuit_states,
+            ..self
+        }
+    }
+
+    /// The maximum total number of bytes allocated for the parsed save.
+    ///
+    /// This is an estimate based on the in-memory size of the parsed values,
+    /// not an exact measure of heap usage.
+    pub fn max_total_alloc(self, max_total_alloc: usize) -> Self {
+        Self {
+            max_total_alloc,
+            ..self
+        }
+    }
+
+    /// The maximum size of a compressed save once decompressed, in bytes.
+    ///
+    /// This only applies to
+    /// [`read_maybe_compressed_with_limits`](crate::BlotterFile::read_maybe_compressed_with_limits),
+    /// and stops a small compressed file from expanding into a huge one.
+    pub fn max_decompressed_len(self, max_decompressed_len: usize) -> Self {
+        Self {
+            max_decompressed_len,
+            ..self
+        }
+    }
+
+    pub(crate) fn get(&self, limit: Limit) -> usize {
+        match limit {
+            Limit::Components => self.max_components,
+            Limit::Wires => self.max_wires,
+            Limit::Mods => self.max_mods,
+            Limit::ComponentTypes => self.max_component_types,
+            Limit::PegsPerComponent => self.max_pegs_per_component,
+            Limit::StringLength => self.max_string_len,
+            Limit::CustomDataLength => self.max_custom_data_len,
+            Limit::CircuitStates => self.max_circuit_states,
+            Limit::TotalAllocation => self.max_total_alloc,
+            Limit::DecompressedLength => self.max_decompressed_len,
+        }
+    }
+}
+
+impl Default for ReadLimits {
+    fn default() -> Self {
+        Self::unlimited()
+    }
+}
@@ -0,0 +1,217 @@
+//! Self-describing saves.
+//!
+//! Tools that generate saves can record which tool made them, when, and with
+//! which parameters. By convention, this is stored as the text of a root
+//! `MHG.Label` component, starting with the line [`MARKER`]. The label is an
+//! ordinary vanilla component, so the save still loads in an unmodded game;
+//! the metadata just shows up as text in the world.
+//!
+//! ```
+//! use blotter::{metadata::Metadata, sandbox::Sandbox};
+//!
+//! let mut sandbox = Sandbox::new();
+//! let metadata = Metadata::new("rom-generator").parameter("words", "256");
+//! sandbox.set_metadata(&metadata).unwrap();
+//! assert_eq!(sandbox.metadata(), Some(metadata));
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::custom_data::{ComponentData, Label};
+use crate::error::Error;
+use crate::latest::BlotterFile;
+use crate::sandbox::{ComponentBuilder, ComponentId, Sandbox};
+
+/// The first line of a metadata label.
+pub const MARKER: &str = "#blotter-metadata";
+
+/// Information about how a save was generated.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    /// The name of the tool that generated the save.
+    pub tool: String,
+    /// When the save was generated, in seconds since the Unix epoch.
+    pub timestamp: Option<u64>,
+    /// Tool-specific parameters, like the options it was run with.
+    pub parameters: BTreeMap<String, String>,
+}
+
+impl Metadata {
+    pub fn new(tool: impl Into<String>) -> Self {
+        Self {
+            tool: tool.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn timestamp(self, timestamp: u64) -> Self {
+        Self {
+            timestamp: Some(timestamp),
+            ..self
+        }
+    }
+
+    /// Set the timestamp to the current time.
+    pub fn generated_now(self) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        self.timestamp(now)
+    }
+
+    pub fn parameter(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.parameters.insert(name.into(), value.into());
+        self
+    }
+
+    /// Encode the metadata as label text.
+    ///
+    /// Each field is on its own line, as `name: value`. Backslashes, colons
+    /// and line breaks in names and values are escaped with a backslash.
+    pub fn to_text(&self) -> String {
+        let mut text = String::from(MARKER);
+        write!(text, "\ntool: {}", escape(&self.tool)).unwrap();
+        if let Some(timestamp) = self.timestamp {
+            write!(text, "\ntimestamp: {}", timestamp).unwrap();
+        }
+        for (name, value) in &self.parameters {
+            write!(text, "\nparam {}: {}", escape(name), escape(value)).unwrap();
+        }
+        text
+    }
+
+    /// Decode label text, returning `None` if it isn't metadata.
+    ///
+    /// Lines that aren't understood are ignored, so that newer versions of
+    /// the format can add fields.
+    pub fn from_text(text: &str) -> Option<Self> {
+        let mut lines = text.lines();
+        if lines.next()? != MARKER {
+            return None;
+        }
+        let mut metadata = Self::default();
+        for line in lines {
+            let Some((name, value)) = split_field(line) else {
+                continue;
+            };
+            if name == "tool" {
+                metadata.tool = unescape(value);
+            } else if name == "timestamp" {
+                metadata.timestamp = value.parse().ok();
+            } else if let Some(param) = name.strip_prefix("param ") {
+                metadata.parameters.insert(unescape(param), unescape(value));
+            }
+        }
+        Some(metadata)
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ':' => escaped.push_str("\\:"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(s: &str) -> String {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(c) => unescaped.push(c),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+/// Split a line at its first unescaped `": "`.
+fn split_field(line: &str) -> Option<(&str, &str)> {
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ':' => return Some((&line[..index], line[index + 1..].strip_prefix(' ')?)),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn decode_label(custom_data: Option<&[u8]>) -> Option<Metadata> {
+    let label = Label::read(&mut custom_data?).ok()?;
+    Metadata::from_text(&label.text)
+}
+
+impl Sandbox {
+    /// The metadata of the save, if it has any.
+    pub fn metadata(&self) -> Option<Metadata> {
+        self.metadata_label().map(|(_, metadata)| metadata)
+    }
+
+    /// Store metadata in the save, replacing any that is already there.
+    ///
+    /// Returns the ID of the label that holds it.
+    pub fn set_metadata(&mut self, metadata: &Metadata) -> Result<ComponentId, Error> {
+        let mut custom_data = Vec::new();
+        Label::new(metadata.to_text()).write(&mut custom_data)?;
+        if let Some((id, _)) = self.metadata_label() {
+            self.component_mut(id)
+                .unwrap()
+                .set_custom_data(Some(custom_data));
+            return Ok(id);
+        }
+        Ok(self.add_component(
+            &ComponentBuilder::new(Label::TYPE_STRING).custom_data(Some(custom_data)),
+        ))
+    }
+
+    /// Remove the metadata label from the save, returning its metadata.
+    pub fn remove_metadata(&mut self) -> Option<Metadata> {
+        let (id, metadata) = self.metadata_label()?;
+        self.remove_component(id);
+        Some(metadata)
+    }
+
+    /// The first root label that holds metadata.
+    fn metadata_label(&self) -> Option<(ComponentId, Metadata)> {
+        self.root_components().find_map(|id| {
+            let component = self.component(id)?;
+            if component.type_name() != Label::TYPE_STRING {
+                return None;
+            }
+            decode_label(component.custom_data()).map(|metadata| (id, metadata))
+        })
+    }
+}
+
+impl BlotterFile {
+    /// The metadata of the save, if it has any.
+    pub fn metadata(&self) -> Option<Metadata> {
+        let label_type = self
+            .component_types
+            .iter()
+            .find(|ctype| ctype.text_id == Label::TYPE_STRING)?
+            .numeric_id;
+        self.components
+            .iter()
+            .filter(|component| component.parent == 0 && component.type_id == label_type)
+            .find_map(|component| decode_label(component.custom_data.as_deref()))
+    }
+}
@@ -2,53 +2,218 @@ use std::{
     fmt::Debug,
     hash::{Hash, Hasher},
     marker::PhantomData,
+    sync::Arc,
 };
 
+use super::object_store::{reserve_chunks, CHUNK_SIZE};
+
+/// A store that keeps its items contiguous, by moving the last item into the
+/// place of a removed one.
+///
+/// Like [`ObjectStore`](super::object_store::ObjectStore), items are stored
+/// in reference-counted chunks, so clones share them until they are
+/// modified.
+///
+/// ```
+/// use blotter::collections::DenseStore;
+///
+/// let mut store = DenseStore::new();
+/// let a = store.insert("a");
+/// let b = store.insert("b");
+/// let (removed, rename) = store.remove(a).unwrap();
+/// assert_eq!(removed, "a");
+/// assert_eq!((rename.src, rename.dest), (b, a));
+/// assert_eq!(store.get(a), Some(&"b"));
+/// assert!(!store.contains(b));
+/// ```
 pub struct DenseStore<T> {
-    items: Vec<T>,
+    len: usize,
+    chunks: Vec<Arc<Vec<T>>>,
 }
 
 impl<T> DenseStore<T> {
     pub fn new() -> Self {
-        Self { items: Vec::new() }
+        Self {
+            len: 0,
+            chunks: Vec::new(),
+        }
     }
 
     pub fn len(&self) -> usize {
-        self.items.len()
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn contains(&self, index: Index<T>) -> bool {
+        index.into_raw() < self.len
+    }
+
+    /// The number of items the store can hold without allocating.
+    pub fn capacity(&self) -> usize {
+        self.chunks.iter().map(|chunk| chunk.capacity()).sum()
     }
 
     pub fn get(&self, index: Index<T>) -> Option<&T> {
-        self.items.get(index.into_raw())
+        let raw = index.into_raw();
+        self.chunks.get(raw / CHUNK_SIZE)?.get(raw % CHUNK_SIZE)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.chunks.iter().flat_map(|chunk| chunk.iter())
+    }
+}
+
+impl<T: Clone> DenseStore<T> {
+    /// Make room for at least `additional` more items.
+    pub fn reserve(&mut self, additional: usize) {
+        reserve_chunks(&mut self.chunks, self.len, self.len + additional);
     }
 
     pub fn get_mut(&mut self, index: Index<T>) -> Option<&mut T> {
-        self.items.get_mut(index.into_raw())
+        let raw = index.into_raw();
+        let chunk = self.chunks.get_mut(raw / CHUNK_SIZE)?;
+        if raw % CHUNK_SIZE >= chunk.len() {
+            return None;
+        }
+        Arc::make_mut(chunk).get_mut(raw % CHUNK_SIZE)
     }
 
     pub fn insert(&mut self, item: T) -> Index<T> {
-        let index = Index::from_raw(self.items.len());
-        self.items.push(item);
+        let index = Index::from_raw(self.len);
+        let chunk = self.len / CHUNK_SIZE;
+        if chunk == self.chunks.len() {
+            self.chunks.push(Arc::new(Vec::with_capacity(CHUNK_SIZE)));
+        }
+        Arc::make_mut(&mut self.chunks[chunk]).push(item);
+        self.len += 1;
         index
     }
 
+    /// Remove the last item.
+    fn pop(&mut self) -> Option<T> {
+        let last = self.len.checked_sub(1)?;
+        let chunk = last / CHUNK_SIZE;
+        let item = Arc::make_mut(&mut self.chunks[chunk]).pop();
+        // Release a chunk once it's emptied, unless room was reserved after
+        // it.
+        if self.chunks[chunk].is_empty() && chunk + 1 == self.chunks.len() {
+            self.chunks.pop();
+        }
+        self.len = last;
+        item
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.chunks.truncate(self.len.div_ceil(CHUNK_SIZE));
+        if let Some(last) = self.chunks.last_mut() {
+            Arc::make_mut(last).shrink_to_fit();
+        }
+        self.chunks.shrink_to_fit();
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.chunks
+            .iter_mut()
+            .flat_map(|chunk| Arc::make_mut(chunk).iter_mut())
+    }
+
+    pub fn into_vec(self) -> Vec<T> {
+        self.chunks
+            .into_iter()
+            .flat_map(Arc::unwrap_or_clone)
+            .collect()
+    }
+
+    /// Remove every item, returning them in index order.
+    ///
+    /// The items are removed right away, even if the iterator isn't used,
+    /// and the memory of the store is released.
+    pub fn drain(&mut self) -> impl Iterator<Item = T> {
+        self.len = 0;
+        std::mem::take(&mut self.chunks)
+            .into_iter()
+            .flat_map(Arc::unwrap_or_clone)
+    }
+
+    /// Keep only the items for which `f` returns true.
+    ///
+    /// Like [`remove`](Self::remove), each removed item is replaced by the
+    /// last item that is kept, so at most one item moves per removed item.
+    /// Returns the old and new indexes of every item that was moved.
+    #[must_use = "DenseStore::retain() renames indexes; all external references must be replaced"]
+    pub fn retain<F: FnMut(Index<T>, &mut T) -> bool>(
+        &mut self,
+        mut f: F,
+    ) -> Vec<(Index<T>, Index<T>)> {
+        let mut moved = Vec::new();
+        let mut index = 0;
+        while index < self.len {
+            let dest = Index::from_raw(index);
+            if f(dest, self.get_mut(dest).unwrap()) {
+                index += 1;
+                continue;
+            }
+            // Pop items until one is kept, and move it into the gap.
+            loop {
+                let src = Index::from_raw(self.len - 1);
+                let mut item = self.pop().unwrap();
+                if src == dest {
+                    break;
+                }
+                if f(src, &mut item) {
+                    *self.get_mut(dest).unwrap() = item;
+                    moved.push((src, dest));
+                    index += 1;
+                    break;
+                }
+            }
+        }
+        moved
+    }
+
     #[must_use = "DenseStore::remove() renames an index; all external references must be replaced"]
     pub fn remove(&mut self, index: Index<T>) -> Option<(T, Rename<T>)> {
         let raw = index.into_raw();
-        if self.items.get(raw).is_some() {
-            let removed = self.items.swap_remove(raw);
-            Some((
-                removed,
-                Rename {
-                    src: Index::from_raw(self.items.len()),
-                    dest: index,
-                },
-            ))
-        } else {
-            None
+        self.get(index)?;
+        let mut removed = self.pop().unwrap();
+        if raw != self.len {
+            removed = std::mem::replace(self.get_mut(index).unwrap(), removed);
         }
+        Some((
+            removed,
+            Rename {
+                src: Index::from_raw(self.len),
+                dest: index,
+            },
+        ))
     }
 }
 
+impl<T> Clone for DenseStore<T> {
+    fn clone(&self) -> Self {
+        Self {
+            len: self.len,
+            chunks: self.chunks.clone(),
+        }
+    }
+}
+
+impl<T> Default for DenseStore<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Debug> Debug for DenseStore<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// The position of an item in a [`DenseStore`].
 pub struct Index<T>(usize, PhantomData<T>);
 
 impl<T> Index<T> {
@@ -69,7 +234,7 @@ impl<T> Debug for Index<T> {
 
 impl<T> Clone for Index<T> {
     fn clone(&self) -> Self {
-        Self(self.0, PhantomData)
+        *self
     }
 }
 
@@ -83,12 +248,26 @@ impl<T> PartialEq for Index<T> {
 
 impl<T> Eq for Index<T> {}
 
+impl<T> PartialOrd for Index<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Index<T> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
 impl<T> Hash for Index<T> {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.0.hash(state);
     }
 }
 
+/// An item that was moved from `src` to `dest` to fill the place of a
+/// removed one.
 pub struct Rename<T> {
     pub src: Index<T>,
     pub dest: Index<T>,
@@ -1,4 +1,8 @@
-//! Miscellaneous internal utilities.
+//! Miscellaneous internal utilities. The stores are public through
+//! [`collections`](crate::collections).
 
 pub mod dense_store;
 pub mod object_store;
+#[cfg(feature = "serde")]
+pub mod serde_hex;
+pub mod small_set;
@@ -3,6 +3,7 @@ use std::{
     hash::{Hash, Hasher},
     marker::PhantomData,
     mem::replace,
+    sync::Arc,
 };
 
 /// An object store that stores a free-list inline with its elements.
@@ -16,131 +17,431 @@ use std::{
 /// Instead of storing this stack separately; the cost of the free-list stack is
 /// amortized by storing it inline with the items themselves. This may require
 /// up to 8 bytes in addition to the size of each item.
+///
+/// Entries are stored in reference-counted chunks, so cloning the store is
+/// cheap: clones share their chunks until one of them modifies a chunk, which
+/// then copies just that chunk.
+///
+/// Each entry counts how many times it has been reused, and addresses carry
+/// the count from when they were handed out, so an address of a removed item
+/// never refers to an item inserted in its place later.
+///
+/// ```
+/// use blotter::collections::ObjectStore;
+///
+/// let mut store = ObjectStore::new();
+/// let a = store.insert("a");
+/// let b = store.insert("b");
+/// assert_eq!(store.remove(a), Some("a"));
+/// let c = store.insert("c");
+/// assert!(!store.contains(a));
+/// assert_eq!(store.get(b), Some(&"b"));
+/// assert_eq!(store.get(c), Some(&"c"));
+/// assert_eq!(store.len(), 2);
+/// ```
 pub struct ObjectStore<T> {
     first_vacant: usize,
-    entries: Vec<Entry<T>>,
+    /// The number of entries, both occupied and vacant.
+    num_entries: usize,
+    /// The number of occupied entries.
+    len: usize,
+    /// The generation of entries appended past the end, which is later than
+    /// that of any entry that [`compact`](Self::compact) dropped, so their
+    /// old addresses stay invalid.
+    fresh_generation: u32,
+    chunks: Vec<Arc<Vec<Entry<T>>>>,
 }
 
 impl<T> ObjectStore<T> {
     pub fn new() -> Self {
         Self {
             first_vacant: usize::MAX,
-            entries: Vec::new(),
+            num_entries: 0,
+            len: 0,
+            fresh_generation: 0,
+            chunks: Vec::new(),
         }
     }
 
-    // pub fn contains(&self, address: Address<T>) -> bool {
-    //     self.get(address).is_some()
-    // }
+    /// The number of items in the store.
+    pub fn len(&self) -> usize {
+        self.len
+    }
 
-    pub fn insert(&mut self, item: T) -> Address<T> {
-        if let Some(entry) = self.entries.get_mut(self.first_vacant) {
-            let address = Address::from_raw(self.first_vacant);
-            let replaced = replace(entry, Entry::Occupied(item));
-            match replaced {
-                Entry::Vacant { next_vacant } => {
-                    self.first_vacant = next_vacant;
-                }
-                _ => {
-                    unreachable!("occupied entry in free list");
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn contains(&self, address: Address<T>) -> bool {
+        self.get(address).is_some()
+    }
+
+    /// The number of items the store can hold without allocating, counting
+    /// the entries of removed items.
+    pub fn capacity(&self) -> usize {
+        let allocated: usize = self.chunks.iter().map(|chunk| chunk.capacity()).sum();
+        allocated - self.len
+    }
+
+    pub fn get(&self, address: Address<T>) -> Option<&T> {
+        self.entry(address.into_raw())
+            .and_then(|entry| entry.get(address.generation))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Address<T>, &T)> {
+        self.chunks
+            .iter()
+            .flat_map(|chunk| chunk.iter())
+            .enumerate()
+            .flat_map(|(index, entry)| match entry {
+                Entry::Occupied { generation, item } => {
+                    Some((Address::new(index, *generation), item))
                 }
-            }
-            address
+                Entry::Vacant { .. } => None,
+            })
+    }
+
+    fn entry(&self, index: usize) -> Option<&Entry<T>> {
+        self.chunks.get(index / CHUNK_SIZE)?.get(index % CHUNK_SIZE)
+    }
+}
+
+impl<T: Clone> ObjectStore<T> {
+    /// Make room for at least `additional` more items.
+    pub fn reserve(&mut self, additional: usize) {
+        let vacant = self.num_entries - self.len;
+        let needed = self.num_entries + additional.saturating_sub(vacant);
+        reserve_chunks(&mut self.chunks, self.num_entries, needed);
+    }
+
+    pub fn insert(&mut self, item: T) -> Address<T> {
+        let first_vacant = self.first_vacant;
+        if let Some(entry) = self.entry_mut(first_vacant) {
+            let Entry::Vacant {
+                next_vacant,
+                generation,
+            } = *entry
+            else {
+                unreachable!("occupied entry in free list");
+            };
+            let generation = generation.wrapping_add(1);
+            *entry = Entry::Occupied { generation, item };
+            self.first_vacant = next_vacant;
+            self.len += 1;
+            Address::new(first_vacant, generation)
         } else {
-            let address = Address::from_raw(self.entries.len());
-            self.entries.push(Entry::Occupied(item));
-            address
+            self.push(item, self.fresh_generation)
         }
     }
 
-    pub fn get(&self, address: Address<T>) -> Option<&T> {
-        self.entries.get(address.into_raw()).and_then(Entry::get)
+    /// Append an item in a new entry with the given generation.
+    fn push(&mut self, item: T, generation: u32) -> Address<T> {
+        let address = Address::new(self.num_entries, generation);
+        let chunk = self.num_entries / CHUNK_SIZE;
+        if chunk == self.chunks.len() {
+            self.chunks.push(Arc::new(Vec::with_capacity(CHUNK_SIZE)));
+        }
+        Arc::make_mut(&mut self.chunks[chunk]).push(Entry::Occupied { generation, item });
+        self.num_entries += 1;
+        self.len += 1;
+        address
     }
 
     pub fn get_mut(&mut self, address: Address<T>) -> Option<&mut T> {
-        self.entries
-            .get_mut(address.into_raw())
-            .and_then(Entry::get_mut)
+        // Check first, so that looking up a vacant entry doesn't copy its
+        // chunk.
+        self.get(address)?;
+        self.entry_mut(address.into_raw())
+            .and_then(|entry| entry.get_mut(address.generation))
     }
 
     pub fn remove(&mut self, address: Address<T>) -> Option<T> {
         let index = address.into_raw();
+        self.get(address)?;
+
+        let vacant = Entry::Vacant {
+            next_vacant: self.first_vacant,
+            generation: address.generation,
+        };
+        let replaced = replace(self.entry_mut(index).unwrap(), vacant);
+        self.first_vacant = index;
+        self.len -= 1;
+        match replaced {
+            Entry::Occupied { item, .. } => Some(item),
+            _ => unreachable!("occupied is not occupied?"),
+        }
+    }
 
-        match self.entries.get(index)? {
-            Entry::Occupied(_) => {
-                let replaced = replace(
-                    &mut self.entries[index],
-                    Entry::Vacant {
-                        next_vacant: self.first_vacant,
-                    },
-                );
-                self.first_vacant = index;
-                match replaced {
-                    Entry::Occupied(x) => Some(x),
-                    _ => unreachable!("occupied is not occupied?"),
+    /// Move all items to the front of the store, removing any vacant entries
+    /// between them, and release unused memory.
+    ///
+    /// Items keep their relative order. Returns the old and new addresses of
+    /// every item that was moved. A moved item's new address has a later
+    /// generation than any earlier address of its entry, and addresses of
+    /// removed items stay invalid.
+    ///
+    /// ```
+    /// use blotter::collections::ObjectStore;
+    ///
+    /// let mut store = ObjectStore::new();
+    /// let a = store.insert("a");
+    /// let b = store.insert("b");
+    /// let c = store.insert("c");
+    /// store.remove(a);
+    /// store.remove(c);
+    /// let moved = store.compact();
+    /// let (_, new_b) = moved[0];
+    /// assert_eq!(moved, [(b, new_b)]);
+    ///
+    /// // Entries that are used again get new addresses.
+    /// let d = store.insert("d");
+    /// assert_eq!(store.get(a), None);
+    /// assert_eq!(store.get(b), None);
+    /// assert_eq!(store.get(c), None);
+    /// assert_ne!(d, b);
+    /// assert_ne!(d, c);
+    /// assert_eq!(store.get(d), Some(&"d"));
+    /// ```
+    pub fn compact(&mut self) -> Vec<(Address<T>, Address<T>)> {
+        let generations: Vec<u32> = self
+            .chunks
+            .iter()
+            .flat_map(|chunk| chunk.iter())
+            .map(Entry::generation)
+            .collect();
+        let mut moved = Vec::new();
+        let mut compacted = Self::new();
+        compacted.fresh_generation = self.fresh_generation;
+        for (index, entry) in std::mem::take(&mut self.chunks)
+            .into_iter()
+            .flat_map(Arc::unwrap_or_clone)
+            .enumerate()
+        {
+            if let Entry::Occupied { generation, item } = entry {
+                let new_index = compacted.num_entries;
+                if new_index == index {
+                    compacted.push(item, generation);
+                } else {
+                    let new = compacted.push(item, generations[new_index].wrapping_add(1));
+                    moved.push((Address::new(index, generation), new));
                 }
             }
-            Entry::Vacant { .. } => None,
         }
+        // Entries past the new end are dropped, and may be appended again.
+        for &generation in &generations[compacted.num_entries..] {
+            let next = generation.wrapping_add(1);
+            if next > compacted.fresh_generation {
+                compacted.fresh_generation = next;
+            }
+        }
+        if let Some(last) = compacted.chunks.last_mut() {
+            Arc::make_mut(last).shrink_to_fit();
+        }
+        compacted.chunks.shrink_to_fit();
+        *self = compacted;
+        moved
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = (Address<T>, &T)> {
-        self.entries
-            .iter()
+    /// Keep only the items for which `f` returns true. The addresses of the
+    /// kept items don't change.
+    pub fn retain<F: FnMut(Address<T>, &mut T) -> bool>(&mut self, mut f: F) {
+        let removed: Vec<Address<T>> = self
+            .iter_mut()
+            .filter_map(|(address, item)| (!f(address, item)).then_some(address))
+            .collect();
+        for address in removed {
+            self.remove(address);
+        }
+    }
+
+    /// Remove every item, returning them with their addresses in address
+    /// order.
+    ///
+    /// The items are removed right away, even if the iterator isn't used.
+    /// Their addresses stay invalid, like those of items removed one at a
+    /// time, and the memory of the store is kept for new items, which fill
+    /// it from the front again.
+    pub fn drain(&mut self) -> impl Iterator<Item = (Address<T>, T)> {
+        let num_entries = self.num_entries;
+        let mut drained = Vec::with_capacity(self.len);
+        for (index, entry) in self
+            .chunks
+            .iter_mut()
+            .flat_map(|chunk| Arc::make_mut(chunk).iter_mut())
             .enumerate()
-            .flat_map(|(index, entry)| entry.get().map(|item| (Address::from_raw(index), item)))
+        {
+            let vacant = Entry::Vacant {
+                next_vacant: if index + 1 < num_entries {
+                    index + 1
+                } else {
+                    usize::MAX
+                },
+                generation: entry.generation(),
+            };
+            if let Entry::Occupied { generation, item } = replace(entry, vacant) {
+                drained.push((Address::new(index, generation), item));
+            }
+        }
+        self.first_vacant = if num_entries > 0 { 0 } else { usize::MAX };
+        self.len = 0;
+        drained.into_iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Address<T>, &mut T)> {
+        self.chunks
+            .iter_mut()
+            .flat_map(|chunk| Arc::make_mut(chunk).iter_mut())
+            .enumerate()
+            .flat_map(|(index, entry)| match entry {
+                Entry::Occupied { generation, item } => {
+                    Some((Address::new(index, *generation), item))
+                }
+                Entry::Vacant { .. } => None,
+            })
     }
 
-    // pub fn iter_mut(&mut self) -> impl Iterator<Item = (Address<T>, &mut T)> {
-    //     self.entries
-    //         .iter_mut()
-    //         .enumerate()
-    //         .flat_map(|(index, entry)| entry.get_mut().map(|item| (Address::from_raw(index), item)))
-    // }
+    fn entry_mut(&mut self, index: usize) -> Option<&mut Entry<T>> {
+        let chunk = self.chunks.get_mut(index / CHUNK_SIZE)?;
+        Arc::make_mut(chunk).get_mut(index % CHUNK_SIZE)
+    }
+}
+
+impl<T> Clone for ObjectStore<T> {
+    fn clone(&self) -> Self {
+        Self {
+            first_vacant: self.first_vacant,
+            num_entries: self.num_entries,
+            fresh_generation: self.fresh_generation,
+            len: self.len,
+            chunks: self.chunks.clone(),
+        }
+    }
 }
 
+impl<T> Default for ObjectStore<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Debug> Debug for ObjectStore<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// The number of entries in each shared chunk of a store.
+pub(crate) const CHUNK_SIZE: usize = 256;
+
+/// Allocate chunks of a store with `len` entries so that they can hold
+/// `needed` entries.
+pub(crate) fn reserve_chunks<E: Clone>(chunks: &mut Vec<Arc<Vec<E>>>, len: usize, needed: usize) {
+    if needed <= len {
+        return;
+    }
+    if let Some(last) = chunks.get_mut(len / CHUNK_SIZE) {
+        let last = Arc::make_mut(last);
+        last.reserve_exact(CHUNK_SIZE - last.len());
+    }
+    while chunks.len() * CHUNK_SIZE < needed {
+        chunks.push(Arc::new(Vec::with_capacity(CHUNK_SIZE)));
+    }
+}
+
+/// An entry of the store. The generation of an occupied entry is that of
+/// the address of its item; a vacant entry keeps the generation of the last
+/// item it held.
+#[derive(Clone)]
 enum Entry<T> {
-    Vacant { next_vacant: usize },
-    Occupied(T),
+    Vacant { next_vacant: usize, generation: u32 },
+    Occupied { generation: u32, item: T },
 }
 
 impl<T> Entry<T> {
-    fn get(&self) -> Option<&T> {
+    fn generation(&self) -> u32 {
+        match *self {
+            Self::Vacant { generation, .. } | Self::Occupied { generation, .. } => generation,
+        }
+    }
+
+    fn get(&self, address_generation: u32) -> Option<&T> {
         match self {
-            Self::Occupied(x) => Some(x),
+            Self::Occupied { generation, item } if *generation == address_generation => Some(item),
             _ => None,
         }
     }
 
-    fn get_mut(&mut self) -> Option<&mut T> {
+    fn get_mut(&mut self, address_generation: u32) -> Option<&mut T> {
         match self {
-            Self::Occupied(x) => Some(x),
+            Self::Occupied { generation, item } if *generation == address_generation => Some(item),
             _ => None,
         }
     }
 }
 
-pub struct Address<T>(usize, PhantomData<T>);
+/// The index of an entry in an [`ObjectStore`], and the generation of the
+/// entry when the address was handed out.
+pub struct Address<T> {
+    index: u32,
+    generation: u32,
+    _marker: PhantomData<T>,
+}
 
 impl<T> Address<T> {
+    fn new(index: usize, generation: u32) -> Self {
+        Self {
+            index: index.try_into().expect("object store is too large"),
+            generation,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The address of the first item at an index, in the first generation.
     pub fn from_raw(raw: usize) -> Self {
-        Self(raw, PhantomData)
+        Self::new(raw, 0)
     }
 
+    /// The index of the address, without its generation.
     pub fn into_raw(self) -> usize {
-        self.0
+        self.index as usize
+    }
+
+    /// How many times the entry had been reused when the address was handed
+    /// out.
+    pub fn generation(self) -> u32 {
+        self.generation
+    }
+
+    /// The index and generation packed into one number, which is the same
+    /// as the index in the first generation.
+    pub(crate) fn to_bits(self) -> u64 {
+        u64::from(self.generation) << 32 | u64::from(self.index)
+    }
+
+    /// The inverse of [`to_bits`](Self::to_bits).
+    pub(crate) fn from_bits(bits: u64) -> Self {
+        Self {
+            index: bits as u32,
+            generation: (bits >> 32) as u32,
+            _marker: PhantomData,
+        }
     }
 }
 
 impl<T> Debug for Address<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_tuple("Address").field(&self.0).finish()
+        let mut tuple = f.debug_tuple("Address");
+        tuple.field(&self.index);
+        if self.generation != 0 {
+            tuple.field(&self.generation);
+        }
+        tuple.finish()
     }
 }
 
 impl<T> Clone for Address<T> {
     fn clone(&self) -> Self {
-        Self(self.0, PhantomData)
+        *self
     }
 }
 
@@ -148,14 +449,46 @@ impl<T> Copy for Address<T> {}
 
 impl<T> PartialEq for Address<T> {
     fn eq(&self, other: &Self) -> bool {
-        self.0 == other.0
+        self.to_bits() == other.to_bits()
     }
 }
 
 impl<T> Eq for Address<T> {}
 
+impl<T> PartialOrd for Address<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Addresses are ordered by index, then by generation.
+impl<T> Ord for Address<T> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.index, self.generation).cmp(&(other.index, other.generation))
+    }
+}
+
 impl<T> Hash for Address<T> {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.0.hash(state);
+        self.to_bits().hash(state);
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T> serde::Serialize for Address<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_bits().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T> serde::Deserialize<'de> for Address<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bits = u64::deserialize(deserializer)?;
+        Ok(Self {
+            index: bits as u32,
+            generation: (bits >> 32) as u32,
+            _marker: PhantomData,
+        })
     }
 }
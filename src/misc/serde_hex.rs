@@ -0,0 +1,55 @@
+//! Serde adapters that encode byte blobs as lowercase hex strings.
+
+use serde::{de::Error as _, Deserialize, Deserializer, Serializer};
+
+pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&encode(bytes))
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    let string = String::deserialize(deserializer)?;
+    decode(&string).ok_or_else(|| D::Error::custom("invalid hex string"))
+}
+
+pub mod option {
+    use serde::{de::Error as _, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        bytes: &Option<Vec<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match bytes {
+            Some(bytes) => serializer.serialize_some(&super::encode(bytes)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<u8>>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(string) => super::decode(&string)
+                .map(Some)
+                .ok_or_else(|| D::Error::custom("invalid hex string")),
+            None => Ok(None),
+        }
+    }
+}
+
+fn encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn decode(string: &str) -> Option<Vec<u8>> {
+    if !string.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..string.len())
+        .step_by(2)
+        .map(|i| {
+            string
+                .get(i..i + 2)
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+        })
+        .collect()
+}
@@ -0,0 +1,220 @@
+use std::{
+    collections::{hash_set, HashSet},
+    fmt::Debug,
+    hash::Hash,
+    slice,
+};
+
+/// The most items that are kept in a vector before switching to a hash set.
+const MAX_SMALL: usize = 8;
+
+/// A set for items that are usually few in number.
+///
+/// Up to [`MAX_SMALL`] items are stored in a vector, which is searched
+/// linearly and grows one step at a time, so a set of one or two items only
+/// allocates room for those. Larger sets are stored in a boxed hash set. An
+/// empty set doesn't allocate, and the set itself is the size of a vector.
+#[derive(Clone)]
+pub struct SmallSet<T>(Repr<T>);
+
+#[derive(Clone)]
+enum Repr<T> {
+    Small(Vec<T>),
+    // Boxed, so that small sets don't pay for the size of a hash set.
+    #[allow(clippy::box_collection)]
+    Large(Box<HashSet<T>>),
+}
+
+impl<T: Eq + Hash> SmallSet<T> {
+    pub fn new() -> Self {
+        Self(Repr::Small(Vec::new()))
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.0 {
+            Repr::Small(items) => items.len(),
+            Repr::Large(items) => items.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, item: &T) -> bool {
+        match &self.0 {
+            Repr::Small(items) => items.contains(item),
+            Repr::Large(items) => items.contains(item),
+        }
+    }
+
+    /// Add an item, returning whether it was not already in the set.
+    pub fn insert(&mut self, item: T) -> bool {
+        match &mut self.0 {
+            Repr::Small(items) => {
+                if items.contains(&item) {
+                    return false;
+                }
+                if items.len() < MAX_SMALL {
+                    if items.len() == items.capacity() {
+                        items.reserve_exact(items.len().max(1));
+                    }
+                    items.push(item);
+                } else {
+                    let mut large: HashSet<T> = items.drain(..).collect();
+                    large.insert(item);
+                    self.0 = Repr::Large(Box::new(large));
+                }
+                true
+            }
+            Repr::Large(items) => items.insert(item),
+        }
+    }
+
+    /// Remove an item, returning whether it was in the set.
+    pub fn remove(&mut self, item: &T) -> bool {
+        match &mut self.0 {
+            Repr::Small(items) => match items.iter().position(|x| x == item) {
+                Some(index) => {
+                    items.swap_remove(index);
+                    true
+                }
+                None => false,
+            },
+            Repr::Large(items) => items.remove(item),
+        }
+    }
+
+    /// An item of this set that is also in `other`, if there is one.
+    pub fn common<'a>(&'a self, other: &Self) -> Option<&'a T> {
+        self.iter().find(|item| other.contains(item))
+    }
+
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        self.common(other).is_none()
+    }
+
+    /// Iterate over the items, in no particular order.
+    pub fn iter(&self) -> Iter<'_, T> {
+        match &self.0 {
+            Repr::Small(items) => Iter::Small(items.iter()),
+            Repr::Large(items) => Iter::Large(items.iter()),
+        }
+    }
+}
+
+impl<T: Eq + Hash> Default for SmallSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Eq + Hash + Debug> Debug for SmallSet<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Eq + Hash> Extend<T> for SmallSet<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.insert(item);
+        }
+    }
+}
+
+impl<T: Eq + Hash> FromIterator<T> for SmallSet<T> {
+    /// Collect items into a set with no spare capacity.
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut items: Vec<T> = Vec::new();
+        let mut iter = iter.into_iter();
+        for item in iter.by_ref() {
+            if !items.contains(&item) {
+                items.push(item);
+                if items.len() > MAX_SMALL {
+                    let mut large: HashSet<T> = items.into_iter().chain(iter).collect();
+                    large.shrink_to_fit();
+                    return Self(Repr::Large(Box::new(large)));
+                }
+            }
+        }
+        items.shrink_to_fit();
+        Self(Repr::Small(items))
+    }
+}
+
+impl<'a, T: Eq + Hash> IntoIterator for &'a SmallSet<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Eq + Hash> IntoIterator for SmallSet<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self.0 {
+            Repr::Small(items) => IntoIter::Small(items.into_iter()),
+            Repr::Large(items) => IntoIter::Large(items.into_iter()),
+        }
+    }
+}
+
+pub enum Iter<'a, T> {
+    Small(slice::Iter<'a, T>),
+    Large(hash_set::Iter<'a, T>),
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Small(iter) => iter.next(),
+            Self::Large(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            Self::Small(iter) => iter.size_hint(),
+            Self::Large(iter) => iter.size_hint(),
+        }
+    }
+}
+
+impl<T> Clone for Iter<'_, T> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Small(iter) => Self::Small(iter.clone()),
+            Self::Large(iter) => Self::Large(iter.clone()),
+        }
+    }
+}
+
+pub enum IntoIter<T> {
+    Small(std::vec::IntoIter<T>),
+    Large(hash_set::IntoIter<T>),
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Small(iter) => iter.next(),
+            Self::Large(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            Self::Small(iter) => iter.size_hint(),
+            Self::Large(iter) => iter.size_hint(),
+        }
+    }
+}
@@ -0,0 +1,318 @@
+//! Managing the mods that a save depends on.
+//!
+//! Component type strings are namespaced by the mod that defines them, like
+//! `"MHG.Inverter"`. Every namespace used in a save, other than the vanilla
+//! ones, should have a matching entry in the save's mod list.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use crate::latest::{BlotterFile, ModInfo};
+use crate::sandbox::DEFAULT_COMPONENT_TYPES;
+
+/// Namespaces of component types that ship with the game.
+pub const VANILLA_NAMESPACES: &[&str] = &["MHG"];
+
+/// Whether a component type ships with the game.
+pub fn is_vanilla_type(type_string: &str) -> bool {
+    DEFAULT_COMPONENT_TYPES
+        .iter()
+        .any(|&(_, name)| name == type_string)
+}
+
+/// The namespace of a component type string: everything before the first
+/// dot.
+pub fn namespace(type_string: &str) -> &str {
+    type_string.split('.').next().unwrap_or(type_string)
+}
+
+/// The name of a component type within its namespace: everything after the
+/// first dot, or the whole string if there is none.
+pub fn local_name(type_string: &str) -> &str {
+    type_string
+        .split_once('.')
+        .map_or(type_string, |(_, name)| name)
+}
+
+/// Whether a component type string matches a query, ignoring ASCII case.
+///
+/// A query with a dot, like `"mhg.inverter"`, has to match the whole type
+/// string. A query without one, like `"inverter"`, matches the type's
+/// [local name](local_name) in any namespace.
+///
+/// ```
+/// use blotter::mods::type_matches;
+///
+/// assert!(type_matches("MHG.Inverter", "MHG.Inverter"));
+/// assert!(type_matches("MHG.Inverter", "mhg.inverter"));
+/// assert!(type_matches("MHG.Inverter", "Inverter"));
+/// assert!(!type_matches("MHG.Inverter", "Other.Inverter"));
+/// ```
+pub fn type_matches(type_string: &str, query: &str) -> bool {
+    if query.contains('.') {
+        type_string.eq_ignore_ascii_case(query)
+    } else {
+        local_name(type_string).eq_ignore_ascii_case(query)
+    }
+}
+
+/// The component types that ship with the game.
+///
+/// Each variant's discriminant is the numeric ID that a new sandbox gives
+/// the type. Parsing accepts the type string in any ASCII case, with or
+/// without its namespace:
+///
+/// ```
+/// use blotter::mods::KnownComponent;
+///
+/// assert_eq!("MHG.Inverter".parse(), Ok(KnownComponent::Inverter));
+/// assert_eq!("dlatch".parse(), Ok(KnownComponent::DLatch));
+/// assert_eq!(KnownComponent::BufferWithOutput.to_string(), "MHG.Buffer_WithOutput");
+/// assert!("MHG.Teleporter".parse::<KnownComponent>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u16)]
+pub enum KnownComponent {
+    Inverter = 0,
+    XorGate,
+    AndGate,
+    Delayer,
+    DLatch,
+    Randomizer,
+    Relay,
+    BufferWithOutput,
+    Buffer,
+    CircuitBoard,
+    Mount,
+    Peg,
+    ThroughPeg,
+    Socket,
+    ThroughSocket,
+    ChubbySocket,
+    ChubbyThroughSocket,
+    Label,
+    PanelLabel,
+    Chair,
+    Flag,
+    StandingDisplay,
+    PanelDisplay,
+    Singer,
+    Drum,
+    Switch,
+    PanelSwitch,
+    Button,
+    PanelButton,
+    Key,
+    PanelKey,
+}
+
+impl KnownComponent {
+    /// Every vanilla type, in order of numeric ID.
+    pub const ALL: [Self; 31] = [
+        Self::Inverter,
+        Self::XorGate,
+        Self::AndGate,
+        Self::Delayer,
+        Self::DLatch,
+        Self::Randomizer,
+        Self::Relay,
+        Self::BufferWithOutput,
+        Self::Buffer,
+        Self::CircuitBoard,
+        Self::Mount,
+        Self::Peg,
+        Self::ThroughPeg,
+        Self::Socket,
+        Self::ThroughSocket,
+        Self::ChubbySocket,
+        Self::ChubbyThroughSocket,
+        Self::Label,
+        Self::PanelLabel,
+        Self::Chair,
+        Self::Flag,
+        Self::StandingDisplay,
+        Self::PanelDisplay,
+        Self::Singer,
+        Self::Drum,
+        Self::Switch,
+        Self::PanelSwitch,
+        Self::Button,
+        Self::PanelButton,
+        Self::Key,
+        Self::PanelKey,
+    ];
+
+    /// The type string, like `"MHG.Inverter"`.
+    pub fn as_str(self) -> &'static str {
+        DEFAULT_COMPONENT_TYPES[self as usize].1
+    }
+
+    /// The numeric ID that a new sandbox gives the type. Loaded saves may
+    /// number types differently.
+    pub fn default_type_id(self) -> u16 {
+        self as u16
+    }
+
+    /// The vanilla type with exactly this type string.
+    pub fn from_type_string(type_string: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|known| known.as_str() == type_string)
+    }
+}
+
+impl Display for KnownComponent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl AsRef<str> for KnownComponent {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl FromStr for KnownComponent {
+    type Err = UnknownComponent;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_type_string(s)
+            .or_else(|| {
+                Self::ALL
+                    .into_iter()
+                    .find(|known| type_matches(known.as_str(), s))
+            })
+            .ok_or_else(|| UnknownComponent(s.to_owned()))
+    }
+}
+
+/// A string that doesn't name a [`KnownComponent`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownComponent(pub String);
+
+/// A comparison of the mods that a save declares with the mods it uses.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModRequirements {
+    /// Every non-vanilla namespace used by a component type.
+    pub used: BTreeSet<String>,
+    /// Namespaces that are used, but have no mod entry.
+    pub missing: Vec<String>,
+    /// Mods that are declared more than once, with different versions.
+    pub mismatched: Vec<(String, Vec<[i32; 4]>)>,
+    /// Mods that are declared, but not used by any component type.
+    pub unused: Vec<String>,
+}
+
+impl ModRequirements {
+    /// Compare declared mods with used component types.
+    pub fn check<'a, I>(mods: &[ModInfo], type_strings: I) -> Self
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let used: BTreeSet<String> = type_strings
+            .into_iter()
+            .map(namespace)
+            .filter(|ns| !VANILLA_NAMESPACES.contains(ns))
+            .map(str::to_owned)
+            .collect();
+
+        let mut declared: BTreeMap<&str, Vec<[i32; 4]>> = BTreeMap::new();
+        for mod_info in mods {
+            let versions = declared.entry(&mod_info.mod_id).or_default();
+            if !versions.contains(&mod_info.mod_version) {
+                versions.push(mod_info.mod_version);
+            }
+        }
+
+        Self {
+            missing: used
+                .iter()
+                .filter(|ns| !declared.contains_key(ns.as_str()))
+                .cloned()
+                .collect(),
+            mismatched: declared
+                .iter()
+                .filter(|(_, versions)| versions.len() > 1)
+                .map(|(id, versions)| (id.to_string(), versions.clone()))
+                .collect(),
+            unused: declared
+                .keys()
+                .filter(|id| !used.contains(**id))
+                .map(|id| id.to_string())
+                .collect(),
+            used,
+        }
+    }
+
+    /// Whether every used namespace is declared exactly once.
+    pub fn is_satisfied(&self) -> bool {
+        self.missing.is_empty() && self.mismatched.is_empty()
+    }
+}
+
+impl Display for ModRequirements {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for id in &self.missing {
+            writeln!(f, "missing mod: {}", id)?;
+        }
+        for (id, versions) in &self.mismatched {
+            writeln!(f, "conflicting versions of mod {}: {:?}", id, versions)?;
+        }
+        for id in &self.unused {
+            writeln!(f, "unused mod: {}", id)?;
+        }
+        Ok(())
+    }
+}
+
+/// Add `mod_info` to `mods`, replacing any entry with the same ID.
+pub(crate) fn add_mod(mods: &mut Vec<ModInfo>, mod_info: ModInfo) {
+    mods.retain(|m| m.mod_id != mod_info.mod_id);
+    mods.push(mod_info);
+}
+
+/// Remove every entry with the given ID, returning the first one removed.
+pub(crate) fn remove_mod(mods: &mut Vec<ModInfo>, mod_id: &str) -> Option<ModInfo> {
+    let index = mods.iter().position(|m| m.mod_id == mod_id)?;
+    let removed = mods.remove(index);
+    mods.retain(|m| m.mod_id != mod_id);
+    Some(removed)
+}
+
+impl BlotterFile {
+    pub fn find_mod(&self, mod_id: &str) -> Option<&ModInfo> {
+        self.mods.iter().find(|m| m.mod_id == mod_id)
+    }
+
+    /// Declare a mod dependency, replacing any existing entry for the same
+    /// mod.
+    pub fn add_mod(&mut self, mod_info: ModInfo) {
+        add_mod(&mut self.mods, mod_info)
+    }
+
+    pub fn remove_mod(&mut self, mod_id: &str) -> Option<ModInfo> {
+        remove_mod(&mut self.mods, mod_id)
+    }
+
+    /// Compare the declared mods with the types of the components in the
+    /// save.
+    ///
+    /// Only types that are in use count; entries in the component type table
+    /// with no components are ignored.
+    pub fn required_mods(&self) -> ModRequirements {
+        let used: BTreeSet<u16> = self
+            .components
+            .iter()
+            .map(|component| component.type_id)
+            .collect();
+        ModRequirements::check(
+            &self.mods,
+            self.component_types
+                .iter()
+                .filter(|ctype| used.contains(&ctype.numeric_id))
+                .map(|ctype| ctype.text_id.as_str()),
+        )
+    }
+}
@@ -0,0 +1,642 @@
+//! Read-only queries directly over raw save bytes.
+//!
+//! [`SaveIndex`] scans a save once to find where each component is stored,
+//! and decodes components on demand. This is much cheaper than building a
+//! [`Sandbox`](crate::sandbox::Sandbox) for tools that only need to look
+//! things up, especially together with [`MappedSave`] (requires the `mmap`
+//! feature), which avoids reading the whole file up front.
+//!
+//! [`SaveCounts`] goes even further: it streams through a save without
+//! keeping anything, and only counts what it contains. [`BlotterHeader`]
+//! doesn't look past the header at all.
+//!
+//! [`extract_region`] copies part of a large world into a new save, without
+//! reading the rest of the world into memory.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::error::Error;
+use crate::geometry::{Aabb, Position, Transform};
+use crate::io::{
+    read_counted_with, read_footer, read_magic, read_until_magic, skip_until_magic, Budget,
+    ReadFrom, ReadFromSeed,
+};
+use crate::latest::SaveType;
+use crate::limits::{Limit, ReadLimits};
+use crate::{latest, v5, v6};
+
+/// The smallest number of bytes a component can occupy in a save.
+const MIN_COMPONENT_SIZE: usize = 50;
+
+/// The number of bytes a wire occupies in a save: two peg addresses, a
+/// circuit state ID and a rotation.
+const WIRE_SIZE: u64 = 2 * 9 + 4 + 4;
+
+/// An index of the components in a raw save.
+///
+/// Components are identified by their position in the save, from `0` to
+/// `len() - 1`.
+pub struct SaveIndex<'a> {
+    data: &'a [u8],
+    save_version: u8,
+    component_types: HashMap<u16, String>,
+    offsets: Vec<usize>,
+    type_ids: Vec<u16>,
+    parents: Vec<Option<usize>>,
+    addresses: HashMap<u32, usize>,
+    children: HashMap<usize, Vec<usize>>,
+    roots: Vec<usize>,
+    by_type: HashMap<u16, Vec<usize>>,
+}
+
+impl<'a> SaveIndex<'a> {
+    /// Scan the header and components of a save.
+    ///
+    /// Only the fixed-size fields of each component are decoded; pegs and
+    /// custom data are skipped over. Wires and circuit states are not read.
+    pub fn new(data: &'a [u8]) -> Result<Self, Error> {
+        let mut cursor = data;
+        let budget = &mut byte_budget(data);
+        read_magic(&mut cursor, latest::SAVE_HEADER)?;
+        let save_version = u8::read_from(&mut cursor)?;
+        if save_version != v5::SAVE_VERSION && save_version != v6::SAVE_VERSION {
+            return Err(Error::IncompatibleVersion(save_version));
+        }
+        <[i32; 4]>::read_from(&mut cursor)?;
+        v5::SaveType::read_from(&mut cursor)?;
+        let num_components = usize::read_from(&mut cursor)?;
+        let _num_wires = usize::read_from(&mut cursor)?;
+
+        let num_mods = usize::read_from(&mut cursor)?;
+        for _ in 0..num_mods {
+            v5::ModInfo::read_from_seed(&mut cursor, &mut *budget)?;
+        }
+        let num_component_types = usize::read_from(&mut cursor)?;
+        let mut component_types = HashMap::new();
+        for _ in 0..num_component_types {
+            let ctype = v5::ComponentType::read_from_seed(&mut cursor, &mut *budget)?;
+            component_types.insert(ctype.numeric_id, ctype.text_id);
+        }
+
+        // Don't trust the count for allocation until it is known to fit.
+        if num_components.saturating_mul(MIN_COMPONENT_SIZE) > cursor.len() {
+            return Err(Error::InvalidSave);
+        }
+
+        let mut offsets = Vec::with_capacity(num_components);
+        let mut type_ids = Vec::with_capacity(num_components);
+        let mut parent_addresses = Vec::with_capacity(num_components);
+        let mut addresses = HashMap::with_capacity(num_components);
+        for index in 0..num_components {
+            offsets.push(data.len() - cursor.len());
+            let address = u32::read_from(&mut cursor)?;
+            parent_addresses.push(u32::read_from(&mut cursor)?);
+            type_ids.push(u16::read_from(&mut cursor)?);
+            // Position and rotation.
+            skip(&mut cursor, 12 + 16)?;
+            let num_inputs = usize::read_from(&mut cursor)?;
+            skip(&mut cursor, num_inputs.saturating_mul(4))?;
+            let num_outputs = usize::read_from(&mut cursor)?;
+            skip(&mut cursor, num_outputs.saturating_mul(4))?;
+            let custom_data_len = i32::read_from(&mut cursor)?;
+            if custom_data_len >= 0 {
+                skip(&mut cursor, custom_data_len as usize)?;
+            }
+            addresses.insert(address, index);
+        }
+
+        let mut parents = Vec::with_capacity(num_components);
+        let mut children: HashMap<usize, Vec<usize>> = HashMap::new();
+        let mut roots = Vec::new();
+        let mut by_type: HashMap<u16, Vec<usize>> = HashMap::new();
+        for (index, &parent_address) in parent_addresses.iter().enumerate() {
+            let parent = match parent_address {
+                0 => None,
+                address => Some(*addresses.get(&address).ok_or(Error::InvalidSave)?),
+            };
+            match parent {
+                Some(parent) => children.entry(parent).or_default().push(index),
+                None => roots.push(index),
+            }
+            parents.push(parent);
+            by_type.entry(type_ids[index]).or_default().push(index);
+        }
+
+        Ok(Self {
+            data,
+            save_version,
+            component_types,
+            offsets,
+            type_ids,
+            parents,
+            addresses,
+            children,
+            roots,
+            by_type,
+        })
+    }
+
+    /// The number of components in the save.
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Decode a whole component.
+    ///
+    /// Components from older save versions are converted to the latest
+    /// version.
+    pub fn component(&self, index: usize) -> Result<latest::Component, Error> {
+        let offset = *self.offsets.get(index).ok_or(Error::InvalidSave)?;
+        let mut cursor = &self.data[offset..];
+        let budget = &mut byte_budget(cursor);
+        if self.save_version == v5::SAVE_VERSION {
+            v5::Component::read_from_seed(&mut cursor, budget).map(Into::into)
+        } else {
+            v6::Component::read_from_seed(&mut cursor, budget)
+        }
+    }
+
+    /// Decode up to `limit` components, skipping the first `offset`, in save
+    /// order.
+    ///
+    /// Components are only decoded as the iterator reaches them, so a page
+    /// of a huge save costs no more than the components on it.
+    ///
+    /// ```
+    /// use blotter::{latest::BlotterFile, query::SaveIndex, sandbox::{ComponentBuilder, Sandbox}};
+    ///
+    /// let mut sandbox = Sandbox::new();
+    /// for x in 0..5 {
+    ///     sandbox.add_component(&ComponentBuilder::new("MHG.Inverter").position([x, 0, 0]));
+    /// }
+    /// let mut bytes = Vec::new();
+    /// BlotterFile::from(&sandbox).write(&mut bytes).unwrap();
+    ///
+    /// let index = SaveIndex::new(&bytes).unwrap();
+    /// let page: Vec<_> = index.components_page(3, 10).map(Result::unwrap).collect();
+    /// assert_eq!(page.len(), 2);
+    /// assert_eq!(page[0].position, [3, 0, 0]);
+    /// ```
+    pub fn components_page(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> impl Iterator<Item = Result<latest::Component, Error>> + '_ {
+        let end = offset.saturating_add(limit).min(self.len());
+        (offset.min(end)..end).map(move |index| self.component(index))
+    }
+
+    /// Find a component by its address in the save.
+    pub fn find(&self, address: u32) -> Option<usize> {
+        self.addresses.get(&address).copied()
+    }
+
+    /// The text ID of a component's type, like `"MHG.Inverter"`.
+    pub fn type_name(&self, index: usize) -> Option<&str> {
+        let type_id = self.type_ids.get(index)?;
+        self.component_types.get(type_id).map(String::as_str)
+    }
+
+    /// All components of the given type, in save order.
+    pub fn components_of_type(&self, type_name: &str) -> &[usize] {
+        self.component_types
+            .iter()
+            .find(|(_, name)| *name == type_name)
+            .and_then(|(type_id, _)| self.by_type.get(type_id))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    pub fn parent(&self, index: usize) -> Option<usize> {
+        self.parents.get(index).copied().flatten()
+    }
+
+    pub fn children(&self, index: usize) -> &[usize] {
+        self.children.get(&index).map_or(&[], Vec::as_slice)
+    }
+
+    /// Components that have no parent, in save order.
+    pub fn roots(&self) -> &[usize] {
+        &self.roots
+    }
+}
+
+/// The start of a save: everything before its components.
+///
+/// Reading just the header is much faster than reading the whole save, since
+/// it stops before the components, which make up nearly all of a save. This
+/// is meant for listing many saves at once, like in a save browser.
+///
+/// ```
+/// use blotter::{latest::BlotterFile, query::BlotterHeader, sandbox::{ComponentBuilder, Sandbox}};
+///
+/// let mut sandbox = Sandbox::new();
+/// sandbox.add_component(&ComponentBuilder::new("MHG.Inverter"));
+/// let mut bytes = Vec::new();
+/// BlotterFile::from(&sandbox).write(&mut bytes).unwrap();
+///
+/// let header = BlotterHeader::read(&mut bytes.as_slice()).unwrap();
+/// assert_eq!(header.components, 1);
+/// assert_eq!(header.wires, 0);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlotterHeader {
+    pub save_version: u8,
+    pub game_version: [i32; 4],
+    pub save_type: SaveType,
+    pub mods: Vec<latest::ModInfo>,
+    pub component_types: Vec<latest::ComponentType>,
+    /// The number of components in the save.
+    pub components: usize,
+    /// The number of wires in the save.
+    pub wires: usize,
+}
+
+impl BlotterHeader {
+    /// Read the header of a save of any supported version, leaving the
+    /// reader at the first component.
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        Self::read_with_limits(reader, &ReadLimits::unlimited())
+    }
+
+    /// Read the header of a save, failing if it exceeds any of the given
+    /// limits.
+    pub fn read_with_limits<R: Read>(reader: &mut R, limits: &ReadLimits) -> Result<Self, Error> {
+        let budget = &mut Budget::new(*limits);
+        read_magic(reader, latest::SAVE_HEADER)?;
+        let save_version = u8::read_from(reader)?;
+        if save_version != v5::SAVE_VERSION && save_version != v6::SAVE_VERSION {
+            return Err(Error::IncompatibleVersion(save_version));
+        }
+        let game_version = <[i32; 4]>::read_from(reader)?;
+        let save_type = SaveType::read_from(reader)?;
+        let components = usize::read_from(reader)?;
+        let wires = usize::read_from(reader)?;
+        budget.check(Limit::Components, components)?;
+        budget.check(Limit::Wires, wires)?;
+
+        let num_mods = usize::read_from(reader)?;
+        let mods = read_counted_with(reader, num_mods, Limit::Mods, budget)?;
+        let num_component_types = usize::read_from(reader)?;
+        let component_types =
+            read_counted_with(reader, num_component_types, Limit::ComponentTypes, budget)?;
+
+        Ok(Self {
+            save_version,
+            game_version,
+            save_type,
+            mods,
+            component_types,
+            components,
+            wires,
+        })
+    }
+}
+
+/// The sizes of the parts of a save, found without decoding it.
+///
+/// This is meant for quick audits of huge files, and for pre-passes that
+/// need totals up front, like progress bars.
+///
+/// ```
+/// use blotter::{latest::BlotterFile, query::SaveCounts, sandbox::{ComponentBuilder, Sandbox}};
+///
+/// let mut sandbox = Sandbox::new();
+/// sandbox.add_component(&ComponentBuilder::new("MHG.Inverter").num_inputs(1).num_outputs(1));
+/// let mut bytes = Vec::new();
+/// BlotterFile::from(&sandbox).write(&mut bytes).unwrap();
+///
+/// let counts = SaveCounts::scan(&mut bytes.as_slice()).unwrap();
+/// assert_eq!(counts.components, 1);
+/// assert_eq!(counts.pegs, 2);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SaveCounts {
+    pub save_version: u8,
+    pub game_version: [i32; 4],
+    pub save_type: SaveType,
+    pub mods: usize,
+    pub component_types: usize,
+    pub components: usize,
+    /// The total number of input and output pegs.
+    pub pegs: usize,
+    pub wires: usize,
+    /// How many components have custom data.
+    pub components_with_custom_data: usize,
+    /// The total length of all custom data.
+    pub custom_data_bytes: u64,
+    /// The size of the circuit state section, not counting its length
+    /// prefix.
+    pub circuit_state_bytes: u64,
+    /// The size of the extra data before the footer of a v6 save, which
+    /// only newer games would write.
+    pub extra_bytes: u64,
+}
+
+impl SaveCounts {
+    /// Stream through a save, skipping over everything but the lengths of
+    /// its records.
+    ///
+    /// Nothing larger than a single field is allocated, so this works on
+    /// files of any size, including through a decompressing reader. The save
+    /// is read up to and including its footer.
+    pub fn scan<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        let budget = &mut Budget::unlimited();
+        read_magic(reader, latest::SAVE_HEADER)?;
+        let save_version = u8::read_from(reader)?;
+        if save_version != v5::SAVE_VERSION && save_version != v6::SAVE_VERSION {
+            return Err(Error::IncompatibleVersion(save_version));
+        }
+        let game_version = <[i32; 4]>::read_from(reader)?;
+        let save_type = SaveType::read_from(reader)?;
+        let components = usize::read_from(reader)?;
+        let wires = usize::read_from(reader)?;
+
+        let mods = usize::read_from(reader)?;
+        for _ in 0..mods {
+            v5::ModInfo::read_from_seed(reader, &mut *budget)?;
+        }
+        let component_types = usize::read_from(reader)?;
+        for _ in 0..component_types {
+            v5::ComponentType::read_from_seed(reader, &mut *budget)?;
+        }
+
+        let mut counts = Self {
+            save_version,
+            game_version,
+            save_type,
+            mods,
+            component_types,
+            components,
+            pegs: 0,
+            wires,
+            components_with_custom_data: 0,
+            custom_data_bytes: 0,
+            circuit_state_bytes: 0,
+            extra_bytes: 0,
+        };
+        for _ in 0..components {
+            // Address, parent, type, position and rotation.
+            skip_reader(reader, 4 + 4 + 2 + 12 + 16)?;
+            for _ in 0..2 {
+                let num_pegs = usize::read_from(reader)?;
+                skip_reader(reader, num_pegs as u64 * 4)?;
+                counts.pegs += num_pegs;
+            }
+            let custom_data_len = i32::read_from(reader)?;
+            if custom_data_len >= 0 {
+                skip_reader(reader, custom_data_len as u64)?;
+                counts.components_with_custom_data += 1;
+                counts.custom_data_bytes += custom_data_len as u64;
+            }
+        }
+        skip_reader(reader, wires as u64 * WIRE_SIZE)?;
+
+        let num_states = usize::read_from(reader)? as u64;
+        counts.circuit_state_bytes = match save_type {
+            SaveType::World => num_states,
+            SaveType::Subassembly => num_states * 4,
+        };
+        skip_reader(reader, counts.circuit_state_bytes)?;
+        if save_version == v5::SAVE_VERSION {
+            read_footer(reader, latest::SAVE_FOOTER)?;
+        } else {
+            counts.extra_bytes = skip_until_magic(reader, latest::SAVE_FOOTER)?;
+        }
+
+        Ok(counts)
+    }
+}
+
+/// Copy the part of a save that lies within a region of the world into a new
+/// save.
+///
+/// A component is in the region if its origin is. The new save also contains
+/// the ancestors of those components, so they stay in place, and every wire
+/// between two components it contains. Circuit states are renumbered densely,
+/// keeping whether each one is on.
+///
+/// The save is read twice: once to find which components to keep, decoding
+/// only their positions, and once to decode just those components. Neither
+/// pass keeps the rest of the save in memory, so this works on worlds much
+/// larger than the region.
+pub fn extract_region<R: Read + Seek>(
+    reader: &mut R,
+    region: &Aabb,
+) -> Result<latest::BlotterFile, Error> {
+    let budget = &mut Budget::unlimited();
+    let header = BlotterHeader::read(reader)?;
+    let is_v5 = header.save_version == v5::SAVE_VERSION;
+
+    // First pass: where each component is stored, and where it is in the
+    // world.
+    let mut offset = reader.stream_position()?;
+    let mut offsets = Vec::new();
+    let mut parents: Vec<Option<usize>> = Vec::new();
+    let mut transforms: Vec<Transform> = Vec::new();
+    let mut addresses: HashMap<u32, usize> = HashMap::new();
+    for index in 0..header.components {
+        offsets.push(offset);
+        let address = u32::read_from(reader)?;
+        let parent_address = u32::read_from(reader)?;
+        u16::read_from(reader)?;
+        let translation = if is_v5 {
+            <[f32; 3]>::read_from(reader)?
+        } else {
+            Position(<[i32; 3]>::read_from(reader)?).to_meters()
+        };
+        let rotation = <[f32; 4]>::read_from(reader)?;
+        let mut size = 4 + 4 + 2 + 12 + 16;
+        for _ in 0..2 {
+            let num_pegs = usize::read_from(reader)? as u64;
+            skip_reader(reader, num_pegs * 4)?;
+            size += 4 + num_pegs * 4;
+        }
+        let custom_data_len = i32::read_from(reader)?;
+        size += 4;
+        if custom_data_len >= 0 {
+            skip_reader(reader, custom_data_len as u64)?;
+            size += custom_data_len as u64;
+        }
+        offset += size;
+
+        let parent = match parent_address {
+            0 => None,
+            address => Some(*addresses.get(&address).ok_or(Error::InvalidSave)?),
+        };
+        let local = Transform {
+            translation,
+            rotation,
+        };
+        transforms.push(match parent {
+            Some(parent) => transforms[parent].then(&local),
+            None => local,
+        });
+        parents.push(parent);
+        addresses.insert(address, index);
+    }
+
+    let mut kept = vec![false; header.components];
+    for (index, transform) in transforms.iter().enumerate() {
+        if !region.contains(transform.translation) {
+            continue;
+        }
+        let mut current = Some(index);
+        while let Some(index) = current.filter(|&index| !kept[index]) {
+            kept[index] = true;
+            current = parents[index];
+        }
+    }
+    drop(transforms);
+
+    let is_kept = |address: u32| addresses.get(&address).is_some_and(|&index| kept[index]);
+    let mut wires = Vec::new();
+    for _ in 0..header.wires {
+        let wire = latest::Wire::read_from(reader)?;
+        if is_kept(wire.start_peg.component_address) && is_kept(wire.end_peg.component_address) {
+            wires.push(wire);
+        }
+    }
+    let circuit_states =
+        latest::CircuitStates::read_from_seed(reader, (header.save_type, &mut *budget))?;
+    let extra = if is_v5 {
+        read_footer(reader, latest::SAVE_FOOTER)?;
+        Vec::new()
+    } else {
+        read_until_magic(reader, latest::SAVE_FOOTER, &mut *budget)?
+    };
+
+    // Second pass: decode the components that are kept.
+    let mut components = Vec::new();
+    for (index, &offset) in offsets.iter().enumerate() {
+        if !kept[index] {
+            continue;
+        }
+        reader.seek(SeekFrom::Start(offset))?;
+        components.push(if is_v5 {
+            v5::Component::read_from_seed(reader, &mut *budget)?.into()
+        } else {
+            v6::Component::read_from_seed(reader, &mut *budget)?
+        });
+    }
+
+    // Renumber circuit states in the order they are first used.
+    let mut state_map: HashMap<i32, i32> = HashMap::new();
+    let mut old_states = Vec::new();
+    let mut renumber = |id: &mut i32| {
+        *id = *state_map.entry(*id).or_insert_with(|| {
+            old_states.push(*id);
+            (old_states.len() - 1) as i32
+        });
+    };
+    for component in &mut components {
+        for peg in &mut component.inputs {
+            renumber(&mut peg.circuit_state_id);
+        }
+        for peg in &mut component.outputs {
+            renumber(&mut peg.circuit_state_id);
+        }
+    }
+    for wire in &mut wires {
+        renumber(&mut wire.circuit_state_id);
+    }
+    let circuit_states = match circuit_states {
+        latest::CircuitStates::WorldFormat { circuit_states } => {
+            let is_on = |id: i32| {
+                usize::try_from(id)
+                    .ok()
+                    .and_then(|id| {
+                        circuit_states
+                            .get(id / 8)
+                            .map(|byte| byte & (1 << (id % 8)))
+                    })
+                    .is_some_and(|bit| bit != 0)
+            };
+            let mut states = vec![0u8; old_states.len().div_ceil(8)];
+            for (new, &old) in old_states.iter().enumerate() {
+                if is_on(old) {
+                    states[new / 8] |= 1 << (new % 8);
+                }
+            }
+            latest::CircuitStates::WorldFormat {
+                circuit_states: states,
+            }
+        }
+        latest::CircuitStates::SubassemblyFormat { on_states } => {
+            let on: HashSet<i32> = on_states.into_iter().collect();
+            latest::CircuitStates::SubassemblyFormat {
+                on_states: (0..old_states.len() as i32)
+                    .filter(|&new| on.contains(&old_states[new as usize]))
+                    .collect(),
+            }
+        }
+    };
+
+    Ok(latest::BlotterFile {
+        game_version: header.game_version,
+        save_type: header.save_type,
+        mods: header.mods,
+        component_types: header.component_types,
+        components,
+        wires,
+        circuit_states,
+        extra,
+        trailing: Vec::new(),
+    })
+}
+
+/// A budget that rejects allocations larger than the data could possibly
+/// hold, so that corrupt lengths fail early instead of allocating.
+fn byte_budget(data: &[u8]) -> Budget {
+    Budget::new(ReadLimits::unlimited().max_total_alloc(data.len()))
+}
+
+fn skip(cursor: &mut &[u8], len: usize) -> Result<(), Error> {
+    if len > cursor.len() {
+        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+    }
+    *cursor = &cursor[len..];
+    Ok(())
+}
+
+fn skip_reader<R: Read>(reader: &mut R, len: u64) -> Result<(), Error> {
+    let skipped = io::copy(&mut reader.take(len), &mut io::sink())?;
+    if skipped < len {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    Ok(())
+}
+
+/// A save file mapped into memory.
+#[cfg(feature = "mmap")]
+pub struct MappedSave {
+    map: memmap2::Mmap,
+}
+
+#[cfg(feature = "mmap")]
+impl MappedSave {
+    /// Map a save file into memory.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while it is mapped, by this
+    /// process or any other. Doing so is undefined behavior.
+    pub unsafe fn open<P: AsRef<std::path::Path>>(path: P) -> Result<Self, Error> {
+        let file = std::fs::File::open(path)?;
+        let map = memmap2::Mmap::map(&file)?;
+        Ok(Self { map })
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.map
+    }
+
+    /// Build an index over the mapped save.
+    pub fn index(&self) -> Result<SaveIndex<'_>, Error> {
+        SaveIndex::new(self.bytes())
+    }
+}
@@ -0,0 +1,216 @@
+//! Salvaging what can be read of a damaged save.
+//!
+//! A save that was cut short, say by a crash while it was being written,
+//! fails to read at all. [`BlotterFile::read_partial`] instead keeps
+//! everything before the damage, which is usually most of the world.
+//!
+//! ```
+//! use blotter::BlotterFile;
+//! use blotter::latest::{self, Component};
+//!
+//! let mut file = latest::BlotterFile::new([0, 91, 0, 510]);
+//! for address in 1..=3 {
+//!     file.components.push(Component {
+//!         address,
+//!         parent: 0,
+//!         type_id: 0,
+//!         position: [0; 3],
+//!         rotation: [0.0, 0.0, 0.0, 1.0],
+//!         inputs: Vec::new(),
+//!         outputs: Vec::new(),
+//!         custom_data: None,
+//!     });
+//! }
+//! let mut bytes = Vec::new();
+//! file.write(&mut bytes).unwrap();
+//!
+//! // Lose the end of the last component, and everything after it.
+//! let damaged = &bytes[..bytes.len() - 40];
+//! let (partial, error) = BlotterFile::read_partial(&mut &damaged[..]);
+//! assert!(error.is_some());
+//! assert_eq!(partial.expected_components, Some(3));
+//! assert_eq!(partial.components.len(), 2);
+//!
+//! let recovered = partial.into_file();
+//! assert_eq!(recovered.components, file.components[..2]);
+//! ```
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::error::Error;
+use crate::io::{
+    read_footer, read_magic, read_until_magic, Budget, IoError, Read, ReadFrom, ReadFromSeed,
+};
+use crate::latest::{self, CircuitStates, Component, ComponentType, ModInfo, SaveType, Wire};
+use crate::limits::{Limit, ReadLimits};
+use crate::{v5, v6, BlotterFile};
+
+/// The part of a save that could be read, in the latest version's terms.
+///
+/// Fields are filled in the order they are stored, so everything before the
+/// first missing one was read in full.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PartialFile {
+    /// The version of the save format, if the header could be read.
+    pub save_version: Option<u8>,
+    pub game_version: Option<[i32; 4]>,
+    pub save_type: Option<SaveType>,
+    /// The number of components that the save says it has.
+    pub expected_components: Option<usize>,
+    /// The number of wires that the save says it has.
+    pub expected_wires: Option<usize>,
+    pub mods: Vec<ModInfo>,
+    pub component_types: Vec<ComponentType>,
+    /// The components that were read in full, in save order.
+    pub components: Vec<Component>,
+    /// The wires that were read in full, in save order.
+    pub wires: Vec<Wire>,
+    pub circuit_states: Option<CircuitStates>,
+    pub extra: Vec<u8>,
+    /// The number of bytes up to the end of the last complete item, where
+    /// the damage starts.
+    pub offset: u64,
+}
+
+impl PartialFile {
+    /// A save with everything that was read, which can be loaded like any
+    /// other.
+    ///
+    /// A missing game version is all zeros, and a missing save type is a
+    /// world. Missing circuit states are all off.
+    pub fn into_file(self) -> latest::BlotterFile {
+        let save_type = self.save_type.unwrap_or(SaveType::World);
+        let mut file = latest::BlotterFile {
+            game_version: self.game_version.unwrap_or_default(),
+            save_type,
+            mods: self.mods,
+            component_types: self.component_types,
+            components: self.components,
+            wires: self.wires,
+            circuit_states: CircuitStates::SubassemblyFormat {
+                on_states: Vec::new(),
+            },
+            extra: self.extra,
+            trailing: Vec::new(),
+        };
+        file.circuit_states = match (self.circuit_states, save_type) {
+            (Some(circuit_states), _) => circuit_states,
+            (None, SaveType::World) => CircuitStates::WorldFormat {
+                circuit_states: vec![0; file.referenced_states().div_ceil(8)],
+            },
+            (None, SaveType::Subassembly) => CircuitStates::SubassemblyFormat {
+                on_states: Vec::new(),
+            },
+        };
+        file
+    }
+
+    fn read<R: Read>(
+        &mut self,
+        reader: &mut Counting<R>,
+        budget: &mut Budget,
+    ) -> Result<(), Error> {
+        read_magic(reader, latest::SAVE_HEADER)?;
+        let save_version = u8::read_from(reader)?;
+        if save_version != v5::SAVE_VERSION && save_version != v6::SAVE_VERSION {
+            return Err(Error::IncompatibleVersion(save_version));
+        }
+        self.save_version = Some(save_version);
+        self.game_version = Some(ReadFrom::read_from(reader)?);
+        let save_type = SaveType::read_from(reader)?;
+        self.save_type = Some(save_type);
+        let num_components = usize::read_from(reader)?;
+        self.expected_components = Some(num_components);
+        let num_wires = usize::read_from(reader)?;
+        self.expected_wires = Some(num_wires);
+        self.offset = reader.count;
+        budget.check(Limit::Components, num_components)?;
+        budget.check(Limit::Wires, num_wires)?;
+
+        let num_mods = usize::read_from(reader)?;
+        budget.check(Limit::Mods, num_mods)?;
+        for _ in 0..num_mods {
+            self.mods
+                .push(ModInfo::read_from_seed(reader, &mut *budget)?);
+            self.offset = reader.count;
+        }
+        let num_component_types = usize::read_from(reader)?;
+        budget.check(Limit::ComponentTypes, num_component_types)?;
+        for _ in 0..num_component_types {
+            let component_type = ComponentType::read_from_seed(reader, &mut *budget)?;
+            self.component_types.push(component_type);
+            self.offset = reader.count;
+        }
+        for _ in 0..num_components {
+            let component = if save_version == v5::SAVE_VERSION {
+                v5::Component::read_from_seed(reader, &mut *budget)?.into()
+            } else {
+                Component::read_from_seed(reader, &mut *budget)?
+            };
+            self.components.push(component);
+            self.offset = reader.count;
+        }
+        for _ in 0..num_wires {
+            budget.alloc(size_of::<Wire>())?;
+            self.wires.push(Wire::read_from(reader)?);
+            self.offset = reader.count;
+        }
+        let circuit_states = CircuitStates::read_from_seed(reader, (save_type, &mut *budget))?;
+        self.circuit_states = Some(circuit_states);
+        self.offset = reader.count;
+
+        if save_version == v5::SAVE_VERSION {
+            read_footer(reader, latest::SAVE_FOOTER)?;
+        } else {
+            self.extra = read_until_magic(reader, latest::SAVE_FOOTER, budget)?;
+        }
+        self.offset = reader.count;
+        Ok(())
+    }
+}
+
+impl BlotterFile {
+    /// Read as much of a save as possible, stopping at the first error.
+    ///
+    /// Returns what was read, along with the error, if there was one. Saves
+    /// of older versions are migrated on the way, as with
+    /// [`migrate`](Self::migrate).
+    ///
+    /// Damage can make any length in the save huge, so this reads with
+    /// [`ReadLimits::untrusted`], and stops with
+    /// [`Error::LimitExceeded`] at anything bigger. To salvage larger saves,
+    /// use [`read_partial_with_limits`](Self::read_partial_with_limits).
+    pub fn read_partial<R: Read>(reader: &mut R) -> (PartialFile, Option<Error>) {
+        Self::read_partial_with_limits(reader, &ReadLimits::untrusted())
+    }
+
+    /// Read as much of a save as possible like
+    /// [`read_partial`](Self::read_partial), with the given limits.
+    pub fn read_partial_with_limits<R: Read>(
+        reader: &mut R,
+        limits: &ReadLimits,
+    ) -> (PartialFile, Option<Error>) {
+        let mut partial = PartialFile::default();
+        let mut reader = Counting {
+            inner: reader,
+            count: 0,
+        };
+        let error = partial.read(&mut reader, &mut Budget::new(*limits)).err();
+        (partial, error)
+    }
+}
+
+/// A reader that counts the bytes read through it.
+struct Counting<R> {
+    inner: R,
+    count: u64,
+}
+
+impl<R: Read> Read for Counting<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+        let len = self.inner.read(buf)?;
+        self.count += len as u64;
+        Ok(len)
+    }
+}
@@ -0,0 +1,199 @@
+//! User annotations attached to components and wires.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use super::{ComponentId, Remap, Sandbox, WireId};
+
+/// A component or wire that annotations can be attached to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Entity {
+    Component(ComponentId),
+    Wire(WireId),
+}
+
+impl Entity {
+    fn remap(self, remap: &Remap) -> Self {
+        match self {
+            Self::Component(id) => Self::Component(remap.component(id)),
+            Self::Wire(id) => Self::Wire(remap.wire(id)),
+        }
+    }
+}
+
+impl From<ComponentId> for Entity {
+    fn from(id: ComponentId) -> Self {
+        Self::Component(id)
+    }
+}
+
+impl From<WireId> for Entity {
+    fn from(id: WireId) -> Self {
+        Self::Wire(id)
+    }
+}
+
+pub(super) type Annotations = HashMap<Entity, BTreeMap<String, Vec<u8>>>;
+
+impl Sandbox {
+    fn contains_entity(&self, entity: Entity) -> bool {
+        match entity {
+            Entity::Component(id) => self.is_valid(id),
+            Entity::Wire(id) => self.wires.get(id.0).is_some(),
+        }
+    }
+
+    /// Attach a value to a component or wire, returning the value that was
+    /// there before.
+    ///
+    /// Annotations map string keys to bytes on each component and wire, for
+    /// tools to keep track of what things are across several passes, like
+    /// which peg of a generated adder is its carry-out. They are never saved:
+    /// they are only kept in memory, and go away with the component or wire
+    /// that they are attached to. They are carried along when components are
+    /// copied or merged from a shard, and follow their entity through
+    /// [`compact`](Sandbox::compact).
+    ///
+    /// Nothing is attached to a component or wire that isn't in the sandbox.
+    ///
+    /// ```
+    /// use blotter::sandbox::component::Peg;
+    /// use blotter::sandbox::Sandbox;
+    ///
+    /// let mut sandbox = Sandbox::new();
+    /// let peg = sandbox.add_component(&Peg::new().build());
+    /// sandbox.set_annotation(peg, "role", "carry-out");
+    ///
+    /// assert_eq!(sandbox.annotation(peg, "role"), Some(&b"carry-out"[..]));
+    /// assert_eq!(sandbox.annotated("role"), [peg.into()]);
+    ///
+    /// sandbox.remove_component(peg);
+    /// assert!(sandbox.annotated("role").is_empty());
+    /// ```
+    pub fn set_annotation(
+        &mut self,
+        entity: impl Into<Entity>,
+        key: impl Into<String>,
+        value: impl Into<Vec<u8>>,
+    ) -> Option<Vec<u8>> {
+        let entity = entity.into();
+        if !self.contains_entity(entity) {
+            return None;
+        }
+        Arc::make_mut(&mut self.annotations)
+            .entry(entity)
+            .or_default()
+            .insert(key.into(), value.into())
+    }
+
+    pub fn annotation(&self, entity: impl Into<Entity>, key: &str) -> Option<&[u8]> {
+        self.annotations
+            .get(&entity.into())?
+            .get(key)
+            .map(Vec::as_slice)
+    }
+
+    pub fn remove_annotation(&mut self, entity: impl Into<Entity>, key: &str) -> Option<Vec<u8>> {
+        let entity = entity.into();
+        if !self.annotations.contains_key(&entity) {
+            return None;
+        }
+        let annotations = Arc::make_mut(&mut self.annotations);
+        let values = annotations.get_mut(&entity).unwrap();
+        let value = values.remove(key);
+        if values.is_empty() {
+            annotations.remove(&entity);
+        }
+        value
+    }
+
+    /// Iterate over the annotations of a component or wire, by key.
+    pub fn annotations(&self, entity: impl Into<Entity>) -> impl Iterator<Item = (&str, &[u8])> {
+        self.annotations
+            .get(&entity.into())
+            .into_iter()
+            .flatten()
+            .map(|(key, value)| (key.as_str(), value.as_slice()))
+    }
+
+    /// Every component and wire with a value for `key`, components first,
+    /// in ID order.
+    pub fn annotated(&self, key: &str) -> Vec<Entity> {
+        let mut entities: Vec<Entity> = self
+            .annotations
+            .iter()
+            .filter(|(_, values)| values.contains_key(key))
+            .map(|(&entity, _)| entity)
+            .collect();
+        entities.sort_unstable();
+        entities
+    }
+
+    /// Remove all annotations from every component and wire.
+    pub fn clear_annotations(&mut self) {
+        self.annotations = Arc::default();
+    }
+
+    /// Attach a value to a component or wire, encoded as JSON.
+    #[cfg(feature = "json")]
+    pub fn set_annotation_value<T: serde::Serialize>(
+        &mut self,
+        entity: impl Into<Entity>,
+        key: impl Into<String>,
+        value: &T,
+    ) -> Result<(), crate::error::Error> {
+        let value = serde_json::to_vec(value)?;
+        self.set_annotation(entity, key, value);
+        Ok(())
+    }
+
+    /// Decode a value that was attached with
+    /// [`set_annotation_value`](Self::set_annotation_value).
+    #[cfg(feature = "json")]
+    pub fn annotation_value<T: serde::de::DeserializeOwned>(
+        &self,
+        entity: impl Into<Entity>,
+        key: &str,
+    ) -> Option<Result<T, crate::error::Error>> {
+        let value = self.annotation(entity, key)?;
+        Some(serde_json::from_slice(value).map_err(Into::into))
+    }
+
+    /// Drop the annotations of a component or wire that is being removed.
+    pub(super) fn forget_annotations(&mut self, entity: Entity) {
+        if self.annotations.contains_key(&entity) {
+            Arc::make_mut(&mut self.annotations).remove(&entity);
+        }
+    }
+
+    /// Move annotations to the new IDs of its components and wires.
+    pub(super) fn remap_annotations(&mut self, remap: &Remap) {
+        if self.annotations.is_empty() || remap.is_empty() {
+            return;
+        }
+        self.annotations = Arc::new(
+            Arc::unwrap_or_clone(std::mem::take(&mut self.annotations))
+                .into_iter()
+                .map(|(entity, values)| (entity.remap(remap), values))
+                .collect(),
+        );
+    }
+
+    /// Copy the annotations of components and wires that were copied from
+    /// `source`, as listed in `remap`.
+    pub(super) fn copy_annotations(&mut self, source: &Sandbox, remap: &Remap) {
+        if source.annotations.is_empty() {
+            return;
+        }
+        let copied: Vec<(Entity, BTreeMap<String, Vec<u8>>)> = source
+            .annotations
+            .iter()
+            .filter(|(entity, _)| match entity {
+                Entity::Component(id) => remap.components.contains_key(id),
+                Entity::Wire(id) => remap.wires.contains_key(id),
+            })
+            .map(|(&entity, values)| (entity.remap(remap), values.clone()))
+            .collect();
+        Arc::make_mut(&mut self.annotations).extend(copied);
+    }
+}
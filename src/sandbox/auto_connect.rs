@@ -0,0 +1,249 @@
+//! Heuristic wiring of nearby components.
+
+use std::collections::HashSet;
+
+use crate::geometry::Rotation;
+
+use super::{AddWireError, ComponentId, ComponentInfo, PegAddress, PegType, Sandbox};
+
+/// Options for [`Sandbox::auto_connect`].
+#[derive(Debug, Clone)]
+pub struct AutoConnect {
+    max_distance: i32,
+    require_alignment: bool,
+    dry_run: bool,
+    peg_offset: fn(&str, PegType, usize) -> [i32; 3],
+}
+
+impl AutoConnect {
+    pub fn new() -> Self {
+        Self {
+            max_distance: 900,
+            require_alignment: true,
+            dry_run: false,
+            peg_offset: |_, _, _| [0; 3],
+        }
+    }
+
+    /// The maximum distance between two components (in fixed-point position
+    /// units) for them to be considered for a connection.
+    pub fn max_distance(self, max_distance: i32) -> Self {
+        Self {
+            max_distance,
+            ..self
+        }
+    }
+
+    /// Only connect components that lie on a common axis, i.e. whose positions
+    /// differ in at most one coordinate.
+    pub fn require_alignment(self, require_alignment: bool) -> Self {
+        Self {
+            require_alignment,
+            ..self
+        }
+    }
+
+    /// Report the proposed wires without adding them to the sandbox.
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    /// Where a peg is relative to the origin of its component, given the
+    /// component's type name, the peg's type and its index. The offset is in
+    /// fixed-point position units, before the component is rotated.
+    ///
+    /// By default every peg is taken to be at the origin of its component.
+    pub fn peg_offset(self, peg_offset: fn(&str, PegType, usize) -> [i32; 3]) -> Self {
+        Self { peg_offset, ..self }
+    }
+}
+
+impl Default for AutoConnect {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A wire proposed by [`Sandbox::auto_connect`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProposedWire {
+    pub output: PegAddress,
+    pub input: PegAddress,
+    /// Distance between the two pegs, in fixed-point position units.
+    pub distance: f32,
+}
+
+impl Sandbox {
+    /// Wire unconnected input pegs to the outputs of nearby components.
+    ///
+    /// Only components within `selection` are considered, and only components
+    /// that share a parent, since positions are relative to the parent. Each
+    /// unconnected input peg is driven by the closest eligible component that
+    /// has outputs; pegs are matched up by index where possible.
+    ///
+    /// Pegs are placed by [`AutoConnect::peg_offset`], rotated along with
+    /// their component, and point along their component's local up axis.
+    /// Pegs that point away from each other aren't connected. Two components
+    /// are only ever wired in one direction: once one drives the other,
+    /// earlier in selection order, the other can't drive it back.
+    ///
+    /// Returns the wires that were added, or in dry-run mode, the wires that
+    /// would have been added. If the sandbox refuses any of the wires, none
+    /// are added.
+    ///
+    /// ```
+    /// use blotter::sandbox::component::Delayer;
+    /// use blotter::sandbox::{AutoConnect, Sandbox};
+    ///
+    /// let mut sandbox = Sandbox::new();
+    /// let a = sandbox.add_component(&Delayer::new().build());
+    /// let b = sandbox.add_component(&Delayer::new().build().position([300, 0, 0]));
+    ///
+    /// let wires = sandbox.auto_connect(&[a, b], &AutoConnect::new()).unwrap();
+    /// assert_eq!(wires.len(), 1);
+    /// assert_eq!(wires[0].output.component, b);
+    /// assert_eq!(wires[0].input.component, a);
+    ///
+    /// // The pegs of a component stacked right on top point away from those
+    /// // below it.
+    /// let c = sandbox.add_component(&Delayer::new().build().position([300, 300, 0]));
+    /// let wires = sandbox.auto_connect(&[b, c], &AutoConnect::new()).unwrap();
+    /// assert!(wires.is_empty());
+    /// ```
+    pub fn auto_connect(
+        &mut self,
+        selection: &[ComponentId],
+        options: &AutoConnect,
+    ) -> Result<Vec<ProposedWire>, AddWireError> {
+        let proposed = self.propose_connections(selection, options);
+        if !options.dry_run {
+            self.add_wires(proposed.iter().map(|wire| (wire.output, wire.input, 0.0)))?;
+        }
+        Ok(proposed)
+    }
+
+    fn propose_connections(
+        &self,
+        selection: &[ComponentId],
+        options: &AutoConnect,
+    ) -> Vec<ProposedWire> {
+        let mut proposed = Vec::new();
+        // Pairs of components where the first drives the second.
+        let mut drives: HashSet<(ComponentId, ComponentId)> = HashSet::new();
+        let mut seen = HashSet::new();
+
+        for &input_id in selection {
+            if !seen.insert(input_id) {
+                continue;
+            }
+            let input_component = match self.components.get(input_id.0) {
+                Some(x) => x,
+                None => continue,
+            };
+
+            for (peg_index, peg) in input_component.inputs.iter().enumerate() {
+                if !peg.wires.is_empty() {
+                    // Already connected; leave it alone.
+                    continue;
+                }
+                let input = PegAddress {
+                    component: input_id,
+                    peg_type: PegType::Input,
+                    peg_index,
+                };
+
+                // Find the closest component that can drive this input.
+                let mut best: Option<(PegAddress, f32)> = None;
+                for &output_id in selection {
+                    if output_id == input_id || drives.contains(&(input_id, output_id)) {
+                        continue;
+                    }
+                    let output_component = match self.components.get(output_id.0) {
+                        Some(x) => x,
+                        None => continue,
+                    };
+                    if output_component.outputs.is_empty()
+                        || output_component.parent != input_component.parent
+                    {
+                        continue;
+                    }
+                    let output = PegAddress {
+                        component: output_id,
+                        peg_type: PegType::Output,
+                        peg_index: peg_index.min(output_component.outputs.len() - 1),
+                    };
+
+                    let Some(distance) = self.peg_distance(
+                        (output_component, output),
+                        (input_component, input),
+                        options,
+                    ) else {
+                        continue;
+                    };
+                    if best.is_none_or(|(_, best_distance)| distance < best_distance) {
+                        best = Some((output, distance));
+                    }
+                }
+
+                if let Some((output, distance)) = best {
+                    drives.insert((output.component, input_id));
+                    proposed.push(ProposedWire {
+                        output,
+                        input,
+                        distance,
+                    });
+                }
+            }
+        }
+
+        proposed
+    }
+
+    /// The distance from an output peg to an input peg, or `None` if they
+    /// can't be connected under `options`.
+    fn peg_distance(
+        &self,
+        (output_component, output): (&ComponentInfo, PegAddress),
+        (input_component, input): (&ComponentInfo, PegAddress),
+        options: &AutoConnect,
+    ) -> Option<f32> {
+        let placement = |component: &ComponentInfo, peg: PegAddress| {
+            let type_name = self.component_types.name_str(component.type_id);
+            let offset = (options.peg_offset)(type_name, peg.peg_type, peg.peg_index);
+            let rotation = Rotation(component.rotation);
+            (
+                rotation.apply(offset.map(|x| x as f32)),
+                rotation.apply([0.0, 1.0, 0.0]),
+            )
+        };
+        let (output_offset, output_up) = placement(output_component, output);
+        let (input_offset, input_up) = placement(input_component, input);
+
+        // Far-apart positions can differ by more than an `i32`, or be too far
+        // apart for an `f32` to hold the difference exactly.
+        let delta: [f64; 3] = [0, 1, 2].map(|axis| {
+            (i64::from(output_component.position[axis]) - i64::from(input_component.position[axis]))
+                as f64
+                + f64::from(output_offset[axis])
+                - f64::from(input_offset[axis])
+        });
+        if options.require_alignment && delta.iter().filter(|d| d.abs() >= 0.5).count() > 1 {
+            return None;
+        }
+        let distance = delta.iter().map(|d| d * d).sum::<f64>().sqrt();
+        if distance > f64::from(options.max_distance) {
+            return None;
+        }
+
+        // Each peg has to point towards the other, or at least not away
+        // from it.
+        let facing = |up: [f32; 3], towards: [f64; 3]| {
+            let dot: f64 = (0..3).map(|axis| f64::from(up[axis]) * towards[axis]).sum();
+            dot >= -1e-3 * distance
+        };
+        if !facing(input_up, delta) || !facing(output_up, delta.map(|d| -d)) {
+            return None;
+        }
+        Some(distance as f32)
+    }
+}
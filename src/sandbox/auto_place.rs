@@ -0,0 +1,334 @@
+//! Placement of components on a grid that keeps their wires short.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::geometry::Position;
+
+use super::{ComponentId, NetId, PegType, Sandbox};
+
+/// Options for [`Sandbox::auto_place`].
+#[derive(Debug, Clone)]
+pub struct AutoPlace {
+    columns: Option<u32>,
+    moves_per_component: u32,
+    seed: u64,
+}
+
+impl AutoPlace {
+    pub fn new() -> Self {
+        Self {
+            columns: None,
+            moves_per_component: 1000,
+            seed: 0,
+        }
+    }
+
+    /// The number of columns of the grid. By default, the grid is as close
+    /// to square as possible.
+    pub fn columns(self, columns: u32) -> Self {
+        assert!(columns > 0);
+        Self {
+            columns: Some(columns),
+            ..self
+        }
+    }
+
+    /// How many moves to try for each component. More moves give shorter
+    /// wires, up to a point, and take proportionally longer.
+    pub fn moves_per_component(self, moves_per_component: u32) -> Self {
+        Self {
+            moves_per_component,
+            ..self
+        }
+    }
+
+    /// The seed of the random moves. The same seed, components and
+    /// connections always give the same placement.
+    pub fn seed(self, seed: u64) -> Self {
+        Self { seed, ..self }
+    }
+}
+
+impl Default for AutoPlace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The outcome of [`Sandbox::auto_place`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutoPlaceReport {
+    /// The size of the grid, in squares, so that a board can be fit to it.
+    pub columns: u32,
+    pub rows: u32,
+    /// The estimated wire length before and after placement: the sum, over
+    /// each signal, of the width plus the depth of the squares it connects.
+    pub wire_length_before: u64,
+    pub wire_length_after: u64,
+}
+
+impl Sandbox {
+    /// Move components into a grid of squares, arranged so that connected
+    /// components are close together.
+    ///
+    /// Every component takes one square, at the center of which it is placed,
+    /// keeping its height. Positions are relative to the parent, so the
+    /// components should share one; usually they are the children of a
+    /// single board, which can be resized to the grid afterwards. Only
+    /// connections between the given components are considered. The initial
+    /// layout is the given order, row by row, which is then improved by
+    /// simulated annealing.
+    ///
+    /// ```
+    /// use blotter::sandbox::{AutoPlace, ComponentBuilder, PegAddress, PegType, Sandbox};
+    ///
+    /// let mut sandbox = Sandbox::new();
+    /// let inverter = ComponentBuilder::new("MHG.Inverter").num_inputs(1).num_outputs(1);
+    /// let chain: Vec<_> = (0..16).map(|_| sandbox.add_component(&inverter)).collect();
+    /// // Wire the chain up in a scrambled order.
+    /// for i in 0..15 {
+    ///     let (a, b) = (chain[i * 7 % 16], chain[(i + 1) * 7 % 16]);
+    ///     let output = PegAddress { component: a, peg_type: PegType::Output, peg_index: 0 };
+    ///     let input = PegAddress { component: b, peg_type: PegType::Input, peg_index: 0 };
+    ///     sandbox.add_wire(output, input, 0.0).unwrap();
+    /// }
+    ///
+    /// let report = sandbox.auto_place(&chain, &AutoPlace::new());
+    /// assert_eq!((report.columns, report.rows), (4, 4));
+    /// assert!(report.wire_length_after < report.wire_length_before);
+    /// ```
+    pub fn auto_place(
+        &mut self,
+        components: &[ComponentId],
+        options: &AutoPlace,
+    ) -> AutoPlaceReport {
+        let mut seen = HashSet::new();
+        let components: Vec<ComponentId> = components
+            .iter()
+            .copied()
+            .filter(|&id| self.components.get(id.0).is_some() && seen.insert(id))
+            .collect();
+
+        let count = components.len() as u32;
+        let columns = options
+            .columns
+            .unwrap_or_else(|| (count as f32).sqrt().ceil() as u32)
+            .max(1);
+        let rows = count.div_ceil(columns);
+
+        let mut grid = Grid::new(self.signals(&components), components.len(), columns, rows);
+        let wire_length_before = grid.total_cost();
+        grid.anneal(options);
+        let wire_length_after = grid.total_cost();
+
+        for (index, &id) in components.iter().enumerate() {
+            let [x, z] = grid.square(grid.slot_of[index]);
+            let info = self.components.get_mut(id.0).unwrap();
+            let [x, _, z] = Position::square(x as i32, z as i32).0;
+            info.position = [x, info.position[1], z];
+        }
+
+        AutoPlaceReport {
+            columns,
+            rows,
+            wire_length_before,
+            wire_length_after,
+        }
+    }
+
+    /// Group the given components by the signals they share, as lists of
+    /// indices into `components`. An output and the nets of inputs that it
+    /// drives are one signal.
+    fn signals(&self, components: &[ComponentId]) -> Vec<Vec<usize>> {
+        let mut parents: HashMap<NetId, NetId> = HashMap::new();
+        fn find(parents: &mut HashMap<NetId, NetId>, mut net: NetId) -> NetId {
+            while let Some(&parent) = parents.get(&net) {
+                if parent == net {
+                    break;
+                }
+                net = parent;
+            }
+            net
+        }
+        for (_, wire) in self.wires.iter() {
+            let input = match (wire.a.peg_type, wire.b.peg_type) {
+                (PegType::Output, _) => wire.b,
+                (_, PegType::Output) => wire.a,
+                _ => continue,
+            };
+            let a = find(&mut parents, self.get_peg(&input).unwrap().net_id);
+            let b = find(&mut parents, wire.net_id);
+            if a != b {
+                parents.insert(a, b);
+            }
+        }
+
+        let mut signals: HashMap<NetId, Vec<usize>> = HashMap::new();
+        let mut order = Vec::new();
+        for (index, &id) in components.iter().enumerate() {
+            let info = self.components.get(id.0).unwrap();
+            for peg in info.inputs.iter().chain(&info.outputs) {
+                let signal = find(&mut parents, peg.net_id);
+                let members = signals.entry(signal).or_insert_with(|| {
+                    order.push(signal);
+                    Vec::new()
+                });
+                if members.last() != Some(&index) {
+                    members.push(index);
+                }
+            }
+        }
+        order
+            .into_iter()
+            .map(|signal| signals.remove(&signal).unwrap())
+            .filter(|members| members.len() > 1)
+            .collect()
+    }
+}
+
+/// The state of the annealer: which slot of the grid each component is in.
+struct Grid {
+    signals: Vec<Vec<usize>>,
+    signals_of: Vec<Vec<usize>>,
+    columns: u32,
+    rows: u32,
+    slot_of: Vec<u32>,
+    at_slot: Vec<Option<usize>>,
+}
+
+impl Grid {
+    fn new(signals: Vec<Vec<usize>>, count: usize, columns: u32, rows: u32) -> Self {
+        let mut signals_of = vec![Vec::new(); count];
+        for (signal, members) in signals.iter().enumerate() {
+            for &member in members {
+                signals_of[member].push(signal);
+            }
+        }
+        let slots = (columns * rows) as usize;
+        Self {
+            signals,
+            signals_of,
+            columns,
+            rows,
+            slot_of: (0..count as u32).collect(),
+            at_slot: (0..slots)
+                .map(|slot| (slot < count).then_some(slot))
+                .collect(),
+        }
+    }
+
+    fn square(&self, slot: u32) -> [u32; 2] {
+        [slot % self.columns, slot / self.columns]
+    }
+
+    fn cost(&self, signal: usize) -> u64 {
+        let mut min = [u32::MAX; 2];
+        let mut max = [0; 2];
+        for &member in &self.signals[signal] {
+            let square = self.square(self.slot_of[member]);
+            for axis in 0..2 {
+                min[axis] = min[axis].min(square[axis]);
+                max[axis] = max[axis].max(square[axis]);
+            }
+        }
+        u64::from(max[0] - min[0] + max[1] - min[1])
+    }
+
+    fn total_cost(&self) -> u64 {
+        (0..self.signals.len())
+            .map(|signal| self.cost(signal))
+            .sum()
+    }
+
+    /// The total cost of the signals of up to two components.
+    fn local_cost(&self, a: usize, b: Option<usize>, signals: &mut Vec<usize>) -> u64 {
+        signals.clear();
+        signals.extend(&self.signals_of[a]);
+        if let Some(b) = b {
+            signals.extend(&self.signals_of[b]);
+        }
+        signals.sort_unstable();
+        signals.dedup();
+        signals.iter().map(|&signal| self.cost(signal)).sum()
+    }
+
+    fn move_to(&mut self, component: usize, slot: u32) {
+        let from = self.slot_of[component];
+        let other = self.at_slot[slot as usize];
+        self.at_slot[from as usize] = other;
+        if let Some(other) = other {
+            self.slot_of[other] = from;
+        }
+        self.at_slot[slot as usize] = Some(component);
+        self.slot_of[component] = slot;
+    }
+
+    fn anneal(&mut self, options: &AutoPlace) {
+        let count = self.signals_of.len();
+        let slots = self.at_slot.len() as u64;
+        if count < 2 || self.signals.is_empty() {
+            return;
+        }
+        let moves = u64::from(options.moves_per_component) * count as u64;
+        let mut rng = XorShift::new(options.seed);
+        let start_temperature = f64::from(self.columns + self.rows) / 2.0;
+        let end_temperature = 0.05_f64;
+        let cooling = (end_temperature / start_temperature).powf(1.0 / moves.max(1) as f64);
+        let mut temperature = start_temperature;
+        let mut signals = Vec::new();
+
+        for _ in 0..moves {
+            let component = (rng.next() % count as u64) as usize;
+            let from = self.slot_of[component];
+            // Look for a new slot near the current one, in a window that
+            // shrinks as the layout cools and big moves stop paying off.
+            let range = ((f64::from(self.columns.max(self.rows)) * temperature / start_temperature)
+                .ceil() as u32)
+                .max(1);
+            let [x, z] = self.square(from);
+            let pick = |rng: &mut XorShift, center: u32, size: u32| {
+                let low = center.saturating_sub(range);
+                let high = (center + range).min(size - 1);
+                low + (rng.next() % u64::from(high - low + 1)) as u32
+            };
+            let slot =
+                pick(&mut rng, z, self.rows) * self.columns + pick(&mut rng, x, self.columns);
+            if slot as u64 >= slots {
+                continue;
+            }
+            if slot == from {
+                continue;
+            }
+            let other = self.at_slot[slot as usize];
+            let before = self.local_cost(component, other, &mut signals);
+            self.move_to(component, slot);
+            let after = self.local_cost(component, other, &mut signals);
+            let delta = after as f64 - before as f64;
+            let accept = delta <= 0.0 || {
+                let threshold = (-delta / temperature).exp();
+                (rng.next() >> 11) as f64 / (1u64 << 53) as f64 <= threshold
+            };
+            if !accept {
+                self.move_to(component, from);
+            }
+            temperature *= cooling;
+        }
+    }
+}
+
+/// A small, fast, deterministic random number generator.
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // The state must not be zero.
+        Self((seed ^ 0x9e37_79b9_7f4a_7c15).max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
@@ -0,0 +1,259 @@
+//! Reusable, parameterized copies of a part of a sandbox.
+
+use std::collections::HashSet;
+
+use crate::custom_data::{self, ComponentData};
+
+use super::{ComponentBuilder, ComponentId, PegAddress, Remap, Sandbox, WireId};
+
+/// A captured subtree of components, with the wires between them, that can
+/// be instantiated any number of times.
+///
+/// Selected fields of the components' custom data can be made into named
+/// parameters, which are filled in for each instance:
+///
+/// ```
+/// use blotter::sandbox::component::{CircuitBoard, Delayer};
+/// use blotter::sandbox::{Blueprint, BlueprintField, BlueprintValue, Sandbox};
+///
+/// let mut sandbox = Sandbox::new();
+/// let board = sandbox.add_component(&CircuitBoard::new().build());
+/// let delayer = sandbox.add_component(&Delayer::new().build().parent(Some(board)));
+///
+/// let blueprint = Blueprint::capture(&sandbox, board)
+///     .parameter("delay", delayer, BlueprintField::DelayerDelay);
+/// for delay in 1..=4 {
+///     let bindings = [("delay", BlueprintValue::Int(delay))];
+///     let position = [delay as i32 * 1000, 0, 0];
+///     let remap = blueprint.instantiate(&mut sandbox, None, position, &bindings).unwrap();
+///     let copy = sandbox.component(remap.component(delayer)).unwrap();
+///     assert_eq!(copy.custom_data().unwrap()[4], delay as u8);
+/// }
+/// assert_eq!(sandbox.component_ids().count(), 10);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Blueprint {
+    sandbox: Sandbox,
+    root: ComponentId,
+    /// The ID in `sandbox` of each captured component and wire.
+    captured: Remap,
+    parameters: Vec<(String, ComponentId, BlueprintField)>,
+}
+
+/// A part of a component's custom data that can be a blueprint parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BlueprintField {
+    /// The delay of a delayer, as an [`Int`](BlueprintValue::Int).
+    DelayerDelay,
+    /// The text of a label, as [`Text`](BlueprintValue::Text).
+    LabelText,
+    /// The color of a label, as a [`Color`](BlueprintValue::Color).
+    LabelColor,
+    /// The color of a circuit board, as a [`Color`](BlueprintValue::Color).
+    BoardColor,
+    /// All of the custom data, as [`Bytes`](BlueprintValue::Bytes).
+    CustomData,
+}
+
+/// The value of a blueprint parameter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlueprintValue {
+    Int(u32),
+    Text(String),
+    Color([u8; 3]),
+    Bytes(Vec<u8>),
+}
+
+/// Why a blueprint couldn't be instantiated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlueprintError {
+    /// A binding names a parameter that the blueprint doesn't have.
+    UnknownParameter(String),
+    /// A binding's value is the wrong kind for its parameter's field.
+    WrongType(String),
+    /// The custom data of a parameter's component can't be parsed as the
+    /// field's component type.
+    InvalidCustomData(String),
+}
+
+impl Blueprint {
+    /// Capture a component, its descendants, and the wires between them.
+    ///
+    /// Wires to components outside the subtree are left out. Every net of
+    /// the copy starts off.
+    pub fn capture(sandbox: &Sandbox, root: ComponentId) -> Self {
+        let mut copy = sandbox.new_shard();
+        let mut captured = Remap::default();
+        for old_id in std::iter::once(root).chain(sandbox.descendants(root)) {
+            let component = sandbox.component(old_id).unwrap();
+            let parent = (old_id != root).then(|| captured.component(component.parent().unwrap()));
+            let new_id = copy.add_component(
+                &ComponentBuilder::new(component.type_name())
+                    .parent(parent)
+                    .position(component.position())
+                    .rotation(component.rotation())
+                    .num_inputs(component.num_inputs() as u32)
+                    .num_outputs(component.num_outputs() as u32)
+                    .custom_data(component.custom_data().map(<[u8]>::to_vec)),
+            );
+            captured.components.insert(old_id, new_id);
+        }
+
+        let inside: HashSet<ComponentId> = captured.components.keys().copied().collect();
+        let map = |addr: PegAddress| PegAddress {
+            component: captured.component(addr.component),
+            ..addr
+        };
+        let mut wires: Vec<_> = sandbox
+            .wires
+            .iter()
+            .filter(|(_, wire)| {
+                inside.contains(&wire.a.component) && inside.contains(&wire.b.component)
+            })
+            .map(|(address, wire)| (WireId(address), (map(wire.a), map(wire.b), wire.rotation)))
+            .collect();
+        wires.sort_by_key(|&(id, _)| id);
+        let new_ids = copy
+            .add_wires(wires.iter().map(|&(_, wire)| wire))
+            .expect("captured wires should connect captured pegs");
+        captured
+            .wires
+            .extend(wires.iter().map(|&(id, _)| id).zip(new_ids));
+
+        Self {
+            sandbox: copy,
+            root: captured.component(root),
+            captured,
+            parameters: Vec::new(),
+        }
+    }
+
+    /// Make a field of a captured component into a named parameter.
+    ///
+    /// A parameter can be given to several components, which all get the
+    /// same value. Parameters that aren't bound when instantiating keep the
+    /// value they had when captured.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the component wasn't captured.
+    pub fn parameter(
+        mut self,
+        name: impl Into<String>,
+        component: ComponentId,
+        field: BlueprintField,
+    ) -> Self {
+        let id = *self
+            .captured
+            .components
+            .get(&component)
+            .expect("parameter component should be in the blueprint");
+        self.parameters.push((name.into(), id, field));
+        self
+    }
+
+    /// Add a copy of the blueprint to a sandbox, with its root at `position`
+    /// relative to `parent`, or as a root component if `parent` is `None`.
+    ///
+    /// The returned [`Remap`] translates the IDs of the captured components
+    /// and wires into the IDs of their copies. If an error is returned, the sandbox is
+    /// unchanged.
+    pub fn instantiate(
+        &self,
+        sandbox: &mut Sandbox,
+        parent: Option<ComponentId>,
+        position: impl Into<[i32; 3]>,
+        bindings: &[(&str, BlueprintValue)],
+    ) -> Result<Remap, BlueprintError> {
+        let mut copy = self.sandbox.clone();
+        for (name, value) in bindings {
+            if !self
+                .parameters
+                .iter()
+                .any(|(parameter, ..)| parameter == name)
+            {
+                return Err(BlueprintError::UnknownParameter(name.to_string()));
+            }
+            for &(_, id, field) in self
+                .parameters
+                .iter()
+                .filter(|(parameter, ..)| parameter == name)
+            {
+                let mut component = copy.component_mut(id).unwrap();
+                let data = component.get().custom_data().map(<[u8]>::to_vec);
+                let data = set_field(data, field, value).ok_or_else(|| match (field, value) {
+                    (BlueprintField::DelayerDelay, BlueprintValue::Int(_))
+                    | (BlueprintField::LabelText, BlueprintValue::Text(_))
+                    | (
+                        BlueprintField::LabelColor | BlueprintField::BoardColor,
+                        BlueprintValue::Color(_),
+                    )
+                    | (BlueprintField::CustomData, BlueprintValue::Bytes(_)) => {
+                        BlueprintError::InvalidCustomData(name.to_string())
+                    }
+                    _ => BlueprintError::WrongType(name.to_string()),
+                })?;
+                component.set_custom_data(Some(data));
+            }
+        }
+        copy.component_mut(self.root)
+            .unwrap()
+            .set_position(position);
+
+        let merged = sandbox.merge_shard(copy, parent);
+        Ok(Remap {
+            components: self
+                .captured
+                .components
+                .iter()
+                .map(|(&original, &id)| (original, merged.component(id)))
+                .collect(),
+            wires: self
+                .captured
+                .wires
+                .iter()
+                .map(|(&original, &id)| (original, merged.wire(id)))
+                .collect(),
+        })
+    }
+}
+
+/// Set a field of some custom data, or return `None` if the value doesn't
+/// fit the field, or the data can't be parsed.
+fn set_field(
+    data: Option<Vec<u8>>,
+    field: BlueprintField,
+    value: &BlueprintValue,
+) -> Option<Vec<u8>> {
+    fn rewrite<T: ComponentData>(data: Option<Vec<u8>>, f: impl FnOnce(&mut T)) -> Option<Vec<u8>> {
+        let mut parsed = T::read(&mut data?.as_slice()).ok()?;
+        f(&mut parsed);
+        let mut data = Vec::new();
+        parsed.write(&mut data).ok()?;
+        Some(data)
+    }
+
+    match (field, value) {
+        (BlueprintField::DelayerDelay, &BlueprintValue::Int(delay)) => {
+            rewrite(data, |delayer: &mut custom_data::Delayer| {
+                delayer.delay = delay
+            })
+        }
+        (BlueprintField::LabelText, BlueprintValue::Text(text)) => {
+            rewrite(data, |label: &mut custom_data::Label| {
+                label.text = text.clone()
+            })
+        }
+        (BlueprintField::LabelColor, &BlueprintValue::Color(color)) => {
+            rewrite(data, |label: &mut custom_data::Label| label.color = color)
+        }
+        (BlueprintField::BoardColor, &BlueprintValue::Color(color)) => {
+            rewrite(data, |board: &mut custom_data::CircuitBoard| {
+                board.color = color
+            })
+        }
+        (BlueprintField::CustomData, BlueprintValue::Bytes(bytes)) => Some(bytes.clone()),
+        _ => None,
+    }
+}
@@ -0,0 +1,232 @@
+//! Components that join nets without wires, like relays and through pegs.
+
+use std::collections::{BTreeMap, HashMap};
+
+use super::{find_root, NetId, PegAddress, PegType, Sandbox};
+
+/// How a component joins the nets of some of its input pegs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Bridge {
+    /// The inputs are always connected, like the two ends of a through peg.
+    Always(Vec<usize>),
+    /// The contact inputs are connected while the control input is on, like
+    /// the contacts of a relay.
+    Switched {
+        control: usize,
+        contacts: Vec<usize>,
+    },
+}
+
+/// The bridges of each component type, for [`Sandbox::bridged_nets`].
+///
+/// A sandbox only knows about wires, so without a model, the pegs of a
+/// relay or a through peg are in separate nets, as [`BridgeModel::none`]
+/// describes. The [default](BridgeModel::game) model has the vanilla
+/// components that join nets:
+///
+/// - `MHG.ThroughPeg`, `MHG.ThroughSocket` and `MHG.ChubbyThroughSocket`
+///   always connect their inputs 0 and 1.
+/// - `MHG.Relay` connects its inputs 0 and 1 while input 2 is on.
+///
+/// Sockets also connect to pegs that are plugged into them, which depends
+/// on where things are placed; that isn't modeled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BridgeModel {
+    pub(super) bridges: BTreeMap<String, Vec<Bridge>>,
+}
+
+impl BridgeModel {
+    /// A model without any bridges, where every net is separate.
+    pub fn none() -> Self {
+        Self {
+            bridges: BTreeMap::new(),
+        }
+    }
+
+    /// The bridges of the vanilla game.
+    pub fn game() -> Self {
+        let through = || Bridge::Always(vec![0, 1]);
+        Self::none()
+            .bridge("MHG.ThroughPeg", through())
+            .bridge("MHG.ThroughSocket", through())
+            .bridge("MHG.ChubbyThroughSocket", through())
+            .bridge(
+                "MHG.Relay",
+                Bridge::Switched {
+                    control: 2,
+                    contacts: vec![0, 1],
+                },
+            )
+    }
+
+    /// Add a bridge to a component type, like a modded one.
+    pub fn bridge(mut self, type_name: impl Into<String>, bridge: Bridge) -> Self {
+        self.bridges
+            .entry(type_name.into())
+            .or_default()
+            .push(bridge);
+        self
+    }
+
+    /// Remove the bridges of a component type.
+    pub fn without(mut self, type_name: &str) -> Self {
+        self.bridges.remove(type_name);
+        self
+    }
+}
+
+impl Default for BridgeModel {
+    fn default() -> Self {
+        Self::game()
+    }
+}
+
+/// Which nets are joined by bridges, returned by [`Sandbox::bridged_nets`].
+///
+/// Pegs are connected when they are in the same net, or in nets joined by
+/// bridges. Like the nets of the sandbox, this doesn't follow wires from
+/// outputs to the inputs that they drive.
+#[derive(Debug, Clone)]
+pub struct BridgedNets<'a> {
+    sandbox: &'a Sandbox,
+    /// The representative net of every net that is joined to another, with
+    /// relays as they are now.
+    current: HashMap<NetId, NetId>,
+    /// The same, as if every relay were closed.
+    potential: HashMap<NetId, NetId>,
+}
+
+impl BridgedNets<'_> {
+    /// Whether two pegs are connected now, with each relay open or closed
+    /// by the current state of its control net.
+    pub fn connected(&self, a: PegAddress, b: PegAddress) -> bool {
+        self.same_group(&self.current, a, b)
+    }
+
+    /// Whether two pegs could be connected, if the right relays were
+    /// closed.
+    pub fn potentially_connected(&self, a: PegAddress, b: PegAddress) -> bool {
+        self.same_group(&self.potential, a, b)
+    }
+
+    /// The pegs of every group of nets that bridges could join, ordered by
+    /// peg, and the groups by their first peg.
+    ///
+    /// Only groups of more than one net are listed.
+    pub fn potential_groups(&self) -> Vec<Vec<PegAddress>> {
+        let mut groups: HashMap<NetId, Vec<PegAddress>> = HashMap::new();
+        for (&net_id, &root) in &self.potential {
+            let net = self.sandbox.nets.get(net_id.0).unwrap();
+            groups.entry(root).or_default().extend(&net.pegs);
+        }
+        let mut groups: Vec<Vec<PegAddress>> = groups
+            .into_values()
+            .map(|mut pegs| {
+                pegs.sort_by_key(peg_order);
+                pegs
+            })
+            .collect();
+        groups.sort_by_key(|pegs| pegs.first().map(peg_order));
+        groups
+    }
+
+    /// The net representing the group that bridges could join `net` to.
+    pub(super) fn potential_root(&self, net: NetId) -> NetId {
+        self.potential.get(&net).copied().unwrap_or(net)
+    }
+
+    fn same_group(&self, groups: &HashMap<NetId, NetId>, a: PegAddress, b: PegAddress) -> bool {
+        let (Some(a), Some(b)) = (self.sandbox.get_peg(&a), self.sandbox.get_peg(&b)) else {
+            return false;
+        };
+        let root = |net_id| groups.get(&net_id).copied().unwrap_or(net_id);
+        root(a.net_id) == root(b.net_id)
+    }
+}
+
+fn peg_order(peg: &PegAddress) -> impl Ord {
+    (
+        peg.component,
+        peg.peg_type == PegType::Output,
+        peg.peg_index,
+    )
+}
+
+impl Sandbox {
+    /// Find the nets that are joined by relays, through pegs and other
+    /// components, as described by `model`.
+    ///
+    /// ```
+    /// use blotter::sandbox::{BridgeModel, ComponentBuilder, PegAddress, PegType, Sandbox};
+    ///
+    /// let mut sandbox = Sandbox::new();
+    /// let relay = sandbox.add_component(&ComponentBuilder::new("MHG.Relay").num_inputs(3));
+    /// let contact = |peg_index| PegAddress { component: relay, peg_type: PegType::Input, peg_index };
+    ///
+    /// let bridged = sandbox.bridged_nets(&BridgeModel::game());
+    /// assert!(!bridged.connected(contact(0), contact(1)));
+    /// assert!(bridged.potentially_connected(contact(0), contact(1)));
+    /// assert!(!bridged.potentially_connected(contact(0), contact(2)));
+    ///
+    /// let separate = sandbox.bridged_nets(&BridgeModel::none());
+    /// assert!(!separate.potentially_connected(contact(0), contact(1)));
+    /// ```
+    pub fn bridged_nets(&self, model: &BridgeModel) -> BridgedNets<'_> {
+        let mut current = HashMap::new();
+        let mut potential = HashMap::new();
+        for (type_name, bridges) in &model.bridges {
+            for id in self.components_of_type(type_name) {
+                let info = self.components.get(id.0).unwrap();
+                let net = |index: usize| info.inputs.get(index).map(|peg| peg.net_id);
+                for bridge in bridges {
+                    let (contacts, closed) = match bridge {
+                        Bridge::Always(pegs) => (pegs, true),
+                        Bridge::Switched { control, contacts } => {
+                            let closed = net(*control)
+                                .is_some_and(|net_id| self.net_states[net_id.0.into_raw()]);
+                            (contacts, closed)
+                        }
+                    };
+                    let mut nets = contacts.iter().filter_map(|&index| net(index));
+                    let Some(first) = nets.next() else {
+                        continue;
+                    };
+                    for other in nets {
+                        union(&mut potential, first, other);
+                        if closed {
+                            union(&mut current, first, other);
+                        }
+                    }
+                }
+            }
+        }
+        flatten(&mut current);
+        flatten(&mut potential);
+        BridgedNets {
+            sandbox: self,
+            current,
+            potential,
+        }
+    }
+}
+
+fn union(parents: &mut HashMap<NetId, NetId>, a: NetId, b: NetId) {
+    let a = find_root(parents, a);
+    let b = find_root(parents, b);
+    if a != b {
+        // Keep the lower ID as the root, so the result doesn't depend on
+        // the order of the bridges.
+        let (root, child) = if a < b { (a, b) } else { (b, a) };
+        parents.insert(child, root);
+        parents.entry(root).or_insert(root);
+    }
+}
+
+/// Point every net directly at the root of its group.
+fn flatten(parents: &mut HashMap<NetId, NetId>) {
+    let nets: Vec<NetId> = parents.keys().copied().collect();
+    for net in nets {
+        let root = find_root(parents, net);
+        parents.insert(net, root);
+    }
+}
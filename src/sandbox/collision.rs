@@ -0,0 +1,220 @@
+//! Detecting circuit boards and components that intersect each other.
+
+use std::collections::HashMap;
+
+use crate::custom_data::{self, ComponentData};
+use crate::geometry::{Obb, Transform, BOARD_THICKNESS, SQUARE_SIZE};
+
+use super::{ComponentBuilder, ComponentId, Sandbox};
+
+/// How deeply boards must overlap, in meters, before they are reported.
+const OVERLAP_TOLERANCE: f32 = 0.001;
+
+impl Sandbox {
+    /// The oriented bounding box of a circuit board, or `None` if the
+    /// component is not a board.
+    ///
+    /// Boards extend along their local positive X and Z axes from their
+    /// origin, and downward along Y by the thickness of a board.
+    pub fn board_bounds(&self, id: ComponentId) -> Option<Obb> {
+        let board_type = self
+            .component_types
+            .get(custom_data::CircuitBoard::TYPE_STRING);
+        let info = self.components.get(id.0)?;
+        let board = self.board_data(info, board_type)?;
+        Some(Obb::from_local(
+            &self.world_transform(id),
+            [0.0, -BOARD_THICKNESS, 0.0],
+            [
+                board.size_x as f32 * SQUARE_SIZE,
+                0.0,
+                board.size_z as f32 * SQUARE_SIZE,
+            ],
+        ))
+    }
+
+    /// Find all pairs of circuit boards that overlap each other.
+    ///
+    /// Boards that only touch, such as a board placed on the surface of
+    /// another, are not reported. Each pair is reported once, with the lower
+    /// ID first.
+    pub fn overlapping_boards(&self) -> Vec<(ComponentId, ComponentId)> {
+        let boards: Vec<(ComponentId, Obb)> = self
+            .component_ids()
+            .filter_map(|id| Some((id, self.board_bounds(id)?)))
+            .collect();
+        let aabbs: Vec<_> = boards.iter().map(|(_, obb)| obb.aabb()).collect();
+
+        let mut overlaps = Vec::new();
+        for (i, (id_a, obb_a)) in boards.iter().enumerate() {
+            for (j, (id_b, obb_b)) in boards.iter().enumerate().skip(i + 1) {
+                if aabbs[i].intersects(&aabbs[j]) && obb_a.intersects(obb_b, OVERLAP_TOLERANCE) {
+                    overlaps.push((*id_a, *id_b));
+                }
+            }
+        }
+        overlaps
+    }
+}
+
+/// Approximate sizes of vanilla components, as `(type, [width, height])` in
+/// meters. Every component is treated as a box standing on its origin.
+const VANILLA_SIZES: [(&str, [f32; 2]); 30] = [
+    ("MHG.Inverter", [SQUARE_SIZE, 0.3]),
+    ("MHG.XorGate", [SQUARE_SIZE, 0.3]),
+    ("MHG.AndGate", [SQUARE_SIZE, 0.3]),
+    ("MHG.Delayer", [SQUARE_SIZE, 0.3]),
+    ("MHG.DLatch", [SQUARE_SIZE, 0.3]),
+    ("MHG.Randomizer", [SQUARE_SIZE, 0.3]),
+    ("MHG.Relay", [SQUARE_SIZE, 0.3]),
+    ("MHG.Buffer_WithOutput", [SQUARE_SIZE, 0.3]),
+    ("MHG.Buffer", [SQUARE_SIZE, 0.3]),
+    ("MHG.Mount", [SQUARE_SIZE, 0.3]),
+    ("MHG.Peg", [SQUARE_SIZE, 0.3]),
+    ("MHG.ThroughPeg", [SQUARE_SIZE, 0.3]),
+    ("MHG.Socket", [SQUARE_SIZE, 0.15]),
+    ("MHG.ThroughSocket", [SQUARE_SIZE, 0.15]),
+    ("MHG.ChubbySocket", [SQUARE_SIZE, 0.15]),
+    ("MHG.ChubbyThroughSocket", [SQUARE_SIZE, 0.15]),
+    ("MHG.Label", [SQUARE_SIZE, 0.03]),
+    ("MHG.PanelLabel", [SQUARE_SIZE, 0.03]),
+    ("MHG.Chair", [SQUARE_SIZE, 0.6]),
+    ("MHG.Flag", [SQUARE_SIZE, 0.9]),
+    ("MHG.StandingDisplay", [SQUARE_SIZE, 0.6]),
+    ("MHG.PanelDisplay", [SQUARE_SIZE, 0.075]),
+    ("MHG.Singer", [SQUARE_SIZE, 0.3]),
+    ("MHG.Drum", [SQUARE_SIZE, 0.3]),
+    ("MHG.Switch", [SQUARE_SIZE, 0.3]),
+    ("MHG.PanelSwitch", [SQUARE_SIZE, 0.075]),
+    ("MHG.Button", [SQUARE_SIZE, 0.3]),
+    ("MHG.PanelButton", [SQUARE_SIZE, 0.075]),
+    ("MHG.Key", [SQUARE_SIZE, 0.3]),
+    ("MHG.PanelKey", [SQUARE_SIZE, 0.075]),
+];
+
+/// Checks whether new components would overlap the components already in a
+/// sandbox.
+///
+/// The footprints of components are only approximate: a box standing on the
+/// component's origin, one grid square wide for vanilla components. Circuit
+/// boards use their actual size. Types without a known footprint, like most
+/// modded components, never collide; give them one with
+/// [`footprint`](Self::footprint).
+#[derive(Debug, Clone)]
+pub struct PlacementValidator {
+    sizes: HashMap<String, [f32; 2]>,
+}
+
+impl PlacementValidator {
+    /// A validator that knows the footprints of vanilla components.
+    pub fn new() -> Self {
+        Self {
+            sizes: VANILLA_SIZES
+                .iter()
+                .map(|&(name, size)| (name.to_owned(), size))
+                .collect(),
+        }
+    }
+
+    /// Set the footprint of a component type to a box `width` meters square
+    /// and `height` meters tall.
+    pub fn footprint(mut self, type_name: impl Into<String>, width: f32, height: f32) -> Self {
+        self.sizes.insert(type_name.into(), [width, height]);
+        self
+    }
+
+    /// The components that the component would overlap, if it was added to
+    /// the sandbox, in ID order.
+    ///
+    /// The component's parent is never reported, since children are meant to
+    /// touch their parent.
+    pub fn collisions(&self, sandbox: &Sandbox, component: &ComponentBuilder) -> Vec<ComponentId> {
+        let transform = match component.parent {
+            Some(parent) => sandbox.world_transform(parent),
+            None => Transform::IDENTITY,
+        }
+        .then(&Transform::from_local(
+            component.position,
+            component.rotation,
+        ));
+        let Some(bounds) = self.bounds(&transform, component.id, component.custom_data.as_deref())
+        else {
+            return Vec::new();
+        };
+        let aabb = bounds.aabb();
+
+        let mut collisions: Vec<ComponentId> = sandbox
+            .components()
+            .filter(|other| Some(other.id()) != component.parent)
+            .filter(|other| {
+                let Some(other_bounds) = self.bounds(
+                    &sandbox.world_transform(other.id()),
+                    other.type_name(),
+                    other.custom_data(),
+                ) else {
+                    return false;
+                };
+                aabb.intersects(&other_bounds.aabb())
+                    && bounds.intersects(&other_bounds, OVERLAP_TOLERANCE)
+            })
+            .map(|other| other.id())
+            .collect();
+        collisions.sort_unstable();
+        collisions
+    }
+
+    fn bounds(
+        &self,
+        transform: &Transform,
+        type_name: &str,
+        custom_data: Option<&[u8]>,
+    ) -> Option<Obb> {
+        if type_name == custom_data::CircuitBoard::TYPE_STRING {
+            let board = custom_data::CircuitBoard::read(&mut custom_data?).ok()?;
+            return Some(Obb::from_local(
+                transform,
+                [0.0, -BOARD_THICKNESS, 0.0],
+                [
+                    board.size_x as f32 * SQUARE_SIZE,
+                    0.0,
+                    board.size_z as f32 * SQUARE_SIZE,
+                ],
+            ));
+        }
+        let [width, height] = *self.sizes.get(type_name)?;
+        let h = width / 2.0;
+        Some(Obb::from_local(transform, [-h, 0.0, -h], [h, height, h]))
+    }
+}
+
+impl Default for PlacementValidator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sandbox {
+    /// Whether adding the component would overlap anything already in the
+    /// sandbox, using the approximate footprints of vanilla components.
+    ///
+    /// See [`PlacementValidator`] for details, and for modded components.
+    ///
+    /// ```
+    /// use blotter::sandbox::{component::CircuitBoard, ComponentBuilder, Sandbox};
+    ///
+    /// let mut sandbox = Sandbox::new();
+    /// let board = sandbox.add_component(&CircuitBoard::new().build());
+    /// let inverter = ComponentBuilder::new("MHG.Inverter")
+    ///     .parent(Some(board))
+    ///     .position([150, 0, 150]);
+    /// assert!(!sandbox.would_collide(&inverter));
+    /// sandbox.add_component(&inverter);
+    /// assert!(sandbox.would_collide(&inverter));
+    /// assert!(!sandbox.would_collide(&inverter.clone().position([450, 0, 150])));
+    /// ```
+    pub fn would_collide(&self, component: &ComponentBuilder) -> bool {
+        !PlacementValidator::new()
+            .collisions(self, component)
+            .is_empty()
+    }
+}
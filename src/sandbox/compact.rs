@@ -0,0 +1,212 @@
+//! Reclaiming memory after large edits.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use bitvec::vec::BitVec;
+
+use super::hierarchy::push_sorted;
+use super::{ComponentId, NetId, NetInfo, PegAddress, Sandbox, WireId};
+
+/// The IDs that were changed by [`Sandbox::compact`].
+///
+/// Components and wires that are not listed here keep their IDs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Remap {
+    pub components: HashMap<ComponentId, ComponentId>,
+    pub wires: HashMap<WireId, WireId>,
+}
+
+impl Remap {
+    /// The new ID of a component that existed before compaction.
+    pub fn component(&self, id: ComponentId) -> ComponentId {
+        self.components.get(&id).copied().unwrap_or(id)
+    }
+
+    /// The new ID of a wire that existed before compaction.
+    pub fn wire(&self, id: WireId) -> WireId {
+        self.wires.get(&id).copied().unwrap_or(id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty() && self.wires.is_empty()
+    }
+
+    fn peg(&self, addr: PegAddress) -> PegAddress {
+        PegAddress {
+            component: self.component(addr.component),
+            ..addr
+        }
+    }
+}
+
+impl Sandbox {
+    /// Rebuild the internal storage of the sandbox so that it is contiguous,
+    /// releasing memory left behind by removed components and wires.
+    ///
+    /// This is worthwhile after deleting a large part of the sandbox. It
+    /// invalidates component and wire IDs held by the caller; use the
+    /// returned [`Remap`] to translate them.
+    pub fn compact(&mut self) -> Remap {
+        let remap = Remap {
+            components: self
+                .components
+                .compact()
+                .into_iter()
+                .map(|(old, new)| (ComponentId(old), ComponentId(new)))
+                .collect(),
+            wires: self
+                .wires
+                .compact()
+                .into_iter()
+                .map(|(old, new)| (WireId(old), WireId(new)))
+                .collect(),
+        };
+
+        self.root_components = Arc::new(rebuild(&self.root_components, |&id| remap.component(id)));
+        let components_by_type = Arc::make_mut(&mut self.components_by_type);
+        components_by_type.retain(|_, ids| !ids.is_empty());
+        for ids in components_by_type.values_mut() {
+            *ids = ids.iter().map(|&id| remap.component(id)).collect();
+        }
+        for (_, component) in self.components.iter_mut() {
+            component.parent = component.parent.map(|id| remap.component(id));
+            component.children = rebuild(&component.children, |&id| remap.component(id));
+            for peg in component.inputs.iter_mut().chain(&mut component.outputs) {
+                peg.wires = rebuild(&peg.wires, |&id| remap.wire(id));
+            }
+            component.inputs.shrink_to_fit();
+            component.outputs.shrink_to_fit();
+        }
+        for (_, wire) in self.wires.iter_mut() {
+            wire.a = remap.peg(wire.a);
+            wire.b = remap.peg(wire.b);
+        }
+        for net in self.nets.iter_mut() {
+            net.wires = rebuild(&net.wires, |&id| remap.wire(id));
+            net.pegs = rebuild(&net.pegs, |&addr| remap.peg(addr));
+        }
+
+        self.remap_annotations(&remap);
+        self.addresses = Arc::new(
+            self.addresses
+                .iter()
+                .map(|(&id, &address)| (remap.component(id), address))
+                .collect(),
+        );
+
+        self.nets.shrink_to_fit();
+        self.net_states.shrink_to_fit();
+        self.component_types.shrink_to_fit();
+        self.mods.shrink_to_fit();
+
+        remap
+    }
+}
+
+impl Sandbox {
+    /// Forget component types that no component uses, and renumber the
+    /// remaining types densely, keeping their relative order.
+    ///
+    /// Returns the number of types that were removed.
+    pub fn compact_component_types(&mut self) -> usize {
+        Arc::make_mut(&mut self.components_by_type).retain(|_, ids| !ids.is_empty());
+        let mut used: Vec<u16> = self.components_by_type.keys().copied().collect();
+        used.sort_unstable();
+        let type_map: HashMap<u16, u16> = used
+            .into_iter()
+            .enumerate()
+            .map(|(new, old)| (old, new as u16))
+            .collect();
+
+        let before = self.component_types.len();
+        self.component_types.renumber(&type_map);
+        for (_, component) in self.components.iter_mut() {
+            component.type_id = type_map[&component.type_id];
+        }
+        self.components_by_type = Arc::new(
+            Arc::unwrap_or_clone(std::mem::take(&mut self.components_by_type))
+                .into_iter()
+                .map(|(type_id, ids)| (type_map[&type_id], ids))
+                .collect(),
+        );
+        self.next_type = type_map.len() as u16;
+
+        before - self.component_types.len()
+    }
+}
+
+impl Sandbox {
+    /// Renumber nets densely, in the order that they are first used when the
+    /// sandbox is serialized, and drop nets that nothing refers to.
+    ///
+    /// Saves loaded from a file start out with one net per stored state bit,
+    /// used or not; after compaction the circuit states match what the game
+    /// itself would write. Returns the number of nets that were removed.
+    pub fn compact_nets(&mut self) -> usize {
+        let order = self.canonical_net_order();
+        let before = self.nets.len();
+
+        let mut old_nets: Vec<Option<NetInfo>> = std::mem::take(&mut self.nets)
+            .into_vec()
+            .into_iter()
+            .map(Some)
+            .collect();
+        let mut net_map = HashMap::with_capacity(order.len());
+        let mut states = BitVec::with_capacity(order.len());
+        for old_id in order {
+            let net = old_nets[old_id.0.into_raw()].take().unwrap();
+            net_map.insert(old_id, NetId(self.nets.insert(net)));
+            states.push(self.net_states[old_id.0.into_raw()]);
+        }
+        self.net_states = states;
+
+        for (_, component) in self.components.iter_mut() {
+            for peg in component.inputs.iter_mut().chain(&mut component.outputs) {
+                peg.net_id = net_map[&peg.net_id];
+            }
+        }
+        for (_, wire) in self.wires.iter_mut() {
+            wire.net_id = net_map[&wire.net_id];
+        }
+
+        before - self.nets.len()
+    }
+
+    /// Every net that is referenced by a peg or wire, in order of first use:
+    /// components in serialization order, then wires.
+    pub(super) fn canonical_net_order(&self) -> Vec<NetId> {
+        let mut seen = HashSet::new();
+        let mut order = Vec::new();
+        let mut visit = |net_id: NetId| {
+            if seen.insert(net_id) {
+                order.push(net_id);
+            }
+        };
+
+        let mut stack = Vec::new();
+        push_sorted(&mut stack, self.root_components.iter());
+        while let Some(id) = stack.pop() {
+            let component = self.components.get(id.0).unwrap();
+            push_sorted(&mut stack, &component.children);
+            for peg in component.inputs.iter().chain(&component.outputs) {
+                visit(peg.net_id);
+            }
+        }
+        for (_, wire) in self.wires.iter() {
+            visit(wire.net_id);
+        }
+        order
+    }
+}
+
+/// Map the elements of a set into a new, tightly-allocated set.
+fn rebuild<'a, S, T, F>(set: &'a S, f: F) -> S
+where
+    &'a S: IntoIterator<Item = &'a T>,
+    S: FromIterator<T>,
+    T: 'a,
+    F: FnMut(&T) -> T,
+{
+    set.into_iter().map(f).collect()
+}
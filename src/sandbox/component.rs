@@ -42,6 +42,12 @@ impl CircuitBoard {
     }
 }
 
+impl Default for CircuitBoard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl From<CircuitBoard> for ComponentBuilder<'static> {
     fn from(board: CircuitBoard) -> Self {
         board.build()
@@ -78,6 +84,12 @@ impl Delayer {
     }
 }
 
+impl Default for Delayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl From<Delayer> for ComponentBuilder<'static> {
     fn from(delayer: Delayer) -> Self {
         delayer.build()
@@ -98,6 +110,12 @@ impl ChubbySocket {
     }
 }
 
+impl Default for ChubbySocket {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl From<ChubbySocket> for ComponentBuilder<'static> {
     fn from(chubby_socket: ChubbySocket) -> Self {
         chubby_socket.build()
@@ -118,6 +136,12 @@ impl Peg {
     }
 }
 
+impl Default for Peg {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl From<Peg> for ComponentBuilder<'static> {
     fn from(peg: Peg) -> Self {
         peg.build()
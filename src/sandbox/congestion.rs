@@ -0,0 +1,120 @@
+//! Finding areas of the world with dense wiring.
+
+use std::collections::HashMap;
+
+use crate::geometry::Transform;
+
+use super::{ComponentId, Sandbox};
+
+/// The number of wires in each cell of a horizontal grid over the world.
+///
+/// Each wire is counted once, in the cell that contains its midpoint. Pegs
+/// are approximated by the origin of the component they belong to, which is
+/// close enough at the scale that wiring becomes a rendering problem.
+///
+/// See [`Sandbox::wire_heatmap`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct WireHeatmap {
+    /// The width of each cell along the X and Z axes, in meters.
+    pub cell_size: f32,
+    /// Wire counts of the non-empty cells, keyed by cell coordinates. The
+    /// cell `[x, z]` covers world X from `x * cell_size` to
+    /// `(x + 1) * cell_size`, and likewise for Z.
+    pub cells: HashMap<[i32; 2], usize>,
+}
+
+/// A single cell of a [`WireHeatmap`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Hotspot {
+    pub cell: [i32; 2],
+    pub wires: usize,
+    /// The minimum corner of the cell on the X-Z plane, in meters.
+    pub min: [f32; 2],
+    /// The maximum corner of the cell on the X-Z plane, in meters.
+    pub max: [f32; 2],
+}
+
+impl WireHeatmap {
+    /// The total number of wires counted.
+    pub fn total(&self) -> usize {
+        self.cells.values().sum()
+    }
+
+    /// The number of wires in the given cell.
+    pub fn get(&self, cell: [i32; 2]) -> usize {
+        self.cells.get(&cell).copied().unwrap_or(0)
+    }
+
+    /// The cell containing a point in world space.
+    pub fn cell_at(&self, point: [f32; 3]) -> [i32; 2] {
+        [
+            (point[0] / self.cell_size).floor() as i32,
+            (point[2] / self.cell_size).floor() as i32,
+        ]
+    }
+
+    /// Cells with at least `min_wires` wires, most congested first. Ties are
+    /// ordered by cell coordinates.
+    pub fn hotspots(&self, min_wires: usize) -> Vec<Hotspot> {
+        let mut hotspots: Vec<Hotspot> = self
+            .cells
+            .iter()
+            .filter(|(_, &wires)| wires >= min_wires)
+            .map(|(&cell, &wires)| self.hotspot(cell, wires))
+            .collect();
+        hotspots.sort_by(|a, b| b.wires.cmp(&a.wires).then(a.cell.cmp(&b.cell)));
+        hotspots
+    }
+
+    /// The `n` most congested cells.
+    pub fn top(&self, n: usize) -> Vec<Hotspot> {
+        let mut hotspots = self.hotspots(1);
+        hotspots.truncate(n);
+        hotspots
+    }
+
+    fn hotspot(&self, cell: [i32; 2], wires: usize) -> Hotspot {
+        Hotspot {
+            cell,
+            wires,
+            min: [
+                cell[0] as f32 * self.cell_size,
+                cell[1] as f32 * self.cell_size,
+            ],
+            max: [
+                (cell[0] + 1) as f32 * self.cell_size,
+                (cell[1] + 1) as f32 * self.cell_size,
+            ],
+        }
+    }
+}
+
+impl Sandbox {
+    /// Count wires in a horizontal grid of `cell_size` meter squares.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cell_size` is not positive.
+    pub fn wire_heatmap(&self, cell_size: f32) -> WireHeatmap {
+        assert!(cell_size > 0.0, "cell size must be positive");
+        let mut heatmap = WireHeatmap {
+            cell_size,
+            cells: HashMap::new(),
+        };
+        let mut transforms: HashMap<ComponentId, Transform> = HashMap::new();
+        let mut origin = |id: ComponentId| {
+            transforms
+                .entry(id)
+                .or_insert_with(|| self.world_transform(id))
+                .apply([0.0; 3])
+        };
+        for (_, wire) in self.wires.iter() {
+            let a = origin(wire.a.component);
+            let b = origin(wire.b.component);
+            let midpoint = [0, 1, 2].map(|axis| (a[axis] + b[axis]) / 2.0);
+            *heatmap.cells.entry(heatmap.cell_at(midpoint)).or_default() += 1;
+        }
+        heatmap
+    }
+}
@@ -0,0 +1,88 @@
+//! Approximate top-down outlines of components.
+
+use crate::custom_data::{self, ComponentData};
+use crate::geometry::SQUARE_SIZE;
+
+use super::{ComponentId, ComponentInfo, Sandbox};
+
+/// What kind of outline a [`Footprint`] describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FootprintKind {
+    /// The full extent of a circuit board.
+    Board,
+    /// A placeholder square the size of one grid cell, centered on any other
+    /// kind of component.
+    Component,
+}
+
+/// The outline of a component, projected onto the horizontal (X-Z) plane.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Footprint {
+    pub component: ComponentId,
+    pub kind: FootprintKind,
+    /// Corners of the outline in world-space meters, as `[x, z]` pairs, in
+    /// winding order.
+    pub outline: [[f32; 2]; 4],
+}
+
+impl Sandbox {
+    /// Compute the footprint of every component in the sandbox.
+    ///
+    /// Circuit boards are outlined using the size stored in their custom
+    /// data, assuming they extend along the positive X and Z axes from their
+    /// origin. Other components are approximated by a single grid square.
+    pub fn footprints(&self) -> Vec<Footprint> {
+        let board_type = self
+            .component_types
+            .get(custom_data::CircuitBoard::TYPE_STRING);
+
+        self.components
+            .iter()
+            .map(|(address, info)| {
+                let id = ComponentId(address);
+                let transform = self.world_transform(id);
+
+                let (kind, corners) = match self.board_data(info, board_type) {
+                    Some(board) => {
+                        let x = board.size_x as f32 * SQUARE_SIZE;
+                        let z = board.size_z as f32 * SQUARE_SIZE;
+                        (
+                            FootprintKind::Board,
+                            [[0.0, 0.0], [x, 0.0], [x, z], [0.0, z]],
+                        )
+                    }
+                    None => {
+                        let h = SQUARE_SIZE / 2.0;
+                        (
+                            FootprintKind::Component,
+                            [[-h, -h], [h, -h], [h, h], [-h, h]],
+                        )
+                    }
+                };
+
+                Footprint {
+                    component: id,
+                    kind,
+                    outline: corners.map(|[x, z]| {
+                        let [wx, _, wz] = transform.apply([x, 0.0, z]);
+                        [wx, wz]
+                    }),
+                }
+            })
+            .collect()
+    }
+
+    /// Decode the custom data of a circuit board, if `info` is one.
+    pub(super) fn board_data(
+        &self,
+        info: &ComponentInfo,
+        board_type: Option<u16>,
+    ) -> Option<custom_data::CircuitBoard> {
+        if Some(info.type_id) != board_type {
+            return None;
+        }
+        info.custom_data
+            .as_deref()
+            .and_then(|mut data| custom_data::CircuitBoard::read(&mut data).ok())
+    }
+}
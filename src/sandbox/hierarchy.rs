@@ -0,0 +1,100 @@
+//! Traversal of the parent/child hierarchy of components.
+
+use super::{ComponentId, Sandbox};
+
+impl Sandbox {
+    /// Iterate over the components that have no parent, in ID order.
+    pub fn root_components(&self) -> impl Iterator<Item = ComponentId> {
+        sorted(self.root_components.iter()).into_iter()
+    }
+
+    /// The parent of a component, or `None` if it is a root component or
+    /// does not exist.
+    pub fn parent(&self, id: ComponentId) -> Option<ComponentId> {
+        self.components.get(id.0)?.parent
+    }
+
+    /// Iterate over the direct children of a component, in ID order.
+    pub fn children(&self, id: ComponentId) -> impl Iterator<Item = ComponentId> {
+        self.components
+            .get(id.0)
+            .map(|info| sorted(&info.children))
+            .unwrap_or_default()
+            .into_iter()
+    }
+
+    /// Iterate over the parent of a component, its parent, and so on up to
+    /// the root.
+    pub fn ancestors(&self, id: ComponentId) -> Ancestors<'_> {
+        Ancestors {
+            sandbox: self,
+            current: self.parent(id),
+        }
+    }
+
+    /// Iterate over every component below `id` in the hierarchy, not
+    /// including `id` itself.
+    ///
+    /// Components are visited depth-first, so every component is visited
+    /// before its children.
+    pub fn descendants(&self, id: ComponentId) -> Descendants<'_> {
+        let mut stack = Vec::new();
+        if let Some(info) = self.components.get(id.0) {
+            push_sorted(&mut stack, &info.children);
+        }
+        Descendants {
+            sandbox: self,
+            stack,
+        }
+    }
+}
+
+/// Iterator returned by [`Sandbox::ancestors`].
+pub struct Ancestors<'a> {
+    sandbox: &'a Sandbox,
+    current: Option<ComponentId>,
+}
+
+impl Iterator for Ancestors<'_> {
+    type Item = ComponentId;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.current?;
+        self.current = self.sandbox.parent(id);
+        Some(id)
+    }
+}
+
+/// Iterator returned by [`Sandbox::descendants`].
+pub struct Descendants<'a> {
+    sandbox: &'a Sandbox,
+    stack: Vec<ComponentId>,
+}
+
+impl Iterator for Descendants<'_> {
+    type Item = ComponentId;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.stack.pop()?;
+        if let Some(info) = self.sandbox.components.get(id.0) {
+            push_sorted(&mut self.stack, &info.children);
+        }
+        Some(id)
+    }
+}
+
+fn sorted<'a>(ids: impl IntoIterator<Item = &'a ComponentId>) -> Vec<ComponentId> {
+    let mut ids: Vec<ComponentId> = ids.into_iter().copied().collect();
+    ids.sort_unstable();
+    ids
+}
+
+/// Push `ids` onto a traversal stack so that they are popped in ascending order.
+pub(super) fn push_sorted<'a>(
+    stack: &mut Vec<ComponentId>,
+    ids: impl IntoIterator<Item = &'a ComponentId>,
+) {
+    let start = stack.len();
+    stack.extend(ids);
+    stack[start..].sort_unstable_by(|a, b| b.cmp(a));
+}
@@ -0,0 +1,483 @@
+//! Logs of edits that can be replayed onto a save, instead of rewriting it.
+
+use std::io::{Read, Write};
+
+use crate::error::Error;
+use crate::io::{read_bytes, read_magic, Budget, ReadFrom, WriteTo};
+use crate::misc::object_store::Address;
+
+use super::{AddWireError, ComponentBuilder, ComponentId, PegAddress, PegType, Sandbox, WireId};
+
+const JOURNAL_HEADER: &[u8; 16] = b"blotter-journal1";
+
+const ADD_COMPONENT: u8 = 1;
+const REMOVE_COMPONENTS: u8 = 2;
+const ADD_WIRE: u8 = 3;
+const REMOVE_WIRES: u8 = 4;
+const SET_POSITION: u8 = 5;
+const SET_ROTATION: u8 = 6;
+const SET_CUSTOM_DATA: u8 = 7;
+
+/// One edit recorded in a [`Journal`].
+///
+/// Edits that add things record the ID that they got, so that replaying
+/// them can check that they get the same one again.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Edit {
+    AddComponent {
+        id: ComponentId,
+        type_name: String,
+        parent: Option<ComponentId>,
+        position: [i32; 3],
+        rotation: [f32; 4],
+        num_inputs: u32,
+        num_outputs: u32,
+        custom_data: Option<Vec<u8>>,
+    },
+    RemoveComponents(Vec<ComponentId>),
+    AddWire {
+        id: WireId,
+        a: PegAddress,
+        b: PegAddress,
+        rotation: f32,
+    },
+    RemoveWires(Vec<WireId>),
+    SetPosition(ComponentId, [i32; 3]),
+    SetRotation(ComponentId, [f32; 4]),
+    SetCustomData(ComponentId, Option<Vec<u8>>),
+}
+
+/// Why a journal couldn't be replayed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JournalError {
+    /// The sandbox has different content from the one that the journal was
+    /// started on. Holds the [content hashes](Sandbox::content_hash) of both.
+    BaseMismatch { expected: u64, actual: u64 },
+    /// The edit at this index didn't have the same effect as when it was
+    /// recorded, because the sandbox was loaded differently or changed in
+    /// some other way. The edits before it have been applied.
+    Diverged { edit: usize },
+}
+
+/// A log of edits to a sandbox, which can be written out and replayed later
+/// onto the save that the sandbox was loaded from.
+///
+/// Writing a journal only takes as long as the edits themselves, so tools
+/// that make many small changes to a large world can save their progress
+/// after each one, and flatten the journal into a full save now and then by
+/// replaying it and saving the result.
+///
+/// Journals are written in a compact binary format: a header, followed by
+/// the edits, one after the other. New edits can be appended to a journal
+/// file with [`write_since`](Self::write_since), without rewriting it.
+///
+/// Only edits made through the journal are recorded. Component and wire IDs
+/// are recorded as they are, so a journal must be started on a sandbox
+/// freshly loaded from a save, and replayed onto the same save loaded the
+/// same way.
+///
+/// ```
+/// use blotter::latest::BlotterFile;
+/// use blotter::sandbox::component::{CircuitBoard, Peg};
+/// use blotter::sandbox::{Journal, Sandbox};
+///
+/// let mut original = Sandbox::new();
+/// original.add_component(&CircuitBoard::new().build());
+/// let save = BlotterFile::from(&original);
+///
+/// let mut sandbox = Sandbox::try_from(&save).unwrap();
+/// let board = sandbox.component_ids().next().unwrap();
+/// let mut journal = Journal::new(&sandbox);
+/// let mut log = Vec::new();
+/// journal.write(&mut log).unwrap();
+///
+/// let peg = journal.add_component(&mut sandbox, &Peg::new().build().parent(Some(board)));
+/// journal.write_since(0, &mut log).unwrap();
+/// journal.set_position(&mut sandbox, peg, [150, 0, 150]);
+/// journal.write_since(1, &mut log).unwrap();
+///
+/// let journal = Journal::read(&mut &log[..]).unwrap();
+/// assert_eq!(journal.len(), 2);
+/// let mut replayed = Sandbox::try_from(&save).unwrap();
+/// journal.replay(&mut replayed).unwrap();
+/// assert_eq!(replayed.content_hash(), sandbox.content_hash());
+/// assert_eq!(replayed.component(peg).unwrap().position(), [150, 0, 150]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Journal {
+    base: u64,
+    edits: Vec<Edit>,
+}
+
+impl Journal {
+    /// Start an empty journal of edits to `base`.
+    pub fn new(base: &Sandbox) -> Self {
+        Self {
+            base: base.content_hash(),
+            edits: Vec::new(),
+        }
+    }
+
+    /// The [content hash](Sandbox::content_hash) of the sandbox that the
+    /// journal was started on.
+    pub fn base_hash(&self) -> u64 {
+        self.base
+    }
+
+    pub fn edits(&self) -> &[Edit] {
+        &self.edits
+    }
+
+    pub fn len(&self) -> usize {
+        self.edits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    /// Add a component to the sandbox, like [`Sandbox::add_component`], and
+    /// record it.
+    pub fn add_component(
+        &mut self,
+        sandbox: &mut Sandbox,
+        component: &ComponentBuilder,
+    ) -> ComponentId {
+        let id = sandbox.add_component(component);
+        self.edits.push(Edit::AddComponent {
+            id,
+            type_name: component.id.to_string(),
+            parent: component.parent,
+            position: component.position,
+            rotation: component.rotation,
+            num_inputs: component.num_inputs,
+            num_outputs: component.num_outputs,
+            custom_data: component.custom_data.clone(),
+        });
+        id
+    }
+
+    /// Remove components like [`Sandbox::remove_components`], and record it.
+    pub fn remove_components(&mut self, sandbox: &mut Sandbox, ids: &[ComponentId]) {
+        sandbox.remove_components(ids);
+        self.edits.push(Edit::RemoveComponents(ids.to_vec()));
+    }
+
+    /// Add a wire like [`Sandbox::add_wire`], and record it if it was added.
+    pub fn add_wire(
+        &mut self,
+        sandbox: &mut Sandbox,
+        a: PegAddress,
+        b: PegAddress,
+        rotation: f32,
+    ) -> Result<WireId, AddWireError> {
+        let added = sandbox.add_wire_with_status(a, b, rotation)?;
+        let id = added.id();
+        if added.is_new() {
+            self.edits.push(Edit::AddWire { id, a, b, rotation });
+        }
+        Ok(id)
+    }
+
+    /// Remove wires like [`Sandbox::remove_wires`], and record it.
+    pub fn remove_wires(&mut self, sandbox: &mut Sandbox, ids: &[WireId]) {
+        sandbox.remove_wires(ids);
+        self.edits.push(Edit::RemoveWires(ids.to_vec()));
+    }
+
+    /// Move a component within its parent, and record it.
+    ///
+    /// Returns `false`, recording nothing, if the component doesn't exist.
+    pub fn set_position(
+        &mut self,
+        sandbox: &mut Sandbox,
+        id: ComponentId,
+        position: impl Into<[i32; 3]>,
+    ) -> bool {
+        self.record(sandbox, Edit::SetPosition(id, position.into()))
+    }
+
+    /// Rotate a component within its parent, and record it.
+    ///
+    /// Returns `false`, recording nothing, if the component doesn't exist.
+    pub fn set_rotation(
+        &mut self,
+        sandbox: &mut Sandbox,
+        id: ComponentId,
+        rotation: impl Into<[f32; 4]>,
+    ) -> bool {
+        self.record(sandbox, Edit::SetRotation(id, rotation.into()))
+    }
+
+    /// Replace a component's custom data, and record it.
+    ///
+    /// Returns `false`, recording nothing, if the component doesn't exist.
+    pub fn set_custom_data(
+        &mut self,
+        sandbox: &mut Sandbox,
+        id: ComponentId,
+        custom_data: Option<Vec<u8>>,
+    ) -> bool {
+        self.record(sandbox, Edit::SetCustomData(id, custom_data))
+    }
+
+    fn record(&mut self, sandbox: &mut Sandbox, edit: Edit) -> bool {
+        let applied = edit.apply(sandbox);
+        if applied {
+            self.edits.push(edit);
+        }
+        applied
+    }
+
+    /// Apply every edit of the journal to `sandbox`, in order.
+    pub fn replay(&self, sandbox: &mut Sandbox) -> Result<(), JournalError> {
+        let actual = sandbox.content_hash();
+        if actual != self.base {
+            return Err(JournalError::BaseMismatch {
+                expected: self.base,
+                actual,
+            });
+        }
+        for (index, edit) in self.edits.iter().enumerate() {
+            if !edit.apply(sandbox) {
+                return Err(JournalError::Diverged { edit: index });
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the whole journal.
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        writer.write_all(JOURNAL_HEADER)?;
+        self.base.write_to(writer)?;
+        self.write_since(0, writer)
+    }
+
+    /// Write the edits from index `start` on, to append them to a journal
+    /// that was written when it had `start` edits.
+    pub fn write_since<W: Write>(&self, start: usize, writer: &mut W) -> Result<(), Error> {
+        for edit in self.edits.get(start..).unwrap_or_default() {
+            edit.write(writer)?;
+        }
+        Ok(())
+    }
+
+    /// Read a journal, up to the end of the reader.
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        read_magic(reader, JOURNAL_HEADER)?;
+        let base = u64::read_from(reader)?;
+        let mut edits = Vec::new();
+        loop {
+            let mut tag = [0u8];
+            if reader.read(&mut tag)? == 0 {
+                break;
+            }
+            edits.push(Edit::read(tag[0], reader)?);
+        }
+        Ok(Self { base, edits })
+    }
+}
+
+impl Edit {
+    /// Apply the edit, returning whether it had the effect it was recorded
+    /// with.
+    fn apply(&self, sandbox: &mut Sandbox) -> bool {
+        match self {
+            Self::AddComponent {
+                id,
+                type_name,
+                parent,
+                position,
+                rotation,
+                num_inputs,
+                num_outputs,
+                custom_data,
+            } => {
+                let component = ComponentBuilder::new(type_name)
+                    .parent(*parent)
+                    .position(*position)
+                    .rotation(*rotation)
+                    .num_inputs(*num_inputs)
+                    .num_outputs(*num_outputs)
+                    .custom_data(custom_data.clone());
+                sandbox.try_add_component(&component) == Some(*id)
+            }
+            Self::RemoveComponents(ids) => {
+                sandbox.remove_components(ids);
+                true
+            }
+            Self::AddWire { id, a, b, rotation } => {
+                sandbox.add_wire(*a, *b, *rotation).ok() == Some(*id)
+            }
+            Self::RemoveWires(ids) => {
+                sandbox.remove_wires(ids);
+                true
+            }
+            Self::SetPosition(id, position) => sandbox
+                .component_mut(*id)
+                .map(|mut component| component.set_position(*position))
+                .is_some(),
+            Self::SetRotation(id, rotation) => sandbox
+                .component_mut(*id)
+                .map(|mut component| component.set_rotation(*rotation))
+                .is_some(),
+            Self::SetCustomData(id, custom_data) => sandbox
+                .component_mut(*id)
+                .map(|mut component| component.set_custom_data(custom_data.clone()))
+                .is_some(),
+        }
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        match self {
+            Self::AddComponent {
+                id,
+                type_name,
+                parent,
+                position,
+                rotation,
+                num_inputs,
+                num_outputs,
+                custom_data,
+            } => {
+                ADD_COMPONENT.write_to(writer)?;
+                write_component(*id, writer)?;
+                type_name.write_to(writer)?;
+                parent.is_some().write_to(writer)?;
+                write_component(parent.unwrap_or(*id), writer)?;
+                position.write_to(writer)?;
+                rotation.write_to(writer)?;
+                num_inputs.write_to(writer)?;
+                num_outputs.write_to(writer)?;
+                write_custom_data(custom_data, writer)
+            }
+            Self::RemoveComponents(ids) => {
+                REMOVE_COMPONENTS.write_to(writer)?;
+                ids.len().write_to(writer)?;
+                ids.iter().try_for_each(|&id| write_component(id, writer))
+            }
+            Self::AddWire { id, a, b, rotation } => {
+                ADD_WIRE.write_to(writer)?;
+                id.0.to_bits().write_to(writer)?;
+                write_peg(a, writer)?;
+                write_peg(b, writer)?;
+                rotation.write_to(writer)
+            }
+            Self::RemoveWires(ids) => {
+                REMOVE_WIRES.write_to(writer)?;
+                ids.len().write_to(writer)?;
+                ids.iter()
+                    .try_for_each(|id| id.0.to_bits().write_to(writer))
+            }
+            Self::SetPosition(id, position) => {
+                SET_POSITION.write_to(writer)?;
+                write_component(*id, writer)?;
+                position.write_to(writer)
+            }
+            Self::SetRotation(id, rotation) => {
+                SET_ROTATION.write_to(writer)?;
+                write_component(*id, writer)?;
+                rotation.write_to(writer)
+            }
+            Self::SetCustomData(id, custom_data) => {
+                SET_CUSTOM_DATA.write_to(writer)?;
+                write_component(*id, writer)?;
+                write_custom_data(custom_data, writer)
+            }
+        }
+    }
+
+    fn read<R: Read>(tag: u8, reader: &mut R) -> Result<Self, Error> {
+        Ok(match tag {
+            ADD_COMPONENT => {
+                let id = read_component(reader)?;
+                let type_name = String::read_from(reader)?;
+                let has_parent = bool::read_from(reader)?;
+                let parent = read_component(reader)?;
+                Self::AddComponent {
+                    id,
+                    type_name,
+                    parent: has_parent.then_some(parent),
+                    position: ReadFrom::read_from(reader)?,
+                    rotation: ReadFrom::read_from(reader)?,
+                    num_inputs: ReadFrom::read_from(reader)?,
+                    num_outputs: ReadFrom::read_from(reader)?,
+                    custom_data: read_custom_data(reader)?,
+                }
+            }
+            REMOVE_COMPONENTS => {
+                let len = usize::read_from(reader)?;
+                let ids = (0..len)
+                    .map(|_| read_component(reader))
+                    .collect::<Result<_, Error>>();
+                Self::RemoveComponents(ids?)
+            }
+            ADD_WIRE => Self::AddWire {
+                id: WireId(Address::from_bits(u64::read_from(reader)?)),
+                a: read_peg(reader)?,
+                b: read_peg(reader)?,
+                rotation: ReadFrom::read_from(reader)?,
+            },
+            REMOVE_WIRES => {
+                let len = usize::read_from(reader)?;
+                let ids = (0..len)
+                    .map(|_| Ok(WireId(Address::from_bits(u64::read_from(reader)?))))
+                    .collect::<Result<_, Error>>();
+                Self::RemoveWires(ids?)
+            }
+            SET_POSITION => {
+                Self::SetPosition(read_component(reader)?, ReadFrom::read_from(reader)?)
+            }
+            SET_ROTATION => {
+                Self::SetRotation(read_component(reader)?, ReadFrom::read_from(reader)?)
+            }
+            SET_CUSTOM_DATA => {
+                Self::SetCustomData(read_component(reader)?, read_custom_data(reader)?)
+            }
+            _ => return Err(Error::InvalidSave),
+        })
+    }
+}
+
+fn write_component<W: Write>(id: ComponentId, writer: &mut W) -> Result<(), Error> {
+    id.0.to_bits().write_to(writer)
+}
+
+fn read_component<R: Read>(reader: &mut R) -> Result<ComponentId, Error> {
+    Ok(ComponentId(Address::from_bits(u64::read_from(reader)?)))
+}
+
+fn write_peg<W: Write>(peg: &PegAddress, writer: &mut W) -> Result<(), Error> {
+    write_component(peg.component, writer)?;
+    (peg.peg_type == PegType::Output).write_to(writer)?;
+    peg.peg_index.write_to(writer)
+}
+
+fn read_peg<R: Read>(reader: &mut R) -> Result<PegAddress, Error> {
+    Ok(PegAddress {
+        component: read_component(reader)?,
+        peg_type: match bool::read_from(reader)? {
+            false => PegType::Input,
+            true => PegType::Output,
+        },
+        peg_index: usize::read_from(reader)?,
+    })
+}
+
+fn write_custom_data<W: Write>(custom_data: &Option<Vec<u8>>, writer: &mut W) -> Result<(), Error> {
+    custom_data.is_some().write_to(writer)?;
+    if let Some(data) = custom_data {
+        data.len().write_to(writer)?;
+        writer.write_all(data)?;
+    }
+    Ok(())
+}
+
+fn read_custom_data<R: Read>(reader: &mut R) -> Result<Option<Vec<u8>>, Error> {
+    if !bool::read_from(reader)? {
+        return Ok(None);
+    }
+    let len = usize::read_from(reader)?;
+    read_bytes(reader, len, &mut Budget::unlimited()).map(Some)
+}
@@ -0,0 +1,167 @@
+//! Re-binding the inputs of keyboard builds.
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::custom_data::{ComponentData, Key, PanelKey};
+use crate::error::Error;
+
+use super::{ComponentId, Sandbox};
+
+/// The input bound to a single key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeyBinding {
+    pub component: ComponentId,
+    /// Whether the key is a `MHG.PanelKey`, rather than a `MHG.Key`.
+    pub panel: bool,
+    pub bound_input: i32,
+}
+
+/// The bindings of every key in a sandbox, ordered by component ID.
+///
+/// Get one from [`Sandbox::key_bindings`], edit it, and write it back with
+/// [`Sandbox::set_key_bindings`]. With the `serde` feature, it can be
+/// serialized, so a keyboard build can be shared along with its layout.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeyBindings {
+    bindings: Vec<KeyBinding>,
+}
+
+impl KeyBindings {
+    pub fn len(&self) -> usize {
+        self.bindings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bindings.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &KeyBinding> {
+        self.bindings.iter()
+    }
+
+    /// The input bound to a key.
+    pub fn get(&self, component: ComponentId) -> Option<i32> {
+        self.find(component)
+            .map(|index| self.bindings[index].bound_input)
+    }
+
+    /// Bind a key to a different input. Returns `false` if the component is
+    /// not in the table.
+    pub fn set(&mut self, component: ComponentId, bound_input: i32) -> bool {
+        match self.find(component) {
+            Some(index) => {
+                self.bindings[index].bound_input = bound_input;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Rebind every key bound to `from` to `to` instead, returning how many
+    /// keys were changed.
+    pub fn remap(&mut self, from: i32, to: i32) -> usize {
+        self.remap_all(&HashMap::from([(from, to)]))
+    }
+
+    /// Rebind keys according to a table of old inputs to new inputs, returning
+    /// how many keys were changed.
+    ///
+    /// Every key is looked up in the table once, so swapping two inputs works
+    /// as expected.
+    pub fn remap_all(&mut self, table: &HashMap<i32, i32>) -> usize {
+        let mut changed = 0;
+        for binding in &mut self.bindings {
+            if let Some(&to) = table.get(&binding.bound_input) {
+                if to != binding.bound_input {
+                    binding.bound_input = to;
+                    changed += 1;
+                }
+            }
+        }
+        changed
+    }
+
+    /// Every input that is bound to at least one key.
+    pub fn inputs(&self) -> BTreeSet<i32> {
+        self.bindings
+            .iter()
+            .map(|binding| binding.bound_input)
+            .collect()
+    }
+
+    fn find(&self, component: ComponentId) -> Option<usize> {
+        self.bindings
+            .binary_search_by_key(&component, |binding| binding.component)
+            .ok()
+    }
+}
+
+impl Sandbox {
+    /// Collect the bindings of every `MHG.Key` and `MHG.PanelKey`.
+    ///
+    /// Keys whose custom data can't be decoded are left out.
+    pub fn key_bindings(&self) -> KeyBindings {
+        let mut bindings = Vec::new();
+        for (panel, type_string) in [(false, Key::TYPE_STRING), (true, PanelKey::TYPE_STRING)] {
+            for id in self.components_of_type(type_string) {
+                let info = self.components.get(id.0).unwrap();
+                let Some(mut data) = info.custom_data.as_deref() else {
+                    continue;
+                };
+                if let Ok(key) = Key::read(&mut data) {
+                    bindings.push(KeyBinding {
+                        component: id,
+                        panel,
+                        bound_input: key.bound_input,
+                    });
+                }
+            }
+        }
+        bindings.sort_by_key(|binding| binding.component);
+        KeyBindings { bindings }
+    }
+
+    /// Write key bindings back into the custom data of their keys, returning
+    /// how many keys were changed.
+    ///
+    /// Bindings for components that no longer exist, or are not keys, are
+    /// ignored. Everything else in the keys' custom data is kept as it was.
+    pub fn set_key_bindings(&mut self, bindings: &KeyBindings) -> Result<usize, Error> {
+        let key_types = [Key::TYPE_STRING, PanelKey::TYPE_STRING]
+            .map(|type_string| self.component_types.get(type_string));
+        let mut changed = 0;
+        for binding in bindings.iter() {
+            let Some(info) = self.components.get_mut(binding.component.0) else {
+                continue;
+            };
+            if !key_types.contains(&Some(info.type_id)) {
+                continue;
+            }
+            let Some(mut data) = info.custom_data.as_deref() else {
+                continue;
+            };
+            let mut key = Key::read(&mut data)?;
+            if key.bound_input == binding.bound_input {
+                continue;
+            }
+            key.bound_input = binding.bound_input;
+            let mut bytes = Vec::new();
+            key.write(&mut bytes)?;
+            // Keep anything after the fields we know about.
+            bytes.extend_from_slice(data);
+            info.custom_data = Some(bytes);
+            changed += 1;
+        }
+        Ok(changed)
+    }
+
+    /// Rebind keys according to a table of old inputs to new inputs, in one
+    /// call. Returns how many keys were changed.
+    pub fn remap_keys(&mut self, table: &HashMap<i32, i32>) -> Result<usize, Error> {
+        let mut bindings = self.key_bindings();
+        bindings.remap_all(table);
+        self.set_key_bindings(&bindings)
+    }
+}
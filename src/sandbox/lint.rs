@@ -0,0 +1,120 @@
+//! Checks for common wiring mistakes.
+
+use std::collections::HashMap;
+
+use super::{ComponentId, NetId, PegAddress, PegType, Sandbox};
+
+/// A likely mistake found by [`Sandbox::lint`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LintIssue {
+    /// An input peg with no wires at all.
+    UnwiredInput(PegAddress),
+    /// An input peg that is wired, but only to other inputs that no output
+    /// drives, so it will never turn on.
+    FloatingInput(PegAddress),
+    /// An output peg that isn't wired to anything.
+    UnusedOutput(PegAddress),
+    /// A net of inputs driven by more than one output, which the game ORs
+    /// together. This is sometimes intended, but often a miswiring.
+    Contention {
+        /// The input pegs of the net, ordered by component ID.
+        inputs: Vec<PegAddress>,
+        /// The outputs driving the net, ordered by component ID.
+        outputs: Vec<PegAddress>,
+    },
+    /// A component with pegs, none of which is wired. Its pegs aren't
+    /// reported separately.
+    Disconnected(ComponentId),
+}
+
+impl Sandbox {
+    /// Look for wiring mistakes.
+    ///
+    /// Issues about single components come first, ordered by component ID,
+    /// followed by contention, ordered by the first input of each net.
+    ///
+    /// ```
+    /// use blotter::sandbox::{component::Peg, LintIssue, Sandbox};
+    ///
+    /// let mut sandbox = Sandbox::new();
+    /// let peg = sandbox.add_component(&Peg::new().build());
+    /// assert_eq!(sandbox.lint(), [LintIssue::Disconnected(peg)]);
+    /// ```
+    pub fn lint(&self) -> Vec<LintIssue> {
+        let mut issues = Vec::new();
+        let mut drivers: HashMap<NetId, Vec<PegAddress>> = HashMap::new();
+        for (_, wire) in self.wires.iter() {
+            let (output, input) = match (wire.a.peg_type, wire.b.peg_type) {
+                (PegType::Output, _) => (wire.a, wire.b),
+                (_, PegType::Output) => (wire.b, wire.a),
+                _ => continue,
+            };
+            let net_id = self.get_peg(&input).unwrap().net_id;
+            drivers.entry(net_id).or_default().push(output);
+        }
+
+        for (address, info) in self.components.iter() {
+            let id = ComponentId(address);
+            let pegs = info.inputs.iter().chain(&info.outputs);
+            if pegs.clone().next().is_some() && pegs.clone().all(|peg| peg.wires.is_empty()) {
+                issues.push(LintIssue::Disconnected(id));
+                continue;
+            }
+            for (peg_index, peg) in info.inputs.iter().enumerate() {
+                let addr = PegAddress {
+                    component: id,
+                    peg_type: PegType::Input,
+                    peg_index,
+                };
+                if peg.wires.is_empty() {
+                    issues.push(LintIssue::UnwiredInput(addr));
+                } else if !drivers.contains_key(&peg.net_id) {
+                    issues.push(LintIssue::FloatingInput(addr));
+                }
+            }
+            for (peg_index, peg) in info.outputs.iter().enumerate() {
+                if peg.wires.is_empty() {
+                    issues.push(LintIssue::UnusedOutput(PegAddress {
+                        component: id,
+                        peg_type: PegType::Output,
+                        peg_index,
+                    }));
+                }
+            }
+        }
+
+        let mut contention: Vec<(Vec<PegAddress>, Vec<PegAddress>)> = drivers
+            .into_iter()
+            .filter_map(|(net_id, mut outputs)| {
+                outputs.sort_by_key(peg_order);
+                outputs.dedup();
+                let mut inputs: Vec<PegAddress> = self
+                    .nets
+                    .get(net_id.0)
+                    .unwrap()
+                    .pegs
+                    .iter()
+                    .copied()
+                    .collect();
+                inputs.sort_by_key(peg_order);
+                (outputs.len() > 1).then_some((inputs, outputs))
+            })
+            .collect();
+        contention.sort_by_key(|(inputs, _)| inputs.first().map(peg_order));
+        issues.extend(
+            contention
+                .into_iter()
+                .map(|(inputs, outputs)| LintIssue::Contention { inputs, outputs }),
+        );
+        issues
+    }
+}
+
+fn peg_order(addr: &PegAddress) -> (ComponentId, bool, usize) {
+    (
+        addr.component,
+        addr.peg_type == PegType::Output,
+        addr.peg_index,
+    )
+}
@@ -0,0 +1,90 @@
+//! A lightweight summary of where boards are in the world.
+
+use crate::custom_data::{self, ComponentData};
+use crate::geometry::SQUARE_SIZE;
+
+use super::{ComponentId, Sandbox};
+
+/// The location and extent of every circuit board in a sandbox.
+///
+/// This is intended for overlaying save contents onto other views of the
+/// world, like map screenshots. With the `serde` feature, it can be serialized
+/// for consumption by external tools.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BoardMap {
+    pub boards: Vec<BoardExtent>,
+}
+
+/// The location and extent of a single circuit board.
+///
+/// All coordinates are in world-space meters.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BoardExtent {
+    pub component: ComponentId,
+    /// The closest ancestor that is also a circuit board, if any.
+    pub parent_board: Option<ComponentId>,
+    /// Size of the board in grid squares, along its local X and Z axes.
+    pub size: [u32; 2],
+    pub color: [u8; 3],
+    /// The center of the board's surface.
+    pub centroid: [f32; 3],
+    /// Minimum corner of the board's axis-aligned bounding box.
+    pub min: [f32; 3],
+    /// Maximum corner of the board's axis-aligned bounding box.
+    pub max: [f32; 3],
+}
+
+impl Sandbox {
+    /// Compute the location and extent of every circuit board.
+    pub fn board_map(&self) -> BoardMap {
+        let board_type = self
+            .component_types
+            .get(custom_data::CircuitBoard::TYPE_STRING);
+
+        let boards = self
+            .components
+            .iter()
+            .filter_map(|(address, info)| {
+                let board = self.board_data(info, board_type)?;
+                let id = ComponentId(address);
+                let transform = self.world_transform(id);
+
+                let x = board.size_x as f32 * SQUARE_SIZE;
+                let z = board.size_z as f32 * SQUARE_SIZE;
+                let corners = [[0.0, 0.0, 0.0], [x, 0.0, 0.0], [x, 0.0, z], [0.0, 0.0, z]]
+                    .map(|corner| transform.apply(corner));
+                let mut min = corners[0];
+                let mut max = corners[0];
+                for corner in &corners[1..] {
+                    for axis in 0..3 {
+                        min[axis] = min[axis].min(corner[axis]);
+                        max[axis] = max[axis].max(corner[axis]);
+                    }
+                }
+
+                let mut parent_board = info.parent;
+                while let Some(parent) = parent_board {
+                    let parent_info = self.components.get(parent.0).unwrap();
+                    if Some(parent_info.type_id) == board_type {
+                        break;
+                    }
+                    parent_board = parent_info.parent;
+                }
+
+                Some(BoardExtent {
+                    component: id,
+                    parent_board,
+                    size: [board.size_x, board.size_z],
+                    color: board.color,
+                    centroid: transform.apply([x / 2.0, 0.0, z / 2.0]),
+                    min,
+                    max,
+                })
+            })
+            .collect();
+
+        BoardMap { boards }
+    }
+}
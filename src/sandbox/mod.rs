@@ -1,34 +1,140 @@
 //! High-level API for constructing and modifying a "Sandbox" / World.
 
+mod annotations;
+mod auto_connect;
+mod auto_place;
+mod blueprint;
+mod bridges;
+mod collision;
+mod compact;
 pub mod component;
+mod congestion;
+mod footprint;
+mod hierarchy;
+mod journal;
+mod keys;
+mod lint;
+mod map;
+mod mods;
+mod netlist;
+#[cfg(feature = "rayon")]
+mod parallel_load;
+mod pattern;
+mod ports;
+mod recolor;
+mod resize;
+mod search;
+mod selection;
 mod serialize;
+mod shard;
+mod simulation;
+mod snapshot;
+mod split;
+mod subassembly;
+mod test_bench;
+mod timing;
+mod transform;
+mod type_name;
+mod types;
+mod usage;
+mod view;
+mod wire_length;
+
+pub use self::annotations::Entity;
+pub use self::auto_connect::{AutoConnect, ProposedWire};
+pub use self::auto_place::{AutoPlace, AutoPlaceReport};
+pub use self::blueprint::{Blueprint, BlueprintError, BlueprintField, BlueprintValue};
+pub use self::bridges::{Bridge, BridgeModel, BridgedNets};
+pub use self::collision::PlacementValidator;
+pub use self::compact::Remap;
+pub use self::congestion::{Hotspot, WireHeatmap};
+pub use self::footprint::{Footprint, FootprintKind};
+pub use self::hierarchy::{Ancestors, Descendants};
+pub use self::journal::{Edit, Journal, JournalError};
+pub use self::keys::{KeyBinding, KeyBindings};
+pub use self::lint::LintIssue;
+pub use self::map::{BoardExtent, BoardMap};
+pub use self::netlist::{Netlist, NetlistComponent, NetlistNet};
+pub use self::pattern::{Pattern, PatternMatch, PatternPeg};
+pub use self::ports::{PortError, PortMismatch, Ports};
+pub use self::recolor::Theme;
+pub use self::resize::{Anchor, BoardAnchor, ResizeError};
+pub use self::search::SearchHit;
+pub use self::selection::Selection;
+pub use self::serialize::{
+    InvalidWire, SandboxLoad, SandboxLoadError, SandboxLoadWarning, SandboxSaveError, SaveOptions,
+    WireProblem,
+};
+pub use self::simulation::Simulation;
+pub use self::snapshot::SandboxSnapshot;
+pub use self::split::{BoardSplit, CutWire};
+pub use self::subassembly::Subassembly;
+pub use self::test_bench::{BenchFailure, FailureKind, Probe, TestBench};
+pub use self::timing::{TimingError, TimingPath};
+pub use self::type_name::TypeName;
+pub use self::types::{TypeConflict, TypeIdsExhausted, TypeRegistration, TypeRemap};
+pub use self::usage::PegUsage;
+pub use self::view::{ComponentMut, ComponentRef};
+pub use self::wire_length::WireLengths;
 
 use bitvec::vec::BitVec;
 
+use self::type_name::TypeTable;
+
 use crate::{
-    latest::ModInfo,
+    latest::{ModInfo, SaveType},
     misc::{
         dense_store::{DenseStore, Index},
         object_store::{Address, ObjectStore},
+        small_set::SmallSet,
     },
 };
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt,
     iter::repeat_with,
+    sync::Arc,
 };
 
 /// An in-memory representation of a Sandbox that is easy to modify.
+///
+/// Cloning a sandbox is cheap: clones share storage until they are modified.
+/// See [`Sandbox::snapshot`].
+///
+/// # Memory
+///
+/// On 64-bit targets, a sandbox takes about 175 bytes per component, plus
+/// about 100 bytes per peg and 90 bytes per wire, not counting custom data.
+/// A typical gate, with three pegs and two wires, takes about 650 bytes, or
+/// six times its size in a save. Components, pegs and nets with many
+/// connections take somewhat more. Component type names are
+/// [interned](TypeName), so they are shared by every sandbox that uses them.
+#[derive(Clone)]
 pub struct Sandbox {
-    root_components: HashSet<ComponentId>,
+    root_components: Arc<HashSet<ComponentId>>,
     components: ObjectStore<ComponentInfo>,
+    /// Components of each type, by numeric type ID.
+    components_by_type: Arc<HashMap<u16, BTreeSet<ComponentId>>>,
     wires: ObjectStore<WireInfo>,
     nets: DenseStore<NetInfo>,
     net_states: BitVec<u8>,
 
     next_type: u16,
-    component_types: HashMap<String, u16>,
+    component_types: TypeTable,
 
     mods: Vec<ModInfo>,
+    save_type: SaveType,
+    /// Data from a newer game that isn't understood, which is saved again
+    /// as it is.
+    extra: Vec<u8>,
+    /// Data after the footer of the save that the sandbox was loaded from,
+    /// which is saved again after the footer.
+    trailing: Vec<u8>,
+    /// User annotations, which are never saved.
+    annotations: Arc<annotations::Annotations>,
+    /// The address of each component loaded from a save, for
+    /// [`SaveOptions::preserve_addresses`].
+    addresses: Arc<HashMap<ComponentId, u32>>,
 }
 
 impl Sandbox {
@@ -37,44 +143,93 @@ impl Sandbox {
         Self::with_meta_info(default_component_types_map(), Vec::new())
     }
 
+    /// Whether the sandbox is saved as a world or as a subassembly.
+    ///
+    /// Loaded sandboxes keep the save type of their file; new sandboxes are
+    /// worlds.
+    pub fn save_type(&self) -> SaveType {
+        self.save_type
+    }
+
+    pub fn set_save_type(&mut self, save_type: SaveType) {
+        self.save_type = save_type;
+    }
+
     /// Create an empty sandbox level with custom component-type / mod info.
-    fn with_meta_info(component_types: HashMap<String, u16>, mods: Vec<ModInfo>) -> Self {
+    fn with_meta_info(component_types: TypeTable, mods: Vec<ModInfo>) -> Self {
         Self {
-            root_components: HashSet::new(),
+            root_components: Arc::default(),
             components: ObjectStore::new(),
+            components_by_type: Arc::default(),
             wires: ObjectStore::new(),
             nets: DenseStore::new(),
             net_states: BitVec::new(),
 
-            next_type: component_types.values().copied().max().unwrap_or(0),
+            next_type: component_types.next_id(),
             component_types,
 
             mods,
+            save_type: SaveType::World,
+            extra: Vec::new(),
+            trailing: Vec::new(),
+            annotations: Arc::default(),
+            addresses: Arc::default(),
         }
     }
 
+    /// Add a component, and give it new nets for each of its pegs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the component's parent isn't in the sandbox, or if its type
+    /// is new and every type ID is taken; see
+    /// [`try_add_component`](Self::try_add_component).
     pub fn add_component(&mut self, component: &ComponentBuilder) -> ComponentId {
+        self.try_add_component(component)
+            .expect("parent of new component is not in the sandbox, or no type ID is free")
+    }
+
+    /// Add a component like [`add_component`](Self::add_component), or
+    /// return `None`, changing nothing, if its parent isn't in the sandbox or
+    /// its type is new and every type ID is taken.
+    ///
+    /// ```
+    /// use blotter::sandbox::{component::Peg, Sandbox};
+    ///
+    /// let mut sandbox = Sandbox::new();
+    /// let parent = sandbox.add_component(&Peg::new().build());
+    /// sandbox.remove_component(parent);
+    /// assert_eq!(sandbox.try_add_component(&Peg::new().build().parent(Some(parent))), None);
+    /// assert_eq!(sandbox.component_ids().count(), 0);
+    /// ```
+    pub fn try_add_component(&mut self, component: &ComponentBuilder) -> Option<ComponentId> {
+        if component
+            .parent
+            .is_some_and(|parent| !self.is_valid(parent))
+        {
+            return None;
+        }
         let info = ComponentInfo {
-            type_id: self.get_component_type(component.id),
+            type_id: self.try_get_component_type(component.id)?,
             parent: component.parent,
             position: component.position,
             rotation: component.rotation,
-            children: HashSet::new(),
+            children: SmallSet::new(),
             inputs: repeat_with(|| PegInfo {
                 net_id: self.make_net(),
-                wires: HashSet::new(),
+                wires: SmallSet::new(),
             })
             .take(component.num_inputs as usize)
             .collect(),
             outputs: repeat_with(|| PegInfo {
                 net_id: self.make_net(),
-                wires: HashSet::new(),
+                wires: SmallSet::new(),
             })
             .take(component.num_outputs as usize)
             .collect(),
             custom_data: component.custom_data.clone(),
         };
-        self.insert_component(info)
+        Some(self.insert_component(info))
     }
 
     // Manually insert a generated component info.
@@ -83,6 +238,10 @@ impl Sandbox {
         // Add component info.
         let id = ComponentId(self.components.insert(info));
         let info = self.components.get(id.0).unwrap();
+        Arc::make_mut(&mut self.components_by_type)
+            .entry(info.type_id)
+            .or_default()
+            .insert(id);
 
         // Add peg-net cross-references.
         for (peg_index, peg_info) in info.inputs.iter().enumerate() {
@@ -110,27 +269,65 @@ impl Sandbox {
 
         // Add parent-child cross-reference.
         if let Some(parent) = info.parent {
-            // Valid savefiles will store and load the parent before the child,
-            // so it is reasonable to assume the parent exists here.
-            //TODO bubble this error; it should be recoverable in file loading.
+            // The parent has to exist by now. `try_add_component` checks it,
+            // copies insert parents before their children, and the loader
+            // inserts every component as a root and links parents later. The
+            // parent that a copy is placed under is up to its caller.
             self.components
                 .get_mut(parent.0)
                 .unwrap()
                 .children
                 .insert(id);
         } else {
-            self.root_components.insert(id);
+            Arc::make_mut(&mut self.root_components).insert(id);
         }
 
         id
     }
 
+    /// Connect two pegs with a wire, or return the wire that already
+    /// connects them.
     pub fn add_wire(
         &mut self,
         addr_a: PegAddress,
         addr_b: PegAddress,
         rotation: f32,
     ) -> Result<WireId, AddWireError> {
+        self.add_wire_with_status(addr_a, addr_b, rotation)
+            .map(|added| added.id())
+    }
+
+    /// Like [`add_wire`](Self::add_wire), but also tell whether the wire is
+    /// new.
+    ///
+    /// ```
+    /// use blotter::sandbox::{component::Delayer, AddWireError, PegAddress, PegType, Sandbox, WireAdded};
+    ///
+    /// let mut sandbox = Sandbox::new();
+    /// let a = sandbox.add_component(&Delayer::new().build());
+    /// let b = sandbox.add_component(&Delayer::new().build());
+    /// let peg = |component, peg_type| PegAddress { component, peg_type, peg_index: 0 };
+    ///
+    /// let added = sandbox.add_wire_with_status(peg(a, PegType::Output), peg(b, PegType::Input), 0.0);
+    /// let Ok(WireAdded::Created(id)) = added else { panic!() };
+    /// let again = sandbox.add_wire_with_status(peg(b, PegType::Input), peg(a, PegType::Output), 0.0);
+    /// assert_eq!(again, Ok(WireAdded::Existing(id)));
+    ///
+    /// let outputs = sandbox.add_wire_with_status(peg(a, PegType::Output), peg(b, PegType::Output), 0.0);
+    /// assert_eq!(outputs, Err(AddWireError::OutputToOutput));
+    /// let missing = PegAddress { component: b, peg_type: PegType::Input, peg_index: 1 };
+    /// let result = sandbox.add_wire_with_status(peg(a, PegType::Input), missing, 0.0);
+    /// assert_eq!(result, Err(AddWireError::MissingPegB(missing)));
+    /// ```
+    pub fn add_wire_with_status(
+        &mut self,
+        addr_a: PegAddress,
+        addr_b: PegAddress,
+        rotation: f32,
+    ) -> Result<WireAdded, AddWireError> {
+        if addr_a == addr_b {
+            return Err(AddWireError::SelfLoop(addr_a));
+        }
         self.insert_wire(addr_a, addr_b, rotation, None)
     }
 
@@ -144,31 +341,29 @@ impl Sandbox {
         addr_b: PegAddress,
         rotation: f32,
         net_id: Option<NetId>,
-    ) -> Result<WireId, AddWireError> {
+    ) -> Result<WireAdded, AddWireError> {
         // It is illegal to directly connect output pegs.
         if addr_a.peg_type == PegType::Output && addr_b.peg_type == PegType::Output {
-            return Err(AddWireError::InvalidPegAddress)?;
+            return Err(AddWireError::OutputToOutput);
         }
 
         let peg_a = self
             .get_peg(&addr_a)
-            .ok_or(AddWireError::InvalidPegAddress)?;
+            .ok_or(AddWireError::MissingPegA(addr_a))?;
         let peg_b = self
             .get_peg(&addr_b)
-            .ok_or(AddWireError::InvalidPegAddress)?;
+            .ok_or(AddWireError::MissingPegB(addr_b))?;
         // If there is already a wire connecting these pegs, nothing needs to be
         // done.
-        if let Some(&wire_id) = peg_a.wires.intersection(&peg_b.wires).next() {
-            return Ok(wire_id);
+        if let Some(&wire_id) = peg_a.wires.common(&peg_b.wires) {
+            return Ok(WireAdded::Existing(wire_id));
         }
 
         let net_id = match net_id {
             Some(id) => {
                 // If net ID is specified (i.e. from an existing savefile),
-                // check savefile validity: ensure that both endpoints and the
+                // the loader has already checked that both endpoints and the
                 // wire all have the same net.
-
-                //TODO bubble this error
                 assert!(
                     (addr_a.peg_type == PegType::Output || id == peg_b.net_id)
                         && (addr_b.peg_type == PegType::Output || id == peg_a.net_id),
@@ -197,7 +392,115 @@ impl Sandbox {
             }
         };
 
-        // Create wire.
+        Ok(WireAdded::Created(
+            self.create_wire(addr_a, addr_b, rotation, net_id),
+        ))
+    }
+
+    /// Add many wires at once, returning their IDs in the same order.
+    ///
+    /// This is equivalent to calling [`add_wire`](Self::add_wire) for each
+    /// wire, but nets are merged once at the end instead of after every wire,
+    /// which is much faster for large scripted builds. If any wire is invalid,
+    /// an error is returned and no wires are added.
+    pub fn add_wires<I>(&mut self, wires: I) -> Result<Vec<WireId>, AddWireError>
+    where
+        I: IntoIterator<Item = (PegAddress, PegAddress, f32)>,
+    {
+        let wires: Vec<(PegAddress, PegAddress, f32)> = wires.into_iter().collect();
+        for (addr_a, addr_b, _) in &wires {
+            if addr_a == addr_b {
+                return Err(AddWireError::SelfLoop(*addr_a));
+            }
+            if addr_a.peg_type == PegType::Output && addr_b.peg_type == PegType::Output {
+                return Err(AddWireError::OutputToOutput);
+            }
+            if self.get_peg(addr_a).is_none() {
+                return Err(AddWireError::MissingPegA(*addr_a));
+            }
+            if self.get_peg(addr_b).is_none() {
+                return Err(AddWireError::MissingPegB(*addr_b));
+            }
+        }
+
+        // Create the wires. Wires between inputs are put in the net of their
+        // first peg for now, and the nets that need to be merged are tracked
+        // with a union-find.
+        let mut ids = Vec::with_capacity(wires.len());
+        let mut parents: HashMap<NetId, NetId> = HashMap::new();
+        for (addr_a, addr_b, rotation) in wires {
+            let peg_a = self.get_peg(&addr_a).unwrap();
+            let peg_b = self.get_peg(&addr_b).unwrap();
+            if let Some(&wire_id) = peg_a.wires.common(&peg_b.wires) {
+                ids.push(wire_id);
+                continue;
+            }
+            let net_id = if addr_a.peg_type == PegType::Output {
+                peg_a.net_id
+            } else if addr_b.peg_type == PegType::Output {
+                peg_b.net_id
+            } else {
+                let root_a = find_root(&mut parents, peg_a.net_id);
+                let root_b = find_root(&mut parents, peg_b.net_id);
+                if root_a != root_b {
+                    parents.insert(root_a, root_b);
+                }
+                peg_a.net_id
+            };
+            ids.push(self.create_wire(addr_a, addr_b, rotation, net_id));
+        }
+
+        // Merge each group of nets into its largest member.
+        let mut groups: HashMap<NetId, Vec<NetId>> = HashMap::new();
+        for net_id in parents.keys().copied().collect::<Vec<_>>() {
+            let root = find_root(&mut parents, net_id);
+            groups.entry(root).or_default().push(net_id);
+        }
+        let mut removed = Vec::new();
+        for (root, mut members) in groups {
+            members.push(root);
+            members.sort_unstable();
+            let id_dest = *members
+                .iter()
+                .max_by_key(|id| (self.nets.get(id.0).unwrap().size(), std::cmp::Reverse(**id)))
+                .unwrap();
+            for id_src in members {
+                if id_src == id_dest {
+                    continue;
+                }
+                let src = self.nets.get_mut(id_src.0).unwrap();
+                let pegs = std::mem::take(&mut src.pegs);
+                let wires = std::mem::take(&mut src.wires);
+                for wire_id in &wires {
+                    self.wires.get_mut(wire_id.0).unwrap().net_id = id_dest;
+                }
+                for peg_id in &pegs {
+                    self.get_peg_mut(peg_id).unwrap().net_id = id_dest;
+                }
+                let dest = self.nets.get_mut(id_dest.0).unwrap();
+                dest.pegs.extend(pegs);
+                dest.wires.extend(wires);
+                removed.push(id_src);
+            }
+        }
+        // Removing in descending order means that the net moved into each
+        // removed position is never one that still has to be removed.
+        removed.sort_unstable_by(|a, b| b.cmp(a));
+        for net_id in removed {
+            self.remove_net(net_id);
+        }
+
+        Ok(ids)
+    }
+
+    /// Create a wire in the given net, and register its cross-references.
+    fn create_wire(
+        &mut self,
+        addr_a: PegAddress,
+        addr_b: PegAddress,
+        rotation: f32,
+        net_id: NetId,
+    ) -> WireId {
         let info = WireInfo {
             a: addr_a,
             b: addr_b,
@@ -211,108 +514,212 @@ impl Sandbox {
         self.get_peg_mut(&addr_b).unwrap().wires.insert(wire_id);
         self.nets.get_mut(net_id.0).unwrap().wires.insert(wire_id);
 
-        Ok(wire_id)
+        wire_id
     }
 
+    /// Remove a component and all of its descendants, along with any wires
+    /// connected to them.
     pub fn remove_component(&mut self, id: ComponentId) {
-        // Remove component.
-        let component = match self.components.remove(id.0) {
-            Some(x) => x,
-            None => {
-                // If component doesn't exist, nothing needs to be done.
-                return;
-            }
-        };
+        self.remove_components(&[id])
+    }
 
-        // For each peg in the removed component:
-        let inputs = component.inputs.iter().enumerate().map(|(index, peg)| {
-            (
-                PegAddress {
-                    component: id,
-                    peg_type: PegType::Input,
-                    peg_index: index,
-                },
-                peg,
-            )
-        });
-        let outputs = component.outputs.iter().enumerate().map(|(index, peg)| {
-            (
-                PegAddress {
-                    component: id,
-                    peg_type: PegType::Output,
-                    peg_index: index,
-                },
-                peg,
-            )
-        });
-        for (peg_addr, peg) in inputs.chain(outputs) {
-            // Remove all wires connected to this peg.
-            for wire_id in &peg.wires {
-                // It is safe to call this here; it is designed to handle the
-                // case where one or more pegs do not exist.
-                self.remove_wire(*wire_id);
+    /// Remove many components and all of their descendants at once, along
+    /// with any wires connected to them.
+    ///
+    /// This is much faster than removing the components one at a time, since
+    /// each affected net is only checked for splits once. IDs that don't
+    /// exist are ignored.
+    pub fn remove_components(&mut self, ids: &[ComponentId]) {
+        // Find every component to remove, including descendants. This is
+        // iterative so deeply nested boards can't overflow the stack. The
+        // order is kept so that the IDs freed by the removal, and the IDs of
+        // split nets, don't depend on hash set iteration order.
+        let mut doomed = HashSet::new();
+        let mut order = Vec::new();
+        let mut stack: Vec<ComponentId> = ids.iter().rev().copied().collect();
+        while let Some(id) = stack.pop() {
+            if let Some(component) = self.components.get(id.0) {
+                if doomed.insert(id) {
+                    order.push(id);
+                    hierarchy::push_sorted(&mut stack, &component.children);
+                }
             }
+        }
 
-            // Remove peg-net cross-references. Remove nets if empty.
-            let net = self.nets.get_mut(peg.net_id.0).unwrap();
-            net.pegs.remove(&peg_addr);
-            if net.size() == 0 {
-                self.remove_net(peg.net_id);
+        // Detach every wire connected to a removed component, without
+        // splitting nets yet.
+        let mut wires: BTreeSet<WireId> = BTreeSet::new();
+        for &id in &order {
+            let component = self.components.get(id.0).unwrap();
+            for peg in component.inputs.iter().chain(&component.outputs) {
+                wires.extend(&peg.wires);
             }
         }
+        let mut affected_nets = BTreeSet::new();
+        for wire_id in wires {
+            let wire = self.detach_wire(wire_id).unwrap();
+            affected_nets.insert(wire.net_id);
+        }
 
-        // Remove component-parent cross-references.
-        // If the parent does not exist, we may be a child of a just-deleted
-        // parent; ignore it.
-        if let Some(parent) = component
-            .parent
-            .and_then(|parent_id| self.components.get_mut(parent_id.0))
-        {
-            parent.children.remove(&id);
+        // Remove the components and their cross-references.
+        for &id in &order {
+            let component = self.components.remove(id.0).unwrap();
+            self.forget_annotations(Entity::Component(id));
+            if self.addresses.contains_key(&id) {
+                Arc::make_mut(&mut self.addresses).remove(&id);
+            }
+            if let Some(ids) =
+                Arc::make_mut(&mut self.components_by_type).get_mut(&component.type_id)
+            {
+                ids.remove(&id);
+            }
+            let inputs = component.inputs.iter().enumerate().map(|(index, peg)| {
+                (
+                    PegAddress {
+                        component: id,
+                        peg_type: PegType::Input,
+                        peg_index: index,
+                    },
+                    peg.net_id,
+                )
+            });
+            let outputs = component.outputs.iter().enumerate().map(|(index, peg)| {
+                (
+                    PegAddress {
+                        component: id,
+                        peg_type: PegType::Output,
+                        peg_index: index,
+                    },
+                    peg.net_id,
+                )
+            });
+            for (peg_addr, net_id) in inputs.chain(outputs) {
+                self.nets.get_mut(net_id.0).unwrap().pegs.remove(&peg_addr);
+                affected_nets.insert(net_id);
+            }
+            match component.parent {
+                Some(parent_id) if !doomed.contains(&parent_id) => {
+                    self.components
+                        .get_mut(parent_id.0)
+                        .unwrap()
+                        .children
+                        .remove(&id);
+                }
+                Some(_) => {}
+                None => {
+                    Arc::make_mut(&mut self.root_components).remove(&id);
+                }
+            }
         }
 
-        // If the component has any children, remove them.
-        // TODO refactor this recursion?
-        for child in component.children {
-            self.remove_component(child)
+        // Split the nets that lost wires or pegs, and collect the ones that
+        // are now empty. Splitting only adds new nets, so net IDs stay valid
+        // until the empty nets are removed.
+        let mut empty_nets = Vec::new();
+        for net_id in affected_nets {
+            if self.nets.get(net_id.0).unwrap().size() == 0 {
+                empty_nets.push(net_id);
+            } else {
+                self.split_net(net_id);
+            }
         }
+        // Each removal moves the last net into the removed position. Removing
+        // in descending order means that the moved net is never one that
+        // still has to be removed.
+        empty_nets.sort_unstable_by(|a, b| b.cmp(a));
+        for net_id in empty_nets {
+            self.remove_net(net_id);
+        }
+    }
+
+    /// Remove every component for which `f` returns false, along with its
+    /// descendants and any wires connected to them.
+    ///
+    /// `f` is called once for every component, in ID order, before anything
+    /// is removed. The descendants of a removed component are removed even
+    /// if `f` keeps them. Like [`remove_components`](Self::remove_components),
+    /// each affected net is only checked for splits once.
+    ///
+    /// ```
+    /// use blotter::sandbox::component::{CircuitBoard, Peg};
+    /// use blotter::sandbox::{ComponentBuilder, Sandbox};
+    ///
+    /// let mut sandbox = Sandbox::new();
+    /// let board = sandbox.add_component(&CircuitBoard::new().build());
+    /// let other = sandbox.add_component(&CircuitBoard::new().build());
+    /// let peg = sandbox.add_component(&Peg::new().build().parent(Some(board)));
+    /// sandbox.add_component(&ComponentBuilder::new("MHG.Label").parent(Some(board)));
+    /// sandbox.add_component(&Peg::new().build().parent(Some(other)));
+    ///
+    /// // Keep only what is on `board`, without its labels.
+    /// sandbox.retain_components(|id, component| {
+    ///     (id == board || component.parent().is_some()) && component.type_name() != "MHG.Label"
+    /// });
+    /// assert_eq!(sandbox.component_ids().collect::<Vec<_>>(), [board, peg]);
+    /// ```
+    pub fn retain_components<F>(&mut self, mut f: F)
+    where
+        F: FnMut(ComponentId, ComponentRef<'_>) -> bool,
+    {
+        let removed: Vec<ComponentId> = self
+            .components()
+            .filter(|&component| !f(component.id(), component))
+            .map(|component| component.id())
+            .collect();
+        self.remove_components(&removed);
     }
 
     pub fn remove_wire(&mut self, id: WireId) {
-        // Remove wire.
-        let wire = match self.wires.remove(id.0) {
-            Some(x) => x,
-            None => {
-                // If wire doesn't exist, nothing needs to be done.
-                return;
-            }
-        };
+        self.remove_wires(&[id])
+    }
 
-        // Remove wire-net and wire-peg cross-references.
-        self.nets.get_mut(wire.net_id.0).unwrap().wires.remove(&id);
-        // The pegs might not exist if a component was just removed.
-        // That is expected and should not panic/error:
-        if let Some(peg) = self.get_peg_mut(&wire.a) {
-            peg.wires.remove(&id);
+    /// Remove many wires at once.
+    ///
+    /// Each affected net is only checked for splits once, so this is much
+    /// faster than removing the wires one at a time when many of them share
+    /// nets, like the wires of a bus. IDs that don't exist are ignored.
+    pub fn remove_wires(&mut self, ids: &[WireId]) {
+        // Nets are split in ID order, so the IDs of new nets don't depend on
+        // hash set iteration order.
+        let mut affected_nets = BTreeSet::new();
+        for &id in ids {
+            if let Some(wire) = self.detach_wire(id) {
+                affected_nets.insert(wire.net_id);
+            }
         }
-        if let Some(peg) = self.get_peg_mut(&wire.b) {
-            peg.wires.remove(&id);
+        for net_id in affected_nets {
+            self.split_net(net_id);
         }
+    }
 
-        // Split net if necessary.
-        self.check_split(&wire.a, &wire.b);
+    /// Remove a wire and its cross-references, without splitting its net.
+    fn detach_wire(&mut self, id: WireId) -> Option<WireInfo> {
+        let wire = self.wires.remove(id.0)?;
+        self.forget_annotations(Entity::Wire(id));
+        self.nets.get_mut(wire.net_id.0).unwrap().wires.remove(&id);
+        for addr in [wire.a, wire.b] {
+            if let Some(peg) = self.get_peg_mut(&addr) {
+                peg.wires.remove(&id);
+            }
+        }
+        Some(wire)
     }
 
     fn get_component_type(&mut self, id: &str) -> u16 {
-        match self.component_types.get(id) {
-            Some(&x) => x,
-            None => {
-                let num = self.next_type;
-                self.next_type += 1;
-                self.component_types.insert(id.to_owned(), num);
-                num
-            }
+        self.try_get_component_type(id)
+            .expect("every component type ID is taken")
+    }
+
+    /// The numeric ID of a component type, registering it under a free ID if
+    /// the sandbox doesn't have it yet, or `None` if every ID is taken.
+    fn try_get_component_type(&mut self, id: &str) -> Option<u16> {
+        if let Some(num) = self.component_types.get(id) {
+            return Some(num);
         }
+        let num = self.component_types.free_id(self.next_type)?;
+        self.next_type = num.checked_add(1).unwrap_or(0);
+        self.component_types.insert(TypeName::new(id), num);
+        Some(num)
     }
 
     fn make_net(&mut self) -> NetId {
@@ -320,21 +727,27 @@ impl Sandbox {
 
         self.net_states.push(false);
         NetId(self.nets.insert(NetInfo {
-            wires: HashSet::new(),
-            pegs: HashSet::new(),
+            wires: SmallSet::new(),
+            pegs: SmallSet::new(),
         }))
     }
 
     fn remove_net(&mut self, net_id: NetId) -> Option<NetInfo> {
         if let Some((net, rename)) = self.nets.remove(net_id.0) {
-            // Rename all the references to the net that was moved into this position.
-            let renamed = self.nets.get(rename.dest).unwrap();
-            for wire_id in &renamed.wires {
-                self.wires.get_mut(wire_id.0).unwrap().net_id = NetId(rename.dest);
-            }
-            //TODO remove this clone, reduce size of get_peg_mut borrow
-            for peg_id in &renamed.pegs.clone() {
-                self.get_peg_mut(peg_id).unwrap().net_id = NetId(rename.dest);
+            // Rename all the references to the net that was moved into this
+            // position. If the removed net was the last one, nothing moved.
+            if let Some(renamed) = self.nets.get(rename.dest) {
+                for wire_id in &renamed.wires {
+                    self.wires.get_mut(wire_id.0).unwrap().net_id = NetId(rename.dest);
+                }
+                //TODO remove this clone, reduce size of get_peg_mut borrow
+                for peg_id in &renamed.pegs.clone() {
+                    // The peg might not exist if its component is being
+                    // removed.
+                    if let Some(peg) = self.get_peg_mut(peg_id) {
+                        peg.net_id = NetId(rename.dest);
+                    }
+                }
             }
 
             // Perform the same swap-remove in net_states:
@@ -356,13 +769,17 @@ impl Sandbox {
         let a = self.nets.get(id_a.0).unwrap();
         let b = &self.nets.get(id_b.0).unwrap();
         // Merge the smaller net into the larger net.
-        let (id_dest, id_src) = if a.size() >= b.size() {
+        let (mut id_dest, id_src) = if a.size() >= b.size() {
             (id_a, id_b)
         } else {
             (id_b, id_a)
         };
 
         let src = self.remove_net(id_src).unwrap();
+        // If dest was the last net, it was just moved into src's position.
+        if id_dest.0.into_raw() == self.nets.len() {
+            id_dest = id_src;
+        }
         // Update net cross-references:
         for wire_id in &src.wires {
             self.wires.get_mut(wire_id.0).unwrap().net_id = id_dest;
@@ -379,69 +796,87 @@ impl Sandbox {
         id_dest
     }
 
-    fn check_split(&mut self, addr_a: &PegAddress, addr_b: &PegAddress) {
-        match (self.get_peg(addr_a), self.get_peg(addr_b)) {
-            (None, _) | (_, None) => {
-                // At least one of the pegs does not exist - nothing needs to be
-                // split.
-                //
-                // This can happen while a component is being removed; its pegs
-                // are also being removed and any wires connected to them, and
-                // so they will not need a new net.
-                return;
-            }
-            (Some(peg_a), Some(peg_b)) if peg_a.net_id != peg_b.net_id => {
-                // The pegs do not have the same net ID. This happens when one
-                // of them is an output peg (however, they cannot _both_ be output pegs.
-                assert!(addr_a.peg_type != addr_b.peg_type);
-                // In this case, nothing needs to be split.
-                return;
-            }
-            _ => {
-                // In any other case, we need to split.
-            }
+    /// Split a net into one net per group of pegs that are still connected
+    /// by the net's wires.
+    ///
+    /// The group containing the lowest peg address keeps the net ID, so the
+    /// result doesn't depend on hash set iteration order. New nets take the
+    /// state of the original net.
+    fn split_net(&mut self, net_id: NetId) {
+        let net = self.nets.get(net_id.0).unwrap();
+        if net.pegs.len() <= 1 {
+            return;
         }
+        let mut pegs: Vec<PegAddress> = net.pegs.iter().copied().collect();
+        pegs.sort_by_key(|addr| {
+            (
+                addr.component,
+                addr.peg_type == PegType::Output,
+                addr.peg_index,
+            )
+        });
 
-        // Traverse the whole net connected to addr_a.
-        // If addr_b is in that set, then they are connected.
-        // If not, a new net needs to be created.
-
-        let mut frontier = Vec::new();
-        let mut visited_pegs = HashSet::new();
-        let mut visited_wires = HashSet::new();
-        frontier.push(*addr_a);
-        visited_pegs.insert(*addr_a);
-
-        while let Some(peg_addr) = frontier.pop() {
-            for wire_id in &self.get_peg(&peg_addr).unwrap().wires {
-                let wire = self.wires.get(wire_id.0).unwrap();
-                let neighbor = if peg_addr == wire.a {
-                    wire.b
-                } else if peg_addr == wire.b {
-                    wire.a
-                } else {
-                    unreachable!("invalid xref between unrelated wire and peg");
-                };
-                visited_wires.insert(*wire_id);
-                if visited_pegs.insert(neighbor) {
-                    frontier.push(neighbor);
+        // Traverse the net from each peg that hasn't been reached yet. Only
+        // wires of this net are followed; wires from outputs belong to the
+        // output's net.
+        let mut unvisited: HashSet<PegAddress> = pegs.iter().copied().collect();
+        let mut groups: Vec<(SmallSet<PegAddress>, SmallSet<WireId>)> = Vec::new();
+        for start in pegs {
+            if !unvisited.remove(&start) {
+                continue;
+            }
+            let mut frontier = vec![start];
+            let mut group_pegs = SmallSet::from_iter([start]);
+            let mut group_wires = SmallSet::new();
+            while let Some(peg_addr) = frontier.pop() {
+                for wire_id in &self.get_peg(&peg_addr).unwrap().wires {
+                    if !net.wires.contains(wire_id) {
+                        continue;
+                    }
+                    let wire = self.wires.get(wire_id.0).unwrap();
+                    let neighbor = if peg_addr == wire.a {
+                        wire.b
+                    } else if peg_addr == wire.b {
+                        wire.a
+                    } else {
+                        unreachable!("invalid xref between unrelated wire and peg");
+                    };
+                    group_wires.insert(*wire_id);
+                    if unvisited.remove(&neighbor) {
+                        group_pegs.insert(neighbor);
+                        frontier.push(neighbor);
+                    }
                 }
             }
+            groups.push((group_pegs, group_wires));
         }
 
-        if visited_pegs.contains(&addr_b) {
-            // Still connected; nothing to do.
+        // Still connected; nothing to do.
+        if groups.len() == 1 {
             return;
         }
 
-        // B is not connected to A; make a new net and update all the
-        // pegs and wires found connected to A.
-        let net_id = self.make_net();
-        for peg_addr in visited_pegs {
-            self.get_peg_mut(&peg_addr).unwrap().net_id = net_id;
-        }
-        for wire_id in visited_wires {
-            self.wires.get_mut(wire_id.0).unwrap().net_id = net_id;
+        let state = self.net_states[net_id.0.into_raw()];
+        for (group_pegs, group_wires) in groups.into_iter().skip(1) {
+            let new_id = self.make_net();
+            self.net_states.set(new_id.0.into_raw(), state);
+
+            let old = self.nets.get_mut(net_id.0).unwrap();
+            for peg_addr in &group_pegs {
+                old.pegs.remove(peg_addr);
+            }
+            for wire_id in &group_wires {
+                old.wires.remove(wire_id);
+            }
+            for peg_addr in &group_pegs {
+                self.get_peg_mut(peg_addr).unwrap().net_id = new_id;
+            }
+            for wire_id in &group_wires {
+                self.wires.get_mut(wire_id.0).unwrap().net_id = new_id;
+            }
+            let new = self.nets.get_mut(new_id.0).unwrap();
+            new.pegs = group_pegs;
+            new.wires = group_wires;
         }
     }
 
@@ -458,6 +893,52 @@ impl Sandbox {
     }
 }
 
+/// Find the representative of a net in a union-find, compressing the path.
+fn find_root(parents: &mut HashMap<NetId, NetId>, net_id: NetId) -> NetId {
+    let mut root = net_id;
+    while let Some(&parent) = parents.get(&root) {
+        if parent == root {
+            break;
+        }
+        root = parent;
+    }
+    let mut current = net_id;
+    while current != root {
+        let next = parents.insert(current, root).unwrap();
+        current = next;
+    }
+    root
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Sandboxes are equal if they would be saved as the same file with the
+/// default [`SaveOptions`], even if their IDs differ.
+impl PartialEq for Sandbox {
+    fn eq(&self, other: &Self) -> bool {
+        crate::latest::BlotterFile::from(self) == crate::latest::BlotterFile::from(other)
+    }
+}
+
+/// Shows a summary of the sandbox; use [`Sandbox::netlist`] or save it for
+/// the full contents.
+impl fmt::Debug for Sandbox {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sandbox")
+            .field("save_type", &self.save_type)
+            .field("mods", &self.mods)
+            .field("component_types", &self.component_types.len())
+            .field("components", &self.components.iter().count())
+            .field("wires", &self.wires.iter().count())
+            .field("nets", &self.nets.len())
+            .finish()
+    }
+}
+
 #[derive(Clone)]
 pub struct ComponentBuilder<'a> {
     id: &'a str,
@@ -486,12 +967,18 @@ impl<'a> ComponentBuilder<'a> {
         Self { parent, ..self }
     }
 
-    pub fn position(self, position: [i32; 3]) -> Self {
-        Self { position, ..self }
+    pub fn position(self, position: impl Into<[i32; 3]>) -> Self {
+        Self {
+            position: position.into(),
+            ..self
+        }
     }
 
-    pub fn rotation(self, rotation: [f32; 4]) -> Self {
-        Self { rotation, ..self }
+    pub fn rotation(self, rotation: impl Into<[f32; 4]>) -> Self {
+        Self {
+            rotation: rotation.into(),
+            ..self
+        }
     }
 
     pub fn num_inputs(self, num_inputs: u32) -> Self {
@@ -513,10 +1000,15 @@ impl<'a> ComponentBuilder<'a> {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
 pub struct ComponentId(Address<ComponentInfo>);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct NetId(Index<NetInfo>);
 
 impl NetId {
@@ -529,33 +1021,80 @@ impl NetId {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct WireId(Address<WireInfo>);
 
-#[derive(Debug)]
+/// Why a wire couldn't be added.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum AddWireError {
-    InvalidPegAddress,
+    /// Both ends are outputs, which can't be wired together.
+    OutputToOutput,
+    /// The first peg doesn't exist.
+    MissingPegA(PegAddress),
+    /// The second peg doesn't exist.
+    MissingPegB(PegAddress),
+    /// Both ends are the same peg.
+    SelfLoop(PegAddress),
+}
+
+impl fmt::Display for AddWireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutputToOutput => write!(f, "can't wire two outputs together"),
+            Self::MissingPegA(_) => write!(f, "the first peg of the wire does not exist"),
+            Self::MissingPegB(_) => write!(f, "the second peg of the wire does not exist"),
+            Self::SelfLoop(_) => write!(f, "can't wire a peg to itself"),
+        }
+    }
+}
+
+impl std::error::Error for AddWireError {}
+
+/// The result of [`Sandbox::add_wire_with_status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireAdded {
+    /// A new wire was added.
+    Created(WireId),
+    /// The pegs were already connected by this wire, which was left as it
+    /// was.
+    Existing(WireId),
+}
+
+impl WireAdded {
+    pub fn id(self) -> WireId {
+        match self {
+            Self::Created(id) | Self::Existing(id) => id,
+        }
+    }
+
+    pub fn is_new(self) -> bool {
+        matches!(self, Self::Created(_))
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PegType {
     Input,
     Output,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PegAddress {
     pub component: ComponentId,
     pub peg_type: PegType,
     pub peg_index: usize,
 }
 
+#[derive(Clone)]
 struct ComponentInfo {
     type_id: u16,
     parent: Option<ComponentId>,
     position: [i32; 3],
     rotation: [f32; 4],
-    children: HashSet<ComponentId>,
+    children: SmallSet<ComponentId>,
     inputs: Vec<PegInfo>,
     outputs: Vec<PegInfo>,
     custom_data: Option<Vec<u8>>,
@@ -577,9 +1116,10 @@ impl ComponentInfo {
     }
 }
 
+#[derive(Clone)]
 struct PegInfo {
     net_id: NetId,
-    wires: HashSet<WireId>,
+    wires: SmallSet<WireId>,
 }
 
 #[derive(Clone, Copy)]
@@ -590,9 +1130,10 @@ struct WireInfo {
     rotation: f32,
 }
 
+#[derive(Clone)]
 struct NetInfo {
-    wires: HashSet<WireId>,
-    pegs: HashSet<PegAddress>,
+    wires: SmallSet<WireId>,
+    pegs: SmallSet<PegAddress>,
 }
 
 impl NetInfo {
@@ -601,7 +1142,7 @@ impl NetInfo {
     }
 }
 
-const DEFAULT_COMPONENT_TYPES: [(u16, &str); 31] = [
+pub(crate) const DEFAULT_COMPONENT_TYPES: [(u16, &str); 31] = [
     (0, "MHG.Inverter"),
     (1, "MHG.XorGate"),
     (2, "MHG.AndGate"),
@@ -635,9 +1176,9 @@ const DEFAULT_COMPONENT_TYPES: [(u16, &str); 31] = [
     (30, "MHG.PanelKey"),
 ];
 
-fn default_component_types_map() -> HashMap<String, u16> {
+fn default_component_types_map() -> TypeTable {
     DEFAULT_COMPONENT_TYPES
         .iter()
-        .map(|&(num, name)| (name.to_owned(), num))
+        .map(|&(num, name)| (TypeName::new(name), num))
         .collect()
 }
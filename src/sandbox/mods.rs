@@ -0,0 +1,41 @@
+//! Managing the mods that a sandbox depends on.
+
+use std::collections::BTreeSet;
+
+use crate::latest::ModInfo;
+use crate::mods::{self, ModRequirements};
+
+use super::Sandbox;
+
+impl Sandbox {
+    pub fn mods(&self) -> &[ModInfo] {
+        &self.mods
+    }
+
+    pub fn find_mod(&self, mod_id: &str) -> Option<&ModInfo> {
+        self.mods.iter().find(|m| m.mod_id == mod_id)
+    }
+
+    /// Declare a mod dependency, replacing any existing entry for the same
+    /// mod.
+    pub fn add_mod(&mut self, mod_info: ModInfo) {
+        mods::add_mod(&mut self.mods, mod_info)
+    }
+
+    pub fn remove_mod(&mut self, mod_id: &str) -> Option<ModInfo> {
+        mods::remove_mod(&mut self.mods, mod_id)
+    }
+
+    /// Compare the declared mods with the types of the components in the
+    /// sandbox.
+    ///
+    /// Only types that are in use count; registered types with no
+    /// components are ignored.
+    pub fn required_mods(&self) -> ModRequirements {
+        let used: BTreeSet<&str> = self
+            .components()
+            .map(|component| component.type_name())
+            .collect();
+        ModRequirements::check(&self.mods, used)
+    }
+}
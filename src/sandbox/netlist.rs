@@ -0,0 +1,126 @@
+//! A plain description of circuit connectivity.
+
+use std::collections::HashMap;
+
+use super::hierarchy::push_sorted;
+use super::{ComponentId, NetId, PegAddress, PegType, Sandbox};
+
+/// The nets of a sandbox as adjacency lists, in both directions: which pegs
+/// each net connects, and which net each peg belongs to.
+///
+/// This is intended for external scripts that only care about connectivity.
+/// With the `serde` feature, it can be serialized for consumption by them.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Netlist {
+    /// Every net that has at least one peg, indexed by net number.
+    pub nets: Vec<NetlistNet>,
+    /// Every component, in serialization order.
+    pub components: Vec<NetlistComponent>,
+}
+
+/// A single net of a [`Netlist`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NetlistNet {
+    /// Whether the net is on.
+    pub state: bool,
+    /// The pegs connected by the net, ordered by component ID.
+    pub pegs: Vec<PegAddress>,
+    /// The number of wires in the net.
+    pub wires: usize,
+    /// The output nets wired to the input pegs of this net, in order. A net
+    /// of inputs is on when any of them is.
+    pub drivers: Vec<usize>,
+}
+
+/// A single component of a [`Netlist`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NetlistComponent {
+    pub id: ComponentId,
+    pub type_name: String,
+    /// The net number of each input peg.
+    pub inputs: Vec<usize>,
+    /// The net number of each output peg.
+    pub outputs: Vec<usize>,
+}
+
+impl Sandbox {
+    /// Describe the connectivity of the sandbox.
+    ///
+    /// Nets are numbered densely in the same order that
+    /// [`compact_nets`](Self::compact_nets) would give them, so the numbers
+    /// match the circuit state IDs of a save written with
+    /// [`SaveOptions::compact_nets`](super::SaveOptions::compact_nets).
+    pub fn netlist(&self) -> Netlist {
+        let order = self.canonical_net_order();
+        let numbers: HashMap<_, _> = order
+            .iter()
+            .enumerate()
+            .map(|(number, &net_id)| (net_id, number))
+            .collect();
+
+        let mut drivers: HashMap<NetId, Vec<usize>> = HashMap::new();
+        for (_, wire) in self.wires.iter() {
+            let input = match (wire.a.peg_type, wire.b.peg_type) {
+                (PegType::Output, _) => wire.b,
+                (_, PegType::Output) => wire.a,
+                _ => continue,
+            };
+            let net_id = self.get_peg(&input).unwrap().net_id;
+            drivers
+                .entry(net_id)
+                .or_default()
+                .push(numbers[&wire.net_id]);
+        }
+
+        let nets = order
+            .iter()
+            .map(|&net_id| {
+                let net = self.nets.get(net_id.0).unwrap();
+                let mut pegs: Vec<PegAddress> = net.pegs.iter().copied().collect();
+                pegs.sort_by_key(|addr| {
+                    (
+                        addr.component,
+                        addr.peg_type == PegType::Output,
+                        addr.peg_index,
+                    )
+                });
+                NetlistNet {
+                    state: self.net_states[net_id.0.into_raw()],
+                    pegs,
+                    wires: net.wires.len(),
+                    drivers: drivers
+                        .remove(&net_id)
+                        .map(|mut drivers| {
+                            drivers.sort_unstable();
+                            drivers.dedup();
+                            drivers
+                        })
+                        .unwrap_or_default(),
+                }
+            })
+            .collect();
+
+        let mut components = Vec::new();
+        let mut stack = Vec::new();
+        push_sorted(&mut stack, self.root_components.iter());
+        while let Some(id) = stack.pop() {
+            let info = self.components.get(id.0).unwrap();
+            push_sorted(&mut stack, &info.children);
+            components.push(NetlistComponent {
+                id,
+                type_name: self.component_types.name_str(info.type_id).to_owned(),
+                inputs: info.inputs.iter().map(|peg| numbers[&peg.net_id]).collect(),
+                outputs: info
+                    .outputs
+                    .iter()
+                    .map(|peg| numbers[&peg.net_id])
+                    .collect(),
+            });
+        }
+
+        Netlist { nets, components }
+    }
+}
@@ -0,0 +1,279 @@
+//! Finding occurrences of small subcircuits.
+
+use std::collections::{BTreeSet, HashSet};
+
+use super::{ComponentId, PegAddress, PegType, Sandbox};
+
+/// A small arrangement of components and wires to look for with
+/// [`Sandbox::find_matches`].
+///
+/// Components are referred to by the index returned from
+/// [`add_component`](Self::add_component).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Pattern {
+    types: Vec<String>,
+    wires: Vec<(PatternPeg, PatternPeg)>,
+}
+
+/// A peg of a component of a [`Pattern`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PatternPeg {
+    /// The index of the component in the pattern.
+    pub component: usize,
+    pub peg_type: PegType,
+    pub peg_index: usize,
+}
+
+impl PatternPeg {
+    pub fn input(component: usize, peg_index: usize) -> Self {
+        Self {
+            component,
+            peg_type: PegType::Input,
+            peg_index,
+        }
+    }
+
+    pub fn output(component: usize, peg_index: usize) -> Self {
+        Self {
+            component,
+            peg_type: PegType::Output,
+            peg_index,
+        }
+    }
+}
+
+impl Pattern {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a component of the given type, like `"MHG.Inverter"`, returning
+    /// its index.
+    pub fn add_component(&mut self, type_name: impl Into<String>) -> usize {
+        self.types.push(type_name.into());
+        self.types.len() - 1
+    }
+
+    /// Require a wire between two pegs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either peg belongs to a component that isn't in the pattern.
+    pub fn add_wire(&mut self, a: PatternPeg, b: PatternPeg) {
+        assert!(a.component < self.types.len() && b.component < self.types.len());
+        self.wires.push((a, b));
+    }
+
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
+    }
+
+    /// The order to assign the components in, so that as many as possible
+    /// are wired to one that was assigned before them, along with that wire.
+    fn search_order(&self) -> Vec<(usize, Option<(PatternPeg, PatternPeg)>)> {
+        let mut order = Vec::new();
+        let mut placed = vec![false; self.types.len()];
+        while order.len() < self.types.len() {
+            let link = self.wires.iter().find_map(|&(a, b)| {
+                match (placed[a.component], placed[b.component]) {
+                    (true, false) => Some((b.component, (a, b))),
+                    (false, true) => Some((a.component, (b, a))),
+                    _ => None,
+                }
+            });
+            let (next, link) = match link {
+                Some((next, link)) => (next, Some(link)),
+                None => (placed.iter().position(|&placed| !placed).unwrap(), None),
+            };
+            placed[next] = true;
+            order.push((next, link));
+        }
+        order
+    }
+}
+
+/// An occurrence of a [`Pattern`] in a sandbox.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PatternMatch {
+    /// The component matched to each component of the pattern, by index.
+    pub components: Vec<ComponentId>,
+}
+
+impl PatternMatch {
+    /// The peg matched to a peg of the pattern.
+    pub fn peg(&self, peg: PatternPeg) -> PegAddress {
+        PegAddress {
+            component: self.components[peg.component],
+            peg_type: peg.peg_type,
+            peg_index: peg.peg_index,
+        }
+    }
+}
+
+impl Sandbox {
+    /// Find every occurrence of a pattern.
+    ///
+    /// Each pattern component matches a distinct component of the same type,
+    /// and each pattern wire a wire directly between the matched pegs. Other
+    /// wires on the matched components are allowed. If a pattern is
+    /// symmetric, the same components could match it in several ways; only
+    /// the first of those is returned. Matches are always found in the same
+    /// order.
+    ///
+    /// ```
+    /// use blotter::sandbox::{ComponentBuilder, PegAddress, PegType, Pattern, PatternPeg, Sandbox};
+    ///
+    /// // Two cross-coupled inverters.
+    /// let mut latch = Pattern::new();
+    /// let a = latch.add_component("MHG.Inverter");
+    /// let b = latch.add_component("MHG.Inverter");
+    /// latch.add_wire(PatternPeg::output(a, 0), PatternPeg::input(b, 0));
+    /// latch.add_wire(PatternPeg::output(b, 0), PatternPeg::input(a, 0));
+    ///
+    /// let mut sandbox = Sandbox::new();
+    /// let inverter = ComponentBuilder::new("MHG.Inverter").num_inputs(1).num_outputs(1);
+    /// let x = sandbox.add_component(&inverter);
+    /// let y = sandbox.add_component(&inverter);
+    /// sandbox.add_component(&inverter);
+    /// let peg = |component, peg_type| PegAddress { component, peg_type, peg_index: 0 };
+    /// sandbox.add_wire(peg(x, PegType::Output), peg(y, PegType::Input), 0.0).unwrap();
+    /// sandbox.add_wire(peg(y, PegType::Output), peg(x, PegType::Input), 0.0).unwrap();
+    ///
+    /// let matches = sandbox.find_matches(&latch);
+    /// assert_eq!(matches.len(), 1);
+    /// assert_eq!(matches[0].components, [x, y]);
+    /// ```
+    pub fn find_matches(&self, pattern: &Pattern) -> Vec<PatternMatch> {
+        let mut search = Search {
+            sandbox: self,
+            pattern,
+            order: pattern.search_order(),
+            assigned: vec![None; pattern.len()],
+            used: HashSet::new(),
+            seen: HashSet::new(),
+            matches: Vec::new(),
+        };
+        if !pattern.is_empty() {
+            search.extend(0);
+        }
+        search.matches
+    }
+
+    /// The pegs at the other end of the wires on a peg.
+    fn wired_to(&self, addr: PegAddress) -> impl Iterator<Item = PegAddress> + '_ {
+        self.get_peg(&addr)
+            .into_iter()
+            .flat_map(|peg| &peg.wires)
+            .map(move |wire_id| {
+                let wire = self.wires.get(wire_id.0).unwrap();
+                if wire.a == addr {
+                    wire.b
+                } else {
+                    wire.a
+                }
+            })
+    }
+
+    fn has_wire(&self, a: PegAddress, b: PegAddress) -> bool {
+        match (self.get_peg(&a), self.get_peg(&b)) {
+            (Some(a), Some(b)) => !a.wires.is_disjoint(&b.wires),
+            _ => false,
+        }
+    }
+}
+
+struct Search<'a> {
+    sandbox: &'a Sandbox,
+    pattern: &'a Pattern,
+    order: Vec<(usize, Option<(PatternPeg, PatternPeg)>)>,
+    assigned: Vec<Option<ComponentId>>,
+    used: HashSet<ComponentId>,
+    /// The component sets of the matches found so far.
+    seen: HashSet<BTreeSet<ComponentId>>,
+    matches: Vec<PatternMatch>,
+}
+
+impl Search<'_> {
+    fn extend(&mut self, depth: usize) {
+        if depth == self.order.len() {
+            let components: Vec<ComponentId> = self.assigned.iter().map(|id| id.unwrap()).collect();
+            if self.seen.insert(components.iter().copied().collect()) {
+                self.matches.push(PatternMatch { components });
+            }
+            return;
+        }
+
+        let (index, link) = self.order[depth];
+        let candidates: Vec<ComponentId> = match link {
+            Some((from, to)) => {
+                let from = PegAddress {
+                    component: self.assigned[from.component].unwrap(),
+                    peg_type: from.peg_type,
+                    peg_index: from.peg_index,
+                };
+                let mut candidates: Vec<ComponentId> = self
+                    .sandbox
+                    .wired_to(from)
+                    .filter(|addr| addr.peg_type == to.peg_type && addr.peg_index == to.peg_index)
+                    .map(|addr| addr.component)
+                    .collect();
+                candidates.sort();
+                candidates.dedup();
+                candidates
+            }
+            None => self
+                .sandbox
+                .components_of_type(&self.pattern.types[index])
+                .collect(),
+        };
+
+        for candidate in candidates {
+            if self.used.contains(&candidate) || !self.fits(index, candidate) {
+                continue;
+            }
+            self.assigned[index] = Some(candidate);
+            self.used.insert(candidate);
+            self.extend(depth + 1);
+            self.used.remove(&candidate);
+            self.assigned[index] = None;
+        }
+    }
+
+    /// Whether a component can be assigned to a pattern component, given the
+    /// components assigned so far.
+    fn fits(&self, index: usize, candidate: ComponentId) -> bool {
+        let sandbox = self.sandbox;
+        let type_matches = sandbox
+            .component(candidate)
+            .is_some_and(|component| component.type_name() == self.pattern.types[index]);
+        if !type_matches {
+            return false;
+        }
+        let resolve = |peg: PatternPeg| {
+            let component = if peg.component == index {
+                Some(candidate)
+            } else {
+                self.assigned[peg.component]
+            };
+            component.map(|component| PegAddress {
+                component,
+                peg_type: peg.peg_type,
+                peg_index: peg.peg_index,
+            })
+        };
+        self.pattern.wires.iter().all(|&(a, b)| {
+            if a.component != index && b.component != index {
+                return true;
+            }
+            match (resolve(a), resolve(b)) {
+                (Some(a), Some(b)) => sandbox.has_wire(a, b),
+                // The other end isn't assigned yet; it's checked then.
+                _ => true,
+            }
+        })
+    }
+}
@@ -0,0 +1,291 @@
+//! Named pegs, used as the external interface of a subcircuit.
+
+use std::collections::BTreeMap;
+
+use super::{AddWireError, PegAddress, PegType, Remap, Sandbox, WireId};
+
+/// A set of named pegs that form the interface of a subcircuit, like `"clk"`
+/// or `"data[3]"`.
+///
+/// Ports named like `"data[0]"`, `"data[1]"` and so on form a bus, which can
+/// be wired to another bus all at once:
+///
+/// ```
+/// use blotter::sandbox::component::Peg;
+/// use blotter::sandbox::{PegAddress, PegType, Ports, Sandbox};
+///
+/// let mut sandbox = Sandbox::new();
+/// let mut pegs = || {
+///     let component = sandbox.add_component(&Peg::new().build());
+///     PegAddress { component, peg_type: PegType::Input, peg_index: 0 }
+/// };
+/// let mut a = Ports::new();
+/// a.insert_bus("data", [pegs(), pegs(), pegs(), pegs()]);
+/// let mut b = Ports::new();
+/// b.insert_bus("in", [pegs(), pegs(), pegs(), pegs()]);
+///
+/// let wires = sandbox.connect_bus(&a, "data", &b, "in").unwrap();
+/// assert_eq!(wires.len(), 4);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Ports {
+    ports: BTreeMap<String, PegAddress>,
+}
+
+impl Ports {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a named port, returning the peg previously bound to that name.
+    pub fn insert(&mut self, name: impl Into<String>, peg: PegAddress) -> Option<PegAddress> {
+        self.ports.insert(name.into(), peg)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<PegAddress> {
+        self.ports.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<PegAddress> {
+        self.ports.get(name).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ports.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ports.is_empty()
+    }
+
+    /// Iterate over all ports, ordered by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, PegAddress)> {
+        self.ports.iter().map(|(name, peg)| (name.as_str(), *peg))
+    }
+
+    /// Add the bits of a bus, named `name[0]`, `name[1]` and so on.
+    pub fn insert_bus(&mut self, name: &str, pegs: impl IntoIterator<Item = PegAddress>) {
+        for (index, peg) in pegs.into_iter().enumerate() {
+            self.insert(format!("{name}[{index}]"), peg);
+        }
+    }
+
+    /// The bits of a bus, ordered by their index.
+    ///
+    /// Bits don't have to be numbered from zero or contiguously. The result
+    /// is empty if there is no port named like `name[index]`.
+    pub fn bus(&self, name: &str) -> Vec<(usize, PegAddress)> {
+        let mut bits: Vec<(usize, PegAddress)> = self
+            .ports
+            .iter()
+            .filter_map(|(port, &peg)| Some((bus_index(port, name)?, peg)))
+            .collect();
+        bits.sort_unstable_by_key(|&(index, _)| index);
+        bits
+    }
+
+    /// The ports of a copy of the subcircuit, like a
+    /// [`Blueprint`](super::Blueprint) instance, using the [`Remap`] from
+    /// making the copy.
+    pub fn remap(&self, remap: &Remap) -> Self {
+        Self {
+            ports: self
+                .ports
+                .iter()
+                .map(|(name, &peg)| {
+                    let peg = PegAddress {
+                        component: remap.component(peg.component),
+                        ..peg
+                    };
+                    (name.clone(), peg)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The index of a bus bit named `bus[index]`.
+fn bus_index(port: &str, bus: &str) -> Option<usize> {
+    port.strip_prefix(bus)?
+        .strip_prefix('[')?
+        .strip_suffix(']')?
+        .parse()
+        .ok()
+}
+
+impl<S: Into<String>> FromIterator<(S, PegAddress)> for Ports {
+    fn from_iter<I: IntoIterator<Item = (S, PegAddress)>>(iter: I) -> Self {
+        Self {
+            ports: iter
+                .into_iter()
+                .map(|(name, peg)| (name.into(), peg))
+                .collect(),
+        }
+    }
+}
+
+/// A difference between the interfaces of two subcircuits that would prevent
+/// one from replacing the other cleanly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PortMismatch {
+    /// The old subcircuit has a port that the new one does not. Any wires
+    /// connected to it would be lost.
+    Missing { name: String, connections: usize },
+    /// The new subcircuit has a port that the old one does not, and so it
+    /// will be left unconnected.
+    Extra { name: String },
+    /// Both subcircuits have the port, but one is an input and the other an
+    /// output.
+    Direction {
+        name: String,
+        old: PegType,
+        new: PegType,
+    },
+}
+
+/// Why ports couldn't be wired together.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PortError {
+    /// There is no port, or no bus, with this name.
+    UnknownPort(String),
+    /// The buses have different numbers of bits.
+    WidthMismatch { a: usize, b: usize },
+    /// The port refers to a peg that isn't in the sandbox.
+    InvalidPeg(String),
+    /// Both ports are outputs, which can't be wired together.
+    BothOutputs(String, String),
+    /// Both ports are the same peg, which can't be wired to itself.
+    SelfLoop(String, String),
+    /// The sandbox refused to add the wire.
+    Wire(AddWireError),
+}
+
+impl Sandbox {
+    /// Wire a port to another port.
+    ///
+    /// ```
+    /// use blotter::sandbox::component::Peg;
+    /// use blotter::sandbox::{PegAddress, PegType, PortError, Ports, Sandbox};
+    ///
+    /// let mut sandbox = Sandbox::new();
+    /// let component = sandbox.add_component(&Peg::new().build());
+    /// let mut ports = Ports::new();
+    /// ports.insert("x", PegAddress { component, peg_type: PegType::Input, peg_index: 0 });
+    ///
+    /// assert_eq!(
+    ///     sandbox.connect_port(&ports, "x", &ports, "x"),
+    ///     Err(PortError::SelfLoop("x".into(), "x".into())),
+    /// );
+    /// assert_eq!(sandbox.wire_ids().count(), 0);
+    /// ```
+    pub fn connect_port(
+        &mut self,
+        a: &Ports,
+        a_name: &str,
+        b: &Ports,
+        b_name: &str,
+    ) -> Result<WireId, PortError> {
+        let peg_a = a
+            .get(a_name)
+            .ok_or_else(|| PortError::UnknownPort(a_name.to_owned()))?;
+        let peg_b = b
+            .get(b_name)
+            .ok_or_else(|| PortError::UnknownPort(b_name.to_owned()))?;
+        self.check_port_wire((a_name, peg_a), (b_name, peg_b))?;
+        self.add_wire(peg_a, peg_b, 0.0).map_err(PortError::Wire)
+    }
+
+    /// Wire each bit of a bus to the bit of another bus at the same
+    /// position, in order of their indices, returning the new wires in
+    /// that order.
+    ///
+    /// Either every bit is connected, or, if an error is returned, nothing
+    /// is.
+    pub fn connect_bus(
+        &mut self,
+        a: &Ports,
+        a_bus: &str,
+        b: &Ports,
+        b_bus: &str,
+    ) -> Result<Vec<WireId>, PortError> {
+        let bits_a = a.bus(a_bus);
+        let bits_b = b.bus(b_bus);
+        if bits_a.is_empty() {
+            return Err(PortError::UnknownPort(a_bus.to_owned()));
+        }
+        if bits_b.is_empty() {
+            return Err(PortError::UnknownPort(b_bus.to_owned()));
+        }
+        if bits_a.len() != bits_b.len() {
+            return Err(PortError::WidthMismatch {
+                a: bits_a.len(),
+                b: bits_b.len(),
+            });
+        }
+        for (&(index_a, peg_a), &(index_b, peg_b)) in bits_a.iter().zip(&bits_b) {
+            self.check_port_wire(
+                (&format!("{a_bus}[{index_a}]"), peg_a),
+                (&format!("{b_bus}[{index_b}]"), peg_b),
+            )?;
+        }
+        let wires = bits_a
+            .iter()
+            .zip(&bits_b)
+            .map(|(&(_, peg_a), &(_, peg_b))| (peg_a, peg_b, 0.0));
+        self.add_wires(wires).map_err(PortError::Wire)
+    }
+
+    /// Check that a wire can be added between two ports.
+    fn check_port_wire(
+        &self,
+        (a_name, a): (&str, PegAddress),
+        (b_name, b): (&str, PegAddress),
+    ) -> Result<(), PortError> {
+        for (name, peg) in [(a_name, a), (b_name, b)] {
+            if self.get_peg(&peg).is_none() {
+                return Err(PortError::InvalidPeg(name.to_owned()));
+            }
+        }
+        if a == b {
+            return Err(PortError::SelfLoop(a_name.to_owned(), b_name.to_owned()));
+        }
+        if a.peg_type == PegType::Output && b.peg_type == PegType::Output {
+            return Err(PortError::BothOutputs(a_name.to_owned(), b_name.to_owned()));
+        }
+        Ok(())
+    }
+
+    /// Check whether a subcircuit with the interface `new` can replace one
+    /// with the interface `old`, where `old` refers to pegs in this sandbox.
+    ///
+    /// This does not modify anything, so it can be used to reject a
+    /// replacement before any components are removed. An empty list means
+    /// the interfaces are compatible.
+    pub fn port_mismatches(&self, old: &Ports, new: &Ports) -> Vec<PortMismatch> {
+        let mut mismatches = Vec::new();
+        for (name, old_peg) in old.iter() {
+            match new.get(name) {
+                None => mismatches.push(PortMismatch::Missing {
+                    name: name.to_owned(),
+                    connections: self.get_peg(&old_peg).map_or(0, |peg| peg.wires.len()),
+                }),
+                Some(new_peg) if new_peg.peg_type != old_peg.peg_type => {
+                    mismatches.push(PortMismatch::Direction {
+                        name: name.to_owned(),
+                        old: old_peg.peg_type,
+                        new: new_peg.peg_type,
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+        for (name, _) in new.iter() {
+            if old.get(name).is_none() {
+                mismatches.push(PortMismatch::Extra {
+                    name: name.to_owned(),
+                });
+            }
+        }
+        mismatches
+    }
+}
@@ -0,0 +1,218 @@
+//! Bulk edits of the colors of circuit boards and labels.
+
+use std::collections::HashMap;
+
+use crate::custom_data::{self, ComponentData};
+
+use super::{ComponentId, Sandbox, Selection};
+
+/// Component types whose custom data is a [`Label`](custom_data::Label).
+const LABEL_TYPES: &[&str] = &["MHG.Label", "MHG.PanelLabel"];
+
+/// A color scheme for [`Sandbox::apply_theme`].
+///
+/// Colors with an explicit [`map`](Self::map)ping are replaced by it. Other
+/// colors are replaced by the nearest color of the [`palette`](Self::palette),
+/// if there is one, or else kept.
+///
+/// ```
+/// use blotter::sandbox::Theme;
+///
+/// let theme = Theme::new()
+///     .map([0, 0, 0], [255, 255, 255])
+///     .palette(vec![[200, 0, 0], [0, 0, 200]]);
+/// assert_eq!(theme.apply([0, 0, 0]), [255, 255, 255]);
+/// assert_eq!(theme.apply([250, 60, 40]), [200, 0, 0]);
+/// assert_eq!(Theme::new().apply([1, 2, 3]), [1, 2, 3]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    colors: HashMap<[u8; 3], [u8; 3]>,
+    palette: Vec<[u8; 3]>,
+    boards: bool,
+    labels: bool,
+}
+
+impl Theme {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace `from` with `to`.
+    pub fn map(mut self, from: [u8; 3], to: [u8; 3]) -> Self {
+        self.colors.insert(from, to);
+        self
+    }
+
+    /// Snap colors without a mapping to the nearest of these colors. Empty by
+    /// default, which keeps them as they are.
+    pub fn palette(self, palette: Vec<[u8; 3]>) -> Self {
+        Self { palette, ..self }
+    }
+
+    /// Whether to recolor circuit boards. Defaults to true.
+    pub fn boards(self, boards: bool) -> Self {
+        Self { boards, ..self }
+    }
+
+    /// Whether to recolor the text of labels. Defaults to true.
+    pub fn labels(self, labels: bool) -> Self {
+        Self { labels, ..self }
+    }
+
+    /// The color that this theme gives to `color`.
+    pub fn apply(&self, color: [u8; 3]) -> [u8; 3] {
+        if let Some(&to) = self.colors.get(&color) {
+            return to;
+        }
+        let distance = |other: &[u8; 3]| -> u32 {
+            (0..3)
+                .map(|i| (i32::from(color[i]) - i32::from(other[i])).pow(2) as u32)
+                .sum()
+        };
+        self.palette
+            .iter()
+            .min_by_key(|other| distance(other))
+            .copied()
+            .unwrap_or(color)
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            colors: HashMap::new(),
+            palette: Vec::new(),
+            boards: true,
+            labels: true,
+        }
+    }
+}
+
+impl Sandbox {
+    /// Replace the color of every circuit board with `f` of it.
+    ///
+    /// Returns the number of boards whose color changed. Boards with invalid
+    /// custom data are left alone.
+    ///
+    /// ```
+    /// use blotter::sandbox::{component::CircuitBoard, Sandbox};
+    ///
+    /// let mut sandbox = Sandbox::new();
+    /// sandbox.add_component(&CircuitBoard::new().color([10, 20, 30]).build());
+    /// assert_eq!(sandbox.recolor_boards(|[r, g, b]| [b, g, r]), 1);
+    /// assert_eq!(sandbox.board_map().boards[0].color, [30, 20, 10]);
+    /// ```
+    pub fn recolor_boards<F>(&mut self, f: F) -> usize
+    where
+        F: FnMut([u8; 3]) -> [u8; 3],
+    {
+        let boards: Vec<ComponentId> = self
+            .components_of_type(custom_data::CircuitBoard::TYPE_STRING)
+            .collect();
+        self.recolor::<custom_data::CircuitBoard, _>(&boards, f)
+    }
+
+    /// Replace the text color of every label with `f` of it.
+    ///
+    /// Returns the number of labels whose color changed. Labels with invalid
+    /// custom data are left alone.
+    pub fn recolor_labels<F>(&mut self, f: F) -> usize
+    where
+        F: FnMut([u8; 3]) -> [u8; 3],
+    {
+        let labels = self.labels();
+        self.recolor::<custom_data::Label, _>(&labels, f)
+    }
+
+    /// Recolor the selected circuit boards and labels with `theme`.
+    ///
+    /// Returns the number of components whose color changed.
+    ///
+    /// ```
+    /// use blotter::sandbox::component::CircuitBoard;
+    /// use blotter::sandbox::{Sandbox, Selection, Theme};
+    ///
+    /// let mut sandbox = Sandbox::new();
+    /// let red = sandbox.add_component(&CircuitBoard::new().color([250, 10, 10]).build());
+    /// sandbox.add_component(&CircuitBoard::new().color([250, 10, 10]).build());
+    ///
+    /// let mut selection = Selection::new();
+    /// selection.add_component(red);
+    /// let theme = Theme::new().palette(vec![[255, 0, 0], [0, 0, 255]]);
+    /// assert_eq!(sandbox.apply_theme(&selection, &theme), 1);
+    ///
+    /// let colors: Vec<_> = sandbox.board_map().boards.iter().map(|b| b.color).collect();
+    /// assert_eq!(colors, [[255, 0, 0], [250, 10, 10]]);
+    /// ```
+    pub fn apply_theme(&mut self, selection: &Selection, theme: &Theme) -> usize {
+        let mut changed = 0;
+        if theme.boards {
+            let boards: Vec<ComponentId> = self
+                .components_of_type(custom_data::CircuitBoard::TYPE_STRING)
+                .filter(|&id| selection.contains_component(id))
+                .collect();
+            changed +=
+                self.recolor::<custom_data::CircuitBoard, _>(&boards, |color| theme.apply(color));
+        }
+        if theme.labels {
+            let mut labels = self.labels();
+            labels.retain(|&id| selection.contains_component(id));
+            changed += self.recolor::<custom_data::Label, _>(&labels, |color| theme.apply(color));
+        }
+        changed
+    }
+
+    fn labels(&self) -> Vec<ComponentId> {
+        LABEL_TYPES
+            .iter()
+            .flat_map(|name| self.components_of_type(name))
+            .collect()
+    }
+
+    fn recolor<D, F>(&mut self, ids: &[ComponentId], mut f: F) -> usize
+    where
+        D: Colored,
+        F: FnMut([u8; 3]) -> [u8; 3],
+    {
+        let mut changed = 0;
+        for id in ids {
+            let Some(info) = self.components.get_mut(id.0) else {
+                continue;
+            };
+            let Some(mut data) = info
+                .custom_data
+                .as_deref()
+                .and_then(|mut data| D::read(&mut data).ok())
+            else {
+                continue;
+            };
+            let color = f(*data.color());
+            if color != *data.color() {
+                *data.color() = color;
+                let mut custom_data = Vec::new();
+                data.write(&mut custom_data).unwrap();
+                info.custom_data = Some(custom_data);
+                changed += 1;
+            }
+        }
+        changed
+    }
+}
+
+/// Custom data with a color that can be themed.
+trait Colored: ComponentData {
+    fn color(&mut self) -> &mut [u8; 3];
+}
+
+impl Colored for custom_data::CircuitBoard {
+    fn color(&mut self) -> &mut [u8; 3] {
+        &mut self.color
+    }
+}
+
+impl Colored for custom_data::Label {
+    fn color(&mut self) -> &mut [u8; 3] {
+        &mut self.color
+    }
+}
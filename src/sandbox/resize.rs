@@ -0,0 +1,190 @@
+//! Resizing circuit boards without disturbing what is on them.
+
+use crate::custom_data::{self, ComponentData};
+use crate::geometry::{Rotation, UNITS_PER_SQUARE};
+
+use super::{ComponentId, Sandbox};
+
+/// Which side of a board stays in place along one of its axes when it is
+/// resized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Anchor {
+    /// The edge at the board's origin, where squares are counted from.
+    #[default]
+    Start,
+    /// The middle of the board, rounded down to a whole square.
+    Center,
+    /// The edge opposite the board's origin.
+    End,
+}
+
+impl Anchor {
+    /// How far the board's origin moves, in squares, to keep this side in
+    /// place when the board goes from `old` to `new` squares.
+    fn shift(self, old: u32, new: u32) -> i32 {
+        let difference = old as i32 - new as i32;
+        match self {
+            Self::Start => 0,
+            Self::Center => difference.div_euclid(2),
+            Self::End => difference,
+        }
+    }
+}
+
+/// The part of a board that stays in place when it is resized, along its X
+/// and Z axes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BoardAnchor {
+    pub x: Anchor,
+    pub z: Anchor,
+}
+
+impl BoardAnchor {
+    /// Keep the board's origin corner in place, growing or shrinking the
+    /// board at the opposite edges.
+    pub const ORIGIN: Self = Self::new(Anchor::Start, Anchor::Start);
+    /// Keep the middle of the board in place.
+    pub const CENTER: Self = Self::new(Anchor::Center, Anchor::Center);
+
+    pub const fn new(x: Anchor, z: Anchor) -> Self {
+        Self { x, z }
+    }
+}
+
+/// Why a board couldn't be resized.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResizeError {
+    /// The component doesn't exist, or isn't a circuit board with valid
+    /// custom data.
+    NotABoard(ComponentId),
+    /// Boards must be at least one square wide and long.
+    EmptyBoard,
+}
+
+impl Sandbox {
+    /// Change the size of a circuit board, in squares, keeping the `anchor`
+    /// side of it in place.
+    ///
+    /// When the board's origin moves, its children are moved the other way,
+    /// so that everything on the board stays where it is in the world. The
+    /// children whose origins are no longer over the board are returned in
+    /// ID order; they are left where they are, and can be brought back onto
+    /// the board with [`clamp_to_board`](Self::clamp_to_board).
+    ///
+    /// ```
+    /// use blotter::geometry::Position;
+    /// use blotter::sandbox::component::{CircuitBoard, Peg};
+    /// use blotter::sandbox::{Anchor, BoardAnchor, Sandbox};
+    ///
+    /// let mut sandbox = Sandbox::new();
+    /// let board = sandbox.add_component(&CircuitBoard::new().width(4).height(4).build());
+    /// let near = sandbox.add_component(&Peg::new().build().parent(Some(board)).position(Position::square(0, 0)));
+    /// let far = sandbox.add_component(&Peg::new().build().parent(Some(board)).position(Position::square(3, 3)));
+    ///
+    /// let anchor = BoardAnchor::new(Anchor::End, Anchor::Start);
+    /// let off = sandbox.resize_board(board, 2, 3, anchor).unwrap();
+    /// assert_eq!(off, vec![near, far]);
+    ///
+    /// // The far peg is still over the same place in the world, but the
+    /// // board now starts two squares further along X.
+    /// assert_eq!(sandbox.component(board).unwrap().position(), [600, 0, 0]);
+    /// assert_eq!(Position(sandbox.component(far).unwrap().position()), Position::square(1, 3));
+    ///
+    /// sandbox.clamp_to_board(board, &off).unwrap();
+    /// assert_eq!(Position(sandbox.component(near).unwrap().position()), Position::square(0, 0));
+    /// assert_eq!(Position(sandbox.component(far).unwrap().position()), Position::square(1, 2));
+    /// ```
+    pub fn resize_board(
+        &mut self,
+        id: ComponentId,
+        width: u32,
+        height: u32,
+        anchor: BoardAnchor,
+    ) -> Result<Vec<ComponentId>, ResizeError> {
+        if width == 0 || height == 0 {
+            return Err(ResizeError::EmptyBoard);
+        }
+        let board = self.board(id)?;
+        let shift = [
+            anchor.x.shift(board.size_x, width) * UNITS_PER_SQUARE,
+            0,
+            anchor.z.shift(board.size_z, height) * UNITS_PER_SQUARE,
+        ];
+
+        let mut custom_data = Vec::new();
+        custom_data::CircuitBoard {
+            color: board.color,
+            size_x: width,
+            size_z: height,
+        }
+        .write(&mut custom_data)
+        .unwrap();
+
+        let info = self.components.get_mut(id.0).unwrap();
+        info.custom_data = Some(custom_data);
+        let children = info.children.clone();
+        if shift != [0; 3] {
+            let offset = Rotation(info.rotation).apply(shift.map(|units| units as f32));
+            for (axis, offset) in offset.into_iter().enumerate() {
+                info.position[axis] += offset.round() as i32;
+            }
+            for &child in &children {
+                let child = self.components.get_mut(child.0).unwrap();
+                for (position, shift) in child.position.iter_mut().zip(shift) {
+                    *position -= shift;
+                }
+            }
+        }
+
+        let bounds = [width, height].map(|squares| squares as i32 * UNITS_PER_SQUARE);
+        let mut off: Vec<ComponentId> = children
+            .into_iter()
+            .filter(|child| {
+                let [x, _, z] = self.components.get(child.0).unwrap().position;
+                !(0..=bounds[0]).contains(&x) || !(0..=bounds[1]).contains(&z)
+            })
+            .collect();
+        off.sort_unstable();
+        Ok(off)
+    }
+
+    /// Move children of a board that are beyond its edges onto the nearest
+    /// square along the edge, keeping their height and rotation.
+    ///
+    /// Components that aren't children of the board are left alone.
+    pub fn clamp_to_board(
+        &mut self,
+        id: ComponentId,
+        children: &[ComponentId],
+    ) -> Result<(), ResizeError> {
+        let board = self.board(id)?;
+        let bounds = [board.size_x, board.size_z].map(|squares| squares as i32 * UNITS_PER_SQUARE);
+        for &child in children {
+            let Some(info) = self.components.get_mut(child.0) else {
+                continue;
+            };
+            if info.parent != Some(id) {
+                continue;
+            }
+            for (axis, bound) in [(0, bounds[0]), (2, bounds[1])] {
+                let position = &mut info.position[axis];
+                if *position < 0 {
+                    *position = UNITS_PER_SQUARE / 2;
+                } else if *position > bound {
+                    *position = bound - UNITS_PER_SQUARE / 2;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn board(&self, id: ComponentId) -> Result<custom_data::CircuitBoard, ResizeError> {
+        let board_type = self
+            .component_types
+            .get(custom_data::CircuitBoard::TYPE_STRING);
+        self.components
+            .get(id.0)
+            .and_then(|info| self.board_data(info, board_type))
+            .ok_or(ResizeError::NotABoard(id))
+    }
+}
@@ -0,0 +1,69 @@
+//! Finding components by the contents of their custom data.
+
+use crate::custom_data::{ComponentData, Label};
+
+use super::{ComponentId, Sandbox};
+
+/// Component types whose custom data is a [`Label`].
+const LABEL_TYPES: &[&str] = &["MHG.Label", "MHG.PanelLabel"];
+
+/// A component found by a search.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchHit {
+    pub id: ComponentId,
+    /// The origin of the component in world space, in meters.
+    pub position: [f32; 3],
+}
+
+impl Sandbox {
+    /// Find the components with custom data for which `f` returns `true`,
+    /// given the text ID of the component's type and the data. Results are
+    /// in ID order.
+    pub fn search_custom_data<F>(&self, mut f: F) -> Vec<SearchHit>
+    where
+        F: FnMut(&str, &[u8]) -> bool,
+    {
+        self.components()
+            .filter(|component| {
+                component
+                    .custom_data()
+                    .is_some_and(|data| f(component.type_name(), data))
+            })
+            .map(|component| SearchHit {
+                id: component.id(),
+                position: self.world_position(component.id()),
+            })
+            .collect()
+    }
+
+    /// Find the labels whose text contains `text`, ignoring case. Results
+    /// are in ID order.
+    ///
+    /// ```
+    /// use blotter::custom_data::{ComponentData, Label};
+    /// use blotter::sandbox::{ComponentBuilder, Sandbox};
+    ///
+    /// let mut sandbox = Sandbox::new();
+    /// let mut data = Vec::new();
+    /// Label::new("Program Counter").write(&mut data).unwrap();
+    /// let label = sandbox.add_component(
+    ///     &ComponentBuilder::new("MHG.PanelLabel")
+    ///         .custom_data(Some(data))
+    ///         .position([1000, 0, 0]),
+    /// );
+    ///
+    /// let hits = sandbox.search_labels("counter");
+    /// assert_eq!(hits.len(), 1);
+    /// assert_eq!(hits[0].id, label);
+    /// assert_eq!(hits[0].position, [1.0, 0.0, 0.0]);
+    /// assert!(sandbox.search_labels("ALU").is_empty());
+    /// ```
+    pub fn search_labels(&self, text: &str) -> Vec<SearchHit> {
+        let text = text.to_lowercase();
+        self.search_custom_data(|type_name, mut data| {
+            LABEL_TYPES.contains(&type_name)
+                && Label::read(&mut data)
+                    .is_ok_and(|label| label.text.to_lowercase().contains(&text))
+        })
+    }
+}
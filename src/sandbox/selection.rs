@@ -0,0 +1,251 @@
+//! Sets of components and wires, and the editing operations that act on
+//! them.
+
+use std::collections::BTreeSet;
+
+use crate::geometry::Position;
+
+use super::subassembly::{copy_components, selection_roots};
+use super::{ComponentId, Remap, Sandbox, Subassembly, WireId};
+
+/// A set of components and wires in a sandbox, like the selection of an
+/// editor.
+///
+/// A selection only holds IDs, so it doesn't borrow the sandbox, and it
+/// isn't updated when the sandbox changes. IDs of removed components and
+/// wires are ignored by every operation, and can be dropped with
+/// [`retain_valid`](Self::retain_valid).
+///
+/// ```
+/// use blotter::sandbox::component::{CircuitBoard, Peg};
+/// use blotter::sandbox::{Sandbox, Selection};
+///
+/// let mut sandbox = Sandbox::new();
+/// let board = sandbox.add_component(&CircuitBoard::new().build());
+/// let peg = sandbox.add_component(&Peg::new().build().parent(Some(board)));
+///
+/// let mut selection = Selection::new();
+/// selection.add_component(board);
+/// selection.expand_to_children(&sandbox);
+/// assert!(selection.contains_component(peg));
+///
+/// let remap = sandbox.duplicate_selection(&selection);
+/// selection.remap(&remap);
+/// sandbox.translate_selection(&selection, [0, 0, 3000]);
+/// assert_eq!(sandbox.component(remap.component(board)).unwrap().position(), [0, 0, 3000]);
+/// assert_eq!(sandbox.component_ids().count(), 4);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Selection {
+    components: BTreeSet<ComponentId>,
+    wires: BTreeSet<WireId>,
+}
+
+impl Selection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Select every component and wire in the sandbox.
+    pub fn all(sandbox: &Sandbox) -> Self {
+        Self {
+            components: sandbox.component_ids().collect(),
+            wires: sandbox.wire_ids().collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty() && self.wires.is_empty()
+    }
+
+    /// Iterate over the selected components, in ID order.
+    pub fn components(&self) -> impl Iterator<Item = ComponentId> + '_ {
+        self.components.iter().copied()
+    }
+
+    /// Iterate over the selected wires, in ID order.
+    pub fn wires(&self) -> impl Iterator<Item = WireId> + '_ {
+        self.wires.iter().copied()
+    }
+
+    pub fn contains_component(&self, id: ComponentId) -> bool {
+        self.components.contains(&id)
+    }
+
+    pub fn contains_wire(&self, id: WireId) -> bool {
+        self.wires.contains(&id)
+    }
+
+    /// Returns whether the component wasn't already selected.
+    pub fn add_component(&mut self, id: ComponentId) -> bool {
+        self.components.insert(id)
+    }
+
+    /// Returns whether the wire wasn't already selected.
+    pub fn add_wire(&mut self, id: WireId) -> bool {
+        self.wires.insert(id)
+    }
+
+    /// Returns whether the component was selected.
+    pub fn remove_component(&mut self, id: ComponentId) -> bool {
+        self.components.remove(&id)
+    }
+
+    /// Returns whether the wire was selected.
+    pub fn remove_wire(&mut self, id: WireId) -> bool {
+        self.wires.remove(&id)
+    }
+
+    pub fn clear(&mut self) {
+        self.components.clear();
+        self.wires.clear();
+    }
+
+    /// Select everything in the sandbox that isn't selected, and deselect
+    /// everything that is.
+    pub fn invert(&mut self, sandbox: &Sandbox) {
+        self.components = sandbox
+            .component_ids()
+            .filter(|id| !self.components.contains(id))
+            .collect();
+        self.wires = sandbox
+            .wire_ids()
+            .filter(|id| !self.wires.contains(id))
+            .collect();
+    }
+
+    /// Add every descendant of the selected components.
+    pub fn expand_to_children(&mut self, sandbox: &Sandbox) {
+        let descendants: Vec<ComponentId> = self
+            .components
+            .iter()
+            .flat_map(|&id| sandbox.descendants(id))
+            .collect();
+        self.components.extend(descendants);
+    }
+
+    /// Add everything that is connected to the selection: every wire
+    /// connected to a peg in the nets of the selected wires and of the pegs
+    /// of the selected components, and the components at both ends of those
+    /// wires.
+    ///
+    /// Wires from an output peg are part of the output's net, so this also
+    /// selects the inputs that the output drives, and the outputs that drive
+    /// the selected inputs.
+    pub fn expand_to_net(&mut self, sandbox: &Sandbox) {
+        let mut nets = BTreeSet::new();
+        for &id in &self.wires {
+            if let Some(wire) = sandbox.wires.get(id.0) {
+                nets.insert(wire.net_id);
+            }
+        }
+        for &id in &self.components {
+            if let Some(info) = sandbox.components.get(id.0) {
+                nets.extend(
+                    info.inputs
+                        .iter()
+                        .chain(&info.outputs)
+                        .map(|peg| peg.net_id),
+                );
+            }
+        }
+        for net_id in nets {
+            let net = sandbox.nets.get(net_id.0).unwrap();
+            for address in &net.pegs {
+                self.components.insert(address.component);
+                for &wire_id in &sandbox.get_peg(address).unwrap().wires {
+                    let wire = sandbox.wires.get(wire_id.0).unwrap();
+                    self.wires.insert(wire_id);
+                    self.components.insert(wire.a.component);
+                    self.components.insert(wire.b.component);
+                }
+            }
+        }
+    }
+
+    /// Drop the IDs of components and wires that are no longer in the
+    /// sandbox.
+    pub fn retain_valid(&mut self, sandbox: &Sandbox) {
+        self.components.retain(|&id| sandbox.is_valid(id));
+        self.wires.retain(|&id| sandbox.wires.get(id.0).is_some());
+    }
+
+    /// Replace the IDs that were changed, e.g. by [`Sandbox::compact`], or
+    /// by [`Sandbox::duplicate_selection`] to select the copies.
+    pub fn remap(&mut self, remap: &Remap) {
+        self.components = self
+            .components
+            .iter()
+            .map(|&id| remap.component(id))
+            .collect();
+        self.wires = self.wires.iter().map(|&id| remap.wire(id)).collect();
+    }
+
+    /// The selected components that aren't descendants of other selected
+    /// components, in ID order.
+    ///
+    /// These are the components that are moved, copied and removed by the
+    /// bulk operations; the rest come along with their ancestors.
+    pub fn roots(&self, sandbox: &Sandbox) -> Vec<ComponentId> {
+        selection_roots(sandbox, self.components.iter().copied())
+    }
+}
+
+impl Sandbox {
+    /// Remove the selected wires, and the selected components along with
+    /// their descendants and the wires connected to them.
+    pub fn delete_selection(&mut self, selection: &Selection) {
+        let wires: Vec<WireId> = selection.wires().collect();
+        self.remove_wires(&wires);
+        let components: Vec<ComponentId> = selection.components().collect();
+        self.remove_components(&components);
+    }
+
+    /// Copy the selected components and their descendants in place, with the
+    /// same parents as the originals, along with the wires between them.
+    ///
+    /// Wires to components outside the selection aren't copied, and the
+    /// copied nets keep their states. The returned [`Remap`] gives the ID of
+    /// each copy.
+    pub fn duplicate_selection(&mut self, selection: &Selection) -> Remap {
+        let roots = selection.roots(self);
+        // Clones share storage, so this only copies what is modified below.
+        let source = self.clone();
+        copy_components(&source, self, &roots, |root| {
+            let info = source.components.get(root.0).unwrap();
+            (info.parent, info.position, info.rotation)
+        })
+    }
+
+    /// Move the selected components, and their descendants with them, by
+    /// `offset` in world space, in fixed-point units.
+    ///
+    /// Wires follow the pegs that they connect, so they don't need to be
+    /// moved.
+    pub fn translate_selection(&mut self, selection: &Selection, offset: [i32; 3]) {
+        for root in selection.roots(self) {
+            let info = self.components.get(root.0).unwrap();
+            let position = match info.parent {
+                // Root components can be moved exactly.
+                None => [0, 1, 2].map(|axis| info.position[axis] + offset[axis]),
+                Some(parent) => {
+                    let mut world = self.world_transform(root);
+                    let offset = Position(offset).to_meters();
+                    world.translation =
+                        [0, 1, 2].map(|axis| world.translation[axis] + offset[axis]);
+                    self.local_placement(Some(parent), &world).0 .0
+                }
+            };
+            self.components.get_mut(root.0).unwrap().position = position;
+        }
+    }
+
+    /// Copy the selected components, their descendants and the wires between
+    /// them into a [`Subassembly`], leaving the sandbox unchanged.
+    ///
+    /// Follow with [`delete_selection`](Self::delete_selection) to cut
+    /// instead of copy.
+    pub fn extract_selection(&self, selection: &Selection) -> Subassembly {
+        Subassembly::from_sandbox_selection(self, &selection.roots(self))
+    }
+}
@@ -2,16 +2,113 @@
 
 use bitvec::vec::BitVec;
 
+use super::hierarchy::push_sorted;
+use super::type_name::TypeTable;
+use crate::error::Error;
 use crate::latest as blotter;
+use crate::misc::object_store::Address;
+use crate::misc::small_set::SmallSet;
+use crate::version::GameVersion;
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
 
-/// The current game version that this save is compatible with.
-const GAME_VERSION: [i32; 4] = [0, 91, 0, 510];
+/// Options for [`Sandbox::to_file`](super::Sandbox::to_file).
+#[derive(Debug, Clone)]
+pub struct SaveOptions {
+    prune_component_types: bool,
+    compact_nets: bool,
+    preserve_addresses: bool,
+    drop_invalid_wires: bool,
+    game_version: GameVersion,
+}
+
+impl SaveOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The game version to write the save for. Defaults to
+    /// [`GameVersion::LATEST`].
+    ///
+    /// This is recorded in the header of the save, and chooses the save
+    /// format of [`Sandbox::to_versioned_file`](super::Sandbox::to_versioned_file).
+    pub fn game_version(self, game_version: impl Into<GameVersion>) -> Self {
+        Self {
+            game_version: game_version.into(),
+            ..self
+        }
+    }
+
+    /// Leave out component types that no component uses, and renumber the
+    /// rest densely. Defaults to `false`.
+    pub fn prune_component_types(self, prune_component_types: bool) -> Self {
+        Self {
+            prune_component_types,
+            ..self
+        }
+    }
+
+    /// Renumber circuit states densely in the order they are first used, and
+    /// leave out states that nothing refers to, like
+    /// [`Sandbox::compact_nets`](super::Sandbox::compact_nets) does. The game
+    /// packs its states the same way. Defaults to `false`.
+    pub fn compact_nets(self, compact_nets: bool) -> Self {
+        Self {
+            compact_nets,
+            ..self
+        }
+    }
+
+    /// Give components loaded from a save the same addresses that they had
+    /// in it, so that anything that refers to them by address still finds
+    /// them. Other components get new addresses after the highest one that
+    /// is kept. Defaults to `false`, which numbers every component from 1 in
+    /// the order they are saved.
+    ///
+    /// See [`Sandbox::loaded_address`](super::Sandbox::loaded_address).
+    pub fn preserve_addresses(self, preserve_addresses: bool) -> Self {
+        Self {
+            preserve_addresses,
+            ..self
+        }
+    }
+
+    /// Leave out wires that [`Sandbox::invalid_wires`](super::Sandbox::invalid_wires)
+    /// finds, instead of failing in
+    /// [`Sandbox::try_to_file`](super::Sandbox::try_to_file). Defaults to
+    /// `false`. Serializing without checking always leaves them out.
+    pub fn drop_invalid_wires(self, drop_invalid_wires: bool) -> Self {
+        Self {
+            drop_invalid_wires,
+            ..self
+        }
+    }
+}
+
+impl Default for SaveOptions {
+    fn default() -> Self {
+        Self {
+            prune_component_types: false,
+            compact_nets: false,
+            preserve_addresses: false,
+            drop_invalid_wires: false,
+            game_version: GameVersion::LATEST,
+        }
+    }
+}
 
 /// Serialization context, mainly tracks ID mappings
 struct Serializer {
     next_component_id: u32,
     component_id_map: HashMap<super::ComponentId, u32>,
+    /// Renumbered component types, if they are being pruned.
+    type_id_map: Option<HashMap<u16, u16>>,
+    /// Renumbered circuit states, if they are being compacted.
+    net_id_map: Option<HashMap<super::NetId, i32>>,
 }
 
 impl Serializer {
@@ -19,10 +116,37 @@ impl Serializer {
         Self {
             next_component_id: 1,
             component_id_map: HashMap::new(),
+            type_id_map: None,
+            net_id_map: None,
+        }
+    }
+
+    fn get_net(&self, net_id: super::NetId) -> i32 {
+        match &self.net_id_map {
+            Some(map) => map[&net_id],
+            None => net_id.into_raw(),
+        }
+    }
+
+    fn get_type(&self, type_id: u16) -> u16 {
+        match &self.type_id_map {
+            Some(map) => map[&type_id],
+            None => type_id,
+        }
+    }
+
+    /// Keep the addresses that components were loaded with.
+    fn preserve_addresses(&mut self, sandbox: &super::Sandbox) {
+        for (&id, &address) in sandbox.addresses.iter() {
+            self.component_id_map.insert(id, address);
+            self.next_component_id = self.next_component_id.max(address + 1);
         }
     }
 
     fn register_component(&mut self, id: super::ComponentId) -> u32 {
+        if let Some(&raw_id) = self.component_id_map.get(&id) {
+            return raw_id;
+        }
         let raw_id = self.next_component_id;
         self.next_component_id += 1;
         self.component_id_map.insert(id, raw_id);
@@ -41,7 +165,7 @@ impl Serializer {
         blotter::Component {
             address: self.register_component(id),
             parent: info.parent.map(|id| self.get_component(id)).unwrap_or(0),
-            type_id: info.type_id,
+            type_id: self.get_type(info.type_id),
             position: info.position,
             rotation: info.rotation,
             inputs: info
@@ -60,13 +184,13 @@ impl Serializer {
 
     fn serialize_input(&self, peg: &super::PegInfo) -> blotter::Input {
         blotter::Input {
-            circuit_state_id: peg.net_id.into_raw(),
+            circuit_state_id: self.get_net(peg.net_id),
         }
     }
 
     fn serialize_output(&self, peg: &super::PegInfo) -> blotter::Output {
         blotter::Output {
-            circuit_state_id: peg.net_id.into_raw(),
+            circuit_state_id: self.get_net(peg.net_id),
         }
     }
 
@@ -74,7 +198,7 @@ impl Serializer {
         blotter::Wire {
             start_peg: self.serialize_peg_address(&wire.a),
             end_peg: self.serialize_peg_address(&wire.b),
-            circuit_state_id: wire.net_id.into_raw(),
+            circuit_state_id: self.get_net(wire.net_id),
             rotation: wire.rotation,
         }
     }
@@ -88,167 +212,925 @@ impl Serializer {
     }
 }
 
+/// Serialization is deterministic: sandboxes built by the same sequence of
+/// edits always produce the same file, and therefore the same bytes.
+///
+/// ```
+/// use blotter::{latest::BlotterFile, sandbox::{ComponentBuilder, Sandbox}};
+///
+/// let build = || {
+///     let mut sandbox = Sandbox::new();
+///     for _ in 0..16 {
+///         let board = sandbox.add_component(&ComponentBuilder::new("MHG.CircuitBoard"));
+///         for _ in 0..4 {
+///             sandbox.add_component(&ComponentBuilder::new("MHG.Inverter").parent(Some(board)));
+///         }
+///     }
+///     let mut bytes = Vec::new();
+///     BlotterFile::from(&sandbox).write(&mut bytes).unwrap();
+///     bytes
+/// };
+/// assert_eq!(build(), build());
+/// ```
 impl From<&super::Sandbox> for blotter::BlotterFile {
     fn from(sandbox: &super::Sandbox) -> Self {
+        sandbox.to_file(&SaveOptions::default())
+    }
+}
+
+impl super::Sandbox {
+    /// Serialize the sandbox, with options.
+    ///
+    /// With the default options, this is the same as the `From` conversion.
+    /// The result is always a v6 save; to write for games that use an older
+    /// format, see [`to_versioned_file`](Self::to_versioned_file).
+    ///
+    /// Wires that [`invalid_wires`](Self::invalid_wires) finds are left out,
+    /// as if [`drop_invalid_wires`](SaveOptions::drop_invalid_wires) was set;
+    /// to be told about them instead, use [`try_to_file`](Self::try_to_file).
+    pub fn to_file(&self, options: &SaveOptions) -> blotter::BlotterFile {
+        let skip = self
+            .invalid_wires()
+            .into_iter()
+            .map(|invalid| invalid.wire)
+            .collect();
+        self.serialize(options, &skip)
+    }
+
+    /// Serialize the sandbox like [`to_file`](Self::to_file), but check its
+    /// wires first, and fail if any of them can't be saved, unless
+    /// [`drop_invalid_wires`](SaveOptions::drop_invalid_wires) is set.
+    pub fn try_to_file(
+        &self,
+        options: &SaveOptions,
+    ) -> Result<blotter::BlotterFile, SandboxSaveError> {
+        let invalid = self.invalid_wires();
+        if !invalid.is_empty() && !options.drop_invalid_wires {
+            return Err(SandboxSaveError {
+                invalid_wires: invalid,
+            });
+        }
+        let skip = invalid.iter().map(|invalid| invalid.wire).collect();
+        Ok(self.serialize(options, &skip))
+    }
+
+    /// Wires that would make a broken save: wires to pegs or components
+    /// that don't exist, and wires between two outputs.
+    ///
+    /// The sandbox doesn't make these itself; this guards against edits
+    /// that bypass its checks.
+    pub fn invalid_wires(&self) -> Vec<InvalidWire> {
+        let mut invalid = Vec::new();
+        for (address, wire) in self.wires.iter() {
+            let id = super::WireId(address);
+            if wire.a.peg_type == super::PegType::Output
+                && wire.b.peg_type == super::PegType::Output
+            {
+                invalid.push(InvalidWire {
+                    wire: id,
+                    problem: WireProblem::OutputToOutput,
+                });
+                continue;
+            }
+            for peg in [wire.a, wire.b] {
+                let problem = match self.components.get(peg.component.0) {
+                    None => WireProblem::MissingComponent(peg),
+                    Some(info) => {
+                        let num_pegs = match peg.peg_type {
+                            super::PegType::Input => info.inputs.len(),
+                            super::PegType::Output => info.outputs.len(),
+                        };
+                        if peg.peg_index < num_pegs {
+                            continue;
+                        }
+                        WireProblem::PegOutOfRange { peg, num_pegs }
+                    }
+                };
+                invalid.push(InvalidWire { wire: id, problem });
+                break;
+            }
+        }
+        invalid
+    }
+
+    fn serialize(
+        &self,
+        options: &SaveOptions,
+        skip: &HashSet<super::WireId>,
+    ) -> blotter::BlotterFile {
+        let sandbox = self;
         let mut ser = Serializer::new();
+        if options.preserve_addresses {
+            ser.preserve_addresses(sandbox);
+        }
+        if options.prune_component_types {
+            let mut used: Vec<u16> = sandbox
+                .components_by_type
+                .iter()
+                .filter(|(_, ids)| !ids.is_empty())
+                .map(|(&type_id, _)| type_id)
+                .collect();
+            used.sort_unstable();
+            ser.type_id_map = Some(
+                used.into_iter()
+                    .enumerate()
+                    .map(|(new, old)| (old, new as u16))
+                    .collect(),
+            );
+        }
+
+        let mut states = sandbox.net_states.clone();
+        if options.compact_nets {
+            let order = sandbox.canonical_net_order();
+            states = order
+                .iter()
+                .map(|net_id| sandbox.net_states[net_id.0.into_raw()])
+                .collect();
+            ser.net_id_map = Some(
+                order
+                    .into_iter()
+                    .enumerate()
+                    .map(|(new, old)| (old, new.try_into().unwrap()))
+                    .collect(),
+            );
+        }
 
         // Blotter format requires that parents must be serialized before children.
         // Serialize components with depth-first, pre-order traversal.
+        // Siblings are visited in ID order so the output doesn't depend on
+        // hash set iteration order.
         let mut components = Vec::new();
         let mut stack: Vec<super::ComponentId> = Vec::new();
-        stack.extend(&sandbox.root_components);
+        push_sorted(&mut stack, sandbox.root_components.iter());
         while let Some(component_id) = stack.pop() {
             let component = sandbox.components.get(component_id.0).unwrap();
-            stack.extend(&component.children);
+            push_sorted(&mut stack, &component.children);
             components.push(ser.serialize_component(component_id, component))
         }
 
-        let mut states = sandbox.net_states.clone();
-        states.set_uninitialized(false);
+        let mut component_types: Vec<blotter::ComponentType> = sandbox
+            .component_types
+            .iter()
+            .filter(|(_, num)| {
+                ser.type_id_map
+                    .as_ref()
+                    .is_none_or(|map| map.contains_key(num))
+            })
+            .map(|(name, num)| blotter::ComponentType {
+                numeric_id: ser.get_type(num),
+                text_id: name.to_string(),
+            })
+            .collect();
+        component_types.sort_by_key(|ctype| ctype.numeric_id);
 
-        Self {
-            game_version: GAME_VERSION,
-            save_type: blotter::SaveType::World,
+        let circuit_states = match sandbox.save_type {
+            blotter::SaveType::World => {
+                states.set_uninitialized(false);
+                blotter::CircuitStates::WorldFormat {
+                    circuit_states: states.into_vec(),
+                }
+            }
+            blotter::SaveType::Subassembly => blotter::CircuitStates::SubassemblyFormat {
+                on_states: states
+                    .iter_ones()
+                    .map(|id| id.try_into().unwrap())
+                    .collect(),
+            },
+        };
+
+        blotter::BlotterFile {
+            game_version: options.game_version.into(),
+            save_type: sandbox.save_type,
             mods: sandbox.mods.clone(),
-            component_types: sandbox
-                .component_types
-                .iter()
-                .map(|(name, num)| blotter::ComponentType {
-                    numeric_id: *num,
-                    text_id: name.clone(),
-                })
-                .collect(),
+            component_types,
             components,
             wires: sandbox
                 .wires
                 .iter()
+                .filter(|&(id, _)| !skip.contains(&super::WireId(id)))
                 .map(|(_id, wire)| ser.serialize_wire(wire))
                 .collect(),
-            circuit_states: blotter::CircuitStates::WorldFormat {
-                circuit_states: states.into_vec(),
-            },
+            circuit_states,
+            extra: sandbox.extra.clone(),
+            trailing: sandbox.trailing.clone(),
+        }
+    }
+
+    /// Serialize the sandbox in the save format of the game version in the
+    /// options.
+    ///
+    /// For games before 0.91, positions are converted to floating-point
+    /// meters, the same way [`BlotterFile::migrate`](crate::BlotterFile::migrate)
+    /// converts them back. Custom data is written as it is for both formats.
+    ///
+    /// ```
+    /// use blotter::sandbox::{SaveOptions, Sandbox};
+    /// use blotter::version::GameVersion;
+    ///
+    /// let sandbox = Sandbox::new();
+    /// let file = sandbox.to_versioned_file(&SaveOptions::new().game_version(GameVersion::V0_90));
+    /// assert!(matches!(file, blotter::BlotterFile::V5(_)));
+    /// assert_eq!(file.game_version(), GameVersion::V0_90);
+    /// ```
+    ///
+    /// Invalid wires are left out, like with [`to_file`](Self::to_file).
+    pub fn to_versioned_file(&self, options: &SaveOptions) -> crate::BlotterFile {
+        versioned(self.to_file(options), options)
+    }
+
+    /// Serialize the sandbox like [`to_versioned_file`](Self::to_versioned_file),
+    /// but fail if any wires can't be saved, like
+    /// [`try_to_file`](Self::try_to_file).
+    pub fn try_to_versioned_file(
+        &self,
+        options: &SaveOptions,
+    ) -> Result<crate::BlotterFile, SandboxSaveError> {
+        Ok(versioned(self.try_to_file(options)?, options))
+    }
+}
+
+/// A save in the format of the game version of `options`.
+fn versioned(file: blotter::BlotterFile, options: &SaveOptions) -> crate::BlotterFile {
+    if options.game_version.has_fixed_positions() {
+        crate::BlotterFile::V6(file)
+    } else {
+        crate::BlotterFile::V5(file.into())
+    }
+}
+
+impl super::Sandbox {
+    /// Read a save of any supported version for editing.
+    ///
+    /// The save may be compressed (see
+    /// [`read_maybe_compressed`](crate::BlotterFile::read_maybe_compressed)).
+    /// Saves that can't be loaded fail with [`Error::SandboxLoad`].
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self, Error> {
+        let file = crate::BlotterFile::read_maybe_compressed(&mut reader)?.migrate();
+        Ok(Self::try_from(&file)?)
+    }
+
+    /// The address that a component had in the save it was loaded from, or
+    /// `None` if it was added since.
+    ///
+    /// Components keep their addresses when the sandbox is
+    /// [compacted](Self::compact). Components copied from another sandbox,
+    /// including merged shards, count as added.
+    ///
+    /// ```
+    /// use blotter::latest::BlotterFile;
+    /// use blotter::sandbox::{component::Peg, SaveOptions, Sandbox};
+    ///
+    /// let mut sandbox = Sandbox::new();
+    /// for _ in 0..3 {
+    ///     sandbox.add_component(&Peg::new().build());
+    /// }
+    /// let file = BlotterFile::from(&sandbox);
+    ///
+    /// let mut sandbox = Sandbox::try_from(&file).unwrap();
+    /// let loaded: Vec<_> = sandbox.component_ids().collect();
+    /// assert_eq!(sandbox.loaded_address(loaded[2]), Some(3));
+    /// sandbox.remove_component(loaded[0]);
+    /// let new = sandbox.add_component(&Peg::new().build());
+    /// assert_eq!(sandbox.loaded_address(new), None);
+    ///
+    /// let options = SaveOptions::new().preserve_addresses(true);
+    /// let mut addresses: Vec<u32> = sandbox
+    ///     .to_file(&options)
+    ///     .components
+    ///     .iter()
+    ///     .map(|component| component.address)
+    ///     .collect();
+    /// addresses.sort_unstable();
+    /// assert_eq!(addresses, [2, 3, 4]);
+    /// ```
+    pub fn loaded_address(&self, id: super::ComponentId) -> Option<u32> {
+        self.addresses.get(&id).copied()
+    }
+
+    /// Write the sandbox as an uncompressed save of the latest version.
+    ///
+    /// Fails with [`Error::SandboxSave`] if any wires can't be saved; see
+    /// [`try_to_file`](Self::try_to_file).
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        self.try_to_file(&SaveOptions::default())?
+            .write(&mut writer)
+    }
+
+    /// Open a save file for editing. See [`from_reader`](Self::from_reader).
+    pub fn load_path<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Write the sandbox to a save file, replacing it if it exists. Fails
+    /// like [`write_to`](Self::write_to).
+    pub fn save_path<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Why a save couldn't be loaded into a [`Sandbox`](super::Sandbox).
+///
+/// Components and wires are identified by their position in the save.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SandboxLoadError {
+    /// Two components have the same address.
+    DuplicateAddress { component: usize, address: u32 },
+    /// A component's parent doesn't exist.
+    MissingParent { component: usize, parent: u32 },
+    /// A component is its own ancestor.
+    ParentCycle { component: usize },
+    /// A peg refers to a circuit state that doesn't exist.
+    PegStateOutOfRange {
+        component: usize,
+        circuit_state_id: i32,
+    },
+    /// A wire refers to a circuit state that doesn't exist.
+    WireStateOutOfRange { wire: usize, circuit_state_id: i32 },
+    /// A wire refers to a component that doesn't exist.
+    MissingWireEndpoint { wire: usize, address: u32 },
+    /// A wire refers to a peg that its component doesn't have.
+    InvalidPegIndex { wire: usize },
+    /// A wire connects two outputs.
+    OutputToOutput { wire: usize },
+    /// A wire's circuit state doesn't match the pegs it connects.
+    WireStateMismatch { wire: usize },
+    /// The component type table needs more numeric IDs than there are.
+    TooManyComponentTypes,
+}
+
+impl fmt::Display for SandboxLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicateAddress { component, address } => {
+                write!(f, "component {} reuses address {}", component, address)
+            }
+            Self::MissingParent { component, parent } => write!(
+                f,
+                "component {} has parent {}, which does not exist",
+                component, parent
+            ),
+            Self::ParentCycle { component } => {
+                write!(f, "component {} is its own ancestor", component)
+            }
+            Self::PegStateOutOfRange {
+                component,
+                circuit_state_id,
+            } => write!(
+                f,
+                "component {} has a peg with circuit state {}, which does not exist",
+                component, circuit_state_id
+            ),
+            Self::WireStateOutOfRange {
+                wire,
+                circuit_state_id,
+            } => write!(
+                f,
+                "wire {} has circuit state {}, which does not exist",
+                wire, circuit_state_id
+            ),
+            Self::MissingWireEndpoint { wire, address } => write!(
+                f,
+                "wire {} connects to component {}, which does not exist",
+                wire, address
+            ),
+            Self::InvalidPegIndex { wire } => {
+                write!(f, "wire {} connects to a peg that does not exist", wire)
+            }
+            Self::OutputToOutput { wire } => write!(f, "wire {} connects two outputs", wire),
+            Self::WireStateMismatch { wire } => write!(
+                f,
+                "wire {} has a different circuit state than its endpoints",
+                wire
+            ),
+            Self::TooManyComponentTypes => write!(f, "the save has too many component types"),
         }
     }
 }
 
+impl std::error::Error for SandboxLoadError {}
+
+/// Why a [`Sandbox`](super::Sandbox) couldn't be saved.
+///
+/// ```
+/// use blotter::sandbox::{Sandbox, SaveOptions};
+///
+/// let sandbox = Sandbox::new();
+/// assert!(sandbox.invalid_wires().is_empty());
+/// assert!(sandbox.try_to_file(&SaveOptions::new()).is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SandboxSaveError {
+    /// The wires that can't be saved, in ID order.
+    pub invalid_wires: Vec<InvalidWire>,
+}
+
+impl fmt::Display for SandboxSaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} wires can't be saved", self.invalid_wires.len())?;
+        for (i, invalid) in self.invalid_wires.iter().enumerate() {
+            write!(f, "{} {}", if i == 0 { ":" } else { ";" }, invalid.problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for SandboxSaveError {}
+
+/// A wire found by [`Sandbox::invalid_wires`](super::Sandbox::invalid_wires).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidWire {
+    pub wire: super::WireId,
+    pub problem: WireProblem,
+}
+
+/// What is wrong with an [`InvalidWire`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WireProblem {
+    /// The wire connects to a component that doesn't exist.
+    MissingComponent(super::PegAddress),
+    /// The wire connects to a peg past the last one of its component.
+    PegOutOfRange {
+        peg: super::PegAddress,
+        /// How many pegs of that type the component has.
+        num_pegs: usize,
+    },
+    /// The wire connects two outputs.
+    OutputToOutput,
+}
+
+impl fmt::Display for WireProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let peg_type = |peg: &super::PegAddress| match peg.peg_type {
+            super::PegType::Input => "input",
+            super::PegType::Output => "output",
+        };
+        match self {
+            Self::MissingComponent(_) => {
+                write!(f, "wire connects to a component that does not exist")
+            }
+            Self::PegOutOfRange { peg, num_pegs } => write!(
+                f,
+                "wire connects to {} {} of a component with {} {}s",
+                peg_type(peg),
+                peg.peg_index,
+                num_pegs,
+                peg_type(peg)
+            ),
+            Self::OutputToOutput => write!(f, "wire connects two outputs"),
+        }
+    }
+}
+
+/// Something unusual about a save that could still be loaded into a
+/// [`Sandbox`](super::Sandbox).
+///
+/// Components are identified by their position in the save.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SandboxLoadWarning {
+    /// A component comes before its parent in the save, which the game
+    /// doesn't allow. Saving the sandbox puts the parent first.
+    ChildBeforeParent { component: usize, parent: u32 },
+    /// A wire connects a peg to itself, and was left out.
+    SelfLoop { wire: usize },
+    /// A wire connects the same pegs as an earlier one, in either
+    /// direction, and was left out.
+    DuplicateWire { wire: usize },
+}
+
+impl fmt::Display for SandboxLoadWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ChildBeforeParent { component, parent } => write!(
+                f,
+                "component {} comes before its parent {}",
+                component, parent
+            ),
+            Self::SelfLoop { wire } => write!(f, "wire {} connects a peg to itself", wire),
+            Self::DuplicateWire { wire } => {
+                write!(f, "wire {} connects the same pegs as an earlier wire", wire)
+            }
+        }
+    }
+}
+
+/// Check that no component is its own ancestor, given the position in the
+/// save of each component's parent, and report the components that come
+/// before their parent.
+pub(super) fn check_parents(
+    file: &blotter::BlotterFile,
+    parents: &[Option<usize>],
+) -> Result<Vec<SandboxLoadWarning>, SandboxLoadError> {
+    if let Some(index) = (0..parents.len()).find(|&index| parents[index] == Some(index)) {
+        return Err(SandboxLoadError::ParentCycle { component: index });
+    }
+    let warnings: Vec<SandboxLoadWarning> = parents
+        .iter()
+        .enumerate()
+        .filter(|&(index, parent)| parent.is_some_and(|parent| parent > index))
+        .map(|(index, _)| SandboxLoadWarning::ChildBeforeParent {
+            component: index,
+            parent: file.components[index].parent,
+        })
+        .collect();
+    // Any other cycle has to go through a component that comes before its
+    // parent.
+    if warnings.is_empty() {
+        return Ok(warnings);
+    }
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum Visit {
+        New,
+        OnPath,
+        Done,
+    }
+    let mut visits = vec![Visit::New; parents.len()];
+    let mut path = Vec::new();
+    for start in 0..parents.len() {
+        let mut current = Some(start);
+        while let Some(index) = current {
+            match visits[index] {
+                Visit::New => {
+                    visits[index] = Visit::OnPath;
+                    path.push(index);
+                    current = parents[index];
+                }
+                Visit::OnPath => return Err(SandboxLoadError::ParentCycle { component: index }),
+                Visit::Done => break,
+            }
+        }
+        for index in path.drain(..) {
+            visits[index] = Visit::Done;
+        }
+    }
+    Ok(warnings)
+}
+
+/// A loaded save, with the information needed to relate it back to the file.
+///
+/// Returned by [`Sandbox::from_file_with_addresses`](super::Sandbox::from_file_with_addresses).
+#[derive(Debug, Clone)]
+pub struct SandboxLoad {
+    pub sandbox: super::Sandbox,
+    /// How the save's component types were renumbered; see
+    /// [`Sandbox::from_file`](super::Sandbox::from_file).
+    pub types: super::TypeRegistration,
+    /// The component with each address of the save.
+    pub address_map: HashMap<u32, super::ComponentId>,
+    /// Problems with the save that didn't stop it from loading.
+    pub warnings: Vec<SandboxLoadWarning>,
+}
+
 /// Deserialization context, mainly tracks ID mappings
-struct Deserializer {
+pub(super) struct Deserializer {
     component_id_map: HashMap<u32, super::ComponentId>,
+    num_nets: usize,
+    pub(super) types: super::TypeRegistration,
 }
 
 impl Deserializer {
-    fn new() -> Self {
+    pub(super) fn new(num_nets: usize, types: super::TypeRegistration) -> Self {
         Self {
             component_id_map: HashMap::new(),
+            num_nets,
+            types,
         }
     }
 
-    fn register_component(&mut self, raw_id: u32, id: super::ComponentId) {
+    pub(super) fn register_component(&mut self, raw_id: u32, id: super::ComponentId) {
         self.component_id_map.insert(raw_id, id);
     }
 
-    fn get_component(&self, id: u32) -> super::ComponentId {
-        self.component_id_map[&id]
+    pub(super) fn get_component(&self, id: u32) -> Option<super::ComponentId> {
+        self.component_id_map.get(&id).copied()
     }
 
-    fn deserialize_component(&self, component: &blotter::Component) -> super::ComponentInfo {
-        super::ComponentInfo {
-            type_id: component.type_id,
-            parent: if component.parent == 0 {
-                None
-            } else {
-                Some(self.get_component(component.parent))
-            },
-            position: component.position,
-            rotation: component.rotation,
-            children: HashSet::new(),
-            inputs: component
-                .inputs
-                .iter()
-                .map(|input| self.deserialize_input(input))
-                .collect(),
-            outputs: component
-                .outputs
-                .iter()
-                .map(|output| self.deserialize_output(output))
-                .collect(),
-            custom_data: component.custom_data.clone(),
-        }
+    pub(super) fn get_net(&self, raw: i32) -> Option<super::NetId> {
+        let in_range = usize::try_from(raw).is_ok_and(|raw| raw < self.num_nets);
+        in_range.then(|| super::NetId::from_raw(raw))
     }
 
-    fn deserialize_input(&self, input: &blotter::Input) -> super::PegInfo {
-        super::PegInfo {
-            net_id: super::NetId::from_raw(input.circuit_state_id),
-            wires: HashSet::new(),
+    pub(super) fn deserialize_component(
+        &self,
+        index: usize,
+        component: &blotter::Component,
+    ) -> Result<super::ComponentInfo, SandboxLoadError> {
+        let peg = |circuit_state_id: i32| {
+            let net_id =
+                self.get_net(circuit_state_id)
+                    .ok_or(SandboxLoadError::PegStateOutOfRange {
+                        component: index,
+                        circuit_state_id,
+                    })?;
+            Ok(super::PegInfo {
+                net_id,
+                wires: SmallSet::new(),
+            })
+        };
+        // Collecting into a `Result` would over-allocate, since the number of
+        // pegs isn't known up front.
+        let mut inputs = Vec::with_capacity(component.inputs.len());
+        for input in &component.inputs {
+            inputs.push(peg(input.circuit_state_id)?);
+        }
+        let mut outputs = Vec::with_capacity(component.outputs.len());
+        for output in &component.outputs {
+            outputs.push(peg(output.circuit_state_id)?);
         }
+        Ok(super::ComponentInfo {
+            type_id: self.types.type_id(component.type_id),
+            // Parents can come after their children, so they are filled in
+            // once every component is loaded; see `deserialize_parent`.
+            parent: None,
+            position: component.position,
+            rotation: component.rotation,
+            children: SmallSet::new(),
+            inputs,
+            outputs,
+            custom_data: component.custom_data.clone(),
+        })
     }
 
-    fn deserialize_output(&self, output: &blotter::Output) -> super::PegInfo {
-        super::PegInfo {
-            net_id: super::NetId::from_raw(output.circuit_state_id),
-            wires: HashSet::new(),
+    /// The parent of a component, once every component has been
+    /// registered.
+    pub(super) fn deserialize_parent(
+        &self,
+        index: usize,
+        component: &blotter::Component,
+    ) -> Result<Option<super::ComponentId>, SandboxLoadError> {
+        match component.parent {
+            0 => Ok(None),
+            parent => self
+                .get_component(parent)
+                .map(Some)
+                .ok_or(SandboxLoadError::MissingParent {
+                    component: index,
+                    parent,
+                }),
         }
     }
 
-    fn deserialize_wire(&self, wire: &blotter::Wire) -> super::WireInfo {
-        super::WireInfo {
-            a: self.deserialize_peg_address(&wire.start_peg),
-            b: self.deserialize_peg_address(&wire.end_peg),
-            net_id: super::NetId::from_raw(wire.circuit_state_id),
+    pub(super) fn deserialize_wire(
+        &self,
+        index: usize,
+        wire: &blotter::Wire,
+    ) -> Result<super::WireInfo, SandboxLoadError> {
+        Ok(super::WireInfo {
+            a: self.deserialize_peg_address(index, &wire.start_peg)?,
+            b: self.deserialize_peg_address(index, &wire.end_peg)?,
+            net_id: self.get_net(wire.circuit_state_id).ok_or(
+                SandboxLoadError::WireStateOutOfRange {
+                    wire: index,
+                    circuit_state_id: wire.circuit_state_id,
+                },
+            )?,
             rotation: wire.rotation,
-        }
+        })
     }
 
-    fn deserialize_peg_address(&self, addr: &blotter::PegAddress) -> super::PegAddress {
-        super::PegAddress {
+    fn deserialize_peg_address(
+        &self,
+        index: usize,
+        addr: &blotter::PegAddress,
+    ) -> Result<super::PegAddress, SandboxLoadError> {
+        Ok(super::PegAddress {
             peg_type: addr.peg_type.into(),
-            component: self.get_component(addr.component_address),
-            peg_index: addr.peg_index.try_into().unwrap(),
-        }
+            component: self.get_component(addr.component_address).ok_or(
+                SandboxLoadError::MissingWireEndpoint {
+                    wire: index,
+                    address: addr.component_address,
+                },
+            )?,
+            peg_index: addr
+                .peg_index
+                .try_into()
+                .map_err(|_| SandboxLoadError::InvalidPegIndex { wire: index })?,
+        })
     }
 }
 
-impl From<&blotter::BlotterFile> for super::Sandbox {
-    fn from(file: &blotter::BlotterFile) -> Self {
-        let mut de = Deserializer::new();
+impl TryFrom<&blotter::BlotterFile> for super::Sandbox {
+    type Error = SandboxLoadError;
 
+    fn try_from(file: &blotter::BlotterFile) -> Result<Self, SandboxLoadError> {
+        Self::from_file(file).map(|(sandbox, _)| sandbox)
+    }
+}
+
+impl super::Sandbox {
+    /// Load a save, like the `TryFrom` conversion, and also report how its
+    /// component types were renumbered.
+    ///
+    /// Types are only renumbered if the save's type table is inconsistent,
+    /// listing the same type or the same numeric ID more than once. See
+    /// [`register_component_types`](Self::register_component_types).
+    pub fn from_file(
+        file: &blotter::BlotterFile,
+    ) -> Result<(Self, super::TypeRegistration), SandboxLoadError> {
+        Self::load(file).map(|(sandbox, types, _)| (sandbox, types))
+    }
+
+    fn load(
+        file: &blotter::BlotterFile,
+    ) -> Result<(Self, super::TypeRegistration, Vec<SandboxLoadWarning>), SandboxLoadError> {
         // Instead of building the sandbox and all the internal cross-references
         // from scratch, re-use the sandbox API as much as possible when loading
         // so there is just one implementation of the cross-referencing.
-        let mut sandbox = super::Sandbox::with_meta_info(
-            file.component_types
-                .iter()
-                .map(|ctype| (ctype.text_id.clone(), ctype.numeric_id))
-                .collect(),
-            file.mods.clone(),
-        );
-
-        match &file.circuit_states {
-            blotter::CircuitStates::WorldFormat { circuit_states } => {
-                sandbox.net_states = BitVec::from_slice(circuit_states);
-                for _ in 0..8 * circuit_states.len() {
-                    sandbox.nets.insert(super::NetInfo {
-                        wires: HashSet::new(),
-                        pegs: HashSet::new(),
-                    });
-                }
-            }
-            _ => panic!("unsupported circuit state format"),
+        let (mut sandbox, types) = Self::for_file(file)?;
+        for _ in 0..sandbox.net_states.len() {
+            sandbox.nets.insert(super::NetInfo {
+                wires: SmallSet::new(),
+                pegs: SmallSet::new(),
+            });
         }
+        let mut de = Deserializer::new(sandbox.nets.len(), types);
 
-        for component in &file.components {
-            let id = sandbox.insert_component(de.deserialize_component(component));
+        for (index, component) in file.components.iter().enumerate() {
+            let info = de.deserialize_component(index, component)?;
+            // Check the address before inserting, so a duplicate can't
+            // replace the component that children refer to.
+            if de.get_component(component.address).is_some() {
+                return Err(SandboxLoadError::DuplicateAddress {
+                    component: index,
+                    address: component.address,
+                });
+            }
+            let id = sandbox.insert_component(info);
             de.register_component(component.address, id);
+            Arc::make_mut(&mut sandbox.addresses).insert(id, component.address);
+        }
+        // Components were inserted as roots, and get their parents once they
+        // all exist. Components of a new sandbox are numbered in save order.
+        let mut parents = Vec::with_capacity(file.components.len());
+        for (index, component) in file.components.iter().enumerate() {
+            let parent = de.deserialize_parent(index, component)?;
+            parents.push(parent.map(|parent| parent.0.into_raw()));
+        }
+        let mut warnings = check_parents(file, &parents)?;
+        for (index, parent) in parents.into_iter().enumerate() {
+            if let Some(parent) = parent {
+                sandbox.link_parent(
+                    super::ComponentId(Address::from_raw(index)),
+                    super::ComponentId(Address::from_raw(parent)),
+                );
+            }
         }
-        for wire in &file.wires {
-            // TODO bubble error
-            let info = de.deserialize_wire(wire);
-            sandbox
+        for (index, wire) in file.wires.iter().enumerate() {
+            let info = de.deserialize_wire(index, wire)?;
+            let (Some(peg_a), Some(peg_b)) = (sandbox.get_peg(&info.a), sandbox.get_peg(&info.b))
+            else {
+                return Err(SandboxLoadError::InvalidPegIndex { wire: index });
+            };
+            if info.a.peg_type == super::PegType::Output
+                && info.b.peg_type == super::PegType::Output
+            {
+                return Err(SandboxLoadError::OutputToOutput { wire: index });
+            }
+            // A wire from an output must be in the output's net; a wire
+            // between inputs must be in the net of both inputs.
+            let consistent = (info.a.peg_type == super::PegType::Output
+                || info.net_id == peg_b.net_id)
+                && (info.b.peg_type == super::PegType::Output || info.net_id == peg_a.net_id);
+            if !consistent {
+                return Err(SandboxLoadError::WireStateMismatch { wire: index });
+            }
+            if info.a == info.b {
+                warnings.push(SandboxLoadWarning::SelfLoop { wire: index });
+                continue;
+            }
+            let added = sandbox
                 .insert_wire(info.a, info.b, info.rotation, Some(info.net_id))
-                .unwrap();
+                .map_err(|_| SandboxLoadError::InvalidPegIndex { wire: index })?;
+            if !added.is_new() {
+                warnings.push(SandboxLoadWarning::DuplicateWire { wire: index });
+            }
         }
 
-        sandbox
+        Ok((sandbox, de.types, warnings))
+    }
+
+    /// Make a component that was loaded as a root a child of `parent`.
+    fn link_parent(&mut self, id: super::ComponentId, parent: super::ComponentId) {
+        Arc::make_mut(&mut self.root_components).remove(&id);
+        self.components.get_mut(id.0).unwrap().parent = Some(parent);
+        self.components
+            .get_mut(parent.0)
+            .unwrap()
+            .children
+            .insert(id);
+    }
+
+    /// Load a save like [`from_file`](Self::from_file), and also return the
+    /// component that each address of the save was loaded as, along with
+    /// anything unusual about the save.
+    ///
+    /// Components may come before their parents, which the game doesn't
+    /// allow, but some tools write anyway:
+    ///
+    /// ```
+    /// use blotter::latest::BlotterFile;
+    /// use blotter::sandbox::{component::Peg, Sandbox, SandboxLoadWarning};
+    ///
+    /// let mut original = Sandbox::new();
+    /// let parent = original.add_component(&Peg::new().build());
+    /// original.add_component(&Peg::new().build().parent(Some(parent)));
+    /// let mut file = BlotterFile::from(&original);
+    /// file.components.reverse();
+    ///
+    /// let load = Sandbox::from_file_with_addresses(&file).unwrap();
+    /// assert_eq!(
+    ///     load.warnings,
+    ///     [SandboxLoadWarning::ChildBeforeParent { component: 0, parent: file.components[1].address }],
+    /// );
+    /// let child = load.address_map[&file.components[0].address];
+    /// let parent = load.address_map[&file.components[1].address];
+    /// assert_eq!(load.sandbox.component(child).unwrap().parent(), Some(parent));
+    /// ```
+    ///
+    /// ```
+    /// use blotter::latest::BlotterFile;
+    /// use blotter::sandbox::{component::Peg, Sandbox};
+    ///
+    /// let mut original = Sandbox::new();
+    /// for _ in 0..3 {
+    ///     original.add_component(&Peg::new().build());
+    /// }
+    /// let file = BlotterFile::from(&original);
+    ///
+    /// let load = Sandbox::from_file_with_addresses(&file).unwrap();
+    /// let id = load.address_map[&file.components[1].address];
+    /// assert_eq!(load.sandbox.loaded_address(id), Some(file.components[1].address));
+    /// assert_eq!(load.address_map.len(), 3);
+    /// ```
+    pub fn from_file_with_addresses(
+        file: &blotter::BlotterFile,
+    ) -> Result<SandboxLoad, SandboxLoadError> {
+        let (sandbox, types, warnings) = Self::load(file)?;
+        Ok(SandboxLoad {
+            address_map: sandbox.address_map(),
+            sandbox,
+            types,
+            warnings,
+        })
+    }
+
+    /// The component with each address of the save that the sandbox was
+    /// loaded from, leaving out those that have been removed since.
+    ///
+    /// This is the reverse of [`loaded_address`](Self::loaded_address), and
+    /// works however the sandbox was loaded.
+    pub fn address_map(&self) -> HashMap<u32, super::ComponentId> {
+        self.addresses
+            .iter()
+            .map(|(&id, &address)| (address, id))
+            .collect()
+    }
+
+    /// An empty sandbox with the mods, component types, circuit states and
+    /// save type of a save, but no nets yet; there is one net to create for
+    /// each circuit state.
+    pub(super) fn for_file(
+        file: &blotter::BlotterFile,
+    ) -> Result<(Self, super::TypeRegistration), SandboxLoadError> {
+        let mut sandbox = super::Sandbox::with_meta_info(TypeTable::default(), file.mods.clone());
+        let types = sandbox
+            .register_component_types(&file.component_types)
+            .map_err(|_| SandboxLoadError::TooManyComponentTypes)?;
+        sandbox.extra = file.extra.clone();
+        sandbox.trailing = file.trailing.clone();
+
+        match &file.circuit_states {
+            blotter::CircuitStates::WorldFormat { circuit_states } => {
+                sandbox.net_states = BitVec::from_slice(circuit_states);
+            }
+            blotter::CircuitStates::SubassemblyFormat { on_states } => {
+                // Subassemblies only list the states that are on, so size the
+                // nets to cover every referenced state, and any stale states
+                // that are listed as on.
+                let on_states: Vec<usize> = on_states
+                    .iter()
+                    .filter_map(|&id| usize::try_from(id).ok())
+                    .collect();
+                let num_nets = on_states
+                    .iter()
+                    .map(|&id| id + 1)
+                    .max()
+                    .unwrap_or(0)
+                    .max(file.referenced_states());
+                sandbox.net_states = BitVec::repeat(false, num_nets);
+                for id in on_states {
+                    sandbox.net_states.set(id, true);
+                }
+            }
+        }
+        sandbox.save_type = file.save_type;
+        Ok((sandbox, types))
     }
 }
 
@@ -269,3 +1151,142 @@ impl From<blotter::PegType> for super::PegType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sandbox::component::Delayer;
+    use crate::sandbox::{PegAddress, PegType, Sandbox, WireProblem};
+
+    /// A sandbox with two wires, the second of which goes to an input that
+    /// its component doesn't have.
+    fn with_invalid_wire() -> (Sandbox, super::super::WireId, PegAddress) {
+        let mut sandbox = Sandbox::new();
+        let a = sandbox.add_component(&Delayer::new().build());
+        let b = sandbox.add_component(&Delayer::new().build());
+        let peg = |component, peg_type| PegAddress {
+            component,
+            peg_type,
+            peg_index: 0,
+        };
+        sandbox
+            .add_wire(peg(a, PegType::Output), peg(b, PegType::Input), 0.0)
+            .unwrap();
+        let invalid = sandbox
+            .add_wire(peg(b, PegType::Output), peg(a, PegType::Input), 0.0)
+            .unwrap();
+        let wire = sandbox.wires.get_mut(invalid.0).unwrap();
+        wire.b.peg_index = 3;
+        let missing = wire.b;
+        (sandbox, invalid, missing)
+    }
+
+    #[test]
+    fn self_parented_component_fails_to_load() {
+        let mut sandbox = Sandbox::new();
+        sandbox.add_component(&Delayer::new().build());
+        sandbox.add_component(&Delayer::new().build());
+        let mut file = blotter::BlotterFile::from(&sandbox);
+        file.components[1].parent = file.components[1].address;
+        assert_eq!(
+            Sandbox::try_from(&file).unwrap_err(),
+            SandboxLoadError::ParentCycle { component: 1 }
+        );
+        #[cfg(feature = "rayon")]
+        assert_eq!(
+            Sandbox::from_file_parallel(&file).unwrap_err(),
+            SandboxLoadError::ParentCycle { component: 1 }
+        );
+    }
+
+    #[test]
+    fn conflicting_type_with_highest_id_gets_a_free_id() {
+        let mut sandbox = Sandbox::new();
+        let a = sandbox.add_component(&Delayer::new().build());
+        let mut file = blotter::BlotterFile::from(&sandbox);
+        file.component_types = vec![
+            crate::latest::ComponentType {
+                numeric_id: u16::MAX,
+                text_id: "MHG.Delayer".into(),
+            },
+            crate::latest::ComponentType {
+                numeric_id: u16::MAX,
+                text_id: "MHG.Inverter".into(),
+            },
+        ];
+        file.components[0].type_id = u16::MAX;
+        let (loaded, types) = Sandbox::from_file(&file).unwrap();
+        assert_eq!(loaded.type_name(u16::MAX), Some("MHG.Delayer"));
+        let inverter = loaded.type_id("MHG.Inverter").unwrap();
+        assert_ne!(inverter, u16::MAX);
+        assert_eq!(types.remaps.len(), 1);
+        assert_eq!(types.remaps[0].to, inverter);
+        assert_eq!(loaded.component(a).unwrap().type_name(), "MHG.Delayer");
+    }
+
+    #[test]
+    fn too_many_component_types_fail_to_load() {
+        let mut file = blotter::BlotterFile::from(&Sandbox::new());
+        file.component_types = (0..=u16::MAX)
+            .map(|numeric_id| crate::latest::ComponentType {
+                numeric_id,
+                text_id: format!("Test.Type{numeric_id}"),
+            })
+            .collect();
+        file.component_types.push(crate::latest::ComponentType {
+            numeric_id: 0,
+            text_id: "Test.OneTooMany".into(),
+        });
+        assert_eq!(
+            Sandbox::try_from(&file).unwrap_err(),
+            SandboxLoadError::TooManyComponentTypes
+        );
+    }
+
+    #[test]
+    fn invalid_wires_fail_to_save() {
+        let (sandbox, wire, peg) = with_invalid_wire();
+        let error = sandbox.try_to_file(&SaveOptions::new()).unwrap_err();
+        assert_eq!(
+            error.invalid_wires,
+            [InvalidWire {
+                wire,
+                problem: WireProblem::PegOutOfRange { peg, num_pegs: 1 },
+            }]
+        );
+        assert_eq!(
+            error.to_string(),
+            "1 wires can't be saved: wire connects to input 3 of a component with 1 inputs"
+        );
+        assert!(matches!(
+            sandbox.write_to(Vec::new()),
+            Err(Error::SandboxSave(_))
+        ));
+        assert!(sandbox.try_to_versioned_file(&SaveOptions::new()).is_err());
+    }
+
+    #[test]
+    fn invalid_wires_are_dropped() {
+        let (sandbox, _, _) = with_invalid_wire();
+        let options = SaveOptions::new().drop_invalid_wires(true);
+        let file = sandbox.try_to_file(&options).unwrap();
+        assert_eq!(file.wires.len(), 1);
+        assert_eq!(sandbox.to_file(&SaveOptions::new()), file);
+        Sandbox::try_from(&file).unwrap();
+    }
+
+    #[test]
+    fn wires_to_missing_components_are_dropped() {
+        let (mut sandbox, wire, _) = with_invalid_wire();
+        let removed = sandbox.add_component(&Delayer::new().build());
+        sandbox.components.remove(removed.0);
+        Arc::make_mut(&mut sandbox.root_components).remove(&removed);
+        sandbox.wires.get_mut(wire.0).unwrap().b.component = removed;
+        let invalid = sandbox.invalid_wires();
+        assert!(matches!(
+            invalid[0].problem,
+            WireProblem::MissingComponent(_)
+        ));
+        assert_eq!(blotter::BlotterFile::from(&sandbox).wires.len(), 1);
+    }
+}
@@ -0,0 +1,140 @@
+//! Experimental support for editing parts of a sandbox in parallel.
+
+use super::{ComponentId, ComponentInfo, NetId, PegAddress, PegInfo, Remap, Sandbox, WireId};
+use crate::misc::small_set::SmallSet;
+
+impl Sandbox {
+    /// Create an empty sandbox that can later be merged into this one.
+    ///
+    /// A shard is an ordinary [`Sandbox`] that shares its component type
+    /// table with the sandbox it was created from. Shards are independent of
+    /// each other, so they can be built on separate threads and merged back
+    /// in when they are done:
+    ///
+    /// ```
+    /// use blotter::sandbox::{ComponentBuilder, Sandbox};
+    ///
+    /// let mut sandbox = Sandbox::new();
+    /// let boards: Vec<_> = (0..4)
+    ///     .map(|_| sandbox.add_component(&ComponentBuilder::new("MHG.CircuitBoard")))
+    ///     .collect();
+    ///
+    /// let shards: Vec<Sandbox> = std::thread::scope(|scope| {
+    ///     let handles: Vec<_> = boards
+    ///         .iter()
+    ///         .map(|_| {
+    ///             let mut shard = sandbox.new_shard();
+    ///             scope.spawn(move || {
+    ///                 for _ in 0..100 {
+    ///                     shard.add_component(&ComponentBuilder::new("MHG.Inverter"));
+    ///                 }
+    ///                 shard
+    ///             })
+    ///         })
+    ///         .collect();
+    ///     handles.into_iter().map(|h| h.join().unwrap()).collect()
+    /// });
+    ///
+    /// for (board, shard) in boards.iter().zip(shards) {
+    ///     sandbox.merge_shard(shard, Some(*board));
+    /// }
+    /// assert_eq!(sandbox.component_ids().count(), 404);
+    /// ```
+    ///
+    /// Wires between shards can only be added after they have been merged,
+    /// using the IDs from the [`Remap`] returned by [`Sandbox::merge_shard`].
+    pub fn new_shard(&self) -> Sandbox {
+        Sandbox::with_meta_info(self.component_types.clone(), self.mods.clone())
+    }
+
+    /// Move all components and wires from `shard` into this sandbox.
+    ///
+    /// Root components of the shard become children of `parent`, or root
+    /// components of this sandbox if `parent` is `None`. Every component and
+    /// wire gets a new ID; the returned [`Remap`] translates them.
+    ///
+    /// Although intended for shards created with [`Sandbox::new_shard`], this
+    /// works with any sandbox. Component types and mods that this sandbox
+    /// doesn't have yet are added.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the shard has component types that this sandbox doesn't,
+    /// and there aren't enough free type IDs left for them.
+    pub fn merge_shard(&mut self, shard: Sandbox, parent: Option<ComponentId>) -> Remap {
+        let mut remap = Remap::default();
+
+        for mod_info in &shard.mods {
+            if !self.mods.iter().any(|m| m.mod_id == mod_info.mod_id) {
+                self.mods.push(mod_info.clone());
+            }
+        }
+        let type_map: Vec<(u16, u16)> = shard
+            .component_types
+            .iter()
+            .map(|(name, num)| (num, self.get_component_type(name)))
+            .collect();
+        let map_type = |type_id: u16| {
+            type_map
+                .iter()
+                .find(|(old, _)| *old == type_id)
+                .map_or(type_id, |(_, new)| *new)
+        };
+
+        let net_map: Vec<NetId> = (0..shard.nets.len())
+            .map(|index| {
+                let net_id = self.make_net();
+                self.net_states
+                    .set(net_id.0.into_raw(), shard.net_states[index]);
+                net_id
+            })
+            .collect();
+        let map_peg = |peg: &PegInfo| PegInfo {
+            net_id: net_map[peg.net_id.0.into_raw()],
+            wires: SmallSet::new(),
+        };
+
+        // Parents must be inserted before their children.
+        for root in shard.root_components() {
+            for old_id in std::iter::once(root).chain(shard.descendants(root)) {
+                let info = shard.components.get(old_id.0).unwrap();
+                let new_id = self.insert_component(ComponentInfo {
+                    type_id: map_type(info.type_id),
+                    parent: match info.parent {
+                        Some(old_parent) => Some(remap.component(old_parent)),
+                        None => parent,
+                    },
+                    position: info.position,
+                    rotation: info.rotation,
+                    children: SmallSet::new(),
+                    inputs: info.inputs.iter().map(map_peg).collect(),
+                    outputs: info.outputs.iter().map(map_peg).collect(),
+                    custom_data: info.custom_data.clone(),
+                });
+                remap.components.insert(old_id, new_id);
+            }
+        }
+
+        let map_address = |addr: PegAddress| PegAddress {
+            component: remap.component(addr.component),
+            ..addr
+        };
+        let mut wires = Vec::new();
+        for (address, wire) in shard.wires.iter() {
+            let new_id = self
+                .insert_wire(
+                    map_address(wire.a),
+                    map_address(wire.b),
+                    wire.rotation,
+                    Some(net_map[wire.net_id.0.into_raw()]),
+                )
+                .unwrap()
+                .id();
+            wires.push((WireId(address), new_id));
+        }
+        remap.wires.extend(wires);
+        self.copy_annotations(&shard, &remap);
+
+        remap
+    }
+}
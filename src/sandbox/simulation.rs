@@ -0,0 +1,438 @@
+//! A tick-by-tick logic simulation of the vanilla components.
+
+use std::collections::HashMap;
+
+use crate::custom_data::{self, ComponentData};
+
+use super::bridges::{Bridge, BridgeModel};
+use super::{find_root, ComponentId, ComponentInfo, NetId, PegAddress, PegType, Sandbox};
+
+/// What a component does each tick.
+#[derive(Debug, Clone)]
+pub(super) enum Logic {
+    /// On when no input is on. Without inputs, this is a constant.
+    Inverter,
+    And,
+    /// On when an odd number of inputs are on.
+    Xor,
+    Buffer,
+    /// Follows its input once the input has been steady for `delay` ticks.
+    Delayer {
+        delay: u32,
+        timer: u32,
+    },
+    /// Follows its first input while the second one is on.
+    DLatch,
+    /// Keeps its outputs as they are, like switches, keys and randomizers,
+    /// or components this simulation doesn't know.
+    Hold,
+}
+
+impl Logic {
+    /// The logic of a component with outputs.
+    pub(super) fn of(type_name: &str, info: &ComponentInfo) -> Self {
+        match (type_name, info.inputs.len()) {
+            ("MHG.Inverter", _) => Self::Inverter,
+            ("MHG.AndGate", 1..) => Self::And,
+            ("MHG.XorGate", 1..) => Self::Xor,
+            ("MHG.Buffer" | "MHG.Buffer_WithOutput", 1) => Self::Buffer,
+            ("MHG.Delayer", 1) => info
+                .custom_data
+                .as_deref()
+                .and_then(|mut data| custom_data::Delayer::read(&mut data).ok())
+                .map_or(Self::Buffer, |delayer| Self::Delayer {
+                    delay: delayer.delay,
+                    timer: delayer.timer.min(delayer.delay),
+                }),
+            ("MHG.DLatch", 2) => Self::DLatch,
+            _ => Self::Hold,
+        }
+    }
+
+    /// How many ticks the outputs take to follow the inputs, or `None` if
+    /// they don't.
+    pub(super) fn ticks(&self) -> Option<u64> {
+        match self {
+            Self::Delayer { delay, .. } => Some(u64::from(*delay).max(1)),
+            Self::Hold => None,
+            _ => Some(1),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Gate {
+    id: ComponentId,
+    logic: Logic,
+    inputs: Vec<usize>,
+    outputs: Vec<usize>,
+}
+
+/// A logic simulation of a sandbox, one game tick at a time.
+///
+/// Every gate takes one tick to respond to its inputs, and delayers take
+/// their configured number of ticks. A net of input pegs is on when any
+/// output wired to it is on, and relays and through pegs join nets as
+/// [`BridgeModel::game`] describes. Switches, buttons, keys, randomizers
+/// and modded components keep the output states they were saved with,
+/// unless they are [`set`](Self::set).
+///
+/// The simulation starts from the states of the sandbox, and doesn't change
+/// the sandbox until [`write_states`](Self::write_states) is called.
+///
+/// ```
+/// use blotter::sandbox::{ComponentBuilder, PegAddress, PegType, Sandbox, Simulation};
+///
+/// let mut sandbox = Sandbox::new();
+/// let a = sandbox.add_component(&ComponentBuilder::new("MHG.Inverter").num_inputs(1).num_outputs(1));
+/// let b = sandbox.add_component(&ComponentBuilder::new("MHG.Inverter").num_inputs(1).num_outputs(1));
+/// let output = |component| PegAddress { component, peg_type: PegType::Output, peg_index: 0 };
+/// let input = |component| PegAddress { component, peg_type: PegType::Input, peg_index: 0 };
+/// sandbox.add_wire(output(a), input(b), 0.0).unwrap();
+///
+/// let mut simulation = Simulation::new(&sandbox);
+/// assert_eq!(simulation.settle(10), Some(2));
+/// assert_eq!(simulation.peg(output(a)), Some(true));
+/// assert_eq!(simulation.peg(output(b)), Some(false));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    gates: Vec<Gate>,
+    /// The state of every net, by raw net index.
+    states: Vec<bool>,
+    /// Whether each net has an output peg, so its state is set by a gate.
+    is_output: Vec<bool>,
+    /// The output nets wired to each net of inputs.
+    drivers: Vec<Vec<usize>>,
+    /// Pairs of nets that are always joined.
+    joined: Vec<(usize, usize)>,
+    /// Control nets, and the nets they join while on.
+    switched: Vec<(usize, Vec<usize>)>,
+    /// The net of every peg, by component.
+    pegs: HashMap<ComponentId, (Vec<usize>, Vec<usize>)>,
+    /// Nets held at a state by [`set`](Self::set).
+    forced: HashMap<usize, bool>,
+    ticks: u64,
+}
+
+impl Simulation {
+    pub fn new(sandbox: &Sandbox) -> Self {
+        let num_nets = sandbox.nets.len();
+        let index = |net_id: NetId| net_id.0.into_raw();
+
+        let mut is_output = vec![false; num_nets];
+        let mut drivers = vec![Vec::new(); num_nets];
+        for (_, wire) in sandbox.wires.iter() {
+            let input = match (wire.a.peg_type, wire.b.peg_type) {
+                (PegType::Output, _) => wire.b,
+                (_, PegType::Output) => wire.a,
+                _ => continue,
+            };
+            let input_net = sandbox.get_peg(&input).unwrap().net_id;
+            drivers[index(input_net)].push(index(wire.net_id));
+        }
+        for net in &mut drivers {
+            net.sort_unstable();
+            net.dedup();
+        }
+
+        let mut gates = Vec::new();
+        let mut pegs = HashMap::new();
+        let mut ids: Vec<ComponentId> = sandbox.component_ids().collect();
+        ids.sort_unstable();
+        for id in ids {
+            let info = sandbox.components.get(id.0).unwrap();
+            let inputs: Vec<usize> = info.inputs.iter().map(|peg| index(peg.net_id)).collect();
+            let outputs: Vec<usize> = info.outputs.iter().map(|peg| index(peg.net_id)).collect();
+            for &net in &outputs {
+                is_output[net] = true;
+            }
+            if !outputs.is_empty() {
+                let type_name = sandbox.component_types.name_str(info.type_id);
+                let logic = Logic::of(type_name, info);
+                gates.push(Gate {
+                    id,
+                    logic,
+                    inputs: inputs.clone(),
+                    outputs: outputs.clone(),
+                });
+            }
+            if !inputs.is_empty() || !outputs.is_empty() {
+                pegs.insert(id, (inputs, outputs));
+            }
+        }
+
+        let mut joined = Vec::new();
+        let mut switched = Vec::new();
+        for (type_name, bridges) in &BridgeModel::game().bridges {
+            for id in sandbox.components_of_type(type_name) {
+                let Some((inputs, _)) = pegs.get(&id) else {
+                    continue;
+                };
+                for bridge in bridges {
+                    match bridge {
+                        Bridge::Always(contacts) => {
+                            let nets: Vec<usize> = contacts
+                                .iter()
+                                .filter_map(|&i| inputs.get(i).copied())
+                                .collect();
+                            joined.extend(nets.windows(2).map(|pair| (pair[0], pair[1])));
+                        }
+                        Bridge::Switched { control, contacts } => {
+                            if let Some(&control) = inputs.get(*control) {
+                                let nets = contacts
+                                    .iter()
+                                    .filter_map(|&i| inputs.get(i).copied())
+                                    .collect();
+                                switched.push((control, nets));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let states = (0..num_nets).map(|net| sandbox.net_states[net]).collect();
+        let mut simulation = Self {
+            gates,
+            states,
+            is_output,
+            drivers,
+            joined,
+            switched,
+            pegs,
+            forced: HashMap::new(),
+            ticks: 0,
+        };
+        simulation.update_inputs();
+        simulation
+    }
+
+    /// The number of ticks that have been simulated.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Whether a peg is on, or `None` if it isn't in the simulated sandbox.
+    pub fn peg(&self, peg: PegAddress) -> Option<bool> {
+        self.peg_net(peg).map(|net| self.states[net])
+    }
+
+    /// Hold the net of a peg on or off, whatever drives it, until it is
+    /// [released](Self::release), like a switch that is flipped from
+    /// outside. This takes effect immediately, without a tick.
+    ///
+    /// Returns `false` if the peg isn't in the simulated sandbox.
+    pub fn set(&mut self, peg: PegAddress, value: bool) -> bool {
+        let Some(net) = self.peg_net(peg) else {
+            return false;
+        };
+        self.forced.insert(net, value);
+        self.states[net] = value;
+        self.update_inputs();
+        true
+    }
+
+    /// Stop holding the net of a peg, so that it follows the circuit again
+    /// from the next tick.
+    pub fn release(&mut self, peg: PegAddress) {
+        if let Some(net) = self.peg_net(peg) {
+            self.forced.remove(&net);
+        }
+    }
+
+    fn peg_net(&self, peg: PegAddress) -> Option<usize> {
+        let (inputs, outputs) = self.pegs.get(&peg.component)?;
+        match peg.peg_type {
+            PegType::Input => inputs.get(peg.peg_index).copied(),
+            PegType::Output => outputs.get(peg.peg_index).copied(),
+        }
+    }
+
+    /// Simulate one tick, returning whether anything changed.
+    pub fn step(&mut self) -> bool {
+        self.ticks += 1;
+        let mut changed = false;
+        let mut updates = Vec::new();
+        for gate in &mut self.gates {
+            let input = |index: usize| gate.inputs.get(index).is_some_and(|&net| self.states[net]);
+            let any_on = gate.inputs.iter().any(|&net| self.states[net]);
+            let value = match &mut gate.logic {
+                Logic::Inverter => !any_on,
+                Logic::And => gate.inputs.iter().all(|&net| self.states[net]),
+                Logic::Xor => gate.inputs.iter().filter(|&&net| self.states[net]).count() % 2 == 1,
+                Logic::Buffer => input(0),
+                Logic::Delayer { delay, timer } => {
+                    let before = *timer;
+                    let output = self.states[gate.outputs[0]];
+                    let mut flip = false;
+                    if input(0) != output {
+                        *timer += 1;
+                        if *timer >= *delay {
+                            *timer = 0;
+                            flip = true;
+                        }
+                    } else {
+                        *timer = 0;
+                    }
+                    changed |= *timer != before;
+                    output ^ flip
+                }
+                Logic::DLatch => {
+                    if input(1) {
+                        input(0)
+                    } else {
+                        self.states[gate.outputs[0]]
+                    }
+                }
+                Logic::Hold => continue,
+            };
+            updates.extend(gate.outputs.iter().map(|&net| (net, value)));
+        }
+        for (net, value) in updates {
+            if self.forced.contains_key(&net) {
+                continue;
+            }
+            changed |= self.states[net] != value;
+            self.states[net] = value;
+        }
+        self.update_inputs();
+        changed
+    }
+
+    /// Simulate `ticks` ticks.
+    pub fn run(&mut self, ticks: u64) {
+        for _ in 0..ticks {
+            self.step();
+        }
+    }
+
+    /// Simulate until nothing changes any more, returning the number of
+    /// ticks that changed something, or `None` if the circuit is still
+    /// changing after `max_ticks`, like a clock.
+    pub fn settle(&mut self, max_ticks: u64) -> Option<u64> {
+        (0..max_ticks).find(|_| !self.step())
+    }
+
+    /// Store the simulated net states, and the timers of delayers, in the
+    /// sandbox.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sandbox isn't the one the simulation was made from, or
+    /// if its components or wires have changed since.
+    pub fn write_states(&self, sandbox: &mut Sandbox) {
+        assert_eq!(
+            sandbox.nets.len(),
+            self.states.len(),
+            "the simulated sandbox should not have changed"
+        );
+        for (net, &state) in self.states.iter().enumerate() {
+            sandbox.net_states.set(net, state);
+        }
+        for gate in &self.gates {
+            if let Logic::Delayer { delay, timer } = gate.logic {
+                let mut data = Vec::new();
+                custom_data::Delayer { timer, delay }
+                    .write(&mut data)
+                    .unwrap();
+                sandbox.components.get_mut(gate.id.0).unwrap().custom_data = Some(data);
+            }
+        }
+    }
+
+    /// Recompute the state of every net of inputs from the outputs wired to
+    /// it and the nets joined to it.
+    fn update_inputs(&mut self) {
+        for net in 0..self.states.len() {
+            if !self.is_output[net] {
+                self.states[net] = self.drivers[net].iter().any(|&driver| self.states[driver]);
+            }
+        }
+        self.apply_forced();
+        if self.joined.is_empty() && self.switched.is_empty() {
+            return;
+        }
+
+        // A relay that closes can turn on its contacts' nets, which can
+        // close more relays, so repeat until no more relays close.
+        let mut parents = HashMap::new();
+        for &(a, b) in &self.joined {
+            union(&mut parents, a, b);
+        }
+        let mut closed = vec![false; self.switched.len()];
+        loop {
+            let groups = group_states(&mut parents, &self.states);
+            let state = |net: usize| groups.get(&net).copied().unwrap_or(self.states[net]);
+            let mut any_closed = false;
+            for (i, (control, contacts)) in self.switched.iter().enumerate() {
+                if !closed[i] && state(*control) {
+                    closed[i] = true;
+                    any_closed = true;
+                    for pair in contacts.windows(2) {
+                        union(&mut parents, pair[0], pair[1]);
+                    }
+                }
+            }
+            if !any_closed {
+                for (net, on) in groups {
+                    if !self.is_output[net] {
+                        self.states[net] = on;
+                    }
+                }
+                self.apply_forced();
+                break;
+            }
+        }
+    }
+
+    fn apply_forced(&mut self) {
+        for (&net, &value) in &self.forced {
+            self.states[net] = value;
+        }
+    }
+}
+
+fn union(parents: &mut HashMap<NetId, NetId>, a: usize, b: usize) {
+    let a = find_root(parents, NetId::from_raw(a as i32));
+    let b = find_root(parents, NetId::from_raw(b as i32));
+    if a != b {
+        parents.insert(a, b);
+    }
+}
+
+/// The state of every joined net: on if any net in its group is on.
+fn group_states(parents: &mut HashMap<NetId, NetId>, states: &[bool]) -> HashMap<usize, bool> {
+    let nets: Vec<NetId> = parents
+        .iter()
+        .flat_map(|(&net, &parent)| [net, parent])
+        .collect();
+    let roots: Vec<(usize, NetId)> = nets
+        .into_iter()
+        .map(|net| (net.0.into_raw(), find_root(parents, net)))
+        .collect();
+    let mut on: HashMap<NetId, bool> = HashMap::new();
+    for &(net, root) in &roots {
+        *on.entry(root).or_default() |= states[net];
+    }
+    roots
+        .into_iter()
+        .map(|(net, root)| (net, on[&root]))
+        .collect()
+}
+
+impl Sandbox {
+    /// Simulate the sandbox until it is stable, and store the resulting net
+    /// states, so that it doesn't flicker while the game recomputes
+    /// everything after loading.
+    ///
+    /// Returns the number of ticks it took, or `None` if it was still
+    /// changing after `max_ticks`; the states after the last tick are
+    /// stored either way. See [`Simulation`] for what is simulated.
+    pub fn settle_states(&mut self, max_ticks: u64) -> Option<u64> {
+        let mut simulation = Simulation::new(self);
+        let ticks = simulation.settle(max_ticks);
+        simulation.write_states(self);
+        ticks
+    }
+}
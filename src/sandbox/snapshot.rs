@@ -0,0 +1,51 @@
+//! Checkpointing a sandbox before speculative edits.
+
+use super::Sandbox;
+
+/// A saved copy of a sandbox, to go back to with [`Sandbox::restore`].
+///
+/// The sandbox and its snapshots share their storage, in chunks that are only
+/// copied when one side modifies them. Taking a snapshot is cheap even for
+/// large worlds, and edits after it only pay for the parts they touch.
+///
+/// ```
+/// use blotter::sandbox::{ComponentBuilder, Sandbox};
+///
+/// let mut sandbox = Sandbox::new();
+/// let board = sandbox.add_component(&ComponentBuilder::new("MHG.CircuitBoard"));
+///
+/// let checkpoint = sandbox.snapshot();
+/// sandbox.remove_component(board);
+/// assert_eq!(sandbox.component_ids().count(), 0);
+///
+/// sandbox.restore(&checkpoint);
+/// assert_eq!(sandbox.component_ids().collect::<Vec<_>>(), [board]);
+/// ```
+#[derive(Clone)]
+pub struct SandboxSnapshot {
+    sandbox: Sandbox,
+}
+
+impl SandboxSnapshot {
+    /// A sandbox in the state of the snapshot, sharing its storage.
+    pub fn to_sandbox(&self) -> Sandbox {
+        self.sandbox.clone()
+    }
+}
+
+impl Sandbox {
+    /// Save the current state of the sandbox.
+    pub fn snapshot(&self) -> SandboxSnapshot {
+        SandboxSnapshot {
+            sandbox: self.clone(),
+        }
+    }
+
+    /// Go back to the state of a snapshot, discarding every change since.
+    ///
+    /// Component and wire IDs are also restored, so IDs taken before the
+    /// snapshot refer to the same things again.
+    pub fn restore(&mut self, snapshot: &SandboxSnapshot) {
+        *self = snapshot.sandbox.clone();
+    }
+}
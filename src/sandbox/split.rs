@@ -0,0 +1,122 @@
+//! Splitting a world into one subassembly per circuit board.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::custom_data::{self, ComponentData};
+use crate::geometry::Rotation;
+use crate::latest::SaveType;
+
+use super::subassembly::{copy_trees, copy_wires};
+use super::{ComponentId, PegAddress, Sandbox, WireId};
+
+/// The outcome of [`Sandbox::split_by_board`].
+#[derive(Debug, Clone)]
+pub struct BoardSplit {
+    /// Each top-level circuit board, in ID order, and a subassembly of it
+    /// with everything on it, with the board at the origin.
+    pub boards: Vec<(ComponentId, Sandbox)>,
+    /// Wires that weren't copied into any subassembly, in ID order.
+    pub cut_wires: Vec<CutWire>,
+    /// Top-level components that aren't circuit boards, which were left out
+    /// along with their descendants, in ID order.
+    pub loose: Vec<ComponentId>,
+}
+
+/// A wire that [`Sandbox::split_by_board`] couldn't keep, because its ends
+/// are on different boards, or not on a board at all.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CutWire {
+    pub wire: WireId,
+    pub a: PegAddress,
+    pub b: PegAddress,
+    /// The top-level board of `a` and of `b`, or `None` for an end that isn't
+    /// on a board.
+    pub boards: [Option<ComponentId>; 2],
+}
+
+impl Sandbox {
+    /// Make a subassembly of every top-level circuit board and everything on
+    /// it, like a library of the boards of a world.
+    ///
+    /// IDs in the result, including those in the [`CutWire`]s, are those of
+    /// this sandbox; the components of each subassembly are numbered afresh.
+    /// Nets keep their states, and wires between boards are cut.
+    ///
+    /// ```
+    /// use blotter::sandbox::component::{CircuitBoard, Peg};
+    /// use blotter::sandbox::{PegAddress, PegType, Sandbox};
+    ///
+    /// let mut sandbox = Sandbox::new();
+    /// let mut board_with_peg = |x| {
+    ///     let board = sandbox.add_component(&CircuitBoard::new().build().position([x, 0, 0]));
+    ///     let peg = sandbox.add_component(&Peg::new().build().parent(Some(board)));
+    ///     PegAddress { component: peg, peg_type: PegType::Input, peg_index: 0 }
+    /// };
+    /// let (a, b) = (board_with_peg(0), board_with_peg(3000));
+    /// let wire = sandbox.add_wire(a, b, 0.0).unwrap();
+    ///
+    /// let split = sandbox.split_by_board();
+    /// assert_eq!(split.boards.len(), 2);
+    /// assert_eq!(split.boards[1].1.component_ids().count(), 2);
+    /// assert_eq!(split.cut_wires[0].wire, wire);
+    /// ```
+    pub fn split_by_board(&self) -> BoardSplit {
+        let board_type = self
+            .component_types
+            .get(custom_data::CircuitBoard::TYPE_STRING);
+        let mut boards: Vec<ComponentId> = Vec::new();
+        let mut loose: Vec<ComponentId> = Vec::new();
+        for &root in self.root_components.iter() {
+            let info = self.components.get(root.0).unwrap();
+            if Some(info.type_id) == board_type {
+                boards.push(root);
+            } else {
+                loose.push(root);
+            }
+        }
+        boards.sort_unstable();
+        loose.sort_unstable();
+
+        let mut top_boards: HashMap<ComponentId, ComponentId> = HashMap::new();
+        for &board in &boards {
+            top_boards.insert(board, board);
+            top_boards.extend(self.descendants(board).map(|id| (id, board)));
+        }
+        let mut board_wires: BTreeMap<ComponentId, Vec<WireId>> = BTreeMap::new();
+        let mut cut_wires = Vec::new();
+        for (address, wire) in self.wires.iter() {
+            let id = WireId(address);
+            let ends = [wire.a, wire.b].map(|peg| top_boards.get(&peg.component).copied());
+            match ends {
+                [Some(a), Some(b)] if a == b => board_wires.entry(a).or_default().push(id),
+                boards => cut_wires.push(CutWire {
+                    wire: id,
+                    a: wire.a,
+                    b: wire.b,
+                    boards,
+                }),
+            }
+        }
+        cut_wires.sort_by_key(|cut| cut.wire);
+
+        let boards = boards
+            .into_iter()
+            .map(|board| {
+                let mut part = self.new_shard();
+                part.save_type = SaveType::Subassembly;
+                let remap = copy_trees(self, &mut part, &[board], |_| {
+                    (None, [0; 3], Rotation::IDENTITY.0)
+                });
+                let wires = board_wires.remove(&board).unwrap_or_default();
+                copy_wires(self, &mut part, remap, wires);
+                (board, part)
+            })
+            .collect();
+
+        BoardSplit {
+            boards,
+            cut_wires,
+            loose,
+        }
+    }
+}
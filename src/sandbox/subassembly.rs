@@ -0,0 +1,248 @@
+//! Copying and pasting parts of a sandbox, like the game's subassemblies.
+
+use std::collections::HashSet;
+
+use crate::geometry::{Position, Transform};
+use crate::latest as blotter;
+use crate::misc::small_set::SmallSet;
+
+use super::{
+    ComponentId, ComponentInfo, PegAddress, PegInfo, Remap, Sandbox, SandboxLoadError, WireId,
+};
+
+/// A set of components, with the wires between them and the states of their
+/// nets, that can be pasted into sandboxes.
+///
+/// Root components of a subassembly are placed relative to its origin, like
+/// in the game's subassembly saves:
+///
+/// ```
+/// use blotter::sandbox::component::{CircuitBoard, Peg};
+/// use blotter::sandbox::{Sandbox, Subassembly};
+///
+/// let mut sandbox = Sandbox::new();
+/// let board = sandbox.add_component(&CircuitBoard::new().build().position([3000, 0, 0]));
+/// sandbox.add_component(&Peg::new().build().parent(Some(board)));
+///
+/// let subassembly = Subassembly::from_sandbox_selection(&sandbox, &[board]);
+/// let mut other = Sandbox::new();
+/// let remap = subassembly.paste_into(&mut other, None, [0, 0, 600], [0.0, 0.0, 0.0, 1.0]);
+/// let pasted = remap.component(subassembly.roots()[0]);
+/// assert_eq!(other.component(pasted).unwrap().position(), [0, 0, 600]);
+/// assert_eq!(other.component_ids().count(), 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Subassembly {
+    sandbox: Sandbox,
+}
+
+impl Subassembly {
+    /// Copy the selected components, their descendants, and the wires
+    /// between them.
+    ///
+    /// Selected components that are descendants of other selected
+    /// components, and IDs that don't exist, are skipped. The origin of the
+    /// subassembly is the first remaining component, so that pasting a
+    /// single board puts it exactly where it is pasted. Wires to
+    /// components outside the selection are left out, and every net keeps
+    /// its state.
+    pub fn from_sandbox_selection(sandbox: &Sandbox, selection: &[ComponentId]) -> Self {
+        let roots = selection_roots(sandbox, selection.iter().copied());
+        let origin = roots.first().map_or(Transform::IDENTITY, |&root| {
+            sandbox.world_transform(root).inverse()
+        });
+
+        let mut copy = sandbox.new_shard();
+        copy.save_type = blotter::SaveType::Subassembly;
+        copy_components(sandbox, &mut copy, &roots, |root| {
+            let local = origin.then(&sandbox.world_transform(root));
+            (
+                None,
+                Position::from_meters(local.translation).0,
+                local.rotation,
+            )
+        });
+        Self { sandbox: copy }
+    }
+
+    /// Load a subassembly from a save. World saves work too; their root
+    /// components are placed relative to the world origin.
+    pub fn from_file(file: &blotter::BlotterFile) -> Result<Self, SandboxLoadError> {
+        let mut sandbox = Sandbox::try_from(file)?;
+        sandbox.save_type = blotter::SaveType::Subassembly;
+        Ok(Self { sandbox })
+    }
+
+    /// Serialize the subassembly as a subassembly save.
+    pub fn to_file(&self) -> blotter::BlotterFile {
+        blotter::BlotterFile::from(&self.sandbox)
+    }
+
+    /// The components and wires of the subassembly.
+    ///
+    /// The [`Remap`] returned by [`paste_into`](Self::paste_into) is keyed
+    /// by the IDs in this sandbox.
+    pub fn sandbox(&self) -> &Sandbox {
+        &self.sandbox
+    }
+
+    /// The root components of the subassembly, in ID order.
+    pub fn roots(&self) -> Vec<ComponentId> {
+        let mut roots: Vec<ComponentId> = self.sandbox.root_components().collect();
+        roots.sort_unstable();
+        roots
+    }
+
+    /// Add a copy of the subassembly to a sandbox, with its origin at
+    /// `position` and `rotation` relative to `parent`, or to the world if
+    /// `parent` is `None`.
+    ///
+    /// Component types and mods that the sandbox doesn't have yet are added,
+    /// and the pasted nets keep their states.
+    pub fn paste_into(
+        &self,
+        sandbox: &mut Sandbox,
+        parent: Option<ComponentId>,
+        position: impl Into<[i32; 3]>,
+        rotation: impl Into<[f32; 4]>,
+    ) -> Remap {
+        let placement = Transform::from_local(position.into(), rotation.into());
+        let mut copy = self.sandbox.clone();
+        for root in self.roots() {
+            let mut component = copy.component_mut(root).unwrap();
+            let info = component.get();
+            let local = placement.then(&Transform::from_local(info.position(), info.rotation()));
+            component.set_position(Position::from_meters(local.translation));
+            component.set_rotation(local.rotation);
+        }
+        sandbox.merge_shard(copy, parent)
+    }
+}
+
+/// The components in `ids` that exist and aren't descendants of other
+/// components in `ids`, in the order they first appear.
+pub(super) fn selection_roots(
+    sandbox: &Sandbox,
+    ids: impl IntoIterator<Item = ComponentId> + Clone,
+) -> Vec<ComponentId> {
+    let selected: HashSet<ComponentId> = ids
+        .clone()
+        .into_iter()
+        .filter(|&id| sandbox.is_valid(id))
+        .collect();
+    let mut seen = HashSet::new();
+    ids.into_iter()
+        .filter(|&id| sandbox.is_valid(id))
+        .filter(|&id| {
+            !sandbox
+                .ancestors(id)
+                .any(|ancestor| selected.contains(&ancestor))
+        })
+        .filter(|&id| seen.insert(id))
+        .collect()
+}
+
+/// Copy `roots`, their descendants and the wires between them from `source`
+/// into `target`, which must number component types the same way.
+///
+/// `place` gives the parent, position and rotation of the copy of each root.
+/// Every copied net keeps its state.
+pub(super) fn copy_components<F>(
+    source: &Sandbox,
+    target: &mut Sandbox,
+    roots: &[ComponentId],
+    place: F,
+) -> Remap
+where
+    F: FnMut(ComponentId) -> (Option<ComponentId>, [i32; 3], [f32; 4]),
+{
+    let remap = copy_trees(source, target, roots, place);
+    let wires: Vec<WireId> = source
+        .wires
+        .iter()
+        .filter(|(_, wire)| {
+            remap.components.contains_key(&wire.a.component)
+                && remap.components.contains_key(&wire.b.component)
+        })
+        .map(|(address, _)| WireId(address))
+        .collect();
+    copy_wires(source, target, remap, wires)
+}
+
+/// Copy `roots` and their descendants, without any wires, as for
+/// [`copy_components`].
+pub(super) fn copy_trees<F>(
+    source: &Sandbox,
+    target: &mut Sandbox,
+    roots: &[ComponentId],
+    mut place: F,
+) -> Remap
+where
+    F: FnMut(ComponentId) -> (Option<ComponentId>, [i32; 3], [f32; 4]),
+{
+    let mut remap = Remap::default();
+    for &root in roots {
+        for old_id in std::iter::once(root).chain(source.descendants(root)) {
+            let info = source.components.get(old_id.0).unwrap();
+            let (parent, position, rotation) = if old_id == root {
+                place(root)
+            } else {
+                let parent = remap.component(info.parent.unwrap());
+                (Some(parent), info.position, info.rotation)
+            };
+            let mut copy_peg = |peg: &PegInfo| {
+                let net_id = target.make_net();
+                let state = source.net_states[peg.net_id.0.into_raw()];
+                target.net_states.set(net_id.0.into_raw(), state);
+                PegInfo {
+                    net_id,
+                    wires: SmallSet::new(),
+                }
+            };
+            let inputs = info.inputs.iter().map(&mut copy_peg).collect();
+            let outputs = info.outputs.iter().map(&mut copy_peg).collect();
+            let new_id = target.insert_component(ComponentInfo {
+                type_id: info.type_id,
+                parent,
+                position,
+                rotation,
+                children: SmallSet::new(),
+                inputs,
+                outputs,
+                custom_data: info.custom_data.clone(),
+            });
+            remap.components.insert(old_id, new_id);
+        }
+    }
+
+    remap
+}
+
+/// Copy `wires` from `source`, whose ends must all have been copied into
+/// `target` by [`copy_trees`], and then the annotations of everything that was
+/// copied.
+pub(super) fn copy_wires(
+    source: &Sandbox,
+    target: &mut Sandbox,
+    mut remap: Remap,
+    mut wires: Vec<WireId>,
+) -> Remap {
+    wires.sort_unstable();
+    let map = |addr: PegAddress| PegAddress {
+        component: remap.component(addr.component),
+        ..addr
+    };
+    let new_wires: Vec<(WireId, WireId)> = wires
+        .into_iter()
+        .map(|old_id| {
+            let wire = source.wires.get(old_id.0).unwrap();
+            let new_id = target
+                .add_wire(map(wire.a), map(wire.b), wire.rotation)
+                .expect("copied wires should connect copied pegs");
+            (old_id, new_id)
+        })
+        .collect();
+    remap.wires.extend(new_wires);
+    target.copy_annotations(source, &remap);
+    remap
+}
@@ -0,0 +1,303 @@
+//! Driving a simulated circuit through its ports, for automated tests.
+
+use std::fmt;
+
+use super::{NetId, PegAddress, PegType, Ports, Sandbox, Simulation};
+
+/// A [`Simulation`] driven and checked through named [`Ports`], for testing
+/// circuits in CI.
+///
+/// Expectations don't stop the bench when they fail; every failure is
+/// recorded, with the nets and positions of the pegs involved, and
+/// [`assert_passed`](Self::assert_passed) reports all of them at the end.
+///
+/// ```
+/// use blotter::sandbox::{ComponentBuilder, PegAddress, PegType, Ports, Sandbox, TestBench};
+///
+/// let mut sandbox = Sandbox::new();
+/// let not = sandbox.add_component(&ComponentBuilder::new("MHG.Inverter").num_inputs(1).num_outputs(1));
+/// let mut ports = Ports::new();
+/// ports.insert("a", PegAddress { component: not, peg_type: PegType::Input, peg_index: 0 });
+/// ports.insert("y", PegAddress { component: not, peg_type: PegType::Output, peg_index: 0 });
+///
+/// let mut bench = TestBench::new(&sandbox, ports);
+/// bench
+///     .set("a", false)
+///     .step(1)
+///     .expect("y", true)
+///     .set("a", true)
+///     .step(1)
+///     .expect("y", false);
+/// bench.assert_passed();
+/// ```
+#[derive(Debug, Clone)]
+pub struct TestBench<'a> {
+    sandbox: &'a Sandbox,
+    ports: Ports,
+    simulation: Simulation,
+    failures: Vec<BenchFailure>,
+}
+
+impl<'a> TestBench<'a> {
+    /// Start simulating the sandbox from its saved states.
+    pub fn new(sandbox: &'a Sandbox, ports: Ports) -> Self {
+        Self {
+            sandbox,
+            ports,
+            simulation: Simulation::new(sandbox),
+            failures: Vec::new(),
+        }
+    }
+
+    /// The underlying simulation, to read pegs that aren't ports.
+    pub fn simulation(&self) -> &Simulation {
+        &self.simulation
+    }
+
+    /// Hold a port on or off until it is set again, as with
+    /// [`Simulation::set`].
+    pub fn set(&mut self, port: &str, value: bool) -> &mut Self {
+        match self.ports.get(port) {
+            Some(peg) => {
+                self.simulation.set(peg, value);
+            }
+            None => self.unknown_port(port),
+        }
+        self
+    }
+
+    /// Hold the bits of a bus to the bits of `value`, with `name[0]` as the
+    /// least significant bit.
+    pub fn set_bus(&mut self, bus: &str, value: u64) -> &mut Self {
+        let bits = self.ports.bus(bus);
+        if bits.is_empty() {
+            self.unknown_port(bus);
+        }
+        for (index, peg) in bits {
+            self.simulation.set(peg, bit(value, index));
+        }
+        self
+    }
+
+    /// Stop holding a port, so that it follows the circuit again.
+    pub fn release(&mut self, port: &str) -> &mut Self {
+        match self.ports.get(port) {
+            Some(peg) => self.simulation.release(peg),
+            None => self.unknown_port(port),
+        }
+        self
+    }
+
+    /// Simulate `ticks` ticks.
+    pub fn step(&mut self, ticks: u64) -> &mut Self {
+        self.simulation.run(ticks);
+        self
+    }
+
+    /// Simulate until nothing changes, recording a failure if the circuit is
+    /// still changing after `max_ticks`.
+    pub fn settle(&mut self, max_ticks: u64) -> &mut Self {
+        if self.simulation.settle(max_ticks).is_none() {
+            self.failures.push(BenchFailure {
+                tick: self.simulation.ticks(),
+                port: String::new(),
+                kind: FailureKind::Unsettled { max_ticks },
+            });
+        }
+        self
+    }
+
+    /// Check that a port is on or off, recording a failure if it isn't.
+    pub fn expect(&mut self, port: &str, value: bool) -> &mut Self {
+        match self.ports.get(port) {
+            Some(peg) => self.check(port, vec![(port.to_owned(), 0, peg)], value.into()),
+            None => self.unknown_port(port),
+        }
+        self
+    }
+
+    /// Check the value of a bus, with `name[0]` as the least significant
+    /// bit, recording a failure if it differs.
+    pub fn expect_bus(&mut self, bus: &str, value: u64) -> &mut Self {
+        let bits: Vec<(String, usize, PegAddress)> = self
+            .ports
+            .bus(bus)
+            .into_iter()
+            .map(|(index, peg)| (format!("{bus}[{index}]"), index, peg))
+            .collect();
+        if bits.is_empty() {
+            self.unknown_port(bus);
+        } else {
+            self.check(bus, bits, value);
+        }
+        self
+    }
+
+    /// Every failure so far, in the order they happened.
+    pub fn failures(&self) -> &[BenchFailure] {
+        &self.failures
+    }
+
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Assert that every expectation so far has passed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if anything failed. The panic message lists every failure.
+    #[track_caller]
+    pub fn assert_passed(&self) {
+        if !self.failures.is_empty() {
+            let mut message = format!("test bench had {} failures:", self.failures.len());
+            for failure in &self.failures {
+                message.push_str("\n    ");
+                message.push_str(&failure.to_string().replace('\n', "\n    "));
+            }
+            panic!("{}", message);
+        }
+    }
+
+    fn check(&mut self, port: &str, bits: Vec<(String, usize, PegAddress)>, expected: u64) {
+        let probes: Vec<Probe> = bits
+            .into_iter()
+            .map(|(name, index, peg)| self.probe(name, index, peg))
+            .collect();
+        let actual = probes
+            .iter()
+            .filter(|probe| probe.state && probe.bit < 64)
+            .fold(0, |value, probe| value | 1 << probe.bit);
+        if actual != expected {
+            self.failures.push(BenchFailure {
+                tick: self.simulation.ticks(),
+                port: port.to_owned(),
+                kind: FailureKind::Mismatch {
+                    expected,
+                    actual,
+                    probes,
+                },
+            });
+        }
+    }
+
+    fn probe(&self, name: String, bit: usize, peg: PegAddress) -> Probe {
+        let net = self.sandbox.get_peg(&peg).map(|info| info.net_id);
+        let component = self.sandbox.component(peg.component);
+        Probe {
+            name,
+            bit,
+            peg,
+            net,
+            type_name: component.map(|c| c.type_name().to_owned()),
+            position: component.map(|_| self.sandbox.world_transform(peg.component).translation),
+            state: self.simulation.peg(peg).unwrap_or(false),
+        }
+    }
+
+    fn unknown_port(&mut self, port: &str) {
+        self.failures.push(BenchFailure {
+            tick: self.simulation.ticks(),
+            port: port.to_owned(),
+            kind: FailureKind::UnknownPort,
+        });
+    }
+}
+
+fn bit(value: u64, index: usize) -> bool {
+    index < 64 && value >> index & 1 == 1
+}
+
+/// Something that went wrong on a [`TestBench`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchFailure {
+    /// The tick of the simulation when it happened.
+    pub tick: u64,
+    /// The port or bus involved, or empty if there isn't one.
+    pub port: String,
+    pub kind: FailureKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FailureKind {
+    /// There is no port or bus with that name.
+    UnknownPort,
+    /// The circuit was still changing after this many ticks.
+    Unsettled { max_ticks: u64 },
+    /// A port or bus had the wrong value. A single port has the value 0 or
+    /// 1.
+    Mismatch {
+        expected: u64,
+        actual: u64,
+        /// Every peg of the port or bus, ordered by bit.
+        probes: Vec<Probe>,
+    },
+}
+
+/// The state of a peg when an expectation failed, and where to find it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Probe {
+    /// The name of the port.
+    pub name: String,
+    /// The bit of the value that the peg holds.
+    pub bit: usize,
+    pub peg: PegAddress,
+    /// The net of the peg, or `None` if the peg doesn't exist.
+    pub net: Option<NetId>,
+    /// The type of the component, or `None` if it doesn't exist.
+    pub type_name: Option<String>,
+    /// The position of the component in the world, in meters.
+    pub position: Option<[f32; 3]>,
+    pub state: bool,
+}
+
+impl fmt::Display for BenchFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "tick {}: ", self.tick)?;
+        match &self.kind {
+            FailureKind::UnknownPort => write!(f, "no port or bus named `{}`", self.port),
+            FailureKind::Unsettled { max_ticks } => {
+                write!(f, "still changing after {} ticks", max_ticks)
+            }
+            FailureKind::Mismatch {
+                expected,
+                actual,
+                probes,
+            } => {
+                write!(f, "`{}` is {}, expected {}", self.port, actual, expected)?;
+                for probe in probes {
+                    write!(f, "\n    {}", probe)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Probe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let peg_type = match self.peg.peg_type {
+            PegType::Input => "input",
+            PegType::Output => "output",
+        };
+        write!(
+            f,
+            "{}: {}, {} {} of {:?}",
+            self.name,
+            if self.state { "on" } else { "off" },
+            peg_type,
+            self.peg.peg_index,
+            self.peg.component,
+        )?;
+        if let Some(type_name) = &self.type_name {
+            write!(f, " ({})", type_name)?;
+        }
+        if let Some([x, y, z]) = self.position {
+            write!(f, " at ({:.3}, {:.3}, {:.3})", x, y, z)?;
+        }
+        match self.net {
+            Some(net) => write!(f, ", net {}", net.into_raw()),
+            None => write!(f, ", missing"),
+        }
+    }
+}
@@ -0,0 +1,221 @@
+//! Static timing analysis: how many ticks signals take to get through a
+//! circuit.
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+
+use super::simulation::Logic;
+use super::{BridgeModel, ComponentId, NetId, PegAddress, PegType, Sandbox};
+
+/// The slowest path through a circuit, returned by
+/// [`Sandbox::critical_path`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimingPath {
+    /// The number of ticks that a change at `from` takes to reach `to`.
+    pub ticks: u64,
+    pub from: PegAddress,
+    pub to: PegAddress,
+    /// The gates along the path, in order.
+    pub gates: Vec<ComponentId>,
+}
+
+impl TimingPath {
+    /// The fastest clock that gives the path time to settle, in cycles per
+    /// second, when the game runs at `ticks_per_second`.
+    ///
+    /// A full clock cycle is two phases, each of which must last at least
+    /// [`ticks`](Self::ticks).
+    pub fn max_clock_hz(&self, ticks_per_second: f64) -> f64 {
+        ticks_per_second / (2 * self.ticks.max(1)) as f64
+    }
+}
+
+/// Why there is no critical path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimingError {
+    /// One of the given pegs doesn't exist.
+    InvalidPeg(PegAddress),
+    /// None of the end pegs can be reached from the start pegs.
+    Unreachable,
+    /// Signals from the start pegs go around a loop, so there is no longest
+    /// path. These are the gates on the loops and after them, in ID order.
+    Loop(Vec<ComponentId>),
+}
+
+/// An edge of the timing graph: signals take `ticks` to get from one net to
+/// another, through `gate`, or through a wire if `gate` is `None`.
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    to: NetId,
+    ticks: u64,
+    gate: Option<ComponentId>,
+}
+
+/// The net and the gate that the longest path to a net comes through.
+type Step = (NetId, Option<ComponentId>);
+
+impl Sandbox {
+    /// Find the path from one of the pegs in `from` to one of the pegs in
+    /// `to` that takes the most ticks, to estimate how fast the circuit can
+    /// be clocked.
+    ///
+    /// Gates take one tick, and delayers their configured number of ticks,
+    /// as in [`Simulation`](super::Simulation). Wires, relays and through
+    /// pegs take none; relays are treated as closed, and the time they take
+    /// to switch isn't counted. Paths end at switches, keys, randomizers
+    /// and modded components.
+    ///
+    /// Ties go to the peg that comes first in `to`.
+    ///
+    /// ```
+    /// use blotter::sandbox::{ComponentBuilder, PegAddress, PegType, Sandbox};
+    ///
+    /// let mut sandbox = Sandbox::new();
+    /// let mut inverter = || sandbox.add_component(&ComponentBuilder::new("MHG.Inverter").num_inputs(1).num_outputs(1));
+    /// let (a, b, c) = (inverter(), inverter(), inverter());
+    /// let output = |component| PegAddress { component, peg_type: PegType::Output, peg_index: 0 };
+    /// let input = |component| PegAddress { component, peg_type: PegType::Input, peg_index: 0 };
+    /// sandbox.add_wire(output(a), input(b), 0.0).unwrap();
+    /// sandbox.add_wire(output(b), input(c), 0.0).unwrap();
+    /// sandbox.add_wire(output(a), input(c), 0.0).unwrap();
+    ///
+    /// let path = sandbox.critical_path(&[input(a)], &[output(c)]).unwrap();
+    /// assert_eq!(path.ticks, 3);
+    /// assert_eq!(path.gates, [a, b, c]);
+    /// ```
+    pub fn critical_path(
+        &self,
+        from: &[PegAddress],
+        to: &[PegAddress],
+    ) -> Result<TimingPath, TimingError> {
+        let bridged = self.bridged_nets(&BridgeModel::game());
+        let node = |peg: &PegAddress| {
+            self.get_peg(peg)
+                .map(|info| bridged.potential_root(info.net_id))
+                .ok_or(TimingError::InvalidPeg(*peg))
+        };
+        let sources = from.iter().map(node).collect::<Result<Vec<_>, _>>()?;
+        let targets = to.iter().map(node).collect::<Result<Vec<_>, _>>()?;
+        let edges = self.timing_edges(|net| bridged.potential_root(net));
+
+        // Only the part of the graph reachable from the sources matters.
+        let mut reachable: BTreeSet<NetId> = sources.iter().copied().collect();
+        let mut queue: VecDeque<NetId> = reachable.iter().copied().collect();
+        while let Some(net) = queue.pop_front() {
+            for edge in edges.get(&net).into_iter().flatten() {
+                if reachable.insert(edge.to) {
+                    queue.push_back(edge.to);
+                }
+            }
+        }
+
+        // Longest paths, in topological order.
+        let mut in_degree: HashMap<NetId, usize> = HashMap::new();
+        for net in &reachable {
+            for edge in edges.get(net).into_iter().flatten() {
+                *in_degree.entry(edge.to).or_default() += 1;
+            }
+        }
+        let mut ready: VecDeque<NetId> = reachable
+            .iter()
+            .copied()
+            .filter(|net| !in_degree.contains_key(net))
+            .collect();
+        let mut best: HashMap<NetId, (u64, Option<Step>)> =
+            sources.iter().map(|&net| (net, (0, None))).collect();
+        let mut visited = 0;
+        while let Some(net) = ready.pop_front() {
+            visited += 1;
+            let ticks = best.get(&net).map_or(0, |&(ticks, _)| ticks);
+            for edge in edges.get(&net).into_iter().flatten() {
+                let ticks = ticks + edge.ticks;
+                let entry = best
+                    .entry(edge.to)
+                    .or_insert((ticks, Some((net, edge.gate))));
+                if ticks > entry.0 {
+                    *entry = (ticks, Some((net, edge.gate)));
+                }
+                let degree = in_degree.get_mut(&edge.to).unwrap();
+                *degree -= 1;
+                if *degree == 0 {
+                    ready.push_back(edge.to);
+                }
+            }
+        }
+        if visited < reachable.len() {
+            // Every net left over is on a loop, or after one.
+            let mut gates: Vec<ComponentId> = in_degree
+                .iter()
+                .filter(|&(_, &degree)| degree > 0)
+                .flat_map(|(net, _)| edges.get(net).into_iter().flatten())
+                .filter(|edge| in_degree.get(&edge.to).is_some_and(|&degree| degree > 0))
+                .filter_map(|edge| edge.gate)
+                .collect();
+            gates.sort_unstable();
+            gates.dedup();
+            return Err(TimingError::Loop(gates));
+        }
+
+        let (end, target) = targets
+            .iter()
+            .zip(to)
+            .filter(|(net, _)| reachable.contains(net))
+            .map(|(&net, &peg)| (net, peg))
+            .reduce(|a, b| if best[&b.0].0 > best[&a.0].0 { b } else { a })
+            .ok_or(TimingError::Unreachable)?;
+
+        let mut gates = Vec::new();
+        let mut net = end;
+        while let Some((previous, gate)) = best[&net].1 {
+            gates.extend(gate);
+            net = previous;
+        }
+        gates.reverse();
+        let source = from[sources.iter().position(|&s| s == net).unwrap()];
+        Ok(TimingPath {
+            ticks: best[&end].0,
+            from: source,
+            to: target,
+            gates,
+        })
+    }
+
+    /// The edges of the timing graph, out of each net, where `root` gives the
+    /// net that stands for every net joined by bridges.
+    fn timing_edges(&self, root: impl Fn(NetId) -> NetId) -> HashMap<NetId, Vec<Edge>> {
+        let mut edges: HashMap<NetId, Vec<Edge>> = HashMap::new();
+        for (_, wire) in self.wires.iter() {
+            let input = match (wire.a.peg_type, wire.b.peg_type) {
+                (PegType::Output, _) => wire.b,
+                (_, PegType::Output) => wire.a,
+                _ => continue,
+            };
+            let input_net = self.get_peg(&input).unwrap().net_id;
+            edges.entry(root(wire.net_id)).or_default().push(Edge {
+                to: root(input_net),
+                ticks: 0,
+                gate: None,
+            });
+        }
+        let mut ids: Vec<ComponentId> = self.component_ids().collect();
+        ids.sort_unstable();
+        for id in ids {
+            let info = self.components.get(id.0).unwrap();
+            if info.outputs.is_empty() {
+                continue;
+            }
+            let type_name = self.component_types.name_str(info.type_id);
+            let Some(ticks) = Logic::of(type_name, info).ticks() else {
+                continue;
+            };
+            for input in &info.inputs {
+                let from = edges.entry(root(input.net_id)).or_default();
+                from.extend(info.outputs.iter().map(|output| Edge {
+                    to: root(output.net_id),
+                    ticks,
+                    gate: Some(id),
+                }));
+            }
+        }
+        edges
+    }
+}
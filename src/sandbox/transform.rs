@@ -0,0 +1,74 @@
+//! Converting between world space and the space of a component.
+
+use crate::geometry::{Position, Rotation, Transform};
+
+use super::{ComponentId, Sandbox};
+
+impl Sandbox {
+    /// The transform from a component's local space to world space, through
+    /// the transforms of all of its ancestors.
+    ///
+    /// Components that don't exist are treated as being at the world origin.
+    pub fn world_transform(&self, id: ComponentId) -> Transform {
+        let mut transform = Transform::IDENTITY;
+        let mut current = Some(id);
+        while let Some(info) = current.and_then(|id| self.components.get(id.0)) {
+            transform = Transform::from_local(info.position, info.rotation).then(&transform);
+            current = info.parent;
+        }
+        transform
+    }
+
+    /// The origin of a component in world space, in meters.
+    pub fn world_position(&self, id: ComponentId) -> [f32; 3] {
+        self.world_transform(id).translation
+    }
+
+    /// The local position and rotation that put a child of `parent` at
+    /// `world` in world space, or a root component if `parent` is `None`.
+    ///
+    /// The position is rounded to the nearest fixed-point unit.
+    ///
+    /// ```
+    /// use blotter::geometry::{Position, Rotation, Transform};
+    /// use blotter::sandbox::{component::CircuitBoard, ComponentBuilder, Sandbox};
+    ///
+    /// let mut sandbox = Sandbox::new();
+    /// let board = sandbox.add_component(
+    ///     &CircuitBoard::new()
+    ///         .build()
+    ///         .position([1000, 0, 0])
+    ///         .rotation(Rotation::about_y(std::f32::consts::FRAC_PI_2)),
+    /// );
+    ///
+    /// let world = Transform {
+    ///     translation: [1.0, 0.0, -0.6],
+    ///     rotation: Rotation::IDENTITY.0,
+    /// };
+    /// let (position, rotation) = sandbox.local_placement(Some(board), &world);
+    /// assert_eq!(position, Position([600, 0, 0]));
+    ///
+    /// let peg = sandbox.add_component(
+    ///     &ComponentBuilder::new("MHG.Peg")
+    ///         .parent(Some(board))
+    ///         .position(position)
+    ///         .rotation(rotation),
+    /// );
+    /// let placed = sandbox.world_position(peg);
+    /// assert!((placed[2] - -0.6).abs() < 1e-4);
+    /// ```
+    pub fn local_placement(
+        &self,
+        parent: Option<ComponentId>,
+        world: &Transform,
+    ) -> (Position, Rotation) {
+        let local = match parent {
+            Some(parent) => self.world_transform(parent).inverse().then(world),
+            None => *world,
+        };
+        (
+            Position::from_meters(local.translation),
+            Rotation(local.rotation),
+        )
+    }
+}
@@ -0,0 +1,200 @@
+//! Interned component type strings.
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+
+/// The text ID of a component type, like `"MHG.Inverter"`, shared by every
+/// sandbox that uses it.
+///
+/// Type names are interned in a process-wide registry, so each distinct
+/// name is allocated once, however many sandboxes, shards and clones refer
+/// to it. Cloning a `TypeName` is cheap. The registry only holds weak
+/// references, so a name is freed once no `TypeName` refers to it, such as
+/// after the sandbox of an untrusted save with made-up types is dropped.
+///
+/// The registry is locked only to create a `TypeName`, which happens once
+/// for each type in a sandbox's type table, not for each component.
+///
+/// ```
+/// use blotter::sandbox::TypeName;
+///
+/// let a = TypeName::new("MHG.Inverter");
+/// let b = TypeName::new(&String::from("MHG.Inverter"));
+/// assert_eq!(a, b);
+/// assert!(a.ptr_eq(&b));
+/// assert_eq!(a.as_str(), "MHG.Inverter");
+/// ```
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TypeName(Arc<str>);
+
+impl TypeName {
+    /// The interned copy of `name`.
+    pub fn new(name: &str) -> Self {
+        static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+        let mut registry = REGISTRY
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        registry.intern(name)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether both names share the same allocation, which is always the
+    /// case for equal names.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// The names that have been interned, which may have been freed since.
+#[derive(Default)]
+struct Registry {
+    names: HashMap<Box<str>, Weak<str>>,
+    /// The number of entries at which to drop those of freed names, so that
+    /// the registry stays within a constant factor of the live names.
+    purge_at: usize,
+}
+
+impl Registry {
+    fn intern(&mut self, name: &str) -> TypeName {
+        if let Some(name) = self.names.get(name).and_then(Weak::upgrade) {
+            return TypeName(name);
+        }
+        if self.names.len() >= self.purge_at {
+            self.names.retain(|_, name| name.strong_count() > 0);
+            self.purge_at = (self.names.len() * 2).max(64);
+        }
+        let interned: Arc<str> = name.into();
+        self.names.insert(name.into(), Arc::downgrade(&interned));
+        TypeName(interned)
+    }
+}
+
+impl Deref for TypeName {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for TypeName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for TypeName {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for TypeName {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl fmt::Debug for TypeName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for TypeName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The component types of a sandbox, looked up by name or by numeric ID.
+#[derive(Debug, Clone, Default)]
+pub(super) struct TypeTable {
+    ids: HashMap<TypeName, u16>,
+    names: HashMap<u16, TypeName>,
+}
+
+impl TypeTable {
+    pub(super) fn get(&self, name: &str) -> Option<u16> {
+        self.ids.get(name).copied()
+    }
+
+    pub(super) fn name(&self, type_id: u16) -> Option<&TypeName> {
+        self.names.get(&type_id)
+    }
+
+    /// The name of a type, or an empty string if there is no such type.
+    pub(super) fn name_str(&self, type_id: u16) -> &str {
+        self.name(type_id).map_or("", TypeName::as_str)
+    }
+
+    /// Add a type, replacing any type with the same name or numeric ID.
+    pub(super) fn insert(&mut self, name: TypeName, type_id: u16) {
+        if let Some(old_id) = self.ids.remove(&name) {
+            self.names.remove(&old_id);
+        }
+        if let Some(old_name) = self.names.insert(type_id, name.clone()) {
+            self.ids.remove(&old_name);
+        }
+        self.ids.insert(name, type_id);
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Iterate over the types, in no particular order.
+    pub(super) fn iter(&self) -> impl Iterator<Item = (&TypeName, u16)> {
+        self.ids.iter().map(|(name, &type_id)| (name, type_id))
+    }
+
+    /// The numeric ID after the highest one in use, or 0 if that is the
+    /// highest possible ID.
+    pub(super) fn next_id(&self) -> u16 {
+        self.names
+            .keys()
+            .max()
+            .map_or(0, |max| max.checked_add(1).unwrap_or(0))
+    }
+
+    /// The first numeric ID from `start` on that no type has, wrapping
+    /// around after the highest possible ID, or `None` if every ID is taken.
+    pub(super) fn free_id(&self, start: u16) -> Option<u16> {
+        (start..=u16::MAX)
+            .chain(0..start)
+            .find(|type_id| !self.names.contains_key(type_id))
+    }
+
+    /// Renumber the types by `type_map`, dropping those that aren't in it.
+    pub(super) fn renumber(&mut self, type_map: &HashMap<u16, u16>) {
+        let types: Vec<(TypeName, u16)> = self.ids.drain().collect();
+        self.names.clear();
+        for (name, type_id) in types {
+            if let Some(&new_id) = type_map.get(&type_id) {
+                self.insert(name, new_id);
+            }
+        }
+    }
+
+    pub(super) fn shrink_to_fit(&mut self) {
+        self.ids.shrink_to_fit();
+        self.names.shrink_to_fit();
+    }
+}
+
+impl FromIterator<(TypeName, u16)> for TypeTable {
+    fn from_iter<I: IntoIterator<Item = (TypeName, u16)>>(iter: I) -> Self {
+        let mut table = Self::default();
+        for (name, type_id) in iter {
+            table.insert(name, type_id);
+        }
+        table
+    }
+}
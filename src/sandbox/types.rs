@@ -0,0 +1,212 @@
+//! Registering component types from other saves.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::latest::ComponentType;
+use crate::mods::{namespace, type_matches};
+
+use super::{Sandbox, TypeName};
+
+/// The outcome of [`Sandbox::register_component_types`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TypeRegistration {
+    /// The sandbox's numeric ID for each numeric ID of the registered table.
+    pub type_ids: BTreeMap<u16, u16>,
+    /// Types that couldn't keep the numeric ID they had in the table, in
+    /// table order.
+    pub remaps: Vec<TypeRemap>,
+}
+
+impl TypeRegistration {
+    /// The sandbox's numeric ID for a numeric ID of the registered table.
+    ///
+    /// IDs that weren't in the table are returned unchanged.
+    pub fn type_id(&self, id: u16) -> u16 {
+        self.type_ids.get(&id).copied().unwrap_or(id)
+    }
+}
+
+/// A component type that was given a different numeric ID than the one it
+/// had in a registered table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeRemap {
+    pub text_id: String,
+    /// The numeric ID in the table.
+    pub from: u16,
+    /// The numeric ID in the sandbox.
+    pub to: u16,
+    pub conflict: TypeConflict,
+}
+
+/// Why a [`TypeRemap`] was needed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeConflict {
+    /// The type was already registered under a different numeric ID.
+    AlreadyRegistered,
+    /// The numeric ID already belongs to a different type.
+    IdTaken { by: String },
+}
+
+/// Every numeric component type ID is taken, so no more types can be
+/// registered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypeIdsExhausted;
+
+impl fmt::Display for TypeIdsExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "every component type ID is taken")
+    }
+}
+
+impl std::error::Error for TypeIdsExhausted {}
+
+impl Sandbox {
+    /// Register the types of a save's component type table, resolving
+    /// conflicts with the types the sandbox already has.
+    ///
+    /// Types keep their numeric ID where they can. A type that is already
+    /// registered keeps its existing ID, and a type whose ID belongs to
+    /// another type gets a new one; both are reported as remaps. If the table
+    /// itself lists an ID twice, components with that ID are taken to be of
+    /// the first type listed.
+    ///
+    /// Registering the same table again changes nothing.
+    ///
+    /// If a type needs a new ID but all 65536 are taken, this returns an
+    /// error. The types before it in the table stay registered.
+    ///
+    /// ```
+    /// use blotter::latest::ComponentType;
+    /// use blotter::sandbox::Sandbox;
+    ///
+    /// let mut sandbox = Sandbox::new();
+    /// sandbox
+    ///     .register_component_types(&[ComponentType {
+    ///         numeric_id: u16::MAX,
+    ///         text_id: "MyMod.Widget".into(),
+    ///     }])
+    ///     .unwrap();
+    /// let registration = sandbox
+    ///     .register_component_types(&[ComponentType {
+    ///         numeric_id: u16::MAX,
+    ///         text_id: "MyMod.Gadget".into(),
+    ///     }])
+    ///     .unwrap();
+    /// let gadget = registration.type_id(u16::MAX);
+    /// assert_ne!(gadget, u16::MAX);
+    /// assert_eq!(sandbox.type_name(gadget), Some("MyMod.Gadget"));
+    /// assert_eq!(sandbox.type_name(u16::MAX), Some("MyMod.Widget"));
+    /// ```
+    pub fn register_component_types(
+        &mut self,
+        types: &[ComponentType],
+    ) -> Result<TypeRegistration, TypeIdsExhausted> {
+        let mut registration = TypeRegistration::default();
+        // Hand out new IDs past the end of the table, so they can't take the
+        // ID of a type that comes later in it.
+        if let Some(max) = types.iter().map(|ctype| ctype.numeric_id).max() {
+            self.next_type = self.next_type.max(max.saturating_add(1));
+        }
+        for ctype in types {
+            let conflict = match self.component_types.get(&ctype.text_id) {
+                Some(existing) if existing == ctype.numeric_id => None,
+                Some(_) => Some(TypeConflict::AlreadyRegistered),
+                None => match self.component_types.name(ctype.numeric_id) {
+                    Some(other) => Some(TypeConflict::IdTaken {
+                        by: other.to_string(),
+                    }),
+                    None => {
+                        self.component_types
+                            .insert(TypeName::new(&ctype.text_id), ctype.numeric_id);
+                        None
+                    }
+                },
+            };
+            let id = self
+                .try_get_component_type(&ctype.text_id)
+                .ok_or(TypeIdsExhausted)?;
+            registration.type_ids.entry(ctype.numeric_id).or_insert(id);
+            if let Some(conflict) = conflict {
+                registration.remaps.push(TypeRemap {
+                    text_id: ctype.text_id.clone(),
+                    from: ctype.numeric_id,
+                    to: id,
+                    conflict,
+                });
+            }
+        }
+        Ok(registration)
+    }
+
+    /// The text ID of a numeric component type ID, like `"MHG.Inverter"`,
+    /// or `None` if the sandbox has no such type.
+    ///
+    /// ```
+    /// use blotter::sandbox::{component::Peg, Sandbox};
+    ///
+    /// let mut sandbox = Sandbox::new();
+    /// let peg = sandbox.add_component(&Peg::new().build());
+    /// let type_id = sandbox.component(peg).unwrap().type_id();
+    /// assert_eq!(sandbox.type_name(type_id), Some("MHG.Peg"));
+    /// assert_eq!(sandbox.type_id("MHG.Peg"), Some(type_id));
+    /// ```
+    pub fn type_name(&self, type_id: u16) -> Option<&str> {
+        self.component_types.name(type_id).map(TypeName::as_str)
+    }
+
+    /// The numeric ID of a component type, or `None` if the sandbox has no
+    /// such type.
+    pub fn type_id(&self, type_name: &str) -> Option<u16> {
+        self.component_types.get(type_name)
+    }
+
+    /// The interned text ID of a numeric component type ID.
+    pub fn type_handle(&self, type_id: u16) -> Option<TypeName> {
+        self.component_types.name(type_id).cloned()
+    }
+
+    /// The types in a namespace, like `"MHG"` for the vanilla types,
+    /// ignoring ASCII case, in order of numeric ID.
+    pub fn types_in_namespace(&self, ns: &str) -> Vec<(u16, &str)> {
+        let mut types: Vec<(u16, &str)> = self
+            .component_types
+            .iter()
+            .filter(|(name, _)| namespace(name).eq_ignore_ascii_case(ns))
+            .map(|(name, type_id)| (type_id, name.as_str()))
+            .collect();
+        types.sort_unstable();
+        types
+    }
+
+    /// The numeric IDs of the types that match `query`, in order of numeric
+    /// ID.
+    ///
+    /// A type whose text ID is exactly `query` is the only match. Otherwise,
+    /// types match as by [`type_matches`]: ignoring ASCII case, and by name
+    /// within any namespace if `query` has none. More than one type can
+    /// match when mods use the same name.
+    ///
+    /// ```
+    /// use blotter::sandbox::Sandbox;
+    ///
+    /// let sandbox = Sandbox::new();
+    /// let inverter = sandbox.type_id("MHG.Inverter").unwrap();
+    /// assert_eq!(sandbox.find_types("inverter"), [inverter]);
+    /// assert_eq!(sandbox.find_types("mhg.INVERTER"), [inverter]);
+    /// assert!(sandbox.find_types("teleporter").is_empty());
+    /// ```
+    pub fn find_types(&self, query: &str) -> Vec<u16> {
+        if let Some(type_id) = self.component_types.get(query) {
+            return vec![type_id];
+        }
+        let mut types: Vec<u16> = self
+            .component_types
+            .iter()
+            .filter(|(name, _)| type_matches(name, query))
+            .map(|(_, type_id)| type_id)
+            .collect();
+        types.sort_unstable();
+        types
+    }
+}
@@ -0,0 +1,61 @@
+//! Finding pegs that are not doing anything useful.
+
+use super::{ComponentId, PegAddress, PegType, Sandbox};
+
+/// Pegs that are likely to be wiring mistakes.
+///
+/// See [`Sandbox::peg_usage`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PegUsage {
+    /// Input pegs that aren't connected to any output, directly or through
+    /// other inputs. These will never turn on.
+    pub floating_inputs: Vec<PegAddress>,
+    /// Output pegs that aren't connected to any input.
+    pub unused_outputs: Vec<PegAddress>,
+}
+
+impl PegUsage {
+    pub fn is_empty(&self) -> bool {
+        self.floating_inputs.is_empty() && self.unused_outputs.is_empty()
+    }
+}
+
+impl Sandbox {
+    /// Find floating inputs and unused outputs, ordered by component ID.
+    pub fn peg_usage(&self) -> PegUsage {
+        let mut usage = PegUsage::default();
+        for (address, info) in self.components.iter() {
+            let id = ComponentId(address);
+            for (peg_index, peg) in info.inputs.iter().enumerate() {
+                // Inputs that are wired together share a net, but the wires
+                // from outputs belong to the output's net. The input is driven
+                // if any input in its net has one of those wires.
+                let net = self.nets.get(peg.net_id.0).unwrap();
+                let driven = net.pegs.iter().any(|addr| {
+                    self.get_peg(addr).unwrap().wires.iter().any(|wire_id| {
+                        let wire = self.wires.get(wire_id.0).unwrap();
+                        wire.a.peg_type == PegType::Output || wire.b.peg_type == PegType::Output
+                    })
+                });
+                if !driven {
+                    usage.floating_inputs.push(PegAddress {
+                        component: id,
+                        peg_type: PegType::Input,
+                        peg_index,
+                    });
+                }
+            }
+            for (peg_index, peg) in info.outputs.iter().enumerate() {
+                // Outputs can only be wired to inputs.
+                if peg.wires.is_empty() {
+                    usage.unused_outputs.push(PegAddress {
+                        component: id,
+                        peg_type: PegType::Output,
+                        peg_index,
+                    });
+                }
+            }
+        }
+        usage
+    }
+}
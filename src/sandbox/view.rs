@@ -0,0 +1,218 @@
+//! Public views into the components of a sandbox.
+
+use crate::mods::KnownComponent;
+
+use super::{ComponentId, ComponentInfo, PegAddress, Sandbox, WireId};
+
+/// A read-only view of a component in a sandbox.
+#[derive(Clone, Copy)]
+pub struct ComponentRef<'a> {
+    id: ComponentId,
+    type_name: &'a str,
+    info: &'a ComponentInfo,
+}
+
+impl<'a> ComponentRef<'a> {
+    pub fn id(&self) -> ComponentId {
+        self.id
+    }
+
+    /// The text ID of the component's type, like `"MHG.Inverter"`.
+    pub fn type_name(&self) -> &'a str {
+        self.type_name
+    }
+
+    /// The numeric ID of the component's type; see
+    /// [`Sandbox::type_name`].
+    pub fn type_id(&self) -> u16 {
+        self.info.type_id
+    }
+
+    /// The component's type, if it is a vanilla one.
+    pub fn known_type(&self) -> Option<KnownComponent> {
+        KnownComponent::from_type_string(self.type_name)
+    }
+
+    pub fn parent(&self) -> Option<ComponentId> {
+        self.info.parent
+    }
+
+    /// Position relative to the parent component.
+    pub fn position(&self) -> [i32; 3] {
+        self.info.position
+    }
+
+    /// Rotation relative to the parent component.
+    pub fn rotation(&self) -> [f32; 4] {
+        self.info.rotation
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.info.inputs.len()
+    }
+
+    pub fn num_outputs(&self) -> usize {
+        self.info.outputs.len()
+    }
+
+    pub fn custom_data(&self) -> Option<&'a [u8]> {
+        self.info.custom_data.as_deref()
+    }
+}
+
+/// A mutable view of a component in a sandbox.
+///
+/// Only the properties that don't affect the structure of the sandbox can be
+/// changed through this view; the component's type, parent and pegs are fixed.
+pub struct ComponentMut<'a> {
+    id: ComponentId,
+    type_name: &'a str,
+    info: &'a mut ComponentInfo,
+}
+
+impl ComponentMut<'_> {
+    pub fn get(&self) -> ComponentRef<'_> {
+        ComponentRef {
+            id: self.id,
+            type_name: self.type_name,
+            info: self.info,
+        }
+    }
+
+    pub fn id(&self) -> ComponentId {
+        self.id
+    }
+
+    pub fn type_name(&self) -> &str {
+        self.type_name
+    }
+
+    pub fn set_position(&mut self, position: impl Into<[i32; 3]>) {
+        self.info.position = position.into();
+    }
+
+    pub fn set_rotation(&mut self, rotation: impl Into<[f32; 4]>) {
+        self.info.rotation = rotation.into();
+    }
+
+    pub fn set_custom_data(&mut self, custom_data: Option<Vec<u8>>) {
+        self.info.custom_data = custom_data;
+    }
+}
+
+impl Sandbox {
+    /// Iterate over the IDs of all components in the sandbox.
+    pub fn component_ids(&self) -> impl Iterator<Item = ComponentId> + '_ {
+        self.components
+            .iter()
+            .map(|(address, _)| ComponentId(address))
+    }
+
+    /// Iterate over the IDs of all wires in the sandbox.
+    pub fn wire_ids(&self) -> impl Iterator<Item = WireId> + '_ {
+        self.wires.iter().map(|(address, _)| WireId(address))
+    }
+
+    /// The pegs at both ends of a wire, or `None` if it doesn't exist.
+    pub fn wire_pegs(&self, id: WireId) -> Option<(PegAddress, PegAddress)> {
+        self.wires.get(id.0).map(|wire| (wire.a, wire.b))
+    }
+
+    /// Iterate over all components of the given type, like `"MHG.Inverter"`,
+    /// in ID order.
+    pub fn components_of_type(&self, type_name: &str) -> impl Iterator<Item = ComponentId> + '_ {
+        self.component_types
+            .get(type_name)
+            .and_then(|type_id| self.components_by_type.get(&type_id))
+            .into_iter()
+            .flatten()
+            .copied()
+    }
+
+    /// Whether a component is in the sandbox.
+    ///
+    /// The ID of a removed component stays invalid, even if another component
+    /// is added in its place. Only [`compact`](Self::compact) reuses IDs.
+    ///
+    /// ```
+    /// use blotter::sandbox::{component::Peg, Sandbox};
+    ///
+    /// let mut sandbox = Sandbox::new();
+    /// let old = sandbox.add_component(&Peg::new().build());
+    /// sandbox.remove_component(old);
+    /// let new = sandbox.add_component(&Peg::new().build());
+    /// assert!(!sandbox.is_valid(old));
+    /// assert!(sandbox.is_valid(new));
+    /// assert!(sandbox.component(old).is_none());
+    /// ```
+    pub fn is_valid(&self, id: ComponentId) -> bool {
+        self.components.get(id.0).is_some()
+    }
+
+    pub fn component(&self, id: ComponentId) -> Option<ComponentRef<'_>> {
+        let info = self.components.get(id.0)?;
+        Some(ComponentRef {
+            id,
+            type_name: self.component_types.name_str(info.type_id),
+            info,
+        })
+    }
+
+    pub fn component_mut(&mut self, id: ComponentId) -> Option<ComponentMut<'_>> {
+        let info = self.components.get_mut(id.0)?;
+        Some(ComponentMut {
+            id,
+            type_name: self.component_types.name_str(info.type_id),
+            info,
+        })
+    }
+
+    /// Iterate over every component in the sandbox.
+    pub fn components(&self) -> impl Iterator<Item = ComponentRef<'_>> {
+        self.components
+            .iter()
+            .map(move |(address, info)| ComponentRef {
+                id: ComponentId(address),
+                type_name: self.component_types.name_str(info.type_id),
+                info,
+            })
+    }
+
+    /// Up to `limit` components, skipping the first `offset`, in ID order.
+    ///
+    /// This is for showing the components of a large sandbox a page at a
+    /// time, like in a virtualized table. Pages are stable as long as the
+    /// sandbox isn't changed; removing components shifts the ones after them
+    /// to earlier pages.
+    ///
+    /// ```
+    /// use blotter::sandbox::{component::Peg, Sandbox};
+    ///
+    /// let mut sandbox = Sandbox::new();
+    /// let ids: Vec<_> = (0..5).map(|_| sandbox.add_component(&Peg::new().build())).collect();
+    ///
+    /// let page: Vec<_> = sandbox.components_page(2, 2).map(|c| c.id()).collect();
+    /// assert_eq!(page, ids[2..4]);
+    /// assert_eq!(sandbox.components_page(4, 2).count(), 1);
+    /// assert_eq!(sandbox.components_page(10, 2).count(), 0);
+    /// ```
+    pub fn components_page(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> impl Iterator<Item = ComponentRef<'_>> {
+        self.components().skip(offset).take(limit)
+    }
+
+    /// Call `f` on every component in the sandbox, allowing each one to be
+    /// modified in place.
+    pub fn for_each_component_mut<F: FnMut(ComponentMut<'_>)>(&mut self, mut f: F) {
+        for (address, info) in self.components.iter_mut() {
+            f(ComponentMut {
+                id: ComponentId(address),
+                type_name: self.component_types.name_str(info.type_id),
+                info,
+            });
+        }
+    }
+}
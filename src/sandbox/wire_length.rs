@@ -0,0 +1,108 @@
+//! Measuring wires.
+
+use std::collections::HashMap;
+
+use super::{ComponentId, Sandbox, WireId};
+
+/// The length of every wire in a sandbox, longest first.
+///
+/// Like [`WireHeatmap`](super::WireHeatmap), pegs are approximated by the
+/// origin of the component they belong to.
+///
+/// See [`Sandbox::wire_lengths`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WireLengths {
+    /// Wires and their lengths in meters, longest first, then by ID.
+    lengths: Vec<(WireId, f32)>,
+}
+
+impl WireLengths {
+    pub fn len(&self) -> usize {
+        self.lengths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lengths.is_empty()
+    }
+
+    /// The total length of all wires, in meters.
+    pub fn total(&self) -> f32 {
+        self.lengths.iter().map(|&(_, length)| length).sum()
+    }
+
+    /// The mean length of a wire, or `None` if there are no wires.
+    pub fn mean(&self) -> Option<f32> {
+        (!self.is_empty()).then(|| self.total() / self.len() as f32)
+    }
+
+    /// The `n` longest wires, or all of them if there are fewer.
+    pub fn longest(&self, n: usize) -> &[(WireId, f32)] {
+        &self.lengths[..n.min(self.len())]
+    }
+
+    /// Wires longer than `threshold` meters, longest first.
+    pub fn above(&self, threshold: f32) -> &[(WireId, f32)] {
+        let count = self
+            .lengths
+            .partition_point(|&(_, length)| length > threshold);
+        &self.lengths[..count]
+    }
+
+    /// Iterate over all wires, longest first.
+    pub fn iter(&self) -> impl Iterator<Item = (WireId, f32)> + '_ {
+        self.lengths.iter().copied()
+    }
+}
+
+impl Sandbox {
+    /// The length of a wire in meters, from the world positions of the
+    /// components at its ends, or `None` if it doesn't exist.
+    pub fn wire_length(&self, id: WireId) -> Option<f32> {
+        let wire = self.wires.get(id.0)?;
+        Some(distance(
+            self.world_position(wire.a.component),
+            self.world_position(wire.b.component),
+        ))
+    }
+
+    /// Measure every wire in the sandbox.
+    ///
+    /// ```
+    /// use blotter::sandbox::{component::Peg, PegAddress, PegType, Sandbox};
+    ///
+    /// let mut sandbox = Sandbox::new();
+    /// let a = sandbox.add_component(&Peg::new().build());
+    /// let b = sandbox.add_component(&Peg::new().build().position([3000, 0, 4000]));
+    /// let peg = |component| PegAddress { component, peg_type: PegType::Input, peg_index: 0 };
+    /// let wire = sandbox.add_wire(peg(a), peg(b), 0.0).unwrap();
+    ///
+    /// let lengths = sandbox.wire_lengths();
+    /// assert_eq!(lengths.longest(1), &[(wire, 5.0)]);
+    /// assert!(lengths.above(5.0).is_empty());
+    /// ```
+    pub fn wire_lengths(&self) -> WireLengths {
+        let mut origins: HashMap<ComponentId, [f32; 3]> = HashMap::new();
+        let mut origin = |id: ComponentId| {
+            *origins
+                .entry(id)
+                .or_insert_with(|| self.world_transform(id).apply([0.0; 3]))
+        };
+        let mut lengths: Vec<(WireId, f32)> = self
+            .wires
+            .iter()
+            .map(|(address, wire)| {
+                let length = distance(origin(wire.a.component), origin(wire.b.component));
+                (WireId(address), length)
+            })
+            .collect();
+        lengths.sort_by(|(a_id, a), (b_id, b)| b.total_cmp(a).then(a_id.cmp(b_id)));
+        WireLengths { lengths }
+    }
+}
+
+fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    (0..3)
+        .map(|axis| (a[axis] - b[axis]).powi(2))
+        .sum::<f32>()
+        .sqrt()
+}
@@ -0,0 +1,186 @@
+//! Removing personal data from a save before sharing it.
+//!
+//! Saves attached to bug reports or published online can carry more than the
+//! circuit: what people wrote on labels, the tunes in their singers and drums,
+//! which mods they have installed and which version of the game they run.
+//! [`BlotterFile::sanitize`] strips or rewrites that data, without touching
+//! anything that affects how the circuit behaves.
+//!
+//! ```
+//! use blotter::custom_data::{ComponentData, Label};
+//! use blotter::latest::BlotterFile;
+//! use blotter::sandbox::{ComponentBuilder, Sandbox};
+//! use blotter::sanitize::SanitizeOptions;
+//!
+//! let mut data = Vec::new();
+//! Label::new("my password").write(&mut data).unwrap();
+//! let mut sandbox = Sandbox::new();
+//! sandbox.add_component(&ComponentBuilder::new("MHG.Label").custom_data(Some(data)));
+//! let mut file = BlotterFile::from(&sandbox);
+//! file.sanitize(&SanitizeOptions::default());
+//!
+//! let data = file.components[0].custom_data.as_deref().unwrap();
+//! assert_eq!(Label::read(&mut &data[..]).unwrap().text, "xx xxxxxxxx");
+//! ```
+
+use std::collections::BTreeSet;
+
+use crate::custom_data::{ComponentData, Label};
+use crate::latest::BlotterFile;
+use crate::mods::{is_vanilla_type, namespace};
+
+/// Component types whose custom data is a [`Label`].
+const LABEL_TYPES: &[&str] = &["MHG.Label", "MHG.PanelLabel"];
+
+/// Component types whose custom data is a sound that someone composed.
+const SOUND_TYPES: &[&str] = &["MHG.Singer", "MHG.Drum"];
+
+/// Which data [`BlotterFile::sanitize`] removes.
+///
+/// The default removes everything it can.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SanitizeOptions {
+    label_text: bool,
+    sound_data: bool,
+    mods: bool,
+    extra: bool,
+    game_version: Option<[i32; 4]>,
+}
+
+impl SanitizeOptions {
+    /// Remove all of the data that can be removed.
+    pub fn all() -> Self {
+        Self {
+            label_text: true,
+            sound_data: true,
+            mods: true,
+            extra: true,
+            game_version: Some([0; 4]),
+        }
+    }
+
+    /// Leave the save as it is; a starting point for picking individual
+    /// options.
+    pub fn none() -> Self {
+        Self {
+            label_text: false,
+            sound_data: false,
+            mods: false,
+            extra: false,
+            game_version: None,
+        }
+    }
+
+    /// Replace every character of label text with `x`, keeping whitespace so
+    /// that the layout of the text is the same.
+    ///
+    /// Labels whose data can't be parsed lose their data instead.
+    pub fn label_text(self, label_text: bool) -> Self {
+        Self { label_text, ..self }
+    }
+
+    /// Remove the custom data of singers and drums, so that they load with
+    /// the game's default sound.
+    pub fn sound_data(self, sound_data: bool) -> Self {
+        Self { sound_data, ..self }
+    }
+
+    /// Remove mod entries, and modded component types, that no component in
+    /// the save uses.
+    ///
+    /// Mods that are in use have to stay, or the save won't load.
+    pub fn mods(self, mods: bool) -> Self {
+        Self { mods, ..self }
+    }
+
+    /// Remove the [extra data](BlotterFile::extra) that newer games may write,
+    /// and the [trailing data](BlotterFile::trailing) that other tools may
+    /// append, since there is no telling what they contain.
+    pub fn extra(self, extra: bool) -> Self {
+        Self { extra, ..self }
+    }
+
+    /// Replace the game version with the given one, or keep it if `None`.
+    pub fn game_version(self, game_version: Option<[i32; 4]>) -> Self {
+        Self {
+            game_version,
+            ..self
+        }
+    }
+}
+
+impl Default for SanitizeOptions {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl BlotterFile {
+    /// Strip or rewrite potentially sensitive data, according to `options`.
+    pub fn sanitize(&mut self, options: &SanitizeOptions) {
+        let type_id = |name: &str| {
+            self.component_types
+                .iter()
+                .find(|ctype| ctype.text_id == name)
+                .map(|ctype| ctype.numeric_id)
+        };
+        let labels: Vec<u16> = LABEL_TYPES
+            .iter()
+            .filter_map(|name| type_id(name))
+            .collect();
+        let sounds: Vec<u16> = SOUND_TYPES
+            .iter()
+            .filter_map(|name| type_id(name))
+            .collect();
+
+        for component in &mut self.components {
+            if options.label_text && labels.contains(&component.type_id) {
+                component.custom_data = component
+                    .custom_data
+                    .as_deref()
+                    .and_then(|data| Label::read(&mut &data[..]).ok())
+                    .map(|mut label| {
+                        label.text = label
+                            .text
+                            .chars()
+                            .map(|c| if c.is_whitespace() { c } else { 'x' })
+                            .collect();
+                        let mut data = Vec::new();
+                        label.write(&mut data).unwrap();
+                        data
+                    });
+            }
+            if options.sound_data && sounds.contains(&component.type_id) {
+                component.custom_data = None;
+            }
+        }
+
+        if options.mods {
+            let unused = self.required_mods().unused;
+            self.mods
+                .retain(|mod_info| !unused.contains(&mod_info.mod_id));
+            let used: BTreeSet<u16> = self
+                .components
+                .iter()
+                .map(|component| component.type_id)
+                .collect();
+            let mods = &self.mods;
+            self.component_types.retain(|ctype| {
+                used.contains(&ctype.numeric_id)
+                    || is_vanilla_type(&ctype.text_id)
+                    || mods
+                        .iter()
+                        .any(|mod_info| mod_info.mod_id == namespace(&ctype.text_id))
+            });
+        }
+
+        if options.extra {
+            self.extra.clear();
+            self.trailing.clear();
+        }
+
+        if let Some(game_version) = options.game_version {
+            self.game_version = game_version;
+        }
+    }
+}
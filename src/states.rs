@@ -0,0 +1,236 @@
+//! Statistics and cleanup for the circuit state array of a save, and
+//! tracking states from one save to another.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{self, Display, Formatter};
+
+use crate::latest::{BlotterFile, CircuitStates};
+use crate::v6::zero_unused_states;
+
+/// A summary of the circuit states stored in a save.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StateStats {
+    /// The number of circuit states referenced by pegs and wires; one more
+    /// than the highest referenced state ID.
+    pub referenced: usize,
+    /// How many referenced states are on.
+    pub on: usize,
+    /// How many referenced states are off.
+    pub off: usize,
+    /// The size of the stored state array, in bytes.
+    pub stored_bytes: usize,
+    /// Bytes at the end of the state array that contain no referenced
+    /// states.
+    pub unused_bytes: usize,
+    /// How many states that are not referenced by anything are on. These are
+    /// stale data left behind by the game or by editing.
+    pub stale_on: usize,
+    /// Shannon entropy of the stored bytes, in bits per byte. Values close to
+    /// 0 compress very well; values close to 8 hardly compress at all.
+    pub entropy: f32,
+}
+
+impl StateStats {
+    /// A rough estimate of the state array's size after compression, in
+    /// bytes, based on its entropy.
+    pub fn estimated_compressed_bytes(&self) -> usize {
+        (self.stored_bytes as f32 * self.entropy / 8.0).ceil() as usize
+    }
+}
+
+impl Display for StateStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} states referenced ({} on, {} off)",
+            self.referenced, self.on, self.off
+        )?;
+        writeln!(
+            f,
+            "{} bytes stored, {} unused, {} stale states on",
+            self.stored_bytes, self.unused_bytes, self.stale_on
+        )?;
+        write!(
+            f,
+            "entropy {:.2} bits/byte, ~{} bytes compressed",
+            self.entropy,
+            self.estimated_compressed_bytes()
+        )
+    }
+}
+
+impl BlotterFile {
+    /// Summarize the circuit states of this save.
+    pub fn state_stats(&self) -> StateStats {
+        let referenced = self.referenced_states();
+        match &self.circuit_states {
+            CircuitStates::WorldFormat { circuit_states } => {
+                let is_on = |id: usize| {
+                    circuit_states
+                        .get(id / 8)
+                        .is_some_and(|byte| byte & (1 << (id % 8)) != 0)
+                };
+                let on = (0..referenced).filter(|&id| is_on(id)).count();
+                let total_on: usize = circuit_states
+                    .iter()
+                    .map(|byte| byte.count_ones() as usize)
+                    .sum();
+                StateStats {
+                    referenced,
+                    on,
+                    off: referenced - on,
+                    stored_bytes: circuit_states.len(),
+                    unused_bytes: circuit_states.len().saturating_sub(referenced.div_ceil(8)),
+                    stale_on: total_on - on,
+                    entropy: entropy(circuit_states),
+                }
+            }
+            CircuitStates::SubassemblyFormat { on_states } => {
+                let on = on_states
+                    .iter()
+                    .filter(|&&id| id >= 0 && (id as usize) < referenced)
+                    .count();
+                StateStats {
+                    referenced,
+                    on,
+                    off: referenced - on,
+                    stored_bytes: on_states.len() * 4,
+                    unused_bytes: 0,
+                    stale_on: on_states.len() - on,
+                    entropy: entropy(
+                        &on_states
+                            .iter()
+                            .flat_map(|id| id.to_le_bytes())
+                            .collect::<Vec<u8>>(),
+                    ),
+                }
+            }
+        }
+    }
+
+    /// Clear every state that isn't referenced by any peg or wire, returning
+    /// how many were on.
+    ///
+    /// The length of the state array is not changed.
+    pub fn zero_unused_states(&mut self) -> usize {
+        let referenced = self.referenced_states();
+        zero_unused_states(&mut self.circuit_states, referenced)
+    }
+}
+
+/// Where the circuit states of one save ended up in another.
+///
+/// Pegs are matched up by component address, input/output and index, and
+/// every state of the old save is mapped to the states of the matching pegs
+/// in the new save. This lets tools that track particular signals, like a
+/// monitored bus, follow them through a migration or an edit that renumbers
+/// states.
+///
+/// A state usually maps to exactly one new state. It maps to several if its
+/// net was split, and to none if all of its pegs are gone.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StateMap {
+    classes: BTreeMap<i32, BTreeSet<i32>>,
+}
+
+impl StateMap {
+    /// Map the states of `old` to the states of `new`.
+    pub fn between(old: &BlotterFile, new: &BlotterFile) -> Self {
+        let new_pegs = peg_states(new);
+        let mut classes: BTreeMap<i32, BTreeSet<i32>> = BTreeMap::new();
+        for (peg, old_state) in peg_states(old) {
+            let targets = classes.entry(old_state).or_default();
+            if let Some(&new_state) = new_pegs.get(&peg) {
+                targets.insert(new_state);
+            }
+        }
+        Self { classes }
+    }
+
+    /// The new state of an old state, if it maps to exactly one.
+    pub fn get(&self, old: i32) -> Option<i32> {
+        let targets = self.classes.get(&old)?;
+        if targets.len() == 1 {
+            targets.first().copied()
+        } else {
+            None
+        }
+    }
+
+    /// Every new state that an old state maps to.
+    pub fn targets(&self, old: i32) -> impl Iterator<Item = i32> + '_ {
+        self.classes.get(&old).into_iter().flatten().copied()
+    }
+
+    /// Every old state, along with the new states it maps to, in order of
+    /// old state ID.
+    pub fn iter(&self) -> impl Iterator<Item = (i32, &BTreeSet<i32>)> {
+        self.classes.iter().map(|(&old, targets)| (old, targets))
+    }
+
+    /// The number of old states that are used by at least one peg.
+    pub fn len(&self) -> usize {
+        self.classes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty()
+    }
+
+    /// Whether every state kept its ID and none were split or removed.
+    pub fn is_identity(&self) -> bool {
+        self.iter()
+            .all(|(old, targets)| targets.len() == 1 && targets.contains(&old))
+    }
+
+    /// Groups of old states that now share a single new state, because their
+    /// nets were merged.
+    pub fn merged(&self) -> Vec<Vec<i32>> {
+        let mut sources: BTreeMap<i32, Vec<i32>> = BTreeMap::new();
+        for (old, targets) in self.iter() {
+            for &new in targets {
+                sources.entry(new).or_default().push(old);
+            }
+        }
+        sources
+            .into_values()
+            .filter(|group| group.len() > 1)
+            .collect()
+    }
+}
+
+/// The state of every peg in a save, keyed by component address, whether
+/// the peg is an output, and peg index.
+fn peg_states(file: &BlotterFile) -> HashMap<(u32, bool, usize), i32> {
+    let mut pegs = HashMap::new();
+    for component in &file.components {
+        for (index, peg) in component.inputs.iter().enumerate() {
+            pegs.insert((component.address, false, index), peg.circuit_state_id);
+        }
+        for (index, peg) in component.outputs.iter().enumerate() {
+            pegs.insert((component.address, true, index), peg.circuit_state_id);
+        }
+    }
+    pegs
+}
+
+/// Shannon entropy of a byte string, in bits per byte.
+fn entropy(bytes: &[u8]) -> f32 {
+    if bytes.is_empty() {
+        return 0.0;
+    }
+    let mut counts = [0usize; 256];
+    for &byte in bytes {
+        counts[byte as usize] += 1;
+    }
+    let len = bytes.len() as f32;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f32 / len;
+            p * (1.0 / p).log2()
+        })
+        .sum()
+}
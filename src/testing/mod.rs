@@ -0,0 +1,308 @@
+//! Utilities for testing code that generates saves.
+//!
+//! With the `testing` feature, the [`strategies`] module also provides
+//! [proptest](https://docs.rs/proptest) strategies for generating random,
+//! valid saves and sandboxes. The [`stress`] module generates pathological
+//! wiring patterns for benchmarks.
+//!
+//! [`check_corpus`] checks that a directory of real saves survives being read
+//! and written byte for byte.
+
+#[cfg(feature = "testing")]
+pub mod strategies;
+pub mod stress;
+
+use std::fmt::{self, Debug};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::Error;
+use crate::latest::{BlotterFile, CircuitStates, Component};
+
+/// A difference between a save and its round-tripped copy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// The path of the field that differs, e.g. `components[3].position`.
+    pub path: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: expected {}, got {}",
+            self.path, self.expected, self.actual
+        )
+    }
+}
+
+/// Write `file`, read it back, and list every difference between the two.
+///
+/// The re-read copy is also written again, to check that serialization is
+/// stable.
+pub fn roundtrip(file: &BlotterFile) -> Result<Vec<Mismatch>, Error> {
+    let mut bytes = Vec::new();
+    file.write(&mut bytes)?;
+    let reread = BlotterFile::read(&mut bytes.as_slice())?;
+
+    let mut mismatches = Vec::new();
+    diff(file, &reread, &mut mismatches);
+
+    let mut rewritten = Vec::new();
+    reread.write(&mut rewritten)?;
+    if rewritten != bytes {
+        let offset = bytes
+            .iter()
+            .zip(&rewritten)
+            .position(|(a, b)| a != b)
+            .unwrap_or(bytes.len().min(rewritten.len()));
+        mismatches.push(Mismatch {
+            path: format!("bytes[{}]", offset),
+            expected: format!("{} bytes", bytes.len()),
+            actual: format!("{} bytes", rewritten.len()),
+        });
+    }
+
+    Ok(mismatches)
+}
+
+/// Assert that `file` survives a write/read round trip unchanged.
+///
+/// # Panics
+///
+/// Panics if the file cannot be written or read back, or if the copy that is
+/// read back differs from the original in any way. The panic message lists
+/// every mismatched field.
+#[track_caller]
+pub fn assert_roundtrip(file: &BlotterFile) {
+    let mismatches = match roundtrip(file) {
+        Ok(x) => x,
+        Err(error) => panic!("round trip failed: {:?}", error),
+    };
+    if !mismatches.is_empty() {
+        let mut message = format!("round trip produced {} mismatches:", mismatches.len());
+        for mismatch in &mismatches {
+            message.push_str("\n    ");
+            message.push_str(&mismatch.to_string());
+        }
+        panic!("{}", message);
+    }
+}
+
+/// A save in a corpus that can't be read back byte for byte.
+#[derive(Debug)]
+pub struct CorpusFailure {
+    pub path: PathBuf,
+    /// Why the save couldn't be read, or [`Error::NotPreserved`] if it would
+    /// be written differently.
+    pub error: Error,
+}
+
+/// Read every save in a directory and its subdirectories with
+/// [`read_preserving`](crate::BlotterFile::read_preserving), and list the
+/// ones that fail.
+///
+/// Saves are found by their extensions, `.logicworld` for worlds and
+/// `.partialworld` for subassemblies, and checked in path order. Errors are
+/// only returned for directories that can't be listed.
+pub fn check_corpus(dir: impl AsRef<Path>) -> Result<Vec<CorpusFailure>, Error> {
+    let mut paths = Vec::new();
+    let mut dirs = vec![dir.as_ref().to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path
+                .extension()
+                .is_some_and(|ext| ext == "logicworld" || ext == "partialworld")
+            {
+                paths.push(path);
+            }
+        }
+    }
+    paths.sort();
+
+    let mut failures = Vec::new();
+    for path in paths {
+        let result = fs::File::open(&path).map_err(Error::from).and_then(|file| {
+            crate::BlotterFile::read_preserving(&mut std::io::BufReader::new(file))
+        });
+        if let Err(error) = result {
+            failures.push(CorpusFailure { path, error });
+        }
+    }
+    Ok(failures)
+}
+
+fn check<T: PartialEq + Debug>(
+    path: impl FnOnce() -> String,
+    a: &T,
+    b: &T,
+    out: &mut Vec<Mismatch>,
+) {
+    if a != b {
+        out.push(Mismatch {
+            path: path(),
+            expected: format!("{:?}", a),
+            actual: format!("{:?}", b),
+        });
+    }
+}
+
+/// Compare the contents of two files field by field.
+///
+/// Floats are compared bitwise, so that `NaN` compares equal to itself and
+/// `-0.0` differs from `0.0`; both must survive serialization exactly.
+pub fn diff(a: &BlotterFile, b: &BlotterFile, out: &mut Vec<Mismatch>) {
+    check(
+        || "game_version".into(),
+        &a.game_version,
+        &b.game_version,
+        out,
+    );
+    check(|| "save_type".into(), &a.save_type, &b.save_type, out);
+
+    check(|| "mods.len()".into(), &a.mods.len(), &b.mods.len(), out);
+    for (i, (x, y)) in a.mods.iter().zip(&b.mods).enumerate() {
+        check(|| format!("mods[{}].mod_id", i), &x.mod_id, &y.mod_id, out);
+        check(
+            || format!("mods[{}].mod_version", i),
+            &x.mod_version,
+            &y.mod_version,
+            out,
+        );
+    }
+
+    check(
+        || "component_types.len()".into(),
+        &a.component_types.len(),
+        &b.component_types.len(),
+        out,
+    );
+    for (i, (x, y)) in a.component_types.iter().zip(&b.component_types).enumerate() {
+        check(
+            || format!("component_types[{}].numeric_id", i),
+            &x.numeric_id,
+            &y.numeric_id,
+            out,
+        );
+        check(
+            || format!("component_types[{}].text_id", i),
+            &x.text_id,
+            &y.text_id,
+            out,
+        );
+    }
+
+    check(
+        || "components.len()".into(),
+        &a.components.len(),
+        &b.components.len(),
+        out,
+    );
+    for (i, (x, y)) in a.components.iter().zip(&b.components).enumerate() {
+        check(
+            || format!("components[{}].address", i),
+            &x.address,
+            &y.address,
+            out,
+        );
+        check(
+            || format!("components[{}].parent", i),
+            &x.parent,
+            &y.parent,
+            out,
+        );
+        check(
+            || format!("components[{}].type_id", i),
+            &x.type_id,
+            &y.type_id,
+            out,
+        );
+        check(
+            || format!("components[{}].position", i),
+            &x.position,
+            &y.position,
+            out,
+        );
+        check(
+            || format!("components[{}].rotation", i),
+            &x.rotation.map(f32::to_bits),
+            &y.rotation.map(f32::to_bits),
+            out,
+        );
+        let inputs = |c: &Component| -> Vec<i32> {
+            c.inputs.iter().map(|peg| peg.circuit_state_id).collect()
+        };
+        let outputs = |c: &Component| -> Vec<i32> {
+            c.outputs.iter().map(|peg| peg.circuit_state_id).collect()
+        };
+        check(
+            || format!("components[{}].inputs", i),
+            &inputs(x),
+            &inputs(y),
+            out,
+        );
+        check(
+            || format!("components[{}].outputs", i),
+            &outputs(x),
+            &outputs(y),
+            out,
+        );
+        check(
+            || format!("components[{}].custom_data", i),
+            &x.custom_data,
+            &y.custom_data,
+            out,
+        );
+    }
+
+    check(|| "wires.len()".into(), &a.wires.len(), &b.wires.len(), out);
+    for (i, (x, y)) in a.wires.iter().zip(&b.wires).enumerate() {
+        check(
+            || format!("wires[{}].start_peg", i),
+            &x.start_peg,
+            &y.start_peg,
+            out,
+        );
+        check(
+            || format!("wires[{}].end_peg", i),
+            &x.end_peg,
+            &y.end_peg,
+            out,
+        );
+        check(
+            || format!("wires[{}].circuit_state_id", i),
+            &x.circuit_state_id,
+            &y.circuit_state_id,
+            out,
+        );
+        check(
+            || format!("wires[{}].rotation", i),
+            &x.rotation.to_bits(),
+            &y.rotation.to_bits(),
+            out,
+        );
+    }
+
+    match (&a.circuit_states, &b.circuit_states) {
+        (
+            CircuitStates::WorldFormat { circuit_states: x },
+            CircuitStates::WorldFormat { circuit_states: y },
+        ) => check(|| "circuit_states".into(), x, y, out),
+        (
+            CircuitStates::SubassemblyFormat { on_states: x },
+            CircuitStates::SubassemblyFormat { on_states: y },
+        ) => check(|| "circuit_states".into(), x, y, out),
+        (x, y) => out.push(Mismatch {
+            path: "circuit_states".into(),
+            expected: format!("{:?}", x),
+            actual: format!("{:?}", y),
+        }),
+    }
+    check(|| "extra".into(), &a.extra, &b.extra, out);
+    check(|| "trailing".into(), &a.trailing, &b.trailing, out);
+}
@@ -0,0 +1,225 @@
+//! Proptest strategies for generating random, valid saves.
+//!
+//! Sandboxes are not generated directly. Instead, strategies generate a
+//! [`SandboxSpec`], a plain description of the components and wires to add,
+//! which shrinks well and can be printed when a test fails. Any spec builds
+//! into a valid sandbox, and therefore into a valid save file.
+//!
+//! ```no_run
+//! use blotter::testing::{assert_roundtrip, strategies::blotter_file};
+//! use proptest::prelude::*;
+//!
+//! proptest! {
+//!     #[test]
+//!     fn roundtrip(file in blotter_file(32, 64)) {
+//!         assert_roundtrip(&file);
+//!     }
+//! }
+//! ```
+
+use proptest::{
+    arbitrary::{any, Arbitrary},
+    collection::vec,
+    option, prop_oneof,
+    sample::{select, Index},
+    strategy::{BoxedStrategy, Just, Strategy},
+};
+
+use crate::latest;
+use crate::sandbox::{ComponentBuilder, ComponentId, PegAddress, PegType, Sandbox};
+
+/// Component types used by the generated components.
+const TYPE_NAMES: [&str; 8] = [
+    "MHG.Inverter",
+    "MHG.AndGate",
+    "MHG.XorGate",
+    "MHG.Buffer",
+    "MHG.Delayer",
+    "MHG.CircuitBoard",
+    "MHG.Peg",
+    "Example.ModdedComponent",
+];
+
+/// Rotations by multiples of 90 degrees around the vertical axis.
+const ROTATIONS: [[f32; 4]; 4] = [
+    [0.0, 0.0, 0.0, 1.0],
+    [
+        0.0,
+        std::f32::consts::FRAC_1_SQRT_2,
+        0.0,
+        std::f32::consts::FRAC_1_SQRT_2,
+    ],
+    [0.0, 1.0, 0.0, 0.0],
+    [
+        0.0,
+        std::f32::consts::FRAC_1_SQRT_2,
+        0.0,
+        -std::f32::consts::FRAC_1_SQRT_2,
+    ],
+];
+
+/// A description of a sandbox to build.
+#[derive(Debug, Clone, Default)]
+pub struct SandboxSpec {
+    pub components: Vec<ComponentSpec>,
+    pub wires: Vec<WireSpec>,
+}
+
+/// A description of a component to add to a sandbox.
+#[derive(Debug, Clone)]
+pub struct ComponentSpec {
+    pub type_name: String,
+    /// Index of the parent in [`SandboxSpec::components`]. Only components
+    /// earlier in the list can be parents; other values are ignored.
+    pub parent: Option<usize>,
+    pub position: [i32; 3],
+    pub rotation: [f32; 4],
+    pub num_inputs: u32,
+    pub num_outputs: u32,
+    pub custom_data: Option<Vec<u8>>,
+}
+
+/// A description of a wire to add to a sandbox.
+#[derive(Debug, Clone, Copy)]
+pub struct WireSpec {
+    /// Component index, peg type and peg index of the first endpoint.
+    pub a: (usize, PegType, usize),
+    /// Component index, peg type and peg index of the second endpoint.
+    pub b: (usize, PegType, usize),
+    pub rotation: f32,
+}
+
+impl SandboxSpec {
+    /// Build the described sandbox.
+    ///
+    /// Wires that refer to nonexistent pegs, or that would connect two
+    /// outputs, are skipped.
+    pub fn build(&self) -> Sandbox {
+        let mut sandbox = Sandbox::new();
+        let mut ids: Vec<ComponentId> = Vec::with_capacity(self.components.len());
+        for spec in &self.components {
+            let parent = spec
+                .parent
+                .filter(|&index| index < ids.len())
+                .map(|index| ids[index]);
+            let id = sandbox.add_component(
+                &ComponentBuilder::new(&spec.type_name)
+                    .parent(parent)
+                    .position(spec.position)
+                    .rotation(spec.rotation)
+                    .num_inputs(spec.num_inputs)
+                    .num_outputs(spec.num_outputs)
+                    .custom_data(spec.custom_data.clone()),
+            );
+            ids.push(id);
+        }
+
+        let peg = |(index, peg_type, peg_index): (usize, PegType, usize)| {
+            ids.get(index).map(|&component| PegAddress {
+                component,
+                peg_type,
+                peg_index,
+            })
+        };
+        for wire in &self.wires {
+            if let (Some(a), Some(b)) = (peg(wire.a), peg(wire.b)) {
+                // Invalid wires are part of the search space; skip them.
+                let _ = sandbox.add_wire(a, b, wire.rotation);
+            }
+        }
+
+        sandbox
+    }
+}
+
+fn peg_type() -> impl Strategy<Value = PegType> {
+    prop_oneof![Just(PegType::Input), Just(PegType::Output)]
+}
+
+/// A single component, with a parent index that is not yet resolved.
+fn component_spec() -> impl Strategy<Value = (ComponentSpec, Option<Index>)> {
+    (
+        select(&TYPE_NAMES[..]),
+        option::of(any::<Index>()),
+        [-3000..3000, -3000..3000, -3000..3000],
+        select(&ROTATIONS[..]),
+        0u32..4,
+        0u32..4,
+        option::of(vec(any::<u8>(), 0..16)),
+    )
+        .prop_map(
+            |(type_name, parent, position, rotation, num_inputs, num_outputs, custom_data)| {
+                (
+                    ComponentSpec {
+                        type_name: type_name.to_owned(),
+                        parent: None,
+                        position,
+                        rotation,
+                        num_inputs,
+                        num_outputs,
+                        custom_data,
+                    },
+                    parent,
+                )
+            },
+        )
+}
+
+/// Strategy for a sandbox with up to `max_components` components and up to
+/// `max_wires` attempted wires.
+pub fn sandbox_spec(max_components: usize, max_wires: usize) -> impl Strategy<Value = SandboxSpec> {
+    let wire = (
+        any::<Index>(),
+        peg_type(),
+        0usize..4,
+        any::<Index>(),
+        peg_type(),
+        0usize..4,
+        select(&[0.0f32, 90.0, 180.0, 270.0][..]),
+    );
+    (
+        vec(component_spec(), 0..=max_components),
+        vec(wire, 0..=max_wires),
+    )
+        .prop_map(|(components, wires)| {
+            let components: Vec<ComponentSpec> = components
+                .into_iter()
+                .enumerate()
+                .map(|(i, (spec, parent))| ComponentSpec {
+                    // Parents must come before their children.
+                    parent: parent.filter(|_| i > 0).map(|index| index.index(i)),
+                    ..spec
+                })
+                .collect();
+            let wires = if components.is_empty() {
+                Vec::new()
+            } else {
+                wires
+                    .into_iter()
+                    .map(|(a, a_type, a_peg, b, b_type, b_peg, rotation)| WireSpec {
+                        a: (a.index(components.len()), a_type, a_peg),
+                        b: (b.index(components.len()), b_type, b_peg),
+                        rotation,
+                    })
+                    .collect()
+            };
+            SandboxSpec { components, wires }
+        })
+}
+
+/// Strategy for a valid save file, generated from a random sandbox.
+pub fn blotter_file(
+    max_components: usize,
+    max_wires: usize,
+) -> impl Strategy<Value = latest::BlotterFile> {
+    sandbox_spec(max_components, max_wires).prop_map(|spec| (&spec.build()).into())
+}
+
+impl Arbitrary for SandboxSpec {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_args: ()) -> Self::Strategy {
+        sandbox_spec(64, 128).boxed()
+    }
+}
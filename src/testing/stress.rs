@@ -0,0 +1,190 @@
+//! Pathological wiring patterns, for benchmarking.
+//!
+//! Each pattern is a [`WorldGenerator`], so it can be placed and repeated like
+//! any other generator. They are meant to exercise the worst cases of net
+//! merging and splitting, both in [`Sandbox`] and in the game's own loader:
+//!
+//! ```
+//! use blotter::generate::WorldGenerator;
+//! use blotter::geometry::Aabb;
+//! use blotter::sandbox::Sandbox;
+//! use blotter::testing::stress::Star;
+//!
+//! let mut sandbox = Sandbox::new();
+//! Star::new(1000).generate(&mut sandbox, Aabb::new([0.0; 3], [0.0; 3]));
+//! assert_eq!(sandbox.wire_ids().count(), 1000);
+//! ```
+
+use crate::generate::WorldGenerator;
+use crate::geometry::{meters_to_position, Aabb, SQUARE_SIZE};
+use crate::sandbox::component::{Delayer, Peg};
+use crate::sandbox::{ComponentBuilder, ComponentId, PegAddress, PegType, Sandbox};
+
+/// One peg wired to many others, so that they all share a single huge net.
+///
+/// When driven, the hub is the output of a delayer instead, and every spoke
+/// keeps a net of its own.
+#[derive(Debug, Clone)]
+pub struct Star {
+    fan_out: u32,
+    driven: bool,
+}
+
+impl Star {
+    pub fn new(fan_out: u32) -> Self {
+        Self {
+            fan_out,
+            driven: false,
+        }
+    }
+
+    pub fn driven(self, driven: bool) -> Self {
+        Self { driven, ..self }
+    }
+}
+
+impl WorldGenerator for Star {
+    fn generate(&self, sandbox: &mut Sandbox, region: Aabb) {
+        let (hub_builder, hub_peg) = if self.driven {
+            (Delayer::new().build(), PegType::Output)
+        } else {
+            (Peg::new().build(), PegType::Input)
+        };
+        let hub = place(sandbox, &hub_builder, &region, 0, 0);
+        let hub = peg(hub, hub_peg, 0);
+
+        let columns = (self.fan_out as f32).sqrt().ceil().max(1.0) as u32;
+        let spokes: Vec<ComponentId> = (0..self.fan_out)
+            .map(|i| {
+                place(
+                    sandbox,
+                    &Peg::new().build(),
+                    &region,
+                    1 + i % columns,
+                    i / columns,
+                )
+            })
+            .collect();
+        sandbox
+            .add_wires(
+                spokes
+                    .into_iter()
+                    .map(|spoke| (hub, peg(spoke, PegType::Input, 0), 0.0)),
+            )
+            .unwrap();
+    }
+}
+
+/// A line of components, each wired to the next.
+///
+/// Made of pegs, the whole chain is a single net that splits in two wherever
+/// a wire is removed. Made of delayers, every link is a net of its own.
+#[derive(Debug, Clone)]
+pub struct DaisyChain {
+    length: u32,
+    delayers: bool,
+}
+
+impl DaisyChain {
+    pub fn new(length: u32) -> Self {
+        Self {
+            length,
+            delayers: false,
+        }
+    }
+
+    pub fn delayers(self, delayers: bool) -> Self {
+        Self { delayers, ..self }
+    }
+}
+
+impl WorldGenerator for DaisyChain {
+    fn generate(&self, sandbox: &mut Sandbox, region: Aabb) {
+        let builder = if self.delayers {
+            Delayer::new().build()
+        } else {
+            Peg::new().build()
+        };
+        let links: Vec<ComponentId> = (0..self.length)
+            .map(|i| place(sandbox, &builder, &region, i, 0))
+            .collect();
+        let from = if self.delayers {
+            PegType::Output
+        } else {
+            PegType::Input
+        };
+        sandbox
+            .add_wires(
+                links
+                    .windows(2)
+                    .map(|pair| (peg(pair[0], from, 0), peg(pair[1], PegType::Input, 0), 0.0)),
+            )
+            .unwrap();
+    }
+}
+
+/// A grid of pegs, each wired to its neighbors along both axes.
+///
+/// The whole grid is a single net full of cycles. With at least two rows and
+/// columns, removing any one wire never splits it, but every removal still
+/// has to search the whole net to find that out.
+#[derive(Debug, Clone)]
+pub struct Mesh {
+    width: u32,
+    depth: u32,
+}
+
+impl Mesh {
+    pub fn new(width: u32, depth: u32) -> Self {
+        Self { width, depth }
+    }
+}
+
+impl WorldGenerator for Mesh {
+    fn generate(&self, sandbox: &mut Sandbox, region: Aabb) {
+        let mut pegs = Vec::with_capacity((self.width * self.depth) as usize);
+        for z in 0..self.depth {
+            for x in 0..self.width {
+                pegs.push(place(sandbox, &Peg::new().build(), &region, x, z));
+            }
+        }
+        let at = |x: u32, z: u32| peg(pegs[(z * self.width + x) as usize], PegType::Input, 0);
+        let mut wires = Vec::new();
+        for z in 0..self.depth {
+            for x in 0..self.width {
+                if x + 1 < self.width {
+                    wires.push((at(x, z), at(x + 1, z), 0.0));
+                }
+                if z + 1 < self.depth {
+                    wires.push((at(x, z), at(x, z + 1), 0.0));
+                }
+            }
+        }
+        sandbox.add_wires(wires).unwrap();
+    }
+}
+
+/// Add a component at a grid square of the region, counted from its minimum
+/// corner.
+fn place(
+    sandbox: &mut Sandbox,
+    builder: &ComponentBuilder,
+    region: &Aabb,
+    x: u32,
+    z: u32,
+) -> ComponentId {
+    let position = meters_to_position([
+        region.min[0] + x as f32 * SQUARE_SIZE,
+        region.min[1],
+        region.min[2] + z as f32 * SQUARE_SIZE,
+    ]);
+    sandbox.add_component(&builder.clone().position(position))
+}
+
+fn peg(component: ComponentId, peg_type: PegType, peg_index: usize) -> PegAddress {
+    PegAddress {
+        component,
+        peg_type,
+        peg_index,
+    }
+}
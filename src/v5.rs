@@ -1,12 +1,15 @@
 use crate::error::Error;
 use crate::io::*;
-use std::io::{Read, Write};
+use crate::layout::{record, Layout, Record};
+use crate::limits::{Limit, ReadLimits};
+use alloc::{string::String, vec::Vec};
 
 pub const SAVE_VERSION: u8 = 5;
 pub const SAVE_HEADER: &[u8; 16] = b"Logic World save";
 pub const SAVE_FOOTER: &[u8; 16] = b"redstone sux lol";
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SaveType {
     World,
     Subassembly,
@@ -34,7 +37,8 @@ impl WriteTo for SaveType {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ModInfo {
     pub mod_id: String,
     pub mod_version: [i32; 4],
@@ -42,7 +46,13 @@ pub struct ModInfo {
 
 impl ReadFrom for ModInfo {
     fn read_from<R: Read>(reader: &mut R) -> Result<Self, Error> {
-        let mod_id = ReadFrom::read_from(reader)?;
+        Self::read_from_seed(reader, &mut Budget::unlimited())
+    }
+}
+
+impl ReadFromSeed<&mut Budget> for ModInfo {
+    fn read_from_seed<R: Read>(reader: &mut R, budget: &mut Budget) -> Result<Self, Error> {
+        let mod_id = ReadFromSeed::read_from_seed(reader, budget)?;
         let mod_version = ReadFrom::read_from(reader)?;
         Ok(Self {
             mod_id,
@@ -59,7 +69,8 @@ impl WriteTo for ModInfo {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ComponentType {
     pub numeric_id: u16,
     pub text_id: String,
@@ -67,8 +78,14 @@ pub struct ComponentType {
 
 impl ReadFrom for ComponentType {
     fn read_from<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        Self::read_from_seed(reader, &mut Budget::unlimited())
+    }
+}
+
+impl ReadFromSeed<&mut Budget> for ComponentType {
+    fn read_from_seed<R: Read>(reader: &mut R, budget: &mut Budget) -> Result<Self, Error> {
         let numeric_id = ReadFrom::read_from(reader)?;
-        let text_id = ReadFrom::read_from(reader)?;
+        let text_id = ReadFromSeed::read_from_seed(reader, budget)?;
         Ok(Self {
             numeric_id,
             text_id,
@@ -84,7 +101,8 @@ impl WriteTo for ComponentType {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Input {
     pub circuit_state_id: i32,
 }
@@ -96,6 +114,10 @@ impl ReadFrom for Input {
     }
 }
 
+impl FixedSize for Input {
+    const SIZE: usize = 4;
+}
+
 impl WriteTo for Input {
     fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
         self.circuit_state_id.write_to(writer)?;
@@ -103,7 +125,8 @@ impl WriteTo for Input {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Output {
     pub circuit_state_id: i32,
 }
@@ -115,6 +138,10 @@ impl ReadFrom for Output {
     }
 }
 
+impl FixedSize for Output {
+    const SIZE: usize = 4;
+}
+
 impl WriteTo for Output {
     fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
         self.circuit_state_id.write_to(writer)?;
@@ -122,7 +149,8 @@ impl WriteTo for Output {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Component {
     pub address: u32,
     pub parent: u32,
@@ -131,30 +159,46 @@ pub struct Component {
     pub rotation: [f32; 4],
     pub inputs: Vec<Input>,
     pub outputs: Vec<Output>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::misc::serde_hex::option"))]
     pub custom_data: Option<Vec<u8>>,
 }
 
+/// The size of a component's address, parent, type, position, rotation and
+/// number of inputs, which is the same in every version so far.
+pub(crate) const COMPONENT_HEADER_SIZE: usize = 4 + 4 + 2 + 3 * 4 + 4 * 4 + 4;
+
 impl ReadFrom for Component {
     fn read_from<R: Read>(reader: &mut R) -> Result<Self, Error> {
-        let address = ReadFrom::read_from(reader)?;
-        let parent = ReadFrom::read_from(reader)?;
-        let type_id = ReadFrom::read_from(reader)?;
-        let position = ReadFrom::read_from(reader)?;
-        let rotation = ReadFrom::read_from(reader)?;
+        Self::read_from_seed(reader, &mut Budget::unlimited())
+    }
+}
+
+impl ReadFromSeed<&mut Budget> for Component {
+    fn read_from_seed<R: Read>(reader: &mut R, budget: &mut Budget) -> Result<Self, Error> {
+        // Read everything up to the inputs at once.
+        let mut header = [0u8; COMPONENT_HEADER_SIZE];
+        reader.read_exact(&mut header)?;
+        let header = &mut &header[..];
+        let address = ReadFrom::read_from(header)?;
+        let parent = ReadFrom::read_from(header)?;
+        let type_id = ReadFrom::read_from(header)?;
+        let position = ReadFrom::read_from(header)?;
+        let rotation = ReadFrom::read_from(header)?;
 
-        let num_inputs = usize::read_from(reader)?;
-        let inputs = ReadFromSeed::read_from_seed(reader, Length(num_inputs))?;
+        let num_inputs = usize::read_from(header)?;
+        let (inputs, num_outputs) =
+            read_counted_then_i32(reader, num_inputs, Limit::PegsPerComponent, budget)?;
 
-        let num_outputs = usize::read_from(reader)?;
-        let outputs = ReadFromSeed::read_from_seed(reader, Length(num_outputs))?;
+        let num_outputs = usize::try_from(num_outputs).map_err(|_| Error::InvalidSave)?;
+        let (outputs, custom_data_len) =
+            read_counted_then_i32(reader, num_outputs, Limit::PegsPerComponent, budget)?;
 
-        let custom_data_len = i32::read_from(reader)?;
         let custom_data = if custom_data_len < 0 {
             None
         } else {
-            let mut data = vec![0u8; custom_data_len as usize];
-            reader.read_exact(&mut data)?;
-            Some(data)
+            let len = custom_data_len as usize;
+            budget.check(Limit::CustomDataLength, len)?;
+            Some(read_bytes(reader, len, budget)?)
         };
 
         Ok(Self {
@@ -190,14 +234,15 @@ impl WriteTo for Component {
             }
             Some(data) => {
                 data.len().write_to(writer)?;
-                writer.write_all(&data)?;
+                writer.write_all(data)?;
             }
         }
         Ok(())
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PegType {
     Output,
     Input,
@@ -225,7 +270,8 @@ impl WriteTo for PegType {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PegAddress {
     pub peg_type: PegType,
     pub component_address: u32,
@@ -255,7 +301,8 @@ impl WriteTo for PegAddress {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Wire {
     pub start_peg: PegAddress,
     pub end_peg: PegAddress,
@@ -263,6 +310,46 @@ pub struct Wire {
     pub rotation: f32,
 }
 
+impl Wire {
+    /// The pegs at both ends, in an order that doesn't depend on which end
+    /// is the start: an output first, or else the lower address.
+    ///
+    /// Two wires between the same pegs have the same canonical pegs, however
+    /// they are stored.
+    ///
+    /// ```
+    /// use blotter::latest::{PegAddress, PegType, Wire};
+    ///
+    /// let peg = |peg_type, component_address| PegAddress { peg_type, component_address, peg_index: 0 };
+    /// let wire = Wire {
+    ///     start_peg: peg(PegType::Input, 1),
+    ///     end_peg: peg(PegType::Output, 2),
+    ///     circuit_state_id: 0,
+    ///     rotation: 0.0,
+    /// };
+    /// assert_eq!(wire.canonical_pegs(), (peg(PegType::Output, 2), peg(PegType::Input, 1)));
+    /// ```
+    pub fn canonical_pegs(&self) -> (PegAddress, PegAddress) {
+        let key = |peg: &PegAddress| {
+            (
+                peg.peg_type == PegType::Input,
+                peg.component_address,
+                peg.peg_index,
+            )
+        };
+        if key(&self.end_peg) < key(&self.start_peg) {
+            (self.end_peg, self.start_peg)
+        } else {
+            (self.start_peg, self.end_peg)
+        }
+    }
+
+    /// Whether both ends are the same peg.
+    pub fn is_self_loop(&self) -> bool {
+        self.start_peg == self.end_peg
+    }
+}
+
 impl ReadFrom for Wire {
     fn read_from<R: Read>(reader: &mut R) -> Result<Self, Error> {
         let start_peg = ReadFrom::read_from(reader)?;
@@ -278,6 +365,12 @@ impl ReadFrom for Wire {
     }
 }
 
+impl FixedSize for Wire {
+    /// Two peg addresses, of a peg type, a component address and a peg
+    /// index each, then the circuit state and the rotation.
+    const SIZE: usize = 2 * (1 + 4 + 4) + 4 + 4;
+}
+
 impl WriteTo for Wire {
     fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
         self.start_peg.write_to(writer)?;
@@ -288,24 +381,40 @@ impl WriteTo for Wire {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "format", rename_all = "snake_case"))]
 pub enum CircuitStates {
-    WorldFormat { circuit_states: Vec<u8> },
-    SubassemblyFormat { on_states: Vec<i32> },
+    WorldFormat {
+        #[cfg_attr(feature = "serde", serde(with = "crate::misc::serde_hex"))]
+        circuit_states: Vec<u8>,
+    },
+    SubassemblyFormat {
+        on_states: Vec<i32>,
+    },
 }
 
 impl ReadFromSeed<SaveType> for CircuitStates {
     fn read_from_seed<R: Read>(reader: &mut R, seed: SaveType) -> Result<Self, Error> {
-        match seed {
+        Self::read_from_seed(reader, (seed, &mut Budget::unlimited()))
+    }
+}
+
+impl ReadFromSeed<(SaveType, &mut Budget)> for CircuitStates {
+    fn read_from_seed<R: Read>(
+        reader: &mut R,
+        (save_type, budget): (SaveType, &mut Budget),
+    ) -> Result<Self, Error> {
+        match save_type {
             SaveType::World => {
                 let num_bytes = usize::read_from(reader)?;
-                let mut circuit_states = vec![0u8; num_bytes];
-                reader.read_exact(&mut circuit_states)?;
+                budget.check(Limit::CircuitStates, num_bytes)?;
+                let circuit_states = read_bytes(reader, num_bytes, budget)?;
                 Ok(Self::WorldFormat { circuit_states })
             }
             SaveType::Subassembly => {
                 let num_on_states = usize::read_from(reader)?;
-                let on_states = Vec::read_from_seed(reader, Length(num_on_states))?;
+                let on_states = read_counted(reader, num_on_states, Limit::CircuitStates, budget)?;
                 Ok(Self::SubassemblyFormat { on_states })
             }
         }
@@ -317,7 +426,7 @@ impl WriteTo for CircuitStates {
         match self {
             Self::WorldFormat { circuit_states } => {
                 circuit_states.len().write_to(writer)?;
-                writer.write_all(&circuit_states)?;
+                writer.write_all(circuit_states)?;
             }
             Self::SubassemblyFormat { on_states } => {
                 on_states.len().write_to(writer)?;
@@ -328,7 +437,8 @@ impl WriteTo for CircuitStates {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BlotterFile {
     pub game_version: [i32; 4],
     pub save_type: SaveType,
@@ -337,6 +447,18 @@ pub struct BlotterFile {
     pub components: Vec<Component>,
     pub wires: Vec<Wire>,
     pub circuit_states: CircuitStates,
+    /// Bytes after the footer, which some tools append, if they were
+    /// captured with [`TrailingData::Capture`](crate::TrailingData::Capture).
+    /// They are written back after the footer.
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            default,
+            skip_serializing_if = "Vec::is_empty",
+            with = "crate::misc::serde_hex"
+        )
+    )]
+    pub trailing: Vec<u8>,
 }
 
 impl BlotterFile {
@@ -351,38 +473,51 @@ impl BlotterFile {
             circuit_states: CircuitStates::WorldFormat {
                 circuit_states: Vec::new(),
             },
+            trailing: Vec::new(),
         }
     }
 
     pub fn read<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        Self::read_with_limits(reader, &ReadLimits::unlimited())
+    }
+
+    /// Read a save, failing if it exceeds any of the given limits.
+    pub fn read_with_limits<R: Read>(reader: &mut R, limits: &ReadLimits) -> Result<Self, Error> {
         read_magic(reader, SAVE_HEADER)?;
 
         let save_version = u8::read_from(reader)?;
         if save_version != SAVE_VERSION {
             return Err(Error::IncompatibleVersion(save_version));
         }
-        Self::read_after_save_version(reader)
+        Self::read_after_save_version(reader, &mut Budget::new(*limits))
     }
 
-    pub(crate) fn read_after_save_version<R: Read>(reader: &mut R) -> Result<Self, Error> {
+    pub(crate) fn read_after_save_version<R: Read>(
+        reader: &mut R,
+        budget: &mut Budget,
+    ) -> Result<Self, Error> {
         let game_version = ReadFrom::read_from(reader)?;
 
         let save_type = SaveType::read_from(reader)?;
         let num_components = usize::read_from(reader)?;
         let num_wires = usize::read_from(reader)?;
+        // Check these early, before spending time on the rest of the header.
+        budget.check(Limit::Components, num_components)?;
+        budget.check(Limit::Wires, num_wires)?;
 
         let num_mods = usize::read_from(reader)?;
-        let mods = Vec::read_from_seed(reader, Length(num_mods))?;
+        let mods = read_counted_with(reader, num_mods, Limit::Mods, budget)?;
 
         let num_component_types: usize = usize::read_from(reader)?;
-        let component_types = Vec::read_from_seed(reader, Length(num_component_types))?;
+        let component_types =
+            read_counted_with(reader, num_component_types, Limit::ComponentTypes, budget)?;
 
-        let components = Vec::read_from_seed(reader, Length(num_components))?;
-        let wires = Vec::read_from_seed(reader, Length(num_wires))?;
+        let components = read_counted_with(reader, num_components, Limit::Components, budget)?;
+        let wires = read_counted(reader, num_wires, Limit::Wires, budget)?;
 
-        let circuit_states = CircuitStates::read_from_seed(reader, save_type)?;
+        let circuit_states = CircuitStates::read_from_seed(reader, (save_type, budget))?;
 
-        read_magic(reader, SAVE_FOOTER)?;
+        read_footer(reader, SAVE_FOOTER)?;
 
         Ok(Self {
             game_version,
@@ -392,6 +527,7 @@ impl BlotterFile {
             components,
             wires,
             circuit_states,
+            trailing: Vec::new(),
         })
     }
 
@@ -424,6 +560,149 @@ impl BlotterFile {
         self.circuit_states.write_to(writer)?;
 
         writer.write_all(SAVE_FOOTER)?;
+        writer.write_all(&self.trailing)?;
         Ok(())
     }
 }
+
+/// The records of this version, as read and written above.
+pub const LAYOUT: Layout = Layout {
+    version: SAVE_VERSION,
+    records: &[
+        &SAVE_LAYOUT,
+        &MOD_INFO_LAYOUT,
+        &COMPONENT_TYPE_LAYOUT,
+        &COMPONENT_LAYOUT,
+        &INPUT_LAYOUT,
+        &OUTPUT_LAYOUT,
+        &WIRE_LAYOUT,
+        &PEG_ADDRESS_LAYOUT,
+        &WORLD_STATES_LAYOUT,
+        &SUBASSEMBLY_STATES_LAYOUT,
+    ],
+};
+
+const SAVE_LAYOUT: Record = record! {
+    /// A whole save file.
+    Save {
+        header: Magic(SAVE_HEADER),
+        /// 5.
+        save_version: U8,
+        /// The version of the game that wrote the save, like `0.91.0.510`.
+        game_version: Array(&I32, 4),
+        /// 1 for a world, 2 for a subassembly.
+        save_type: U8,
+        num_components: I32,
+        num_wires: I32,
+        num_mods: I32,
+        mods: Counted(&Record(&MOD_INFO_LAYOUT), "num_mods"),
+        num_component_types: I32,
+        component_types: Counted(&Record(&COMPONENT_TYPE_LAYOUT), "num_component_types"),
+        components: Counted(&Record(&COMPONENT_LAYOUT), "num_components"),
+        wires: Counted(&Record(&WIRE_LAYOUT), "num_wires"),
+        /// Depending on `save_type`.
+        circuit_states: OneOf(&[&WORLD_STATES_LAYOUT, &SUBASSEMBLY_STATES_LAYOUT]),
+        footer: Magic(SAVE_FOOTER),
+    }
+};
+
+pub(crate) const MOD_INFO_LAYOUT: Record = record! {
+    /// A mod that the save was made with.
+    ModInfo {
+        mod_id: String,
+        mod_version: Array(&I32, 4),
+    }
+};
+
+pub(crate) const COMPONENT_TYPE_LAYOUT: Record = record! {
+    /// The name of a type of component used in the save.
+    ComponentType {
+        /// The ID that components of this type are saved with.
+        numeric_id: U16,
+        /// Like `MHG.CircuitBoard`.
+        text_id: String,
+    }
+};
+
+const COMPONENT_LAYOUT: Record = record! {
+    /// A component, like a peg or a circuit board.
+    Component {
+        /// Unique within the save, and never 0.
+        address: U32,
+        /// The address of the parent component, or 0 for none.
+        parent: U32,
+        /// The `numeric_id` of the component's type.
+        type_id: U16,
+        /// Relative to the parent, in meters.
+        position: Array(&F32, 3),
+        /// A quaternion, `x, y, z, w`, relative to the parent.
+        rotation: Array(&F32, 4),
+        num_inputs: I32,
+        inputs: Counted(&Record(&INPUT_LAYOUT), "num_inputs"),
+        num_outputs: I32,
+        outputs: Counted(&Record(&OUTPUT_LAYOUT), "num_outputs"),
+        /// -1 for no custom data.
+        custom_data_len: I32,
+        /// Depends on the type of component.
+        custom_data: Counted(&U8, "custom_data_len"),
+    }
+};
+
+pub(crate) const INPUT_LAYOUT: Record = record! {
+    /// An input peg of a component.
+    Input {
+        circuit_state_id: I32,
+    }
+};
+
+pub(crate) const OUTPUT_LAYOUT: Record = record! {
+    /// An output peg of a component.
+    Output {
+        circuit_state_id: I32,
+    }
+};
+
+pub(crate) const WIRE_LAYOUT: Record = record! {
+    /// A wire between two pegs.
+    Wire {
+        start_peg: Record(&PEG_ADDRESS_LAYOUT),
+        end_peg: Record(&PEG_ADDRESS_LAYOUT),
+        circuit_state_id: I32,
+        /// The rotation of the wire around its length.
+        rotation: F32,
+    }
+};
+
+pub(crate) const PEG_ADDRESS_LAYOUT: Record = record! {
+    /// One end of a wire.
+    PegAddress {
+        /// 0 for an output, 1 for an input.
+        peg_type: U8,
+        component_address: U32,
+        /// The index of the peg among the inputs or outputs of the component.
+        peg_index: I32,
+    }
+};
+
+pub(crate) const WORLD_STATES_LAYOUT: Record = record! {
+    /// The circuit states of a world.
+    WorldCircuitStates {
+        num_bytes: I32,
+        /// One bit per circuit state ID, least significant bit first, set if
+        /// the state is on.
+        circuit_states: Counted(&U8, "num_bytes"),
+    }
+};
+
+pub(crate) const SUBASSEMBLY_STATES_LAYOUT: Record = record! {
+    /// The circuit states of a subassembly.
+    SubassemblyCircuitStates {
+        num_on_states: I32,
+        /// The IDs of the circuit states that are on.
+        on_states: Counted(&I32, "num_on_states"),
+    }
+};
+
+const _: () = assert!(matches!(INPUT_LAYOUT.size(), Some(size) if size == Input::SIZE));
+const _: () = assert!(matches!(OUTPUT_LAYOUT.size(), Some(size) if size == Output::SIZE));
+const _: () = assert!(matches!(WIRE_LAYOUT.size(), Some(size) if size == Wire::SIZE));
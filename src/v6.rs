@@ -1,16 +1,27 @@
 use crate::error::Error;
 use crate::io::*;
-use std::io::{Read, Write};
+use crate::layout::{record, Layout, Record};
+use crate::limits::{Limit, ReadLimits};
+use alloc::vec::Vec;
+use bitvec::vec::BitVec;
 
 pub const SAVE_VERSION: u8 = 6;
 
+/// Fixed-point position units per meter.
+pub const UNITS_PER_METER: f32 = 1000.0;
+
 // Unchanged from previous version:
 pub use crate::v5::{
     CircuitStates, ComponentType, Input, ModInfo, Output, PegAddress, PegType, SaveType, Wire,
     SAVE_FOOTER, SAVE_HEADER,
 };
+use crate::v5::{
+    COMPONENT_HEADER_SIZE, COMPONENT_TYPE_LAYOUT, INPUT_LAYOUT, MOD_INFO_LAYOUT, OUTPUT_LAYOUT,
+    PEG_ADDRESS_LAYOUT, SUBASSEMBLY_STATES_LAYOUT, WIRE_LAYOUT, WORLD_STATES_LAYOUT,
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Component {
     pub address: u32,
     pub parent: u32,
@@ -19,30 +30,42 @@ pub struct Component {
     pub rotation: [f32; 4],
     pub inputs: Vec<Input>,
     pub outputs: Vec<Output>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::misc::serde_hex::option"))]
     pub custom_data: Option<Vec<u8>>,
 }
 
 impl ReadFrom for Component {
     fn read_from<R: Read>(reader: &mut R) -> Result<Self, Error> {
-        let address = ReadFrom::read_from(reader)?;
-        let parent = ReadFrom::read_from(reader)?;
-        let type_id = ReadFrom::read_from(reader)?;
-        let position = ReadFrom::read_from(reader)?;
-        let rotation = ReadFrom::read_from(reader)?;
-
-        let num_inputs = usize::read_from(reader)?;
-        let inputs = ReadFromSeed::read_from_seed(reader, Length(num_inputs))?;
+        Self::read_from_seed(reader, &mut Budget::unlimited())
+    }
+}
 
-        let num_outputs = usize::read_from(reader)?;
-        let outputs = ReadFromSeed::read_from_seed(reader, Length(num_outputs))?;
+impl ReadFromSeed<&mut Budget> for Component {
+    fn read_from_seed<R: Read>(reader: &mut R, budget: &mut Budget) -> Result<Self, Error> {
+        // Read everything up to the inputs at once.
+        let mut header = [0u8; COMPONENT_HEADER_SIZE];
+        reader.read_exact(&mut header)?;
+        let header = &mut &header[..];
+        let address = ReadFrom::read_from(header)?;
+        let parent = ReadFrom::read_from(header)?;
+        let type_id = ReadFrom::read_from(header)?;
+        let position = ReadFrom::read_from(header)?;
+        let rotation = ReadFrom::read_from(header)?;
+
+        let num_inputs = usize::read_from(header)?;
+        let (inputs, num_outputs) =
+            read_counted_then_i32(reader, num_inputs, Limit::PegsPerComponent, budget)?;
+
+        let num_outputs = usize::try_from(num_outputs).map_err(|_| Error::InvalidSave)?;
+        let (outputs, custom_data_len) =
+            read_counted_then_i32(reader, num_outputs, Limit::PegsPerComponent, budget)?;
 
-        let custom_data_len = i32::read_from(reader)?;
         let custom_data = if custom_data_len < 0 {
             None
         } else {
-            let mut data = vec![0u8; custom_data_len as usize];
-            reader.read_exact(&mut data)?;
-            Some(data)
+            let len = custom_data_len as usize;
+            budget.check(Limit::CustomDataLength, len)?;
+            Some(read_bytes(reader, len, budget)?)
         };
 
         Ok(Self {
@@ -78,14 +101,20 @@ impl WriteTo for Component {
             }
             Some(data) => {
                 data.len().write_to(writer)?;
-                writer.write_all(&data)?;
+                writer.write_all(data)?;
             }
         }
         Ok(())
     }
 }
 
-#[derive(Debug)]
+/// A v6 save file.
+///
+/// Equality compares floating-point fields exactly, so a file equals itself
+/// after a round trip. To compare rotations by what they do, use
+/// [`Rotation::approx_eq`](crate::geometry::Rotation::approx_eq).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BlotterFile {
     pub game_version: [i32; 4],
     pub save_type: SaveType,
@@ -94,6 +123,30 @@ pub struct BlotterFile {
     pub components: Vec<Component>,
     pub wires: Vec<Wire>,
     pub circuit_states: CircuitStates,
+    /// Bytes between the circuit states and the footer, which this version
+    /// of the format doesn't have, but a newer game might add. They are kept
+    /// as they are, and written back unchanged.
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            default,
+            skip_serializing_if = "Vec::is_empty",
+            with = "crate::misc::serde_hex"
+        )
+    )]
+    pub extra: Vec<u8>,
+    /// Bytes after the footer, which some tools append, if they were
+    /// captured with [`TrailingData::Capture`](crate::TrailingData::Capture).
+    /// They are written back after the footer.
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            default,
+            skip_serializing_if = "Vec::is_empty",
+            with = "crate::misc::serde_hex"
+        )
+    )]
+    pub trailing: Vec<u8>,
 }
 
 impl BlotterFile {
@@ -108,38 +161,122 @@ impl BlotterFile {
             circuit_states: CircuitStates::WorldFormat {
                 circuit_states: Vec::new(),
             },
+            extra: Vec::new(),
+            trailing: Vec::new(),
+        }
+    }
+
+    /// Convert the save into a subassembly, which lists the circuit states
+    /// that are on instead of storing every state.
+    ///
+    /// Saves with the wrong format of states for their save type can't be
+    /// written; converting them also fixes that.
+    ///
+    /// ```
+    /// use blotter::latest::{BlotterFile, CircuitStates, SaveType};
+    ///
+    /// let mut file = BlotterFile::new([0, 91, 0, 0]);
+    /// file.circuit_states = CircuitStates::WorldFormat {
+    ///     circuit_states: vec![0b0000_0101, 0b1000_0000],
+    /// };
+    /// let file = file.into_subassembly();
+    /// assert_eq!(file.save_type, SaveType::Subassembly);
+    /// assert_eq!(
+    ///     file.circuit_states,
+    ///     CircuitStates::SubassemblyFormat {
+    ///         on_states: vec![0, 2, 15]
+    ///     }
+    /// );
+    /// let file = file.into_world();
+    /// assert_eq!(
+    ///     file.circuit_states,
+    ///     CircuitStates::WorldFormat {
+    ///         circuit_states: vec![0b0000_0101, 0b1000_0000]
+    ///     }
+    /// );
+    /// ```
+    pub fn into_subassembly(mut self) -> Self {
+        if let CircuitStates::WorldFormat { circuit_states } = self.circuit_states {
+            let on_states = BitVec::<u8>::from_vec(circuit_states)
+                .iter_ones()
+                .filter_map(|id| i32::try_from(id).ok())
+                .collect();
+            self.circuit_states = CircuitStates::SubassemblyFormat { on_states };
+        }
+        self.save_type = SaveType::Subassembly;
+        self
+    }
+
+    /// Convert the save into a world, which stores every circuit state.
+    ///
+    /// The stored states cover every state referenced by a peg or wire, and
+    /// any other state that is listed as on. Negative state IDs are dropped.
+    pub fn into_world(mut self) -> Self {
+        if let CircuitStates::SubassemblyFormat { on_states } = &self.circuit_states {
+            let on_states: Vec<usize> = on_states
+                .iter()
+                .filter_map(|&id| usize::try_from(id).ok())
+                .collect();
+            let len = on_states
+                .iter()
+                .map(|&id| id + 1)
+                .max()
+                .unwrap_or(0)
+                .max(self.referenced_states());
+            let mut states = BitVec::<u8>::repeat(false, len);
+            for id in on_states {
+                states.set(id, true);
+            }
+            states.set_uninitialized(false);
+            self.circuit_states = CircuitStates::WorldFormat {
+                circuit_states: states.into_vec(),
+            };
         }
+        self.save_type = SaveType::World;
+        self
     }
 
     pub fn read<R: Read>(reader: &mut R) -> Result<Self, Error> {
+        Self::read_with_limits(reader, &ReadLimits::unlimited())
+    }
+
+    /// Read a save, failing if it exceeds any of the given limits.
+    pub fn read_with_limits<R: Read>(reader: &mut R, limits: &ReadLimits) -> Result<Self, Error> {
         read_magic(reader, SAVE_HEADER)?;
 
         let save_version = u8::read_from(reader)?;
         if save_version != SAVE_VERSION {
             return Err(Error::IncompatibleVersion(save_version));
         }
-        Self::read_after_save_version(reader)
+        Self::read_after_save_version(reader, &mut Budget::new(*limits))
     }
 
-    pub(crate) fn read_after_save_version<R: Read>(reader: &mut R) -> Result<Self, Error> {
+    pub(crate) fn read_after_save_version<R: Read>(
+        reader: &mut R,
+        budget: &mut Budget,
+    ) -> Result<Self, Error> {
         let game_version = ReadFrom::read_from(reader)?;
 
         let save_type = SaveType::read_from(reader)?;
         let num_components = usize::read_from(reader)?;
         let num_wires = usize::read_from(reader)?;
+        // Check these early, before spending time on the rest of the header.
+        budget.check(Limit::Components, num_components)?;
+        budget.check(Limit::Wires, num_wires)?;
 
         let num_mods = usize::read_from(reader)?;
-        let mods = Vec::read_from_seed(reader, Length(num_mods))?;
+        let mods = read_counted_with(reader, num_mods, Limit::Mods, budget)?;
 
         let num_component_types: usize = usize::read_from(reader)?;
-        let component_types = Vec::read_from_seed(reader, Length(num_component_types))?;
+        let component_types =
+            read_counted_with(reader, num_component_types, Limit::ComponentTypes, budget)?;
 
-        let components = Vec::read_from_seed(reader, Length(num_components))?;
-        let wires = Vec::read_from_seed(reader, Length(num_wires))?;
+        let components = read_counted_with(reader, num_components, Limit::Components, budget)?;
+        let wires = read_counted(reader, num_wires, Limit::Wires, budget)?;
 
-        let circuit_states = CircuitStates::read_from_seed(reader, save_type)?;
+        let circuit_states = CircuitStates::read_from_seed(reader, (save_type, &mut *budget))?;
 
-        read_magic(reader, SAVE_FOOTER)?;
+        let extra = read_until_magic(reader, SAVE_FOOTER, budget)?;
 
         Ok(Self {
             game_version,
@@ -149,10 +286,53 @@ impl BlotterFile {
             components,
             wires,
             circuit_states,
+            extra,
+            trailing: Vec::new(),
         })
     }
 
+    /// One more than the highest circuit state ID used by any peg or wire.
+    pub(crate) fn referenced_states(&self) -> usize {
+        let pegs = self.components.iter().flat_map(|component| {
+            let inputs = component.inputs.iter().map(|peg| peg.circuit_state_id);
+            let outputs = component.outputs.iter().map(|peg| peg.circuit_state_id);
+            inputs.chain(outputs)
+        });
+        let wires = self.wires.iter().map(|wire| wire.circuit_state_id);
+        pegs.chain(wires)
+            .filter_map(|id| usize::try_from(id).ok())
+            .map(|id| id + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
     pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        self.write_with_states(writer, &self.circuit_states)
+    }
+
+    /// Write the save, applying the given options.
+    ///
+    /// The options only affect the written bytes; the file itself is not
+    /// modified.
+    pub fn write_with<W: Write>(
+        &self,
+        writer: &mut W,
+        options: &WriteOptions,
+    ) -> Result<(), Error> {
+        if options.zero_unused_states {
+            let mut circuit_states = self.circuit_states.clone();
+            zero_unused_states(&mut circuit_states, self.referenced_states());
+            self.write_with_states(writer, &circuit_states)
+        } else {
+            self.write(writer)
+        }
+    }
+
+    fn write_with_states<W: Write>(
+        &self,
+        writer: &mut W,
+        circuit_states: &CircuitStates,
+    ) -> Result<(), Error> {
         writer.write_all(SAVE_HEADER)?;
 
         SAVE_VERSION.write_to(writer)?;
@@ -171,16 +351,130 @@ impl BlotterFile {
         self.components.write_to(writer)?;
         self.wires.write_to(writer)?;
 
-        match (self.save_type, &self.circuit_states) {
+        match (self.save_type, circuit_states) {
             (SaveType::World, CircuitStates::WorldFormat { .. }) => {}
             (SaveType::Subassembly, CircuitStates::SubassemblyFormat { .. }) => {}
             _ => {
                 return Err(Error::InvalidSave);
             }
         }
-        self.circuit_states.write_to(writer)?;
+        circuit_states.write_to(writer)?;
 
+        writer.write_all(&self.extra)?;
         writer.write_all(SAVE_FOOTER)?;
+        writer.write_all(&self.trailing)?;
         Ok(())
     }
 }
+
+/// Clear all states with an ID of `referenced` or greater, returning how many
+/// were on.
+pub(crate) fn zero_unused_states(states: &mut CircuitStates, referenced: usize) -> usize {
+    match states {
+        CircuitStates::WorldFormat { circuit_states } => {
+            let mut cleared = 0;
+            for (index, byte) in circuit_states.iter_mut().enumerate() {
+                let first = index * 8;
+                if first + 8 <= referenced {
+                    continue;
+                }
+                let keep = referenced.saturating_sub(first);
+                let mask = if keep == 0 { 0 } else { 0xff >> (8 - keep) };
+                cleared += (*byte & !mask).count_ones() as usize;
+                *byte &= mask;
+            }
+            cleared
+        }
+        CircuitStates::SubassemblyFormat { on_states } => {
+            let before = on_states.len();
+            on_states.retain(|&id| id >= 0 && (id as usize) < referenced);
+            before - on_states.len()
+        }
+    }
+}
+
+/// Options for [`BlotterFile::write_with`].
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    zero_unused_states: bool,
+}
+
+impl WriteOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clear circuit states that aren't referenced by any peg or wire, so
+    /// stale state data isn't shipped with the save. Defaults to `false`.
+    pub fn zero_unused_states(self, zero_unused_states: bool) -> Self {
+        Self { zero_unused_states }
+    }
+}
+
+/// The records of this version, as read and written above. Only the save
+/// itself and components have changed since version 5.
+pub const LAYOUT: Layout = Layout {
+    version: SAVE_VERSION,
+    records: &[
+        &SAVE_LAYOUT,
+        &MOD_INFO_LAYOUT,
+        &COMPONENT_TYPE_LAYOUT,
+        &COMPONENT_LAYOUT,
+        &INPUT_LAYOUT,
+        &OUTPUT_LAYOUT,
+        &WIRE_LAYOUT,
+        &PEG_ADDRESS_LAYOUT,
+        &WORLD_STATES_LAYOUT,
+        &SUBASSEMBLY_STATES_LAYOUT,
+    ],
+};
+
+const SAVE_LAYOUT: Record = record! {
+    /// A whole save file.
+    Save {
+        header: Magic(SAVE_HEADER),
+        /// 6.
+        save_version: U8,
+        /// The version of the game that wrote the save, like `0.91.0.510`.
+        game_version: Array(&I32, 4),
+        /// 1 for a world, 2 for a subassembly.
+        save_type: U8,
+        num_components: I32,
+        num_wires: I32,
+        num_mods: I32,
+        mods: Counted(&Record(&MOD_INFO_LAYOUT), "num_mods"),
+        num_component_types: I32,
+        component_types: Counted(&Record(&COMPONENT_TYPE_LAYOUT), "num_component_types"),
+        components: Counted(&Record(&COMPONENT_LAYOUT), "num_components"),
+        wires: Counted(&Record(&WIRE_LAYOUT), "num_wires"),
+        /// Depending on `save_type`.
+        circuit_states: OneOf(&[&WORLD_STATES_LAYOUT, &SUBASSEMBLY_STATES_LAYOUT]),
+        /// Room for data that a newer game might add. Empty so far.
+        extra: BytesUntil(SAVE_FOOTER),
+        footer: Magic(SAVE_FOOTER),
+    }
+};
+
+const COMPONENT_LAYOUT: Record = record! {
+    /// A component, like a peg or a circuit board.
+    Component {
+        /// Unique within the save, and never 0.
+        address: U32,
+        /// The address of the parent component, or 0 for none.
+        parent: U32,
+        /// The `numeric_id` of the component's type.
+        type_id: U16,
+        /// Relative to the parent, in millimeters.
+        position: Array(&I32, 3),
+        /// A quaternion, `x, y, z, w`, relative to the parent.
+        rotation: Array(&F32, 4),
+        num_inputs: I32,
+        inputs: Counted(&Record(&INPUT_LAYOUT), "num_inputs"),
+        num_outputs: I32,
+        outputs: Counted(&Record(&OUTPUT_LAYOUT), "num_outputs"),
+        /// -1 for no custom data.
+        custom_data_len: I32,
+        /// Depends on the type of component.
+        custom_data: Counted(&U8, "custom_data_len"),
+    }
+};
@@ -0,0 +1,480 @@
+//! Consistency checks for save files.
+//!
+//! [`BlotterFile::validate`] collects every problem it can find into a
+//! [`Report`] instead of stopping at the first one. Each [`Issue`] carries a
+//! [`Severity`], and a [`Policy`] decides which severities are fatal, so
+//! interactive tools can be lenient while automated pipelines reject anything
+//! suspicious.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    io::Read,
+};
+
+use crate::error::Error;
+use crate::latest::{BlotterFile, CircuitStates, PegAddress, PegType, SaveType};
+use crate::mods;
+
+/// How serious an [`Issue`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Not a problem, but possibly interesting (e.g. wasted space).
+    Info,
+    /// The game will likely load the save, but it is probably not what was
+    /// intended.
+    Warning,
+    /// The save is malformed and will not load correctly.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        })
+    }
+}
+
+/// A specific problem found during validation.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum IssueKind {
+    /// The save type does not match the format of the circuit states.
+    CircuitStatesFormatMismatch,
+    /// Two component types share the same numeric ID.
+    DuplicateTypeId(u16),
+    /// Two component types share the same text ID.
+    DuplicateTypeName(String),
+    /// A component type is declared but not used by any component.
+    UnusedComponentType(u16),
+    /// Two components share the same address.
+    DuplicateAddress(u32),
+    /// A component has the reserved address 0.
+    ZeroAddress,
+    /// A component refers to a numeric type ID that is not declared.
+    UnknownComponentType { component: u32, type_id: u16 },
+    /// A component refers to a parent that does not exist.
+    MissingParent { component: u32, parent: u32 },
+    /// A component appears before its parent.
+    ChildBeforeParent { component: u32, parent: u32 },
+    /// A peg or wire refers to a circuit state beyond the stored states.
+    CircuitStateOutOfRange { circuit_state_id: i32 },
+    /// A wire refers to a component that does not exist.
+    MissingWireEndpoint { wire: usize, component: u32 },
+    /// A wire refers to a peg index that the component does not have.
+    InvalidPegIndex { wire: usize, peg: PegAddress },
+    /// A wire directly connects two output pegs.
+    OutputToOutput { wire: usize },
+    /// A wire connects a peg to itself.
+    SelfLoop { wire: usize },
+    /// A wire connects the same pegs as an earlier wire, in either
+    /// direction.
+    DuplicateWire { wire: usize, first: usize },
+    /// A wire's circuit state does not match the peg(s) it should share a
+    /// state with.
+    WireStateMismatch { wire: usize },
+    /// A component type that is in use is neither a vanilla type nor
+    /// provided by one of the declared mods, so the save may not load in a
+    /// vanilla game.
+    UnrecognizedComponentType(String),
+}
+
+impl IssueKind {
+    /// The severity of this kind of issue.
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::UnusedComponentType(..) | Self::UnrecognizedComponentType(..) => Severity::Info,
+            Self::DuplicateTypeName(..)
+            | Self::ChildBeforeParent { .. }
+            | Self::SelfLoop { .. }
+            | Self::DuplicateWire { .. } => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for IssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CircuitStatesFormatMismatch => {
+                write!(f, "circuit states format does not match the save type")
+            }
+            Self::DuplicateTypeId(id) => write!(f, "duplicate component type ID {}", id),
+            Self::DuplicateTypeName(name) => write!(f, "duplicate component type {:?}", name),
+            Self::UnusedComponentType(id) => write!(f, "component type {} is never used", id),
+            Self::DuplicateAddress(address) => {
+                write!(f, "duplicate component address {}", address)
+            }
+            Self::ZeroAddress => write!(f, "component uses the reserved address 0"),
+            Self::UnknownComponentType { component, type_id } => write!(
+                f,
+                "component {} has undeclared type ID {}",
+                component, type_id
+            ),
+            Self::MissingParent { component, parent } => write!(
+                f,
+                "component {} refers to missing parent {}",
+                component, parent
+            ),
+            Self::ChildBeforeParent { component, parent } => write!(
+                f,
+                "component {} appears before its parent {}",
+                component, parent
+            ),
+            Self::CircuitStateOutOfRange { circuit_state_id } => {
+                write!(f, "circuit state {} is out of range", circuit_state_id)
+            }
+            Self::MissingWireEndpoint { wire, component } => {
+                write!(f, "wire {} refers to missing component {}", wire, component)
+            }
+            Self::InvalidPegIndex { wire, peg } => write!(
+                f,
+                "wire {} refers to nonexistent {:?} peg {} on component {}",
+                wire, peg.peg_type, peg.peg_index, peg.component_address
+            ),
+            Self::OutputToOutput { wire } => {
+                write!(f, "wire {} connects two output pegs", wire)
+            }
+            Self::SelfLoop { wire } => write!(f, "wire {} connects a peg to itself", wire),
+            Self::DuplicateWire { wire, first } => {
+                write!(f, "wire {} connects the same pegs as wire {}", wire, first)
+            }
+            Self::WireStateMismatch { wire } => write!(
+                f,
+                "wire {} has a different circuit state than its endpoints",
+                wire
+            ),
+            Self::UnrecognizedComponentType(name) => write!(
+                f,
+                "component type {:?} is not vanilla or from a declared mod",
+                name
+            ),
+        }
+    }
+}
+
+/// A problem found during validation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issue {
+    pub severity: Severity,
+    pub kind: IssueKind,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.kind)
+    }
+}
+
+/// Decides which issues cause validation to fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Policy {
+    fail_at: Severity,
+    reject_unrecognized_types: bool,
+}
+
+impl Policy {
+    /// Fail on issues of the given severity or higher.
+    pub fn fail_at(fail_at: Severity) -> Self {
+        Self {
+            fail_at,
+            reject_unrecognized_types: false,
+        }
+    }
+
+    /// Also fail on component types that are neither vanilla nor provided by
+    /// a declared mod, regardless of their severity. This catches saves that
+    /// won't load in a vanilla game install.
+    ///
+    /// Types that no component uses don't count.
+    ///
+    /// ```
+    /// use blotter::latest::{BlotterFile, ComponentType};
+    /// use blotter::validate::Policy;
+    ///
+    /// let mut file = BlotterFile::new([0, 91, 0, 510]);
+    /// file.component_types.push(ComponentType {
+    ///     numeric_id: 0,
+    ///     text_id: "SomeMod.Unused".into(),
+    /// });
+    /// let policy = Policy::lenient().reject_unrecognized_types(true);
+    /// assert!(!file.validate().fails(&policy));
+    /// ```
+    pub fn reject_unrecognized_types(self, reject_unrecognized_types: bool) -> Self {
+        Self {
+            reject_unrecognized_types,
+            ..self
+        }
+    }
+
+    /// Fail only on errors. Suitable for interactive tools.
+    pub fn lenient() -> Self {
+        Self::fail_at(Severity::Error)
+    }
+
+    /// Fail on warnings and errors. Suitable for automated pipelines.
+    pub fn strict() -> Self {
+        Self::fail_at(Severity::Warning)
+    }
+
+    /// Fail on any issue at all.
+    pub fn pedantic() -> Self {
+        Self::fail_at(Severity::Info)
+    }
+
+    /// Whether an issue with the given severity should fail the operation.
+    pub fn fails(&self, severity: Severity) -> bool {
+        severity >= self.fail_at
+    }
+
+    /// Whether the given issue should fail the operation.
+    pub fn fails_on(&self, issue: &Issue) -> bool {
+        self.fails(issue.severity)
+            || (self.reject_unrecognized_types
+                && matches!(issue.kind, IssueKind::UnrecognizedComponentType(..)))
+    }
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self::lenient()
+    }
+}
+
+/// The issues found by a validation pass.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    issues: Vec<Issue>,
+}
+
+impl Report {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, kind: IssueKind) {
+        self.issues.push(Issue {
+            severity: kind.severity(),
+            kind,
+        });
+    }
+
+    pub fn issues(&self) -> &[Issue] {
+        &self.issues
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// The number of issues with exactly the given severity.
+    pub fn count(&self, severity: Severity) -> usize {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity == severity)
+            .count()
+    }
+
+    /// The highest severity in the report, if there are any issues.
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.issues.iter().map(|issue| issue.severity).max()
+    }
+
+    /// Whether the report contains any issues that fail the given policy.
+    pub fn fails(&self, policy: &Policy) -> bool {
+        self.issues.iter().any(|issue| policy.fails_on(issue))
+    }
+
+    /// Turn the report into an error if it fails the given policy.
+    pub fn check(self, policy: &Policy) -> Result<Self, Error> {
+        if self.fails(policy) {
+            Err(Error::Validation(self))
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for issue in &self.issues {
+            writeln!(f, "{}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+impl BlotterFile {
+    /// Check the internal consistency of the save.
+    pub fn validate(&self) -> Report {
+        let mut report = Report::new();
+
+        let num_states = match (self.save_type, &self.circuit_states) {
+            (SaveType::World, CircuitStates::WorldFormat { circuit_states }) => {
+                Some(8 * circuit_states.len())
+            }
+            (SaveType::Subassembly, CircuitStates::SubassemblyFormat { .. }) => None,
+            _ => {
+                report.push(IssueKind::CircuitStatesFormatMismatch);
+                None
+            }
+        };
+        let check_state = |report: &mut Report, circuit_state_id: i32| {
+            let in_range = usize::try_from(circuit_state_id)
+                .is_ok_and(|id| num_states.is_none_or(|num_states| id < num_states));
+            if !in_range {
+                report.push(IssueKind::CircuitStateOutOfRange { circuit_state_id });
+            }
+        };
+
+        let mut type_ids = HashSet::new();
+        let mut type_names = HashSet::new();
+        for ty in &self.component_types {
+            if !type_ids.insert(ty.numeric_id) {
+                report.push(IssueKind::DuplicateTypeId(ty.numeric_id));
+            }
+            if !type_names.insert(ty.text_id.as_str()) {
+                report.push(IssueKind::DuplicateTypeName(ty.text_id.clone()));
+            }
+        }
+
+        let positions: HashMap<u32, usize> = self
+            .components
+            .iter()
+            .enumerate()
+            .map(|(index, component)| (component.address, index))
+            .collect();
+        let mut seen_addresses = HashSet::new();
+        let mut used_types = HashSet::new();
+        for (index, component) in self.components.iter().enumerate() {
+            if component.address == 0 {
+                report.push(IssueKind::ZeroAddress);
+            }
+            if !seen_addresses.insert(component.address) {
+                report.push(IssueKind::DuplicateAddress(component.address));
+            }
+            if type_ids.contains(&component.type_id) {
+                used_types.insert(component.type_id);
+            } else {
+                report.push(IssueKind::UnknownComponentType {
+                    component: component.address,
+                    type_id: component.type_id,
+                });
+            }
+            if component.parent != 0 {
+                match positions.get(&component.parent) {
+                    None => report.push(IssueKind::MissingParent {
+                        component: component.address,
+                        parent: component.parent,
+                    }),
+                    Some(&parent_index) if parent_index > index => {
+                        report.push(IssueKind::ChildBeforeParent {
+                            component: component.address,
+                            parent: component.parent,
+                        })
+                    }
+                    Some(_) => {}
+                }
+            }
+            for input in &component.inputs {
+                check_state(&mut report, input.circuit_state_id);
+            }
+            for output in &component.outputs {
+                check_state(&mut report, output.circuit_state_id);
+            }
+        }
+        // Like `required_mods`, only types that are in use need to be
+        // recognized.
+        for ty in &self.component_types {
+            if !used_types.contains(&ty.numeric_id) {
+                report.push(IssueKind::UnusedComponentType(ty.numeric_id));
+            } else if !mods::is_vanilla_type(&ty.text_id)
+                && self.find_mod(mods::namespace(&ty.text_id)).is_none()
+            {
+                report.push(IssueKind::UnrecognizedComponentType(ty.text_id.clone()));
+            }
+        }
+
+        let mut wire_pegs = HashMap::new();
+        for (wire_index, wire) in self.wires.iter().enumerate() {
+            check_state(&mut report, wire.circuit_state_id);
+            if wire.start_peg.peg_type == PegType::Output
+                && wire.end_peg.peg_type == PegType::Output
+            {
+                report.push(IssueKind::OutputToOutput { wire: wire_index });
+            }
+            if wire.is_self_loop() {
+                report.push(IssueKind::SelfLoop { wire: wire_index });
+            } else if let Some(&first) = wire_pegs.get(&wire.canonical_pegs()) {
+                report.push(IssueKind::DuplicateWire {
+                    wire: wire_index,
+                    first,
+                });
+            } else {
+                wire_pegs.insert(wire.canonical_pegs(), wire_index);
+            }
+
+            // A wire driven by an output peg shares the output's circuit state;
+            // a wire between two input pegs shares the state of both.
+            let driven = wire.start_peg.peg_type == PegType::Output
+                || wire.end_peg.peg_type == PegType::Output;
+            let mut state_mismatch = false;
+            for peg in [&wire.start_peg, &wire.end_peg] {
+                let component = match positions.get(&peg.component_address) {
+                    Some(&index) => &self.components[index],
+                    None => {
+                        report.push(IssueKind::MissingWireEndpoint {
+                            wire: wire_index,
+                            component: peg.component_address,
+                        });
+                        continue;
+                    }
+                };
+                let circuit_state_id =
+                    usize::try_from(peg.peg_index)
+                        .ok()
+                        .and_then(|index| match peg.peg_type {
+                            PegType::Input => {
+                                component.inputs.get(index).map(|x| x.circuit_state_id)
+                            }
+                            PegType::Output => {
+                                component.outputs.get(index).map(|x| x.circuit_state_id)
+                            }
+                        });
+                match circuit_state_id {
+                    None => report.push(IssueKind::InvalidPegIndex {
+                        wire: wire_index,
+                        peg: *peg,
+                    }),
+                    Some(id) => {
+                        let shares_state = !driven || peg.peg_type == PegType::Output;
+                        if shares_state && id != wire.circuit_state_id {
+                            state_mismatch = true;
+                        }
+                    }
+                }
+            }
+            if state_mismatch {
+                report.push(IssueKind::WireStateMismatch { wire: wire_index });
+            }
+        }
+
+        report
+    }
+
+    /// Validate the save, failing if any issue is fatal under `policy`.
+    pub fn validate_with(&self, policy: &Policy) -> Result<Report, Error> {
+        self.validate().check(policy)
+    }
+
+    /// Read a save of any supported version and validate it, failing if any
+    /// issue is fatal under `policy`.
+    pub fn read_validated<R: Read>(reader: &mut R, policy: &Policy) -> Result<Self, Error> {
+        let file = crate::BlotterFile::read(reader)?.migrate();
+        file.validate_with(policy)?;
+        Ok(file)
+    }
+}
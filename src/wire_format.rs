@@ -0,0 +1,27 @@
+//! The binary encoding of save files, for reading and writing other formats
+//! built from the same parts, like saves of game versions this crate doesn't
+//! support yet, or the data of modded components.
+//!
+//! All numbers are little-endian. Strings and counts have a four-byte signed
+//! length prefix, and arrays of fixed length have none:
+//!
+//! ```
+//! use blotter::wire_format::{Length, ReadFrom, ReadFromSeed, WriteTo};
+//!
+//! let mut bytes = Vec::new();
+//! "MHG.Peg".write_to(&mut bytes).unwrap();
+//! [1u16, 2, 3][..].write_to(&mut bytes).unwrap();
+//!
+//! let reader = &mut bytes.as_slice();
+//! assert_eq!(String::read_from(reader).unwrap(), "MHG.Peg");
+//! assert_eq!(Vec::<u16>::read_from_seed(reader, Length(3)).unwrap(), [1, 2, 3]);
+//! ```
+//!
+//! # Stability
+//!
+//! The encoding of every type here is fixed by the save format, so it won't
+//! change. Implementations for more types may be added in any release, as
+//! the format needs them. The traits themselves may gain provided methods,
+//! but not required ones, outside of a major release.
+
+pub use crate::io::{Length, ReadFrom, ReadFromSeed, VarInt, VarString, WriteTo};
@@ -0,0 +1,332 @@
+//! Whole worlds, as the game stores them on disk.
+//!
+//! A world is a directory with the save itself in [`SAVE_FILE`], its
+//! metadata in [`META_FILE`], and other files like a thumbnail or backups.
+//! [`World`] loads all of them, so that tools can edit a world and save it
+//! again without losing anything the game keeps next to the save.
+//! [`WorldMeta`] has the details from the metadata that save browsers show,
+//! like the title and play time.
+//!
+//! ```no_run
+//! use blotter::world::World;
+//!
+//! let mut world = World::load("saves/My World").unwrap();
+//! world.save.components.retain(|component| component.parent != 0);
+//! if let Some(meta) = &mut world.meta {
+//!     meta.set("Title", "My World, without loose components");
+//! }
+//! world.write("saves/My World (edited)").unwrap();
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::error::Error;
+use crate::latest::BlotterFile;
+use crate::{ReadOptions, TrailingData};
+
+/// The name of the save in a world directory.
+pub const SAVE_FILE: &str = "data.logicworld";
+
+/// The name of the world's metadata, like its title, in a world directory.
+pub const META_FILE: &str = "meta.succ";
+
+/// A world directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct World {
+    pub save: BlotterFile,
+    /// The contents of [`META_FILE`], if there is one.
+    pub meta: Option<Succ>,
+    /// Every other file, by its path within the directory, kept as it is.
+    pub other_files: BTreeMap<PathBuf, Vec<u8>>,
+}
+
+impl World {
+    /// A world with just a save.
+    pub fn new(save: BlotterFile) -> Self {
+        Self {
+            save,
+            meta: None,
+            other_files: BTreeMap::new(),
+        }
+    }
+
+    /// Load a world directory.
+    ///
+    /// Saves of older versions are migrated to the latest one, and data after
+    /// the footer of the save is kept.
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Self, Error> {
+        let dir = dir.as_ref();
+        let mut reader = BufReader::new(File::open(dir.join(SAVE_FILE))?);
+        let options = ReadOptions::new().trailing(TrailingData::Capture);
+        let save = crate::BlotterFile::read_with(&mut reader, &options)?.migrate();
+
+        let mut meta = None;
+        let mut other_files = BTreeMap::new();
+        let mut dirs = vec![PathBuf::new()];
+        while let Some(relative) = dirs.pop() {
+            for entry in fs::read_dir(dir.join(&relative))? {
+                let entry = entry?;
+                let path = relative.join(entry.file_name());
+                if entry.file_type()?.is_dir() {
+                    dirs.push(path);
+                } else if path == Path::new(SAVE_FILE) {
+                    continue;
+                } else if path == Path::new(META_FILE) {
+                    let text = fs::read_to_string(entry.path())?;
+                    meta = Some(Succ::parse(&text));
+                } else {
+                    other_files.insert(path, fs::read(entry.path())?);
+                }
+            }
+        }
+        Ok(Self {
+            save,
+            meta,
+            other_files,
+        })
+    }
+
+    /// Write the world to a directory, creating it if needed, and replacing
+    /// the files of the world that are already there. Other files in the
+    /// directory are left alone.
+    pub fn write<P: AsRef<Path>>(&self, dir: P) -> Result<(), Error> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let mut writer = BufWriter::new(File::create(dir.join(SAVE_FILE))?);
+        self.save.write(&mut writer)?;
+        writer.flush()?;
+
+        if let Some(meta) = &self.meta {
+            fs::write(dir.join(META_FILE), meta.to_text())?;
+        }
+        for (path, contents) in &self.other_files {
+            let path = dir.join(path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, contents)?;
+        }
+        Ok(())
+    }
+
+    /// The typed part of the world's metadata, or the defaults if it has
+    /// none.
+    pub fn world_meta(&self) -> WorldMeta {
+        self.meta
+            .as_ref()
+            .map(WorldMeta::from_succ)
+            .unwrap_or_default()
+    }
+
+    /// Update the world's metadata, adding a metadata file if there isn't
+    /// one. Values that are `None` are left as they are.
+    pub fn set_world_meta(&mut self, meta: &WorldMeta) {
+        meta.write_to(self.meta.get_or_insert_with(Succ::default));
+    }
+}
+
+/// What a save browser shows about a world, from its [`META_FILE`].
+///
+/// Dates are kept as the game writes them. Other values in the file are
+/// kept as they are by [`World::set_world_meta`].
+///
+/// ```
+/// use std::time::Duration;
+/// use blotter::world::{Succ, WorldMeta};
+///
+/// let mut succ = Succ::parse("Title: Adders\nTotalPlayTime: 1.02:03:04.5\nSeed: 42\n");
+/// let mut meta = WorldMeta::from_succ(&succ);
+/// assert_eq!(meta.title.as_deref(), Some("Adders"));
+/// assert_eq!(meta.play_time, Some(Duration::from_secs_f64(93784.5)));
+///
+/// meta.title = Some(" Adders # 2".to_string());
+/// meta.play_time = Some(Duration::from_secs(90));
+/// meta.write_to(&mut succ);
+/// assert_eq!(succ.to_text(), "Title: \" Adders # 2\"\nTotalPlayTime: 00:01:30\nSeed: 42\n");
+/// assert_eq!(WorldMeta::from_succ(&succ), meta);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct WorldMeta {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub created: Option<String>,
+    pub last_played: Option<String>,
+    /// How long the world has been played for.
+    pub play_time: Option<Duration>,
+}
+
+impl WorldMeta {
+    const TITLE: &'static str = "Title";
+    const DESCRIPTION: &'static str = "Description";
+    const CREATED: &'static str = "CreationDate";
+    const LAST_PLAYED: &'static str = "LastPlayedDate";
+    const PLAY_TIME: &'static str = "TotalPlayTime";
+
+    /// Read the values that are there. Play times that can't be read are
+    /// left out.
+    pub fn from_succ(succ: &Succ) -> Self {
+        let string = |key| succ.get(key).map(String::from);
+        Self {
+            title: string(Self::TITLE),
+            description: string(Self::DESCRIPTION),
+            created: string(Self::CREATED),
+            last_played: string(Self::LAST_PLAYED),
+            play_time: succ.get(Self::PLAY_TIME).and_then(parse_time_span),
+        }
+    }
+
+    /// Set the values that are `Some` in `succ`.
+    pub fn write_to(&self, succ: &mut Succ) {
+        let strings = [
+            (Self::TITLE, &self.title),
+            (Self::DESCRIPTION, &self.description),
+            (Self::CREATED, &self.created),
+            (Self::LAST_PLAYED, &self.last_played),
+        ];
+        for (key, value) in strings {
+            if let Some(value) = value {
+                succ.set(key, &quote(value));
+            }
+        }
+        if let Some(play_time) = self.play_time {
+            succ.set(Self::PLAY_TIME, &format_time_span(play_time));
+        }
+    }
+}
+
+/// A string as a SUCC value, quoted if it would otherwise read back
+/// differently.
+fn quote(value: &str) -> String {
+    let needs_quotes = value.trim() != value || value.contains(" #") || value.starts_with('"');
+    if needs_quotes {
+        format!("\"{}\"", value)
+    } else {
+        value.to_string()
+    }
+}
+
+/// A duration in the format of .NET's `TimeSpan`, `[d.]hh:mm:ss[.fffffff]`.
+fn parse_time_span(text: &str) -> Option<Duration> {
+    let (days, rest) = match text.split_once('.') {
+        Some((days, rest)) if !days.contains(':') => (days.parse::<u64>().ok()?, rest),
+        _ => (0, text),
+    };
+    let mut parts = rest.splitn(3, ':');
+    let hours: u64 = parts.next()?.parse().ok()?;
+    let minutes: u64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    if hours >= 24 || minutes >= 60 || !(0.0..60.0).contains(&seconds) {
+        return None;
+    }
+    let whole = ((days * 24 + hours) * 60 + minutes) * 60;
+    Some(Duration::from_secs(whole) + Duration::from_secs_f64(seconds))
+}
+
+fn format_time_span(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (days, hours) = (seconds / 86400, seconds / 3600 % 24);
+    let (minutes, seconds) = (seconds / 60 % 60, seconds % 60);
+    let mut text = String::new();
+    if days > 0 {
+        text.push_str(&format!("{}.", days));
+    }
+    text.push_str(&format!("{:02}:{:02}:{:02}", hours, minutes, seconds));
+    // Ticks of 100 ns, as .NET writes them.
+    let ticks = duration.subsec_nanos() / 100;
+    if ticks > 0 {
+        text.push_str(&format!(".{:07}", ticks));
+    }
+    text
+}
+
+/// A file in SUCC, the game's configuration format, of `key: value` lines.
+///
+/// Only top-level values are read and changed; everything else, like
+/// comments and nested values, is kept as it was written.
+///
+/// ```
+/// use blotter::world::Succ;
+///
+/// let mut meta = Succ::parse("# A world\nTitle: \"Adders\" # shown in the menu\nSeed: 42\n");
+/// assert_eq!(meta.get("Title"), Some("Adders"));
+/// assert_eq!(meta.get("Seed"), Some("42"));
+///
+/// meta.set("Seed", "7");
+/// meta.set("Description", "Four bits");
+/// assert_eq!(
+///     meta.to_text(),
+///     "# A world\nTitle: \"Adders\" # shown in the menu\nSeed: 7\nDescription: Four bits\n",
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Succ {
+    lines: Vec<String>,
+}
+
+impl Succ {
+    pub fn parse(text: &str) -> Self {
+        Self {
+            lines: text.lines().map(String::from).collect(),
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for line in &self.lines {
+            text.push_str(line);
+            text.push('\n');
+        }
+        text
+    }
+
+    /// The value of a top-level key, without a comment after it or the
+    /// quotes around it.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        let value = self.lines.iter().find_map(|line| value_of(line, key))?;
+        if let Some((quoted, _)) = value
+            .strip_prefix('"')
+            .and_then(|rest| rest.split_once('"'))
+        {
+            return Some(quoted);
+        }
+        match value.find(" #") {
+            Some(comment) => Some(value[..comment].trim_end()),
+            None => Some(value),
+        }
+    }
+
+    /// Set the value of a top-level key, adding it at the end if it isn't
+    /// there yet. The value is written as it is given.
+    pub fn set(&mut self, key: &str, value: &str) {
+        let line = format!("{}: {}", key, value);
+        match self
+            .lines
+            .iter_mut()
+            .find(|line| value_of(line, key).is_some())
+        {
+            Some(existing) => *existing = line,
+            None => self.lines.push(line),
+        }
+    }
+
+    /// The top-level keys, in order.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().filter_map(|line| {
+            if line.starts_with(char::is_whitespace) || line.starts_with('#') {
+                return None;
+            }
+            line.split_once(':').map(|(key, _)| key.trim_end())
+        })
+    }
+}
+
+/// The value on `line`, if it is a top-level line for `key`.
+fn value_of<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let rest = line.strip_prefix(key)?;
+    let value = rest.trim_start().strip_prefix(':')?;
+    Some(value.trim())
+}